@@ -0,0 +1,57 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use cryptopals::aes::*;
+use cryptopals::backend::*;
+use cryptopals::block_cipher::*;
+use cryptopals::iv::*;
+use cryptopals::modes::*;
+use std::hint::black_box;
+
+fn bench_variants(c: &mut Criterion) {
+    let key = [0x2bu8; 16];
+    let block = [0x32u8; 16];
+
+    let variants = [
+        ("round_function", AesVariant::RoundFunction),
+        ("ttable", AesVariant::TTable),
+    ];
+    for (name, variant) in variants.iter() {
+        let aes = AesEncrypt::with_variant(16, *variant);
+
+        c.bench_function(&format!("aes128_encrypt_{}", name), |b| {
+            b.iter(|| aes.encrypt_block(black_box(&block), black_box(&key)))
+        });
+        c.bench_function(&format!("aes128_decrypt_{}", name), |b| {
+            b.iter(|| aes.decrypt_block(black_box(&block), black_box(&key)))
+        });
+    }
+}
+
+/// CTR over 64 KiB on each backend, which goes a block at a time through
+/// `Aes`, so it shows what the backend costs outside bulk ECB.
+fn bench_backend_ctr(c: &mut Criterion) {
+    let key = [0x2bu8; 16];
+    let data = vec![0x32u8; 64 * 1024];
+
+    let mut backends: Vec<&'static dyn AesBackend> = vec![&RustAes, &BitslicedAes];
+    #[cfg(feature = "openssl")]
+    backends.push(&OpensslAes);
+    #[cfg(target_arch = "x86_64")]
+    {
+        if AesNiBackend.keyed(&key).is_ok() {
+            backends.push(&AesNiBackend);
+        }
+    }
+
+    let mut group = c.benchmark_group("aes128_ctr_64k");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    for backend in backends {
+        let aes = Aes::with_backend(backend, &key).unwrap();
+        group.bench_function(backend.name(), |b| {
+            b.iter(|| ctr_apply(&aes, Nonce::fixed(0), black_box(&data)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_variants, bench_backend_ctr);
+criterion_main!(benches);
@@ -1,5 +1,12 @@
 #![allow(dead_code)]
 
+use std::sync::OnceLock;
+
+// Round constants for the key schedule; index 0 is unused.
+const RCON: [u8; 11] = [
+    0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36,
+];
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AesByte(u8);
 
@@ -9,20 +16,112 @@ impl AesByte {
         let mut ans = n << 1;
 
         if overflow != 0 {
-            ans = ans ^ 0x1b;
+            ans ^= 0x1b;
         }
 
         ans
     }
+
+    pub fn new(n: u8) -> Self {
+        AesByte(n)
+    }
+
+    pub fn value(self) -> u8 {
+        self.0
+    }
+
+    /// `self^e` by square-and-multiply. The multiplicative group has order
+    /// 255, so exponents wrap around mod 255 (with `0^0` being 1).
+    pub fn pow(self, e: u32) -> Self {
+        let mut ans = AesByte(1);
+        let mut square = self;
+        let mut e = e;
+
+        while e > 0 {
+            if e & 0x1 == 0x1 {
+                ans = ans * square;
+            }
+            square = square * square;
+            e >>= 1;
+        }
+        ans
+    }
+
+    /// Multiplicative inverse in GF(2^8), as `self^254`; zero maps to zero,
+    /// which is the convention SubBytes needs.
+    pub fn inverse(self) -> Self {
+        self.pow(254)
+    }
+
+    /// The inverse, or `None` for zero.
+    pub fn checked_inverse(self) -> Option<Self> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(self.inverse())
+        }
+    }
+
+    /// The affine map over GF(2) that follows the inverse in SubBytes.
+    fn affine(self) -> Self {
+        let b = self.0;
+        AesByte(
+            b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63,
+        )
+    }
+
+    fn sub(self) -> Self {
+        AesByte(sbox_tables().0[self.0 as usize])
+    }
+
+    fn inv_sub(self) -> Self {
+        AesByte(sbox_tables().1[self.0 as usize])
+    }
+}
+
+/// The S-box and its inverse, built once from `inverse` and `affine`.
+fn sbox_tables() -> &'static ([u8; 256], [u8; 256]) {
+    static TABLES: OnceLock<([u8; 256], [u8; 256])> = OnceLock::new();
+
+    TABLES.get_or_init(|| {
+        let mut sbox = [0u8; 256];
+        let mut inv = [0u8; 256];
+        for b in 0..=255u8 {
+            let s = AesByte(b).inverse().affine().0;
+            sbox[b as usize] = s;
+            inv[s as usize] = b;
+        }
+        (sbox, inv)
+    })
 }
 
 impl std::ops::Add for AesByte {
     type Output = Self;
+    #[allow(clippy::suspicious_arithmetic_impl)]
     fn add(self, rhs: Self) -> Self::Output {
         AesByte(self.0 ^ rhs.0)
     }
 }
 
+impl std::ops::Sub for AesByte {
+    type Output = Self;
+    // Characteristic 2: subtraction is addition.
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, rhs: Self) -> Self::Output {
+        AesByte(self.0 ^ rhs.0)
+    }
+}
+
+impl std::ops::Div for AesByte {
+    type Output = Self;
+
+    /// Panics on division by zero, like the integer types.
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, rhs: Self) -> Self::Output {
+        self * rhs.checked_inverse().expect("division by zero in GF(2^8)")
+    }
+}
+
 impl std::ops::Mul for AesByte {
     type Output = Self;
 
@@ -32,18 +131,18 @@ impl std::ops::Mul for AesByte {
         let mut multiplier = self.0;
 
         if m & 0x1 == 0x1 {
-            ans = ans ^ multiplier;
+            ans ^= multiplier;
         }
 
-        m = m >> 1;
+        m >>= 1;
         while m > 0x0 {
             multiplier = AesByte::xtime(multiplier);
 
             if m & 0x1 == 0x1 {
-                ans = ans ^ multiplier;
+                ans ^= multiplier;
             }
 
-            m = m >> 1;
+            m >>= 1;
         }
 
         AesByte(ans)
@@ -58,11 +157,25 @@ impl AesWord {
         AesWord(AesByte(a1), AesByte(a2), AesByte(a3), AesByte(a4))
     }
 
+    pub fn to_bytes(self) -> [u8; 4] {
+        [self.0 .0, self.1 .0, self.2 .0, self.3 .0]
+    }
+
     fn rotate_rigt(n: Self) -> Self {
         let AesWord(a, b, c, d) = n;
         AesWord(d, a, b, c)
     }
 
+    fn rotate_left(n: Self) -> Self {
+        let AesWord(a, b, c, d) = n;
+        AesWord(b, c, d, a)
+    }
+
+    fn sub_word(n: Self) -> Self {
+        let AesWord(a, b, c, d) = n;
+        AesWord(a.sub(), b.sub(), c.sub(), d.sub())
+    }
+
     fn get_mutiplier(n: Self) -> (Self, Self, Self, Self) {
         let AesWord(a0, a1, a2, a3) = n;
 
@@ -114,31 +227,520 @@ impl std::ops::Mul for AesWord {
 
 struct AesUtil {}
 
-impl AesUtil {}
+impl AesUtil {
+    fn words_from_block(block: &[u8; 16]) -> [AesWord; 4] {
+        let w = |i: usize| AesWord::from_bytes(block[i], block[i + 1], block[i + 2], block[i + 3]);
+        [w(0), w(4), w(8), w(12)]
+    }
+
+    fn block_from_words(words: &[AesWord; 4]) -> [u8; 16] {
+        let mut block = [0u8; 16];
+        for (chunk, w) in block.chunks_mut(4).zip(words.iter()) {
+            chunk.copy_from_slice(&w.to_bytes());
+        }
+        block
+    }
+
+    // RotWord, SubWord and the round constant, applied to the last word of the
+    // previous round key.
+    fn schedule_core(w: AesWord, round: usize) -> AesWord {
+        AesWord::sub_word(AesWord::rotate_left(w)) + AesWord::from_bytes(RCON[round], 0, 0, 0)
+    }
+
+    /// Derives AES-128 round key `round` from round key `round - 1`.
+    fn next_round_key(prev: [AesWord; 4], round: usize) -> [AesWord; 4] {
+        let w0 = prev[0] + Self::schedule_core(prev[3], round);
+        let w1 = prev[1] + w0;
+        let w2 = prev[2] + w1;
+        let w3 = prev[3] + w2;
+        [w0, w1, w2, w3]
+    }
+
+    /// Inverse of `next_round_key`: every word of round key `round - 1` is the
+    /// sum of two adjacent words of round key `round`, except the first which
+    /// also needs the (already recovered) last word.
+    fn prev_round_key(key: [AesWord; 4], round: usize) -> [AesWord; 4] {
+        let p3 = key[3] + key[2];
+        let p2 = key[2] + key[1];
+        let p1 = key[1] + key[0];
+        let p0 = key[0] + Self::schedule_core(p3, round);
+        [p0, p1, p2, p3]
+    }
+}
+
+/// Runs the AES-128 key schedule backwards from round key `round_index`
+/// (0 being the cipher key itself) to recover the cipher key. Because the
+/// schedule is a bijection, leaking any single round key leaks the key.
+pub fn recover_key_from_round_key(round_key: &[u8; 16], round_index: usize) -> [u8; 16] {
+    assert!(round_index < RCON.len(), "AES-128 has round keys 0..=10");
+
+    let mut words = AesUtil::words_from_block(round_key);
+    for round in (1..=round_index).rev() {
+        words = AesUtil::prev_round_key(words, round);
+    }
+    AesUtil::block_from_words(&words)
+}
+
+/// Number of rounds for a key of `nk` words: 10, 12 or 14.
+pub fn num_rounds(nk: usize) -> usize {
+    assert!(
+        nk == 4 || nk == 6 || nk == 8,
+        "AES keys are 16, 24 or 32 bytes"
+    );
+    nk + 6
+}
+
+/// The AES key schedule (FIPS-197 section 5.2) for 128, 192 and 256-bit
+/// keys: the cipher key followed by derived words, four per round key.
+pub fn expand_key(key: &[u8]) -> Vec<AesWord> {
+    let nk = key.len() / 4;
+    let nr = num_rounds(nk);
+
+    let mut w: Vec<AesWord> = key
+        .chunks(4)
+        .map(|c| AesWord::from_bytes(c[0], c[1], c[2], c[3]))
+        .collect();
+
+    for i in nk..4 * (nr + 1) {
+        let mut temp = w[i - 1];
+        if i % nk == 0 {
+            temp = AesUtil::schedule_core(temp, i / nk);
+        } else if nk > 6 && i % nk == 4 {
+            temp = AesWord::sub_word(temp);
+        }
+        w.push(w[i - nk] + temp);
+    }
+    w
+}
+
+/// The AES-128 key schedule: the cipher key followed by 40 derived words.
+pub fn expand_key_128(key: &[u8; 16]) -> [AesWord; 44] {
+    let mut w = [AesWord::from_bytes(0, 0, 0, 0); 44];
+    w.copy_from_slice(&expand_key(key));
+    w
+}
+
+/// The eleven AES-128 round keys, round key 0 being the cipher key.
+pub fn round_keys_128(key: &[u8; 16]) -> [[u8; 16]; 11] {
+    let w = expand_key_128(key);
+
+    let mut keys = [[0u8; 16]; 11];
+    for (k, words) in keys.iter_mut().zip(w.chunks(4)) {
+        *k = AesUtil::block_from_words(&[words[0], words[1], words[2], words[3]]);
+    }
+    keys
+}
+
+pub fn sbox(b: u8) -> u8 {
+    AesByte(b).sub().0
+}
+
+pub fn inv_sbox(b: u8) -> u8 {
+    AesByte(b).inv_sub().0
+}
+
+/// The 4x4 AES state, one `AesWord` per column. Blocks load column-major:
+/// byte `4 * c + r` sits in row `r` of column `c`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AesState([AesWord; 4]);
+
+impl AesState {
+    pub fn from_block(block: &[u8; 16]) -> Self {
+        AesState(AesUtil::words_from_block(block))
+    }
+
+    pub fn to_block(self) -> [u8; 16] {
+        AesUtil::block_from_words(&self.0)
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> u8 {
+        self.0[col].to_bytes()[row]
+    }
+
+    pub fn sub_bytes(&mut self) {
+        for w in self.0.iter_mut() {
+            *w = AesWord::sub_word(*w);
+        }
+    }
+
+    pub fn inv_sub_bytes(&mut self) {
+        for w in self.0.iter_mut() {
+            let AesWord(a, b, c, d) = *w;
+            *w = AesWord(a.inv_sub(), b.inv_sub(), c.inv_sub(), d.inv_sub());
+        }
+    }
+
+    /// Row `r` rotates left by `r` columns.
+    pub fn shift_rows(&mut self) {
+        let old = *self;
+        self.permute(|r, c| old.get(r, (c + r) % 4));
+    }
+
+    pub fn inv_shift_rows(&mut self) {
+        let old = *self;
+        self.permute(|r, c| old.get(r, (c + 4 - r) % 4));
+    }
+
+    fn permute<F: Fn(usize, usize) -> u8>(&mut self, f: F) {
+        for (c, w) in self.0.iter_mut().enumerate() {
+            *w = AesWord::from_bytes(f(0, c), f(1, c), f(2, c), f(3, c));
+        }
+    }
+
+    /// Multiplies every column by {03}x^3 + {01}x^2 + {01}x + {02}.
+    pub fn mix_columns(&mut self) {
+        self.mul_columns(AesWord::from_bytes(0x02, 0x01, 0x01, 0x03));
+    }
+
+    /// Multiplies every column by {0b}x^3 + {0d}x^2 + {09}x + {0e}, the
+    /// inverse of the `mix_columns` polynomial mod x^4 + 1.
+    pub fn inv_mix_columns(&mut self) {
+        self.mul_columns(AesWord::from_bytes(0x0e, 0x09, 0x0d, 0x0b));
+    }
+
+    fn mul_columns(&mut self, a: AesWord) {
+        for w in self.0.iter_mut() {
+            *w = a * *w;
+        }
+    }
+
+    /// XORs in four consecutive words of the key schedule.
+    pub fn add_round_key(&mut self, key: &[AesWord]) {
+        for (w, k) in self.0.iter_mut().zip(key.iter()) {
+            *w = *w + *k;
+        }
+    }
+}
+
+fn with_state<F: FnOnce(&mut AesState)>(block: &mut [u8; 16], f: F) {
+    let mut state = AesState::from_block(block);
+    f(&mut state);
+    *block = state.to_block();
+}
+
+pub fn sub_bytes(state: &mut [u8; 16]) {
+    with_state(state, AesState::sub_bytes);
+}
+
+pub fn inv_sub_bytes(state: &mut [u8; 16]) {
+    with_state(state, AesState::inv_sub_bytes);
+}
+
+pub fn shift_rows(state: &mut [u8; 16]) {
+    with_state(state, AesState::shift_rows);
+}
+
+pub fn inv_shift_rows(state: &mut [u8; 16]) {
+    with_state(state, AesState::inv_shift_rows);
+}
+
+pub fn mix_columns(state: &mut [u8; 16]) {
+    with_state(state, AesState::mix_columns);
+}
+
+pub fn inv_mix_columns(state: &mut [u8; 16]) {
+    with_state(state, AesState::inv_mix_columns);
+}
+
+fn word_to_u32(w: AesWord) -> u32 {
+    u32::from_be_bytes(w.to_bytes())
+}
+
+fn u32_to_word(x: u32) -> AesWord {
+    let b = x.to_be_bytes();
+    AesWord::from_bytes(b[0], b[1], b[2], b[3])
+}
+
+/// `t[0][x]` is the MixColumns column of S(x) placed in row 0, i.e.
+/// `m * S(x)` for the column polynomial `m`; `t[r]` is the same column
+/// rotated down `r` rows.
+fn build_ttables(m: AesWord, sub: fn(u8) -> u8) -> [[u32; 256]; 4] {
+    let mut t = [[0u32; 256]; 4];
+    for x in 0..256 {
+        let s = sub(x as u8);
+        let col = word_to_u32(m * AesWord::from_bytes(s, 0, 0, 0));
+        for (r, table) in t.iter_mut().enumerate() {
+            table[x] = col.rotate_right(8 * r as u32);
+        }
+    }
+    t
+}
+
+fn enc_ttables() -> &'static [[u32; 256]; 4] {
+    static TABLES: OnceLock<[[u32; 256]; 4]> = OnceLock::new();
+    TABLES.get_or_init(|| build_ttables(AesWord::from_bytes(0x02, 0x01, 0x01, 0x03), sbox))
+}
+
+fn dec_ttables() -> &'static [[u32; 256]; 4] {
+    static TABLES: OnceLock<[[u32; 256]; 4]> = OnceLock::new();
+    TABLES.get_or_init(|| build_ttables(AesWord::from_bytes(0x0e, 0x09, 0x0d, 0x0b), inv_sbox))
+}
+
+fn load_columns(block: &[u8; 16]) -> [u32; 4] {
+    let mut s = [0u32; 4];
+    for (c, chunk) in s.iter_mut().zip(block.chunks(4)) {
+        *c = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    s
+}
+
+fn store_columns(s: &[u32; 4]) -> [u8; 16] {
+    let mut block = [0u8; 16];
+    for (chunk, c) in block.chunks_mut(4).zip(s.iter()) {
+        chunk.copy_from_slice(&c.to_be_bytes());
+    }
+    block
+}
+
+fn byte(x: u32, row: usize) -> usize {
+    (x >> (24 - 8 * row)) as usize & 0xff
+}
+
+/// One table-driven round. `shift` picks the source column for each row:
+/// +r for ShiftRows, -r for InvShiftRows. `observe` sees every lookup as
+/// `(table, index)`.
+fn ttable_round<F: FnMut(usize, u8)>(
+    s: &[u32; 4],
+    t: &[[u32; 256]; 4],
+    rk: &[u32],
+    shift: fn(usize, usize) -> usize,
+    observe: &mut F,
+) -> [u32; 4] {
+    let mut out = [0u32; 4];
+    for (c, o) in out.iter_mut().enumerate() {
+        *o = rk[c];
+        for (r, table) in t.iter().enumerate() {
+            let i = byte(s[shift(c, r)], r);
+            observe(r, i as u8);
+            *o ^= table[i];
+        }
+    }
+    out
+}
+
+fn final_round(
+    s: &[u32; 4],
+    sub: fn(u8) -> u8,
+    rk: &[u32],
+    shift: fn(usize, usize) -> usize,
+) -> [u32; 4] {
+    let mut out = [0u32; 4];
+    for (c, o) in out.iter_mut().enumerate() {
+        let b = |r: usize| sub(byte(s[shift(c, r)], r) as u8);
+        *o = u32::from_be_bytes([b(0), b(1), b(2), b(3)]) ^ rk[c];
+    }
+    out
+}
+
+fn shift_left(c: usize, r: usize) -> usize {
+    (c + r) % 4
+}
+
+fn shift_right(c: usize, r: usize) -> usize {
+    (c + 4 - r) % 4
+}
+
+fn ttable_encrypt(block: &[u8; 16], w: &[AesWord], nr: usize) -> [u8; 16] {
+    ttable_encrypt_observed(block, w, nr, |_, _, _| {})
+}
+
+fn ttable_encrypt_observed<F: FnMut(usize, usize, u8)>(
+    block: &[u8; 16],
+    w: &[AesWord],
+    nr: usize,
+    mut observe: F,
+) -> [u8; 16] {
+    let rk: Vec<u32> = w.iter().map(|w| word_to_u32(*w)).collect();
+    let t = enc_ttables();
+
+    let mut s = load_columns(block);
+    for (c, k) in s.iter_mut().zip(rk.iter()) {
+        *c ^= k;
+    }
+    for round in 1..nr {
+        let mut observe = |table, index| observe(round, table, index);
+        s = ttable_round(&s, t, &rk[4 * round..], shift_left, &mut observe);
+    }
+    store_columns(&final_round(&s, sbox, &rk[4 * nr..], shift_left))
+}
+
+/// T-table encryption that reports every lookup into the four round tables
+/// as `(round, table, index)`, rounds counting from 1. What a cache side
+/// channel gets to see, minus the noise.
+pub fn ttable_encrypt_traced<F: FnMut(usize, usize, u8)>(
+    block: &[u8; 16],
+    key: &[u8],
+    observe: F,
+) -> [u8; 16] {
+    let nr = num_rounds(key.len() / 4);
+    ttable_encrypt_observed(block, &expand_key(key), nr, observe)
+}
+
+/// The FIPS-197 "equivalent inverse cipher" (section 5.3.5): the same
+/// round shape as encryption, which needs InvMixColumns applied to the
+/// middle round keys. InvMixColumns is linear, so moving it ahead of
+/// AddRoundKey only needs the key pushed through it too.
+fn ttable_decrypt(block: &[u8; 16], w: &[AesWord], nr: usize) -> [u8; 16] {
+    let inv_mix = AesWord::from_bytes(0x0e, 0x09, 0x0d, 0x0b);
+    let rk: Vec<u32> = w
+        .iter()
+        .enumerate()
+        .map(|(i, w)| {
+            let round = i / 4;
+            if round == 0 || round == nr {
+                word_to_u32(*w)
+            } else {
+                word_to_u32(inv_mix * *w)
+            }
+        })
+        .collect();
+    let t = dec_ttables();
 
-struct AesEncrypt {
+    let mut s = load_columns(block);
+    for (c, k) in s.iter_mut().zip(rk[4 * nr..].iter()) {
+        *c ^= k;
+    }
+    for round in (1..nr).rev() {
+        s = ttable_round(&s, t, &rk[4 * round..], shift_right, &mut |_, _| {});
+    }
+    store_columns(&final_round(&s, inv_sbox, &rk[..4], shift_right))
+}
+
+/// How `AesEncrypt` computes a round.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AesVariant {
+    /// SubBytes, ShiftRows, MixColumns and AddRoundKey one after another on
+    /// an `AesState`, as written in FIPS-197.
+    RoundFunction,
+    /// Four table lookups per column per round. Much faster, but the lookups
+    /// depend on secret data, so it leaks through the cache.
+    TTable,
+}
+
+pub struct AesEncrypt {
     num_words: u8,
     block_size_bytes: u8,
     num_rounds: u8,
+    variant: AesVariant,
 }
 
 impl AesEncrypt {
-    fn new() -> Self {
-        let num_words = 4;
+    pub fn new() -> Self {
+        Self::with_key_len(16)
+    }
+
+    /// AES for `key_len` byte keys: 16, 24 or 32.
+    pub fn with_key_len(key_len: usize) -> Self {
+        Self::with_variant(key_len, AesVariant::RoundFunction)
+    }
+
+    pub fn with_variant(key_len: usize, variant: AesVariant) -> Self {
+        let num_words = key_len / 4;
         let block_size_bytes = 4;
-        let num_rounds = 10;
+        let num_rounds = num_rounds(num_words) as u8;
 
         Self {
-            num_words,
+            num_words: num_words as u8,
             block_size_bytes,
             num_rounds,
+            variant,
+        }
+    }
+
+    fn expand(&self, key: &[u8]) -> Vec<AesWord> {
+        assert_eq!(key.len(), 4 * self.num_words as usize, "wrong key length");
+        expand_key(key)
+    }
+
+    pub fn encrypt_block(&self, block: &[u8; 16], key: &[u8]) -> [u8; 16] {
+        self.encrypt_with_schedule(block, &self.expand(key))
+    }
+
+    pub fn decrypt_block(&self, block: &[u8; 16], key: &[u8]) -> [u8; 16] {
+        self.decrypt_with_schedule(block, &self.expand(key))
+    }
+
+    /// `encrypt_block` with the key already expanded by `expand_key`, for
+    /// encrypting many blocks under one key.
+    pub fn encrypt_with_schedule(&self, block: &[u8; 16], w: &[AesWord]) -> [u8; 16] {
+        assert_eq!(
+            w.len(),
+            4 * (self.num_rounds as usize + 1),
+            "wrong key schedule"
+        );
+        match self.variant {
+            AesVariant::RoundFunction => self.encrypt_rounds(block, w),
+            AesVariant::TTable => ttable_encrypt(block, w, self.num_rounds as usize),
+        }
+    }
+
+    pub fn decrypt_with_schedule(&self, block: &[u8; 16], w: &[AesWord]) -> [u8; 16] {
+        assert_eq!(
+            w.len(),
+            4 * (self.num_rounds as usize + 1),
+            "wrong key schedule"
+        );
+        match self.variant {
+            AesVariant::RoundFunction => self.decrypt_rounds(block, w),
+            AesVariant::TTable => ttable_decrypt(block, w, self.num_rounds as usize),
         }
     }
+
+    /// The FIPS-197 cipher: an initial AddRoundKey, `num_rounds - 1` full
+    /// rounds and a final round without MixColumns.
+    fn encrypt_rounds(&self, block: &[u8; 16], w: &[AesWord]) -> [u8; 16] {
+        let nb = self.block_size_bytes as usize;
+        let nr = self.num_rounds as usize;
+
+        let mut state = AesState::from_block(block);
+        state.add_round_key(&w[..nb]);
+
+        for round in 1..nr {
+            state.sub_bytes();
+            state.shift_rows();
+            state.mix_columns();
+            state.add_round_key(&w[round * nb..(round + 1) * nb]);
+        }
+
+        state.sub_bytes();
+        state.shift_rows();
+        state.add_round_key(&w[nr * nb..(nr + 1) * nb]);
+        state.to_block()
+    }
+
+    /// The FIPS-197 inverse cipher: `encrypt_rounds` with every step
+    /// inverted and the round keys applied last to first.
+    fn decrypt_rounds(&self, block: &[u8; 16], w: &[AesWord]) -> [u8; 16] {
+        let nb = self.block_size_bytes as usize;
+        let nr = self.num_rounds as usize;
+
+        let mut state = AesState::from_block(block);
+        state.add_round_key(&w[nr * nb..(nr + 1) * nb]);
+
+        for round in (1..nr).rev() {
+            state.inv_shift_rows();
+            state.inv_sub_bytes();
+            state.add_round_key(&w[round * nb..(round + 1) * nb]);
+            state.inv_mix_columns();
+        }
+
+        state.inv_shift_rows();
+        state.inv_sub_bytes();
+        state.add_round_key(&w[..nb]);
+        state.to_block()
+    }
+}
+
+impl Default for AesEncrypt {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_helpers::*;
 
     #[test]
     fn test_add_aes_byte() {
@@ -158,6 +760,388 @@ mod tests {
         assert_eq!(AesByte(0xfe), AesByte(0x57) * AesByte(0x13))
     }
 
+    #[test]
+    fn test_field_arithmetic() {
+        let g = AesByte::new(0x03);
+        assert_eq!(g.pow(0), AesByte(1));
+        assert_eq!(g.pow(1), g);
+        assert_eq!(g.pow(255), AesByte(1));
+        assert_eq!(AesByte(0).pow(0), AesByte(1));
+        assert_eq!(AesByte(0).checked_inverse(), None);
+
+        // 0x03 generates the multiplicative group.
+        let mut seen: Vec<u8> = (0..255).map(|e| g.pow(e).value()).collect();
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen.len(), 255);
+
+        for a in 0..=255u8 {
+            for b in 1..=255u8 {
+                let (a, b) = (AesByte(a), AesByte(b));
+                assert_eq!(a / b * b, a);
+                assert_eq!(a - b + b, a);
+            }
+        }
+        assert_eq!(AesByte(0xfe) / AesByte(0x13), AesByte(0x57));
+    }
+
+    #[test]
+    #[should_panic(expected = "division by zero")]
+    fn test_div_by_zero() {
+        let _ = AesByte(0x57) / AesByte(0);
+    }
+
+    #[test]
+    fn test_sub_aes_byte() {
+        assert_eq!(AesByte(0x63), AesByte(0x00).sub());
+        assert_eq!(AesByte(0xed), AesByte(0x53).sub());
+    }
+
+    // FIPS-197 figure 7.
+    #[rustfmt::skip]
+    const SBOX: [u8; 256] = [
+        0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+        0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+        0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+        0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+        0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+        0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+        0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+        0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+        0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+        0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+        0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+        0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+        0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+        0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+        0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+        0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+    ];
+
+    #[test]
+    fn test_sbox_from_inverse() {
+        assert_eq!(AesByte(0x53).inverse(), AesByte(0xca));
+        assert_eq!(AesByte(0x00).inverse(), AesByte(0x00));
+
+        for b in 0..=255u8 {
+            assert_eq!(sbox(b), SBOX[b as usize], "{:#04x}", b);
+            assert_eq!(inv_sbox(sbox(b)), b);
+            if b != 0 {
+                assert_eq!(AesByte(b) * AesByte(b).inverse(), AesByte(1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_sub_bytes() {
+        // FIPS-197 appendix B, round 1
+        let mut state = block("193de3bea0f4e22b9ac68d2ae9f84808");
+        sub_bytes(&mut state);
+        assert_eq!(state, block("d42711aee0bf98f1b8b45de51e415230"));
+
+        inv_sub_bytes(&mut state);
+        assert_eq!(state, block("193de3bea0f4e22b9ac68d2ae9f84808"));
+    }
+
+    #[test]
+    fn test_key_schedule_round_keys() {
+        // FIPS-197 appendix A.1
+        let key = AesUtil::words_from_block(&block("2b7e151628aed2a6abf7158809cf4f3c"));
+
+        let round1 = AesUtil::next_round_key(key, 1);
+        assert_eq!(
+            AesUtil::block_from_words(&round1),
+            block("a0fafe1788542cb123a339392a6c7605")
+        );
+        assert_eq!(AesUtil::prev_round_key(round1, 1), key);
+    }
+
+    #[test]
+    fn test_recover_key_from_round_key() {
+        let key = block("2b7e151628aed2a6abf7158809cf4f3c");
+
+        assert_eq!(key, recover_key_from_round_key(&key, 0));
+        assert_eq!(
+            key,
+            recover_key_from_round_key(&block("a0fafe1788542cb123a339392a6c7605"), 1)
+        );
+        assert_eq!(
+            key,
+            recover_key_from_round_key(&block("d014f9a8c9ee2589e13f0cc8b6630ca6"), 10)
+        );
+    }
+
+    #[test]
+    fn test_expand_key_128() {
+        // FIPS-197 appendix A.1
+        let w = expand_key_128(&block("2b7e151628aed2a6abf7158809cf4f3c"));
+        let word = |hex: &str| {
+            let b = crate::raw_bytes::RawBytes::from_hex(hex).bytes;
+            AesWord::from_bytes(b[0], b[1], b[2], b[3])
+        };
+
+        let expected = [
+            (0, "2b7e1516"),
+            (3, "09cf4f3c"),
+            (4, "a0fafe17"),
+            (5, "88542cb1"),
+            (9, "7a96b943"),
+            (20, "d4d1c6f8"),
+            (31, "4ea6dc4f"),
+            (40, "d014f9a8"),
+            (43, "b6630ca6"),
+        ];
+        for (i, hex) in expected.iter() {
+            assert_eq!(w[*i], word(hex), "w[{}]", i);
+        }
+    }
+
+    #[test]
+    fn test_expand_key_192_256() {
+        // FIPS-197 appendix A.2 and A.3
+        let vectors = [
+            (
+                "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+                "fe0c91f7",
+                "01002202",
+                52,
+            ),
+            (
+                "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+                "9ba35411",
+                "706c631e",
+                60,
+            ),
+        ];
+        for (key, first, last, len) in vectors.iter() {
+            let key = crate::raw_bytes::RawBytes::from_hex(key).bytes;
+            let nk = key.len() / 4;
+            let w = expand_key(&key);
+
+            assert_eq!(w.len(), *len);
+            assert_eq!(hex(&w[nk].to_bytes()), *first);
+            assert_eq!(hex(&w[len - 1].to_bytes()), *last);
+        }
+    }
+
+    #[test]
+    fn test_round_keys_128() {
+        let keys = round_keys_128(&block("2b7e151628aed2a6abf7158809cf4f3c"));
+
+        assert_eq!(keys[1], block("a0fafe1788542cb123a339392a6c7605"));
+        assert_eq!(keys[10], block("d014f9a8c9ee2589e13f0cc8b6630ca6"));
+    }
+
+    #[test]
+    fn test_shift_rows_mix_columns() {
+        // FIPS-197 appendix B, round 1
+        let mut state = block("d42711aee0bf98f1b8b45de51e415230");
+        shift_rows(&mut state);
+        assert_eq!(state, block("d4bf5d30e0b452aeb84111f11e2798e5"));
+
+        mix_columns(&mut state);
+        assert_eq!(state, block("046681e5e0cb199a48f8d37a2806264c"));
+
+        let mut state = block("d4bf5d30e0b452aeb84111f11e2798e5");
+        inv_shift_rows(&mut state);
+        assert_eq!(state, block("d42711aee0bf98f1b8b45de51e415230"));
+    }
+
+    #[test]
+    fn test_aes_state_rounds() {
+        // FIPS-197 appendix B: the state at the start of round 1, then
+        // after SubBytes, ShiftRows, MixColumns and AddRoundKey for rounds
+        // 1 and 2.
+        let w = expand_key_128(&block("2b7e151628aed2a6abf7158809cf4f3c"));
+        let rounds = [
+            [
+                "193de3bea0f4e22b9ac68d2ae9f84808",
+                "d42711aee0bf98f1b8b45de51e415230",
+                "d4bf5d30e0b452aeb84111f11e2798e5",
+                "046681e5e0cb199a48f8d37a2806264c",
+                "a49c7ff2689f352b6b5bea43026a5049",
+            ],
+            [
+                "a49c7ff2689f352b6b5bea43026a5049",
+                "49ded28945db96f17f39871a7702533b",
+                "49db873b453953897f02d2f177de961a",
+                "584dcaf11b4b5aacdbe7caa81b6bb0e5",
+                "aa8f5f0361dde3ef82d24ad26832469a",
+            ],
+        ];
+
+        for (r, hex) in rounds.iter().enumerate() {
+            let round = r + 1;
+            let mut state = AesState::from_block(&block(hex[0]));
+            assert_eq!(state.get(1, 0), block(hex[0])[1]);
+            assert_eq!(state.get(0, 1), block(hex[0])[4]);
+
+            let steps: [fn(&mut AesState); 3] = [
+                AesState::sub_bytes,
+                AesState::shift_rows,
+                AesState::mix_columns,
+            ];
+            let inverses: [fn(&mut AesState); 3] = [
+                AesState::inv_sub_bytes,
+                AesState::inv_shift_rows,
+                AesState::inv_mix_columns,
+            ];
+            for (i, (step, inv)) in steps.iter().zip(inverses.iter()).enumerate() {
+                step(&mut state);
+                assert_eq!(
+                    state.to_block(),
+                    block(hex[i + 1]),
+                    "round {} step {}",
+                    round,
+                    i
+                );
+
+                let mut undone = state;
+                inv(&mut undone);
+                assert_eq!(undone.to_block(), block(hex[i]));
+            }
+
+            state.add_round_key(&w[4 * round..4 * round + 4]);
+            assert_eq!(state.to_block(), block(hex[4]));
+        }
+    }
+
+    #[test]
+    fn test_encrypt_block() {
+        let aes = AesEncrypt::new();
+
+        // FIPS-197 appendix B and appendix C.1
+        let vectors = [
+            (
+                "3243f6a8885a308d313198a2e0370734",
+                "2b7e151628aed2a6abf7158809cf4f3c",
+                "3925841d02dc09fbdc118597196a0b32",
+            ),
+            (
+                "00112233445566778899aabbccddeeff",
+                "000102030405060708090a0b0c0d0e0f",
+                "69c4e0d86a7b0430d8cdb78070b4c55a",
+            ),
+        ];
+        for (pt, key, ct) in vectors.iter() {
+            assert_eq!(aes.encrypt_block(&block(pt), &block(key)), block(ct));
+            assert_eq!(aes.decrypt_block(&block(ct), &block(key)), block(pt));
+        }
+    }
+
+    #[test]
+    fn test_encrypt_block_all_key_sizes() {
+        // FIPS-197 appendix C
+        let pt = block("00112233445566778899aabbccddeeff");
+        let vectors = [
+            (
+                "000102030405060708090a0b0c0d0e0f",
+                "69c4e0d86a7b0430d8cdb78070b4c55a",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f1011121314151617",
+                "dda97ca4864cdfe06eaf70a0ec0d7191",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "8ea2b7ca516745bfeafc49904b496089",
+            ),
+        ];
+        for (key, ct) in vectors.iter() {
+            let key = crate::raw_bytes::RawBytes::from_hex(key).bytes;
+            let aes = AesEncrypt::with_key_len(key.len());
+
+            assert_eq!(aes.encrypt_block(&pt, &key), block(ct));
+            assert_eq!(aes.decrypt_block(&block(ct), &key), pt);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_encrypt_block_wrong_key_len() {
+        AesEncrypt::new().encrypt_block(&[0u8; 16], &[0u8; 24]);
+    }
+
+    #[test]
+    fn test_ttable_variant() {
+        use rand::prelude::*;
+
+        let mut rng = StdRng::seed_from_u64(563);
+        for key_len in [16, 24, 32] {
+            let reference = AesEncrypt::with_key_len(key_len);
+            let ttable = AesEncrypt::with_variant(key_len, AesVariant::TTable);
+
+            for _ in 0..50 {
+                let (mut key, mut pt) = (vec![0u8; key_len], [0u8; 16]);
+                rng.fill_bytes(&mut key);
+                rng.fill_bytes(&mut pt);
+
+                let ct = reference.encrypt_block(&pt, &key);
+                assert_eq!(ttable.encrypt_block(&pt, &key), ct);
+                assert_eq!(ttable.decrypt_block(&ct, &key), pt);
+            }
+        }
+
+        // FIPS-197 appendix B
+        let ttable = AesEncrypt::with_variant(16, AesVariant::TTable);
+        let ct = ttable.encrypt_block(
+            &block("3243f6a8885a308d313198a2e0370734"),
+            &block("2b7e151628aed2a6abf7158809cf4f3c"),
+        );
+        assert_eq!(ct, block("3925841d02dc09fbdc118597196a0b32"));
+    }
+
+    #[test]
+    fn test_equivalent_inverse_cipher() {
+        // FIPS-197 appendix C.1, decrypted by the T-table path.
+        let key = block("000102030405060708090a0b0c0d0e0f");
+        let aes = AesEncrypt::with_variant(16, AesVariant::TTable);
+        let ct = block("69c4e0d86a7b0430d8cdb78070b4c55a");
+        let pt = block("00112233445566778899aabbccddeeff");
+        assert_eq!(aes.decrypt_block(&ct, &key), pt);
+        assert_eq!(aes.encrypt_block(&pt, &key), ct);
+    }
+
+    #[test]
+    fn test_decrypt_block_round_trip() {
+        use rand::prelude::*;
+
+        let aes = AesEncrypt::new();
+        let mut rng = StdRng::seed_from_u64(560);
+        for _ in 0..200 {
+            let (mut key, mut pt) = ([0u8; 16], [0u8; 16]);
+            rng.fill_bytes(&mut key);
+            rng.fill_bytes(&mut pt);
+
+            let ct = aes.encrypt_block(&pt, &key);
+            assert_ne!(ct, pt);
+            assert_eq!(aes.decrypt_block(&ct, &key), pt);
+            assert_eq!(aes.encrypt_block(&aes.decrypt_block(&pt, &key), &key), pt);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn test_encrypt_block_matches_openssl() {
+        use openssl::symm::{Cipher, Crypter, Mode};
+        use rand::prelude::*;
+
+        let aes = AesEncrypt::new();
+        let mut rng = StdRng::seed_from_u64(559);
+        for _ in 0..50 {
+            let (mut key, mut pt) = ([0u8; 16], [0u8; 16]);
+            rng.fill_bytes(&mut key);
+            rng.fill_bytes(&mut pt);
+
+            let mut c = Crypter::new(Cipher::aes_128_ecb(), Mode::Encrypt, &key, None).unwrap();
+            c.pad(false);
+            let mut ct = vec![0u8; 32];
+            let n = c.update(&pt, &mut ct).unwrap();
+
+            assert_eq!(aes.encrypt_block(&pt, &key)[..], ct[..n]);
+        }
+    }
+
     #[test]
     fn test_mul_aes_word() {
         let c = AesWord::from_bytes;
@@ -0,0 +1,297 @@
+#![allow(dead_code)]
+
+/// A block in bitsliced form: bit `i` of plane `j` is bit `j` of byte `i`,
+/// bytes in the usual column-major order. Every operation is a fixed
+/// sequence of AND, XOR and shifts on the planes, so nothing depends on
+/// the data: no table lookups and no secret branches.
+type Planes = [u16; 8];
+
+const ALL: u16 = 0xffff;
+
+fn to_planes(block: &[u8; 16]) -> Planes {
+    let mut p = [0u16; 8];
+    for (i, b) in block.iter().enumerate() {
+        for (j, plane) in p.iter_mut().enumerate() {
+            *plane |= (((*b >> j) & 1) as u16) << i;
+        }
+    }
+    p
+}
+
+fn from_planes(p: &Planes) -> [u8; 16] {
+    let mut block = [0u8; 16];
+    for (i, b) in block.iter_mut().enumerate() {
+        for (j, plane) in p.iter().enumerate() {
+            *b |= (((plane >> i) & 1) as u8) << j;
+        }
+    }
+    block
+}
+
+fn xor(a: &Planes, b: &Planes) -> Planes {
+    let mut out = *a;
+    for (o, b) in out.iter_mut().zip(b.iter()) {
+        *o ^= b;
+    }
+    out
+}
+
+/// Byte-wise GF(2^8) product modulo x^8 + x^4 + x^3 + x + 1.
+fn gf_mul(a: &Planes, b: &Planes) -> Planes {
+    let mut prod = [0u16; 15];
+    for (i, a) in a.iter().enumerate() {
+        for (j, b) in b.iter().enumerate() {
+            prod[i + j] ^= a & b;
+        }
+    }
+    for k in (8..15).rev() {
+        let hi = prod[k];
+        prod[k - 8] ^= hi;
+        prod[k - 7] ^= hi;
+        prod[k - 5] ^= hi;
+        prod[k - 4] ^= hi;
+    }
+
+    let mut out = [0u16; 8];
+    out.copy_from_slice(&prod[..8]);
+    out
+}
+
+/// `a^254`, the multiplicative inverse with 0 mapped to 0. The exponent is
+/// public, so square-and-multiply runs the same steps for every input.
+fn gf_inv(a: &Planes) -> Planes {
+    let mut ans = [ALL, 0, 0, 0, 0, 0, 0, 0];
+    for bit in (0..8).rev() {
+        ans = gf_mul(&ans, &ans);
+        if (254 >> bit) & 1 == 1 {
+            ans = gf_mul(&ans, a);
+        }
+    }
+    ans
+}
+
+fn xtime(a: &Planes) -> Planes {
+    [
+        a[7],
+        a[0] ^ a[7],
+        a[1],
+        a[2] ^ a[7],
+        a[3] ^ a[7],
+        a[4],
+        a[5],
+        a[6],
+    ]
+}
+
+/// Multiplies every byte by the public constant `c`.
+fn mul_const(a: &Planes, c: u8) -> Planes {
+    let mut ans = [0u16; 8];
+    let mut power = *a;
+    for bit in 0..8 {
+        if (c >> bit) & 1 == 1 {
+            ans = xor(&ans, &power);
+        }
+        power = xtime(&power);
+    }
+    ans
+}
+
+fn constant(c: u8) -> Planes {
+    let mut p = [0u16; 8];
+    for (j, plane) in p.iter_mut().enumerate() {
+        if (c >> j) & 1 == 1 {
+            *plane = ALL;
+        }
+    }
+    p
+}
+
+fn sub_bytes(a: &Planes) -> Planes {
+    let inv = gf_inv(a);
+    let mut out = [0u16; 8];
+    for (i, o) in out.iter_mut().enumerate() {
+        *o = inv[i] ^ inv[(i + 4) % 8] ^ inv[(i + 5) % 8] ^ inv[(i + 6) % 8] ^ inv[(i + 7) % 8];
+    }
+    xor(&out, &constant(0x63))
+}
+
+fn inv_sub_bytes(a: &Planes) -> Planes {
+    let mut out = [0u16; 8];
+    for (i, o) in out.iter_mut().enumerate() {
+        *o = a[(i + 2) % 8] ^ a[(i + 5) % 8] ^ a[(i + 7) % 8];
+    }
+    gf_inv(&xor(&out, &constant(0x05)))
+}
+
+/// Moves bytes around: byte `i` of the result is byte `src(i)` of `a`.
+fn permute<F: Fn(usize) -> usize>(a: &Planes, src: F) -> Planes {
+    let mut out = [0u16; 8];
+    for (o, plane) in out.iter_mut().zip(a.iter()) {
+        for i in 0..16 {
+            *o |= ((plane >> src(i)) & 1) << i;
+        }
+    }
+    out
+}
+
+fn shift_rows(a: &Planes) -> Planes {
+    permute(a, |i| {
+        let (c, r) = (i / 4, i % 4);
+        4 * ((c + r) % 4) + r
+    })
+}
+
+fn inv_shift_rows(a: &Planes) -> Planes {
+    permute(a, |i| {
+        let (c, r) = (i / 4, i % 4);
+        4 * ((c + 4 - r) % 4) + r
+    })
+}
+
+/// Every column rotated up `k` rows: row `r` gets row `r + k`.
+fn rotate_rows(a: &Planes, k: usize) -> Planes {
+    permute(a, |i| 4 * (i / 4) + (i % 4 + k) % 4)
+}
+
+fn mix_columns(a: &Planes) -> Planes {
+    let (a1, a2, a3) = (rotate_rows(a, 1), rotate_rows(a, 2), rotate_rows(a, 3));
+    let mut out = xor(&xtime(a), &xtime(&a1));
+    for p in [a1, a2, a3].iter() {
+        out = xor(&out, p);
+    }
+    out
+}
+
+fn inv_mix_columns(a: &Planes) -> Planes {
+    let mut out = mul_const(a, 0x0e);
+    for (k, c) in [(1, 0x0b), (2, 0x0d), (3, 0x09)].iter() {
+        out = xor(&out, &mul_const(&rotate_rows(a, *k), *c));
+    }
+    out
+}
+
+/// Constant-time AES-128 with no table lookups anywhere, the key schedule
+/// included. Much slower than the other implementations; it is the
+/// reference for the timing-attack challenges, where the leak under test
+/// has to come from the oracle rather than the cipher.
+pub struct BitslicedAes128 {
+    round_keys: [Planes; 11],
+}
+
+impl BitslicedAes128 {
+    pub fn new(key: &[u8; 16]) -> Self {
+        const RCON: [u8; 11] = [
+            0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36,
+        ];
+
+        let mut w: Vec<[u8; 4]> = key.chunks(4).map(|c| [c[0], c[1], c[2], c[3]]).collect();
+        for i in 4..44 {
+            let mut temp = w[i - 1];
+            if i % 4 == 0 {
+                temp.rotate_left(1);
+
+                // SubWord through the bitsliced S-box.
+                let mut block = [0u8; 16];
+                block[..4].copy_from_slice(&temp);
+                let sub = from_planes(&sub_bytes(&to_planes(&block)));
+                temp.copy_from_slice(&sub[..4]);
+                temp[0] ^= RCON[i / 4];
+            }
+            let prev = w[i - 4];
+            w.push([
+                prev[0] ^ temp[0],
+                prev[1] ^ temp[1],
+                prev[2] ^ temp[2],
+                prev[3] ^ temp[3],
+            ]);
+        }
+
+        let mut round_keys = [[0u16; 8]; 11];
+        for (rk, words) in round_keys.iter_mut().zip(w.chunks(4)) {
+            let mut block = [0u8; 16];
+            for (chunk, word) in block.chunks_mut(4).zip(words.iter()) {
+                chunk.copy_from_slice(word);
+            }
+            *rk = to_planes(&block);
+        }
+        Self { round_keys }
+    }
+
+    pub fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut s = xor(&to_planes(block), &self.round_keys[0]);
+        for rk in self.round_keys[1..10].iter() {
+            s = xor(&mix_columns(&shift_rows(&sub_bytes(&s))), rk);
+        }
+        s = xor(&shift_rows(&sub_bytes(&s)), &self.round_keys[10]);
+        from_planes(&s)
+    }
+
+    pub fn decrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut s = xor(&to_planes(block), &self.round_keys[10]);
+        for rk in self.round_keys[1..10].iter().rev() {
+            s = inv_mix_columns(&xor(&inv_sub_bytes(&inv_shift_rows(&s)), rk));
+        }
+        s = xor(&inv_sub_bytes(&inv_shift_rows(&s)), &self.round_keys[0]);
+        from_planes(&s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes::{sbox, AesEncrypt};
+    use crate::test_helpers::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_bitsliced_sbox() {
+        for chunk in (0..=255u8).collect::<Vec<u8>>().chunks(16) {
+            let mut b = [0u8; 16];
+            b.copy_from_slice(chunk);
+
+            let sub = from_planes(&sub_bytes(&to_planes(&b)));
+            for (x, s) in b.iter().zip(sub.iter()) {
+                assert_eq!(*s, sbox(*x));
+            }
+            assert_eq!(from_planes(&inv_sub_bytes(&to_planes(&sub))), b);
+        }
+    }
+
+    #[test]
+    fn test_bitsliced_fips_vectors() {
+        // FIPS-197 appendix B and appendix C.1
+        let vectors = [
+            (
+                "3243f6a8885a308d313198a2e0370734",
+                "2b7e151628aed2a6abf7158809cf4f3c",
+                "3925841d02dc09fbdc118597196a0b32",
+            ),
+            (
+                "00112233445566778899aabbccddeeff",
+                "000102030405060708090a0b0c0d0e0f",
+                "69c4e0d86a7b0430d8cdb78070b4c55a",
+            ),
+        ];
+        for (pt, key, ct) in vectors.iter() {
+            let aes = BitslicedAes128::new(&block(key));
+            assert_eq!(aes.encrypt_block(&block(pt)), block(ct));
+            assert_eq!(aes.decrypt_block(&block(ct)), block(pt));
+        }
+    }
+
+    #[test]
+    fn test_bitsliced_matches_reference() {
+        let reference = AesEncrypt::new();
+        let mut rng = StdRng::seed_from_u64(564);
+        for _ in 0..50 {
+            let (mut key, mut pt) = ([0u8; 16], [0u8; 16]);
+            rng.fill_bytes(&mut key);
+            rng.fill_bytes(&mut pt);
+
+            let aes = BitslicedAes128::new(&key);
+            let ct = aes.encrypt_block(&pt);
+            assert_eq!(ct, reference.encrypt_block(&pt, &key));
+            assert_eq!(aes.decrypt_block(&ct), pt);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Known-answer and differential tests for the home-grown AES in `aes`: the
+//! AESAVS ECB vectors (GFSbox, KeySbox, VarKey, VarTxt) for every key size,
+//! both directions, and random keys and blocks checked against OpenSSL.
+use crate::aes::*;
+use crate::raw_bytes::*;
+use crate::test_helpers::*;
+
+/// One `COUNT` entry from an AESAVS `.rsp` file.
+struct KnownAnswer {
+    /// Whether it came from the `[DECRYPT]` section.
+    decrypt: bool,
+    key: Vec<u8>,
+    plaintext: [u8; 16],
+    ciphertext: [u8; 16],
+}
+
+/// Reads the `[ENCRYPT]` and `[DECRYPT]` vectors of an `.rsp` file. An
+/// entry is done once it has both texts, whichever order they come in.
+fn parse_rsp(text: &str) -> Vec<KnownAnswer> {
+    let mut ans = Vec::new();
+    let mut decrypt = None;
+    let (mut key, mut plaintext, mut ciphertext) = (None, None, None);
+
+    for line in text.lines().map(str::trim) {
+        match line {
+            "[ENCRYPT]" => decrypt = Some(false),
+            "[DECRYPT]" => decrypt = Some(true),
+            _ => {}
+        }
+        match line.split_once(" = ") {
+            Some(("KEY", v)) => key = Some(RawBytes::from_hex(v).bytes),
+            Some(("PLAINTEXT", v)) => plaintext = Some(block(v)),
+            Some(("CIPHERTEXT", v)) => ciphertext = Some(block(v)),
+            _ => continue,
+        }
+        if let (Some(p), Some(c)) = (plaintext, ciphertext) {
+            ans.push(KnownAnswer {
+                decrypt: decrypt.expect("vector outside a section"),
+                key: key.take().expect("texts before KEY"),
+                plaintext: p,
+                ciphertext: c,
+            });
+            plaintext = None;
+            ciphertext = None;
+        }
+    }
+    ans
+}
+
+/// Each file with how many vectors its `[ENCRYPT]` section has; the
+/// `[DECRYPT]` section has as many again.
+const AESAVS: [(&str, &str, usize); 12] = [
+    (
+        "ECBGFSbox128",
+        include_str!("../inputs/aesavs/ECBGFSbox128.rsp"),
+        7,
+    ),
+    (
+        "ECBGFSbox192",
+        include_str!("../inputs/aesavs/ECBGFSbox192.rsp"),
+        6,
+    ),
+    (
+        "ECBGFSbox256",
+        include_str!("../inputs/aesavs/ECBGFSbox256.rsp"),
+        5,
+    ),
+    (
+        "ECBKeySbox128",
+        include_str!("../inputs/aesavs/ECBKeySbox128.rsp"),
+        21,
+    ),
+    (
+        "ECBKeySbox192",
+        include_str!("../inputs/aesavs/ECBKeySbox192.rsp"),
+        24,
+    ),
+    (
+        "ECBKeySbox256",
+        include_str!("../inputs/aesavs/ECBKeySbox256.rsp"),
+        16,
+    ),
+    (
+        "ECBVarKey128",
+        include_str!("../inputs/aesavs/ECBVarKey128.rsp"),
+        128,
+    ),
+    (
+        "ECBVarKey192",
+        include_str!("../inputs/aesavs/ECBVarKey192.rsp"),
+        192,
+    ),
+    (
+        "ECBVarKey256",
+        include_str!("../inputs/aesavs/ECBVarKey256.rsp"),
+        256,
+    ),
+    (
+        "ECBVarTxt128",
+        include_str!("../inputs/aesavs/ECBVarTxt128.rsp"),
+        128,
+    ),
+    (
+        "ECBVarTxt192",
+        include_str!("../inputs/aesavs/ECBVarTxt192.rsp"),
+        128,
+    ),
+    (
+        "ECBVarTxt256",
+        include_str!("../inputs/aesavs/ECBVarTxt256.rsp"),
+        128,
+    ),
+];
+
+const VARIANTS: [AesVariant; 2] = [AesVariant::RoundFunction, AesVariant::TTable];
+
+#[test]
+fn test_aesavs_known_answers() {
+    for (name, text, count) in AESAVS.iter() {
+        let vectors = parse_rsp(text);
+        let decrypts = vectors.iter().filter(|v| v.decrypt).count();
+        assert_eq!(
+            vectors.len() - decrypts,
+            *count,
+            "{}: [ENCRYPT] vectors",
+            name
+        );
+        assert_eq!(decrypts, *count, "{}: [DECRYPT] vectors", name);
+
+        for (i, v) in vectors.iter().enumerate() {
+            for variant in VARIANTS.iter() {
+                let aes = AesEncrypt::with_variant(v.key.len(), *variant);
+                if v.decrypt {
+                    let pt = aes.decrypt_block(&v.ciphertext, &v.key);
+                    assert_eq!(pt, v.plaintext, "{} #{} {:?}", name, i, variant);
+                } else {
+                    let ct = aes.encrypt_block(&v.plaintext, &v.key);
+                    assert_eq!(ct, v.ciphertext, "{} #{} {:?}", name, i, variant);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+#[test]
+fn test_random_blocks_match_openssl() {
+    use crate::backend::*;
+    use rand::prelude::*;
+
+    let mut rng = StdRng::seed_from_u64(567);
+    for key_len in [16, 24, 32] {
+        for _ in 0..1000 {
+            let mut key = vec![0u8; key_len];
+            let mut pt = [0u8; 16];
+            rng.fill_bytes(&mut key);
+            rng.fill_bytes(&mut pt);
+
+            let expected = OpensslAes.encrypt_block(&key, &pt).unwrap();
+            for variant in VARIANTS.iter() {
+                let aes = AesEncrypt::with_variant(key_len, *variant);
+                assert_eq!(aes.encrypt_block(&pt, &key), expected, "{:?}", variant);
+                assert_eq!(aes.decrypt_block(&expected, &key), pt, "{:?}", variant);
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+#![allow(dead_code)]
+use crate::aes::*;
+use std::arch::x86_64::*;
+
+/// Blocks handled per pass in bulk mode. AESENC has a latency of several
+/// cycles but issues every cycle, so independent blocks pipeline.
+const LANES: usize = 4;
+
+/// AES on the AES-NI instructions, for any key size. Only constructible on
+/// CPUs that have them; check with `is_available` or use `new`, which
+/// returns `None` elsewhere so callers can fall back to software.
+pub struct AesNi {
+    enc: Vec<__m128i>,
+    /// Round keys for the equivalent inverse cipher: reversed, with
+    /// InvMixColumns applied to all but the first and last.
+    dec: Vec<__m128i>,
+}
+
+impl AesNi {
+    pub fn is_available() -> bool {
+        is_x86_feature_detected!("aes") && is_x86_feature_detected!("sse2")
+    }
+
+    /// Panics on a bad key length, like `expand_key`.
+    pub fn new(key: &[u8]) -> Option<Self> {
+        if !Self::is_available() {
+            return None;
+        }
+
+        let round_keys: Vec<[u8; 16]> = expand_key(key)
+            .chunks(4)
+            .map(|words| {
+                let mut rk = [0u8; 16];
+                for (chunk, w) in rk.chunks_mut(4).zip(words.iter()) {
+                    chunk.copy_from_slice(&w.to_bytes());
+                }
+                rk
+            })
+            .collect();
+        Some(unsafe { Self::load(&round_keys) })
+    }
+
+    #[target_feature(enable = "aes,sse2")]
+    unsafe fn load(round_keys: &[[u8; 16]]) -> Self {
+        let enc: Vec<__m128i> = round_keys
+            .iter()
+            .map(|rk| _mm_loadu_si128(rk.as_ptr() as *const __m128i))
+            .collect();
+
+        let last = enc.len() - 1;
+        let mut dec = vec![enc[last]];
+        dec.extend(enc[1..last].iter().rev().map(|rk| _mm_aesimc_si128(*rk)));
+        dec.push(enc[0]);
+
+        Self { enc, dec }
+    }
+
+    pub fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut out = *block;
+        self.encrypt_blocks(&mut out);
+        out
+    }
+
+    pub fn decrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut out = *block;
+        self.decrypt_blocks(&mut out);
+        out
+    }
+
+    /// Encrypts whole blocks in place. Panics if `data` isn't a multiple of
+    /// the block size.
+    pub fn encrypt_blocks(&self, data: &mut [u8]) {
+        assert!(data.len().is_multiple_of(16), "partial block");
+        unsafe { crypt_blocks(&self.enc, data, true) }
+    }
+
+    pub fn decrypt_blocks(&self, data: &mut [u8]) {
+        assert!(data.len().is_multiple_of(16), "partial block");
+        unsafe { crypt_blocks(&self.dec, data, false) }
+    }
+}
+
+#[target_feature(enable = "aes,sse2")]
+unsafe fn crypt_blocks(round_keys: &[__m128i], data: &mut [u8], encrypt: bool) {
+    let last = round_keys.len() - 1;
+
+    for chunk in data.chunks_mut(16 * LANES) {
+        let n = chunk.len() / 16;
+        let mut s = [_mm_setzero_si128(); LANES];
+        for (i, s) in s.iter_mut().enumerate().take(n) {
+            let p = chunk[16 * i..].as_ptr() as *const __m128i;
+            *s = _mm_xor_si128(_mm_loadu_si128(p), round_keys[0]);
+        }
+
+        for rk in round_keys[1..last].iter() {
+            for s in s.iter_mut().take(n) {
+                *s = if encrypt {
+                    _mm_aesenc_si128(*s, *rk)
+                } else {
+                    _mm_aesdec_si128(*s, *rk)
+                };
+            }
+        }
+
+        for (i, s) in s.iter().enumerate().take(n) {
+            let out = if encrypt {
+                _mm_aesenclast_si128(*s, round_keys[last])
+            } else {
+                _mm_aesdeclast_si128(*s, round_keys[last])
+            };
+            _mm_storeu_si128(chunk[16 * i..].as_mut_ptr() as *mut __m128i, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_bytes::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_aes_ni_fips_vectors() {
+        if !AesNi::is_available() {
+            return;
+        }
+
+        // FIPS-197 appendix C
+        let pt = RawBytes::from_hex("00112233445566778899aabbccddeeff").bytes;
+        let vectors = [
+            (
+                "000102030405060708090a0b0c0d0e0f",
+                "69c4e0d86a7b0430d8cdb78070b4c55a",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f1011121314151617",
+                "dda97ca4864cdfe06eaf70a0ec0d7191",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "8ea2b7ca516745bfeafc49904b496089",
+            ),
+        ];
+        for (key, ct) in vectors.iter() {
+            let aes = AesNi::new(&RawBytes::from_hex(key).bytes).unwrap();
+            let mut data = pt.clone();
+            aes.encrypt_blocks(&mut data);
+            assert_eq!(
+                RawBytes {
+                    bytes: data.clone()
+                }
+                .to_hex(),
+                *ct
+            );
+            aes.decrypt_blocks(&mut data);
+            assert_eq!(data, pt);
+        }
+    }
+
+    #[test]
+    fn test_aes_ni_matches_software() {
+        if !AesNi::is_available() {
+            return;
+        }
+
+        let mut rng = StdRng::seed_from_u64(565);
+        for key_len in [16, 24, 32] {
+            let mut key = vec![0u8; key_len];
+            rng.fill_bytes(&mut key);
+            let aes = AesNi::new(&key).unwrap();
+            let software = AesEncrypt::with_key_len(key_len);
+
+            // Odd block counts exercise the partially filled last pass.
+            let mut data = vec![0u8; 16 * 7];
+            rng.fill_bytes(&mut data);
+            let mut ct = data.clone();
+            aes.encrypt_blocks(&mut ct);
+
+            for (p, c) in data.chunks(16).zip(ct.chunks(16)) {
+                let mut block = [0u8; 16];
+                block.copy_from_slice(p);
+                assert_eq!(&software.encrypt_block(&block, &key)[..], c);
+            }
+            aes.decrypt_blocks(&mut ct);
+            assert_eq!(ct, data);
+        }
+    }
+}
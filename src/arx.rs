@@ -0,0 +1,76 @@
+#![allow(dead_code)]
+
+//! Pieces shared by the add-rotate-XOR stream ciphers, ChaCha20 and
+//! Salsa20: both permute a 4x4 matrix of 32-bit words, add the input back
+//! in, and use the result as a 64-byte keystream block.
+
+/// Reads `N` little-endian words from the start of `bytes`.
+pub fn le_words<const N: usize>(bytes: &[u8]) -> [u32; N] {
+    let mut out = [0u32; N];
+    for (w, chunk) in out.iter_mut().zip(bytes.chunks(4)) {
+        *w = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    out
+}
+
+/// Adds the block's input words back into the permuted state and writes
+/// the sums out little-endian. Without this step the rounds could simply
+/// be run backwards from the output to the key.
+pub fn feed_forward(state: &[u32; 16], input: &[u32; 16]) -> [u8; 64] {
+    let mut out = [0u8; 64];
+    for (i, chunk) in out.chunks_mut(4).enumerate() {
+        chunk.copy_from_slice(&state[i].wrapping_add(input[i]).to_le_bytes());
+    }
+    out
+}
+
+/// A position in a keystream made of 64-byte blocks, with the block it
+/// falls in cached so that short reads don't recompute it.
+#[derive(Debug, Clone)]
+pub struct BlockCursor {
+    pos: u64,
+    block: [u8; 64],
+    block_index: Option<u64>,
+}
+
+impl BlockCursor {
+    pub fn new() -> Self {
+        Self {
+            pos: 0,
+            block: [0; 64],
+            block_index: None,
+        }
+    }
+
+    /// Fills `buf` from the current position, computing block `i` with
+    /// `block_fn(i)` as needed.
+    pub fn fill<F: FnMut(u64) -> [u8; 64]>(&mut self, buf: &mut [u8], mut block_fn: F) {
+        let mut done = 0;
+        while done < buf.len() {
+            let index = self.pos / 64;
+            if self.block_index != Some(index) {
+                self.block = block_fn(index);
+                self.block_index = Some(index);
+            }
+            let offset = (self.pos % 64) as usize;
+            let n = (64 - offset).min(buf.len() - done);
+            buf[done..done + n].copy_from_slice(&self.block[offset..offset + n]);
+            done += n;
+            self.pos += n as u64;
+        }
+    }
+
+    pub fn seek(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+}
+
+impl Default for BlockCursor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
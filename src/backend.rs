@@ -0,0 +1,409 @@
+#![allow(dead_code)]
+use crate::aes::*;
+use crate::aes_bitsliced::*;
+#[cfg(target_arch = "x86_64")]
+use crate::aes_ni::*;
+use std::convert::TryInto;
+
+/// AES under one key, expanded once when the backend made it, so modes
+/// that go a block at a time don't pay for the key schedule each time.
+pub trait KeyedAes: Send + Sync {
+    fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16];
+
+    fn decrypt_block(&self, block: &[u8; 16]) -> [u8; 16];
+
+    /// Encrypts whole blocks in place. Panics on a partial block.
+    /// Override when there's a faster bulk path than one block at a time.
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        assert!(data.len().is_multiple_of(16), "partial block");
+        for chunk in data.chunks_mut(16) {
+            let out = self.encrypt_block(&as_block(chunk));
+            chunk.copy_from_slice(&out);
+        }
+    }
+
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        assert!(data.len().is_multiple_of(16), "partial block");
+        for chunk in data.chunks_mut(16) {
+            let out = self.decrypt_block(&as_block(chunk));
+            chunk.copy_from_slice(&out);
+        }
+    }
+}
+
+/// A raw AES block cipher. Modes and padding are built on top of it, so the
+/// crate works the same with or without OpenSSL.
+pub trait AesBackend: Sync {
+    fn name(&self) -> &'static str;
+
+    /// Expands `key` for use on any number of blocks. Fails if this
+    /// backend doesn't take a key of that length.
+    fn keyed(&self, key: &[u8]) -> anyhow::Result<Box<dyn KeyedAes>>;
+
+    /// One block under a key used once. Anything encrypting more than a
+    /// block under the same key should hold on to `keyed` instead.
+    fn encrypt_block(&self, key: &[u8], block: &[u8; 16]) -> anyhow::Result<[u8; 16]> {
+        Ok(self.keyed(key)?.encrypt_block(block))
+    }
+
+    fn decrypt_block(&self, key: &[u8], block: &[u8; 16]) -> anyhow::Result<[u8; 16]> {
+        Ok(self.keyed(key)?.decrypt_block(block))
+    }
+
+    /// ECB without padding; `data` must be a whole number of blocks.
+    fn ecb_encrypt(&self, key: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        check_blocks(data)?;
+        let mut out = data.to_vec();
+        self.keyed(key)?.encrypt_blocks(&mut out);
+        Ok(out)
+    }
+
+    fn ecb_decrypt(&self, key: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        check_blocks(data)?;
+        let mut out = data.to_vec();
+        self.keyed(key)?.decrypt_blocks(&mut out);
+        Ok(out)
+    }
+}
+
+fn check_key(key: &[u8]) -> anyhow::Result<()> {
+    if ![16, 24, 32].contains(&key.len()) {
+        anyhow::bail!("AES keys are 16, 24 or 32 bytes, not {}", key.len());
+    }
+    Ok(())
+}
+
+fn check_blocks(data: &[u8]) -> anyhow::Result<()> {
+    if !data.len().is_multiple_of(16) {
+        anyhow::bail!("{} bytes is not a whole number of blocks", data.len());
+    }
+    Ok(())
+}
+
+fn as_block(chunk: &[u8]) -> [u8; 16] {
+    let mut block = [0u8; 16];
+    block.copy_from_slice(chunk);
+    block
+}
+
+/// The home-grown AES in `aes.rs`.
+pub struct RustAes;
+
+/// `RustAes` with its key schedule.
+struct RustAesKey {
+    aes: AesEncrypt,
+    schedule: Vec<AesWord>,
+}
+
+impl KeyedAes for RustAesKey {
+    fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        self.aes.encrypt_with_schedule(block, &self.schedule)
+    }
+
+    fn decrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        self.aes.decrypt_with_schedule(block, &self.schedule)
+    }
+}
+
+impl AesBackend for RustAes {
+    fn name(&self) -> &'static str {
+        "rust"
+    }
+
+    fn keyed(&self, key: &[u8]) -> anyhow::Result<Box<dyn KeyedAes>> {
+        check_key(key)?;
+        Ok(Box::new(RustAesKey {
+            aes: AesEncrypt::with_key_len(key.len()),
+            schedule: expand_key(key),
+        }))
+    }
+}
+
+/// `BitslicedAes128`: constant time, but AES-128 only.
+pub struct BitslicedAes;
+
+impl KeyedAes for BitslicedAes128 {
+    fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        BitslicedAes128::encrypt_block(self, block)
+    }
+
+    fn decrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        BitslicedAes128::decrypt_block(self, block)
+    }
+}
+
+impl AesBackend for BitslicedAes {
+    fn name(&self) -> &'static str {
+        "bitsliced"
+    }
+
+    fn keyed(&self, key: &[u8]) -> anyhow::Result<Box<dyn KeyedAes>> {
+        let key: &[u8; 16] = key
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("bitsliced AES only takes 16 byte keys"))?;
+        Ok(Box::new(BitslicedAes128::new(key)))
+    }
+}
+
+/// `AesNi`. Fails on CPUs without AES-NI; `default_backend` only picks it
+/// when the instructions are there.
+#[cfg(target_arch = "x86_64")]
+pub struct AesNiBackend;
+
+#[cfg(target_arch = "x86_64")]
+impl KeyedAes for AesNi {
+    fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        AesNi::encrypt_block(self, block)
+    }
+
+    fn decrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        AesNi::decrypt_block(self, block)
+    }
+
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        AesNi::encrypt_blocks(self, data);
+    }
+
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        AesNi::decrypt_blocks(self, data);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl AesBackend for AesNiBackend {
+    fn name(&self) -> &'static str {
+        "aes-ni"
+    }
+
+    fn keyed(&self, key: &[u8]) -> anyhow::Result<Box<dyn KeyedAes>> {
+        check_key(key)?;
+        let cipher = AesNi::new(key).ok_or_else(|| anyhow::anyhow!("this CPU has no AES-NI"))?;
+        Ok(Box::new(cipher))
+    }
+}
+
+#[cfg(feature = "openssl")]
+pub struct OpensslAes;
+
+/// An OpenSSL ECB context each way, set up once. `Crypter` needs `&mut`
+/// to run, so each sits behind a lock.
+#[cfg(feature = "openssl")]
+struct OpensslAesKey {
+    enc: std::sync::Mutex<openssl::symm::Crypter>,
+    dec: std::sync::Mutex<openssl::symm::Crypter>,
+}
+
+#[cfg(feature = "openssl")]
+impl OpensslAesKey {
+    fn new(key: &[u8]) -> anyhow::Result<Self> {
+        use openssl::symm::{Cipher, Crypter, Mode};
+
+        check_key(key)?;
+        let cipher = match key.len() {
+            16 => Cipher::aes_128_ecb(),
+            24 => Cipher::aes_192_ecb(),
+            _ => Cipher::aes_256_ecb(),
+        };
+        let crypter = |mode| -> anyhow::Result<_> {
+            let mut crypter = Crypter::new(cipher, mode, key, None)?;
+            crypter.pad(false);
+            Ok(std::sync::Mutex::new(crypter))
+        };
+        Ok(Self {
+            enc: crypter(Mode::Encrypt)?,
+            dec: crypter(Mode::Decrypt)?,
+        })
+    }
+
+    /// Without padding, ECB hands back every whole block it's given, so
+    /// the context never holds anything over between calls.
+    fn crypt(crypter: &std::sync::Mutex<openssl::symm::Crypter>, data: &mut [u8]) {
+        assert!(data.len().is_multiple_of(16), "partial block");
+        let mut out = vec![0u8; data.len() + 16];
+        let n = crypter
+            .lock()
+            .unwrap()
+            .update(data, &mut out)
+            .expect("OpenSSL ECB update");
+        assert_eq!(n, data.len());
+        data.copy_from_slice(&out[..n]);
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl KeyedAes for OpensslAesKey {
+    fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut out = *block;
+        self.encrypt_blocks(&mut out);
+        out
+    }
+
+    fn decrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut out = *block;
+        self.decrypt_blocks(&mut out);
+        out
+    }
+
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        Self::crypt(&self.enc, data)
+    }
+
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        Self::crypt(&self.dec, data)
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl AesBackend for OpensslAes {
+    fn name(&self) -> &'static str {
+        "openssl"
+    }
+
+    fn keyed(&self, key: &[u8]) -> anyhow::Result<Box<dyn KeyedAes>> {
+        Ok(Box::new(OpensslAesKey::new(key)?))
+    }
+}
+
+/// AES-NI when the CPU has it, checked at runtime. Otherwise OpenSSL when
+/// the `openssl` feature is on and the pure-Rust AES without it.
+pub fn default_backend() -> &'static dyn AesBackend {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if AesNi::is_available() {
+            return &AesNiBackend;
+        }
+    }
+
+    #[cfg(feature = "openssl")]
+    {
+        &OpensslAes
+    }
+
+    #[cfg(not(feature = "openssl"))]
+    {
+        &RustAes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_bytes::*;
+    use rand::prelude::*;
+
+    fn backends() -> Vec<&'static dyn AesBackend> {
+        let mut ans: Vec<&'static dyn AesBackend> = vec![
+            &RustAes,
+            #[cfg(feature = "openssl")]
+            &OpensslAes,
+        ];
+        #[cfg(target_arch = "x86_64")]
+        {
+            if AesNi::is_available() {
+                ans.push(&AesNiBackend);
+            }
+        }
+        ans
+    }
+
+    #[test]
+    fn test_backends_fips_vectors() {
+        // FIPS-197 appendix C
+        let pt = RawBytes::from_hex("00112233445566778899aabbccddeeff").bytes;
+        let vectors = [
+            (
+                "000102030405060708090a0b0c0d0e0f",
+                "69c4e0d86a7b0430d8cdb78070b4c55a",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f1011121314151617",
+                "dda97ca4864cdfe06eaf70a0ec0d7191",
+            ),
+            (
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "8ea2b7ca516745bfeafc49904b496089",
+            ),
+        ];
+
+        for backend in backends() {
+            for (key, ct) in vectors.iter() {
+                let key = RawBytes::from_hex(key).bytes;
+                let ans = backend.ecb_encrypt(&key, &pt).unwrap();
+                assert_eq!(RawBytes { bytes: ans }.to_hex(), *ct, "{}", backend.name());
+                assert_eq!(
+                    backend
+                        .ecb_decrypt(&key, &RawBytes::from_hex(ct).bytes)
+                        .unwrap(),
+                    pt
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let mut rng = StdRng::seed_from_u64(562);
+        let mut key = [0u8; 24];
+        let mut data = vec![0u8; 16 * 9];
+        rng.fill_bytes(&mut key);
+        rng.fill_bytes(&mut data);
+
+        let expected = RustAes.ecb_encrypt(&key, &data).unwrap();
+        for backend in backends() {
+            assert_eq!(backend.ecb_encrypt(&key, &data).unwrap(), expected);
+            assert_eq!(backend.ecb_decrypt(&key, &expected).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_keyed_cipher_reused() {
+        let mut rng = StdRng::seed_from_u64(565);
+        let mut key = [0u8; 32];
+        let mut data = vec![0u8; 16 * 7];
+        rng.fill_bytes(&mut key);
+        rng.fill_bytes(&mut data);
+
+        let expected = RustAes.ecb_encrypt(&key, &data).unwrap();
+        for backend in backends() {
+            // Block by block and in bulk, both directions, through the
+            // same keyed cipher: nothing carries over between calls.
+            let cipher = backend.keyed(&key).unwrap();
+            for (chunk, want) in data.chunks(16).zip(expected.chunks(16)) {
+                let block: [u8; 16] = chunk.try_into().unwrap();
+                let ct = cipher.encrypt_block(&block);
+                assert_eq!(ct[..], *want, "{}", backend.name());
+                assert_eq!(cipher.decrypt_block(&ct), block);
+            }
+            let mut bulk = data.clone();
+            cipher.encrypt_blocks(&mut bulk);
+            assert_eq!(bulk, expected, "{}", backend.name());
+            cipher.decrypt_blocks(&mut bulk);
+            assert_eq!(bulk, data);
+        }
+    }
+
+    #[test]
+    fn test_bitsliced_backend_agrees() {
+        let mut rng = StdRng::seed_from_u64(564);
+        let mut key = [0u8; 16];
+        let mut data = vec![0u8; 16 * 5];
+        rng.fill_bytes(&mut key);
+        rng.fill_bytes(&mut data);
+
+        let ct = BitslicedAes.ecb_encrypt(&key, &data).unwrap();
+        for backend in backends() {
+            assert_eq!(backend.ecb_encrypt(&key, &data).unwrap(), ct);
+        }
+        assert_eq!(BitslicedAes.ecb_decrypt(&key, &ct).unwrap(), data);
+        assert!(BitslicedAes.ecb_encrypt(&[0u8; 24], &data).is_err());
+    }
+
+    #[test]
+    fn test_backends_reject_bad_input() {
+        for backend in backends() {
+            assert!(backend.ecb_encrypt(&[0u8; 15], &[0u8; 16]).is_err());
+            assert!(backend.ecb_decrypt(&[0u8; 16], &[0u8; 17]).is_err());
+            assert!(backend.ecb_encrypt(&[0u8; 16], &[]).unwrap().is_empty());
+        }
+    }
+}
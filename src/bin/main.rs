@@ -1,5 +1,38 @@
+use cryptopals::challenges::*;
+use cryptopals::report::*;
+use std::path::Path;
 
-pub fn main() {
-    println!("Hello world !!!");
+fn report(path: &str) -> anyhow::Result<()> {
+    let report = run_report(&registry());
 
+    for c in report.challenges.iter() {
+        println!(
+            "{:>2} {:<32} {:>4} {:>10.1}ms {:>8} queries {:>10} bytes",
+            c.number,
+            c.name,
+            if c.passed { "ok" } else { "FAIL" },
+            c.wall_time_ms,
+            c.oracle_queries,
+            c.bytes_processed
+        );
+    }
+    report.write(Path::new(path))?;
+    println!("wrote {}", path);
+
+    if !report.all_passed() {
+        anyhow::bail!("some challenges failed");
+    }
+    Ok(())
+}
+
+pub fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(|s| s.as_str()) {
+        Some("report") => report(args.get(1).map_or(DEFAULT_REPORT_PATH, |s| s.as_str())),
+        _ => {
+            println!("usage: cryptopals report [path]");
+            Ok(())
+        }
+    }
 }
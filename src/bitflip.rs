@@ -0,0 +1,199 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::iv::*;
+use crate::kv::*;
+use crate::modes::*;
+use crate::oracles::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+use rand::prelude::*;
+
+pub const COMMENT_PREFIX: &str = "comment1=cooking%20MCs;userdata=";
+pub const COMMENT_SUFFIX: &str = ";comment2=%20like%20a%20pound%20of%20bacon";
+/// What the bitflipping attacks splice into the decrypted comment string.
+pub const ADMIN_TARGET: &[u8] = b";admin=true;";
+
+/// `userdata` between the two comments, escaped. `COMMENT_PREFIX` and
+/// `COMMENT_SUFFIX` are what ends up around it.
+pub fn comment_string(userdata: &str) -> String {
+    COOKIE.serialize(&[
+        ("comment1", "cooking MCs"),
+        ("userdata", userdata),
+        ("comment2", " like a pound of bacon"),
+    ])
+}
+
+/// Whether some field of `plaintext` is `admin=true`. Other fields may be
+/// garbage.
+pub fn has_admin(plaintext: &[u8]) -> bool {
+    COOKIE
+        .parse_lenient(plaintext)
+        .iter()
+        .any(|(k, v)| *k == b"admin" && *v == b"true")
+}
+
+/// The challenge 16 server: encrypts comment strings around user data
+/// under AES-128-CBC with a key and IV fixed at startup.
+pub struct CbcCommentOracle {
+    aes: Aes,
+    iv: Iv,
+}
+
+impl CbcCommentOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        Self {
+            aes: Aes::new(&key).expect("16 byte key"),
+            iv: Iv::random(rng, 16),
+        }
+    }
+
+    pub fn encrypt(&self, userdata: &str) -> Vec<u8> {
+        let pt = RawBytes::from_str(&comment_string(userdata));
+        cbc_encrypt_with_padding(&self.aes, &self.iv, &pt, &Pkcs7)
+            .expect("padded")
+            .bytes
+    }
+
+    /// Decrypts `ct` and checks for `admin=true`. Fails on bad padding.
+    pub fn is_admin(&self, ct: &[u8]) -> anyhow::Result<bool> {
+        let ct = RawBytes { bytes: ct.to_vec() };
+        let pt = cbc_decrypt_with_padding(&self.aes, &self.iv, &ct, &Pkcs7)?;
+        Ok(has_admin(&pt.bytes))
+    }
+}
+
+/// User data that isn't UTF-8 is read the way a lenient web form would.
+impl EncryptionOracle for &CbcCommentOracle {
+    fn encrypt(&mut self, userdata: &[u8]) -> Vec<u8> {
+        CbcCommentOracle::encrypt(self, &String::from_utf8_lossy(userdata))
+    }
+}
+
+impl DecryptionOracle<bool> for &CbcCommentOracle {
+    fn decrypt(&mut self, ct: &[u8]) -> anyhow::Result<bool> {
+        self.is_admin(ct)
+    }
+}
+
+/// The challenge 26 server: the same comment strings, under AES-CTR with
+/// a key and nonce fixed at startup.
+pub struct CtrCommentOracle {
+    aes: Aes,
+    nonce: u64,
+}
+
+impl CtrCommentOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        Self {
+            aes: Aes::new(&key).expect("16 byte key"),
+            nonce: rng.random(),
+        }
+    }
+
+    pub fn encrypt(&self, userdata: &str) -> Vec<u8> {
+        let pt = comment_string(userdata);
+        ctr_apply(&self.aes, Nonce::fixed(self.nonce), pt.as_bytes()).expect("16 byte block")
+    }
+
+    pub fn is_admin(&self, ct: &[u8]) -> bool {
+        let pt = ctr_apply(&self.aes, Nonce::fixed(self.nonce), ct).expect("16 byte block");
+        has_admin(&pt)
+    }
+}
+
+impl EncryptionOracle for &CtrCommentOracle {
+    fn encrypt(&mut self, userdata: &[u8]) -> Vec<u8> {
+        CtrCommentOracle::encrypt(self, &String::from_utf8_lossy(userdata))
+    }
+}
+
+impl DecryptionOracle<bool> for &CtrCommentOracle {
+    fn decrypt(&mut self, ct: &[u8]) -> anyhow::Result<bool> {
+        Ok(self.is_admin(ct))
+    }
+}
+
+/// Turns filler bytes at `at` in `ct` into `ADMIN_TARGET`, given that they
+/// decrypt to `filler`.
+fn flip_in_target(ct: &mut [u8], at: usize, filler: u8) {
+    for (i, t) in ADMIN_TARGET.iter().enumerate() {
+        ct[at + i] ^= filler ^ t;
+    }
+}
+
+/// CBC bitflipping: flipping a bit of ciphertext block `i` flips the same
+/// bit of plaintext block `i + 1` (and garbles block `i`). Sends enough
+/// filler to finish the prefix's last block plus two blocks, then turns
+/// the second filler block into `;admin=true;` by flipping the first.
+pub fn cbc_bitflip_admin<O: EncryptionOracle>(mut oracle: O) -> Vec<u8> {
+    const BS: usize = 16;
+    const FILLER: u8 = b'A';
+
+    let align = (BS - COMMENT_PREFIX.len() % BS) % BS;
+    let userdata = vec![FILLER; align + 2 * BS];
+    let mut ct = oracle.encrypt(&userdata);
+
+    flip_in_target(&mut ct, COMMENT_PREFIX.len() + align, FILLER);
+    ct
+}
+
+/// CTR bitflipping: a flipped ciphertext bit flips just the same plaintext
+/// bit, so send filler as long as the target and flip it where it lands,
+/// right after the prefix. Nothing else is garbled, and nothing here is
+/// specific to CTR: any XOR keystream falls the same way.
+pub fn ctr_bitflip_admin<O: EncryptionOracle>(mut oracle: O) -> Vec<u8> {
+    const FILLER: u8 = b'A';
+
+    let userdata = vec![FILLER; ADMIN_TARGET.len()];
+    let mut ct = oracle.encrypt(&userdata);
+    flip_in_target(&mut ct, COMMENT_PREFIX.len(), FILLER);
+    ct
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comment_string_quotes() {
+        let s = comment_string(";admin=true;");
+        assert_eq!(
+            s,
+            "comment1=cooking%20MCs;userdata=%3Badmin%3Dtrue%3B;comment2=%20like%20a%20pound%20of%20bacon"
+        );
+        assert!(!has_admin(s.as_bytes()));
+        assert!(s.starts_with(COMMENT_PREFIX) && s.ends_with(COMMENT_SUFFIX));
+        assert!(has_admin(b"x;admin=true;y"));
+        assert!(!has_admin(b"x;admin=true1;y"));
+    }
+
+    #[test]
+    fn test_cbc_bitflip_admin() {
+        let oracle = CbcCommentOracle::new(&mut StdRng::seed_from_u64(16));
+
+        let honest = oracle.encrypt(";admin=true;");
+        assert!(!oracle.is_admin(&honest).unwrap());
+
+        let forged = cbc_bitflip_admin(&oracle);
+        assert!(oracle.is_admin(&forged).unwrap());
+    }
+
+    #[test]
+    fn test_ctr_bitflip_admin() {
+        let oracle = CtrCommentOracle::new(&mut StdRng::seed_from_u64(26));
+
+        let honest = oracle.encrypt(";admin=true;");
+        assert!(!oracle.is_admin(&honest));
+
+        let forged = ctr_bitflip_admin(&oracle);
+        assert!(oracle.is_admin(&forged));
+        assert_eq!(
+            forged.len(),
+            COMMENT_PREFIX.len() + ADMIN_TARGET.len() + COMMENT_SUFFIX.len()
+        );
+    }
+}
@@ -0,0 +1,235 @@
+#![allow(dead_code)]
+use crate::aes_bitsliced::*;
+#[cfg(target_arch = "x86_64")]
+use crate::aes_ni::*;
+use crate::backend::*;
+use crate::des::*;
+use crate::spn::*;
+use crate::tea::*;
+
+/// A keyed block cipher. The modes of operation in `modes` only go through
+/// this, so any cipher with a fixed block size plugs into them.
+pub trait BlockCipher {
+    fn block_size(&self) -> usize;
+
+    /// Encrypts one block in place. Panics unless `block` is exactly
+    /// `block_size` bytes.
+    fn encrypt_block(&self, block: &mut [u8]);
+
+    fn decrypt_block(&self, block: &mut [u8]);
+
+    /// Encrypts whole blocks in place. Override when the cipher has a faster
+    /// bulk path than one block at a time.
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        for block in data.chunks_mut(self.block_size()) {
+            self.encrypt_block(block);
+        }
+    }
+
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        for block in data.chunks_mut(self.block_size()) {
+            self.decrypt_block(block);
+        }
+    }
+}
+
+fn as_block(block: &[u8]) -> [u8; 16] {
+    let mut b = [0u8; 16];
+    b.copy_from_slice(block);
+    b
+}
+
+/// AES under a fixed key on any `AesBackend`, so OpenSSL and the pure-Rust
+/// implementations are interchangeable wherever a `BlockCipher` goes. The
+/// key is expanded once, here.
+pub struct Aes {
+    backend: &'static dyn AesBackend,
+    cipher: Box<dyn KeyedAes>,
+}
+
+impl Aes {
+    /// AES on `default_backend()`.
+    pub fn new(key: &[u8]) -> anyhow::Result<Self> {
+        Self::with_backend(default_backend(), key)
+    }
+
+    /// Fails if `backend` doesn't take this key, so the block functions
+    /// can't.
+    pub fn with_backend(backend: &'static dyn AesBackend, key: &[u8]) -> anyhow::Result<Self> {
+        Ok(Self {
+            backend,
+            cipher: backend.keyed(key)?,
+        })
+    }
+
+    pub fn backend(&self) -> &'static dyn AesBackend {
+        self.backend
+    }
+}
+
+impl BlockCipher for Aes {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let out = self.cipher.encrypt_block(&as_block(block));
+        block.copy_from_slice(&out);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let out = self.cipher.decrypt_block(&as_block(block));
+        block.copy_from_slice(&out);
+    }
+
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        self.cipher.encrypt_blocks(data);
+    }
+
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        self.cipher.decrypt_blocks(data);
+    }
+}
+
+impl BlockCipher for BitslicedAes128 {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let out = BitslicedAes128::encrypt_block(self, &as_block(block));
+        block.copy_from_slice(&out);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let out = BitslicedAes128::decrypt_block(self, &as_block(block));
+        block.copy_from_slice(&out);
+    }
+}
+
+/// An 8-byte block as a big-endian word.
+fn be_block(block: &[u8]) -> u64 {
+    let mut b = [0u8; 8];
+    b.copy_from_slice(block);
+    u64::from_be_bytes(b)
+}
+
+impl BlockCipher for Des {
+    fn block_size(&self) -> usize {
+        8
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let x = be_block(block);
+        block.copy_from_slice(&self.encrypt_u64(x).to_be_bytes());
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let x = be_block(block);
+        block.copy_from_slice(&self.decrypt_u64(x).to_be_bytes());
+    }
+}
+
+/// TEA and XTEA both work on a block as two big-endian words.
+macro_rules! impl_word_pair_block_cipher {
+    ($($cipher:ty),*) => {$(
+        impl BlockCipher for $cipher {
+            fn block_size(&self) -> usize {
+                8
+            }
+
+            fn encrypt_block(&self, block: &mut [u8]) {
+                let x = be_block(block);
+                let [v0, v1] = self.encrypt_words([(x >> 32) as u32, x as u32]);
+                block.copy_from_slice(&(((v0 as u64) << 32) | v1 as u64).to_be_bytes());
+            }
+
+            fn decrypt_block(&self, block: &mut [u8]) {
+                let x = be_block(block);
+                let [v0, v1] = self.decrypt_words([(x >> 32) as u32, x as u32]);
+                block.copy_from_slice(&(((v0 as u64) << 32) | v1 as u64).to_be_bytes());
+            }
+        }
+    )*};
+}
+
+impl_word_pair_block_cipher!(Tea, Xtea);
+
+impl BlockCipher for Spn {
+    fn block_size(&self) -> usize {
+        2
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let x = self.encrypt_u16(u16::from_be_bytes([block[0], block[1]]));
+        block.copy_from_slice(&x.to_be_bytes());
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let x = self.decrypt_u16(u16::from_be_bytes([block[0], block[1]]));
+        block.copy_from_slice(&x.to_be_bytes());
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl BlockCipher for AesNi {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        self.encrypt_blocks(block);
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        self.decrypt_blocks(block);
+    }
+
+    fn encrypt_blocks(&self, data: &mut [u8]) {
+        AesNi::encrypt_blocks(self, data);
+    }
+
+    fn decrypt_blocks(&self, data: &mut [u8]) {
+        AesNi::decrypt_blocks(self, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_bytes::*;
+
+    #[test]
+    fn test_aes_block_cipher() {
+        // FIPS-197 appendix C.1
+        let key = RawBytes::from_hex("000102030405060708090a0b0c0d0e0f").bytes;
+        let pt = RawBytes::from_hex("00112233445566778899aabbccddeeff").bytes;
+        let ct = RawBytes::from_hex("69c4e0d86a7b0430d8cdb78070b4c55a").bytes;
+
+        let mut ciphers: Vec<Box<dyn BlockCipher>> = vec![
+            Box::new(Aes::new(&key).unwrap()),
+            Box::new(Aes::with_backend(&RustAes, &key).unwrap()),
+            Box::new(BitslicedAes128::new(&as_block(&key))),
+        ];
+        #[cfg(target_arch = "x86_64")]
+        {
+            if let Some(aes) = AesNi::new(&key) {
+                ciphers.push(Box::new(aes));
+            }
+        }
+
+        for cipher in ciphers.iter() {
+            let mut block = pt.clone();
+            cipher.encrypt_block(&mut block);
+            assert_eq!(block, ct);
+            cipher.decrypt_block(&mut block);
+            assert_eq!(block, pt);
+        }
+    }
+
+    #[test]
+    fn test_aes_rejects_bad_key() {
+        assert!(Aes::new(&[0u8; 17]).is_err());
+        assert!(Aes::with_backend(&BitslicedAes, &[0u8; 32]).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+#![allow(dead_code)]
+use crate::aes::*;
+use rand::prelude::*;
+
+/// T-table entries per cache line: 64 byte lines of `u32`s.
+pub const LINE_ENTRIES: usize = 16;
+pub const LINES_PER_TABLE: usize = 256 / LINE_ENTRIES;
+
+/// Simulated cycles to reload a line, by whether the encryption touched it.
+pub const HIT_CYCLES: u32 = 40;
+pub const MISS_CYCLES: u32 = 200;
+
+/// Which table lookups end up in the cache.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Leakage {
+    /// Only the first round's, as if the attacker could stop the victim
+    /// right after it. Clean, so few queries are needed.
+    FirstRound,
+    /// Every round's. Later rounds touch lines unrelated to the first round
+    /// and most lines end up cached, so it takes many more queries.
+    AllRounds,
+}
+
+/// Reload time for each line of each of the four T-tables, as measured by
+/// a prime+probe attacker after one encryption.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeTimes(pub [[u32; LINES_PER_TABLE]; 4]);
+
+impl ProbeTimes {
+    /// Lines that reloaded fast enough to have been in the cache.
+    pub fn accessed(&self, table: usize) -> [bool; LINES_PER_TABLE] {
+        let threshold = (HIT_CYCLES + MISS_CYCLES) / 2;
+        let mut ans = [false; LINES_PER_TABLE];
+        for (a, t) in ans.iter_mut().zip(self.0[table].iter()) {
+            *a = *t < threshold;
+        }
+        ans
+    }
+}
+
+/// AES-128 on T-tables, running next to an attacker who shares its cache.
+/// Every query returns the ciphertext and how long each table line takes
+/// to reload afterwards.
+pub struct CacheTimingOracle {
+    key: [u8; 16],
+    leakage: Leakage,
+    jitter: u32,
+    rng: StdRng,
+}
+
+impl CacheTimingOracle {
+    pub fn new(key: [u8; 16], leakage: Leakage, seed: u64) -> Self {
+        Self {
+            key,
+            leakage,
+            jitter: 60,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn encrypt(&mut self, block: &[u8; 16]) -> ([u8; 16], ProbeTimes) {
+        let mut cached = [[false; LINES_PER_TABLE]; 4];
+        let leakage = self.leakage;
+        let ct = ttable_encrypt_traced(block, &self.key, |round, table, index| {
+            if round == 1 || leakage == Leakage::AllRounds {
+                cached[table][index as usize / LINE_ENTRIES] = true;
+            }
+        });
+
+        let mut times = [[0u32; LINES_PER_TABLE]; 4];
+        for (t, c) in times.iter_mut().zip(cached.iter()) {
+            for (t, c) in t.iter_mut().zip(c.iter()) {
+                let base = if *c { HIT_CYCLES } else { MISS_CYCLES };
+                *t = base + self.rng.random_range(0..self.jitter);
+            }
+        }
+        (ct, ProbeTimes(times))
+    }
+}
+
+/// What the first-round attack learns: the cache line of `p[i] ^ k[i]`,
+/// and so the top four bits of every key byte. The low bits stay hidden
+/// inside the line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredNibbles {
+    /// Key bytes with the low nibble zeroed.
+    pub high_nibbles: [u8; 16],
+    pub queries: usize,
+}
+
+/// The first-round access-driven attack (Osvik, Shamir and Tromer). Byte
+/// `i` of the first round looks up `T[i % 4][p[i] ^ k[i]]`, so whenever a
+/// line of that table was not touched, every key nibble that would have
+/// put `p[i]` there is ruled out. Random plaintexts are sent until one
+/// candidate is left per byte.
+pub fn first_round_attack<F: FnMut(&[u8; 16]) -> ProbeTimes>(
+    mut oracle: F,
+    seed: u64,
+    max_queries: usize,
+) -> anyhow::Result<RecoveredNibbles> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut candidates = [[true; LINES_PER_TABLE]; 16];
+
+    for queries in 1..=max_queries {
+        let mut pt = [0u8; 16];
+        rng.fill_bytes(&mut pt);
+        let times = oracle(&pt);
+
+        for (i, cands) in candidates.iter_mut().enumerate() {
+            let accessed = times.accessed(i % 4);
+            let line = pt[i] as usize / LINE_ENTRIES;
+            for (h, c) in cands.iter_mut().enumerate() {
+                *c &= accessed[line ^ h];
+            }
+            if !cands.iter().any(|c| *c) {
+                anyhow::bail!("no key nibble fits the timings for byte {}", i);
+            }
+        }
+
+        let counts: Vec<usize> = candidates
+            .iter()
+            .map(|c| c.iter().filter(|c| **c).count())
+            .collect();
+        if counts.iter().all(|n| *n == 1) {
+            let mut high_nibbles = [0u8; 16];
+            for (k, c) in high_nibbles.iter_mut().zip(candidates.iter()) {
+                let h = c.iter().position(|c| *c).unwrap_or_default();
+                *k = (h * LINE_ENTRIES) as u8;
+            }
+            return Ok(RecoveredNibbles {
+                high_nibbles,
+                queries,
+            });
+        }
+    }
+    anyhow::bail!("key nibbles still ambiguous after {} queries", max_queries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected(key: &[u8; 16]) -> [u8; 16] {
+        let mut ans = *key;
+        for b in ans.iter_mut() {
+            *b &= 0xf0;
+        }
+        ans
+    }
+
+    #[test]
+    fn test_oracle_encrypts() {
+        let key = *b"YELLOW SUBMARINE";
+        let mut oracle = CacheTimingOracle::new(key, Leakage::AllRounds, 1);
+        let pt = [0x42u8; 16];
+        let (ct, _) = oracle.encrypt(&pt);
+        assert_eq!(ct, AesEncrypt::new().encrypt_block(&pt, &key));
+    }
+
+    #[test]
+    fn test_first_round_attack() {
+        let mut rng = StdRng::seed_from_u64(570);
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+
+        let mut queries = Vec::new();
+        for leakage in [Leakage::FirstRound, Leakage::AllRounds] {
+            let mut oracle = CacheTimingOracle::new(key, leakage, 7);
+            let ans = first_round_attack(|pt| oracle.encrypt(pt).1, 11, 5000).unwrap();
+            assert_eq!(ans.high_nibbles, expected(&key), "{:?}", leakage);
+            queries.push(ans.queries);
+        }
+        // Noise from the later rounds costs queries.
+        assert!(queries[0] < queries[1], "{:?}", queries);
+    }
+
+    #[test]
+    fn test_first_round_attack_gives_up() {
+        let mut oracle = CacheTimingOracle::new([0u8; 16], Leakage::AllRounds, 3);
+        assert!(first_round_attack(|pt| oracle.encrypt(pt).1, 5, 2).is_err());
+    }
+}
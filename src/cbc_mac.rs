@@ -0,0 +1,239 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::iv::*;
+use crate::kv::*;
+use crate::modes::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+use rand::prelude::*;
+
+/// CBC-MAC: CBC-encrypt `data` (a whole number of blocks) and keep only the
+/// last block. Secure for messages of one fixed length under a fixed IV;
+/// let the sender pick the IV, or let lengths vary, and it isn't.
+pub fn cbc_mac<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &Iv,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let ct = cbc_encrypt(cipher, iv, data)?;
+    let bs = cipher.block_size();
+    if ct.is_empty() {
+        anyhow::bail!("CBC-MAC of an empty message");
+    }
+    Ok(ct[ct.len() - bs..].to_vec())
+}
+
+pub fn cbc_mac_with_padding<C: BlockCipher + ?Sized, P: Padding>(
+    cipher: &C,
+    iv: &Iv,
+    data: &[u8],
+    padding: &P,
+) -> anyhow::Result<Vec<u8>> {
+    let padded = padding.pad(
+        &RawBytes {
+            bytes: data.to_vec(),
+        },
+        cipher.block_size(),
+    );
+    cbc_mac(cipher, iv, &padded.bytes)
+}
+
+/// CBC-MAC as a hash, as challenge 50 has it: a fixed, public key, a zero
+/// IV and PKCS#7. Knowing the key makes collisions trivial.
+pub fn cbc_mac_hash(key: &[u8], data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let aes = Aes::new(key)?;
+    cbc_mac_with_padding(&aes, &Iv::zero(16), data, &Pkcs7)
+}
+
+/// A transfer as the bank carries it out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transfer {
+    pub from: u32,
+    pub to: u32,
+    pub amount: u64,
+}
+
+/// The challenge 49 bank. The web front end (which only signs for the
+/// account that is logged in) and the API server share an AES key, and
+/// the API server carries out whatever comes with a valid CBC-MAC.
+///
+/// The first protocol sends `from=#&to=#&amount=#`, then the IV the client
+/// picked, then the MAC. The second fixes the IV at zero and sends
+/// `from=#&tx_list=#:#;#:#...`, then the MAC.
+pub struct CbcMacBank {
+    aes: Aes,
+}
+
+impl CbcMacBank {
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        Self {
+            aes: Aes::new(&key).expect("16 byte key"),
+        }
+    }
+
+    fn mac(&self, iv: &Iv, message: &[u8]) -> Vec<u8> {
+        cbc_mac_with_padding(&self.aes, iv, message, &Pkcs7).expect("padded")
+    }
+
+    /// The front end's request for a transfer out of `from`, under a fresh
+    /// random IV.
+    pub fn sign_transfer<R: Rng>(&self, from: u32, to: u32, amount: u64, rng: &mut R) -> Vec<u8> {
+        let message = QUERY.serialize(&[
+            ("from", from.to_string()),
+            ("to", to.to_string()),
+            ("amount", amount.to_string()),
+        ]);
+        let iv = Iv::random(rng, 16);
+        let mac = self.mac(&iv, message.as_bytes());
+        [message.as_bytes(), iv.as_bytes(), &mac].concat()
+    }
+
+    /// The API server for the first protocol.
+    pub fn process_transfer(&self, request: &[u8]) -> anyhow::Result<Transfer> {
+        if request.len() < 32 {
+            anyhow::bail!("request too short for an IV and a MAC");
+        }
+        let (message, rest) = request.split_at(request.len() - 32);
+        let (iv, mac) = rest.split_at(16);
+        if self.mac(&Iv::from_bytes(iv), message) != mac {
+            anyhow::bail!("bad MAC");
+        }
+
+        let pairs = QUERY.parse(std::str::from_utf8(message)?)?;
+        let get = |name: &str| -> anyhow::Result<&str> {
+            pairs
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+                .ok_or_else(|| anyhow::anyhow!("no {} in the request", name))
+        };
+        Ok(Transfer {
+            from: get("from")?.parse()?,
+            to: get("to")?.parse()?,
+            amount: get("amount")?.parse()?,
+        })
+    }
+
+    /// The front end's request for several transfers out of `from`.
+    pub fn sign_transfers(&self, from: u32, transfers: &[(u32, u64)]) -> Vec<u8> {
+        let message = tx_list_message(from, transfers);
+        let mac = self.mac(&Iv::zero(16), message.as_bytes());
+        [message.as_bytes(), &mac].concat()
+    }
+
+    /// The API server for the second protocol. Transfers in the list it
+    /// can't read are skipped rather than failing the whole request.
+    pub fn process_transfers(&self, request: &[u8]) -> anyhow::Result<Vec<Transfer>> {
+        if request.len() < 16 {
+            anyhow::bail!("request too short for a MAC");
+        }
+        let (message, mac) = request.split_at(request.len() - 16);
+        if self.mac(&Iv::zero(16), message) != mac {
+            anyhow::bail!("bad MAC");
+        }
+
+        let pairs = QUERY.parse_lenient(message);
+        let get = |name: &[u8]| {
+            pairs
+                .iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| *v)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("no {} in the request", String::from_utf8_lossy(name))
+                })
+        };
+        let from: u32 = std::str::from_utf8(get(b"from")?)?.parse()?;
+        Ok(get(b"tx_list")?
+            .split(|b| *b == b';')
+            .filter_map(|tx| {
+                let (to, amount) = std::str::from_utf8(tx).ok()?.split_once(':')?;
+                Some(Transfer {
+                    from,
+                    to: to.parse().ok()?,
+                    amount: amount.parse().ok()?,
+                })
+            })
+            .collect())
+    }
+}
+
+pub fn tx_list_message(from: u32, transfers: &[(u32, u64)]) -> String {
+    let list = transfers
+        .iter()
+        .map(|(to, amount)| format!("{}:{}", to, amount))
+        .collect::<Vec<_>>()
+        .join(";");
+    format!("from={}&tx_list={}", from, list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cbc_mac_is_last_cbc_block() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let iv = Iv::zero(16);
+        let data = [7u8; 48];
+        let ct = cbc_encrypt(&aes, &iv, &data).unwrap();
+        assert_eq!(cbc_mac(&aes, &iv, &data).unwrap(), ct[32..]);
+        assert!(cbc_mac(&aes, &iv, &data[..40]).is_err());
+        assert!(cbc_mac(&aes, &iv, &[]).is_err());
+        assert_eq!(
+            cbc_mac_with_padding(&aes, &iv, &data[..40], &Pkcs7)
+                .unwrap()
+                .len(),
+            16
+        );
+    }
+
+    #[test]
+    fn test_challenge_50_hash() {
+        let hash = cbc_mac_hash(b"YELLOW SUBMARINE", b"alert('MZA who was that?');\n").unwrap();
+        assert_eq!(
+            RawBytes { bytes: hash }.to_hex(),
+            "296b8d7cb78a243dda4d0a61d33bbdd1"
+        );
+    }
+
+    #[test]
+    fn test_bank_round_trips() {
+        let mut rng = StdRng::seed_from_u64(622);
+        let bank = CbcMacBank::new(&mut rng);
+
+        let request = bank.sign_transfer(2, 3, 100, &mut rng);
+        assert_eq!(
+            bank.process_transfer(&request).unwrap(),
+            Transfer {
+                from: 2,
+                to: 3,
+                amount: 100
+            }
+        );
+        let mut tampered = request.clone();
+        tampered[5] = b'1';
+        assert!(bank.process_transfer(&tampered).is_err());
+
+        let request = bank.sign_transfers(2, &[(3, 100), (4, 5)]);
+        assert_eq!(
+            bank.process_transfers(&request).unwrap(),
+            [
+                Transfer {
+                    from: 2,
+                    to: 3,
+                    amount: 100
+                },
+                Transfer {
+                    from: 2,
+                    to: 4,
+                    amount: 5
+                }
+            ]
+        );
+        let mut tampered = request;
+        tampered[5] = b'1';
+        assert!(bank.process_transfers(&tampered).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+#![allow(dead_code)]
+use crate::cbc_mac::*;
+use crate::iv::*;
+use crate::modes::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+
+/// The IV that gives a CBC-MAC'd message with `first_block` swapped for
+/// `wanted` the same tag: the first encryption only sees `IV ^ P1`, so
+/// move the difference into the IV.
+pub fn forge_first_block(iv: &[u8], first_block: &[u8], wanted: &[u8]) -> Iv {
+    let mut forged = iv.to_vec();
+    xor_into(&mut forged, first_block);
+    xor_into(&mut forged, wanted);
+    Iv::from_bytes(&forged)
+}
+
+/// Challenge 49, first protocol: turns the attacker's own signed transfer
+/// into one out of `victim`'s account. The sender's id sits in the first
+/// block, which the attacker controls through the IV; ids must be the same
+/// length so nothing else moves.
+pub fn forge_transfer_source(request: &[u8], victim: u32) -> anyhow::Result<Vec<u8>> {
+    if request.len() < 48 {
+        anyhow::bail!("request too short to forge");
+    }
+    let (message, rest) = request.split_at(request.len() - 32);
+    let (iv, mac) = rest.split_at(16);
+
+    let end = message
+        .iter()
+        .position(|b| *b == b'&')
+        .ok_or_else(|| anyhow::anyhow!("no fields in the request"))?;
+    if !message.starts_with(b"from=") {
+        anyhow::bail!("the request doesn't start with the sender");
+    }
+    let victim = victim.to_string();
+    if victim.len() != end - 5 || end > 16 {
+        anyhow::bail!("the victim's id has to fit over the sender's in the first block");
+    }
+
+    let mut forged = message.to_vec();
+    forged[5..end].copy_from_slice(victim.as_bytes());
+    let iv = forge_first_block(iv, &message[..16], &forged[..16]);
+    Ok([&forged, iv.as_bytes(), mac].concat())
+}
+
+/// Glues `second` onto `first` (both CBC-MAC'd with PKCS#7 under the same
+/// fixed IV) so that the result has `second`'s tag: after `first` and its
+/// padding the chaining value is `first_mac`, so XOR it into `second`'s
+/// first block and from there on the chain is the one `second` had.
+pub fn splice_cbc_mac(first: &[u8], first_mac: &[u8], second: &[u8], block_size: usize) -> Vec<u8> {
+    let padded = Pkcs7.pad(
+        &RawBytes {
+            bytes: first.to_vec(),
+        },
+        block_size,
+    );
+    let mut glued = second.to_vec();
+    xor_into(&mut glued[..block_size], first_mac);
+    [padded.bytes, glued].concat()
+}
+
+/// Challenge 49, second protocol: splices a transfer list the attacker
+/// had signed for their own account onto a captured request from the
+/// victim, which the bank then carries out under the victim's name.
+///
+/// The attacker's first block comes out as garbage, so it is given a
+/// throwaway transfer to sit in. The garbage must not hold a `&`, which
+/// would end the list early; the throwaway amount is changed until it
+/// doesn't. `sign(transfers)` is the front end signing for the attacker.
+pub fn forge_transfer_list<S: FnMut(&[(u32, u64)]) -> Vec<u8>>(
+    captured: &[u8],
+    attacker: u32,
+    amount: u64,
+    mut sign: S,
+) -> anyhow::Result<Vec<u8>> {
+    if captured.len() < 16 {
+        anyhow::bail!("captured request too short");
+    }
+    let (victim_message, victim_mac) = captured.split_at(captured.len() - 16);
+
+    for throwaway in 0..256 {
+        let signed = sign(&[(attacker, throwaway), (attacker, amount)]);
+        let (message, mac) = signed.split_at(signed.len() - 16);
+        let first_entry_end = message
+            .iter()
+            .position(|b| *b == b';')
+            .ok_or_else(|| anyhow::anyhow!("no list in the signed request"))?;
+        if first_entry_end < 16 {
+            anyhow::bail!("the throwaway transfer doesn't cover the first block");
+        }
+
+        let forged = splice_cbc_mac(victim_message, victim_mac, message, 16);
+        let glued_at = forged.len() - message.len();
+        let garbage = &forged[glued_at..glued_at + 16];
+        if !garbage.contains(&b'&') {
+            return Ok([&forged[..], mac].concat());
+        }
+    }
+    anyhow::bail!("every throwaway transfer left a & in the garbage block")
+}
+
+/// Challenge 50: JavaScript that runs `snippet` and has the same
+/// CBC-MAC hash (under the public `key`) as `target`. The snippet is
+/// followed by a line comment, its padding, one block that steers the
+/// chain onto `target`'s, and `target` less its first block; the comment
+/// swallows everything up to `target`'s first line break.
+///
+/// The comment ends at a line break, so the padding and the steering
+/// block mustn't contain one; spaces go into the comment until they
+/// don't.
+pub fn forge_cbc_mac_hash_js(key: &[u8], target: &[u8], snippet: &str) -> anyhow::Result<Vec<u8>> {
+    if target.len() < 16 {
+        anyhow::bail!("the target has to be at least a block long");
+    }
+    let breaks_line = |b: &u8| *b == b'\n' || *b == b'\r';
+    if target[..16].iter().any(breaks_line) {
+        anyhow::bail!("the target's first block would end the comment early");
+    }
+
+    for spaces in 0..256 {
+        let prefix = format!("{}//{}", snippet, " ".repeat(spaces));
+        let mac = cbc_mac_hash(key, prefix.as_bytes())?;
+        let forged = splice_cbc_mac(prefix.as_bytes(), &mac, target, 16);
+        let glue = &forged[prefix.len()..forged.len() - target.len() + 16];
+        if !glue.iter().any(breaks_line) {
+            return Ok(forged);
+        }
+    }
+    anyhow::bail!("every padding tried put a line break in the glue")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_cipher::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_splice_keeps_the_second_tag() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let iv = Iv::zero(16);
+        let first = b"a message of some length";
+        let second = b"and then another, longer one";
+        let first_mac = cbc_mac_with_padding(&aes, &iv, first, &Pkcs7).unwrap();
+        let second_mac = cbc_mac_with_padding(&aes, &iv, second, &Pkcs7).unwrap();
+
+        let spliced = splice_cbc_mac(first, &first_mac, second, 16);
+        assert_eq!(spliced.len(), 32 + second.len());
+        assert_eq!(
+            cbc_mac_with_padding(&aes, &iv, &spliced, &Pkcs7).unwrap(),
+            second_mac
+        );
+    }
+
+    #[test]
+    fn test_forge_transfer_source() {
+        let mut rng = StdRng::seed_from_u64(49);
+        let bank = CbcMacBank::new(&mut rng);
+        let own = bank.sign_transfer(2, 2, 1_000_000, &mut rng);
+        let forged = forge_transfer_source(&own, 7).unwrap();
+        assert_eq!(
+            bank.process_transfer(&forged).unwrap(),
+            Transfer {
+                from: 7,
+                to: 2,
+                amount: 1_000_000
+            }
+        );
+
+        assert!(forge_transfer_source(&own, 77).is_err());
+    }
+
+    #[test]
+    fn test_forge_transfer_list() {
+        let mut rng = StdRng::seed_from_u64(49);
+        let bank = CbcMacBank::new(&mut rng);
+        let captured = bank.sign_transfers(7, &[(3, 25), (4, 1000)]);
+
+        for attacker in [2, 31337] {
+            let forged =
+                forge_transfer_list(&captured, attacker, 1_000_000, |txs: &[(u32, u64)]| {
+                    bank.sign_transfers(attacker, txs)
+                })
+                .unwrap();
+            let transfers = bank.process_transfers(&forged).unwrap();
+            assert!(transfers.iter().all(|t| t.from == 7));
+            assert!(transfers.contains(&Transfer {
+                from: 7,
+                to: attacker,
+                amount: 1_000_000
+            }));
+        }
+    }
+
+    #[test]
+    fn test_forge_cbc_mac_hash_js() {
+        let key = b"YELLOW SUBMARINE";
+        let target = b"alert('MZA who was that?');\n";
+        let forged = forge_cbc_mac_hash_js(key, target, "alert('Ayo, the Wu is back!');").unwrap();
+        assert_eq!(
+            cbc_mac_hash(key, &forged).unwrap(),
+            cbc_mac_hash(key, target).unwrap()
+        );
+
+        // The snippet, then a comment that runs up to the target's line
+        // break, and nothing after it but that.
+        assert!(forged.starts_with(b"alert('Ayo, the Wu is back!');//"));
+        assert_eq!(
+            forged.iter().position(|b| *b == b'\n'),
+            Some(forged.len() - 1)
+        );
+        assert!(forged.ends_with(&target[16..]));
+    }
+}
@@ -0,0 +1,260 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::iv::*;
+use crate::keystream::*;
+use crate::modes::*;
+
+/// CCM (RFC 3610): a CBC-MAC over the nonce, the associated data and the
+/// plaintext, then CTR over the plaintext and the tag, all under one key
+/// and with a 16-byte block cipher. The nonce is 7 to 13 bytes; whatever
+/// is left of the block holds the message length and the counter.
+pub struct Ccm<'a, C: BlockCipher + ?Sized> {
+    cipher: &'a C,
+    tag_len: usize,
+    nonce_len: usize,
+}
+
+impl<'a, C: BlockCipher + ?Sized> Ccm<'a, C> {
+    /// `tag_len` is the `M` of the RFC: an even number of bytes from 4 to
+    /// 16. Short tags are cheaper to forge; at 4 bytes a forgery takes
+    /// about 2^31 tries, which is within reach of anyone who can submit
+    /// messages quickly.
+    pub fn new(cipher: &'a C, tag_len: usize, nonce_len: usize) -> anyhow::Result<Self> {
+        if cipher.block_size() != 16 {
+            anyhow::bail!("CCM needs a 16 byte block cipher");
+        }
+        if !(4..=16).contains(&tag_len) || !tag_len.is_multiple_of(2) {
+            anyhow::bail!("CCM tags are 4 to 16 bytes and even, not {}", tag_len);
+        }
+        if !(7..=13).contains(&nonce_len) {
+            anyhow::bail!("CCM nonces are 7 to 13 bytes, not {}", nonce_len);
+        }
+        Ok(Self {
+            cipher,
+            tag_len,
+            nonce_len,
+        })
+    }
+
+    /// The `L` of the RFC: how many bytes hold the message length.
+    fn length_len(&self) -> usize {
+        15 - self.nonce_len
+    }
+
+    fn check(&self, nonce: &Nonce, len: usize) -> anyhow::Result<()> {
+        if nonce.as_bytes().len() != self.nonce_len {
+            anyhow::bail!(
+                "nonce is {} bytes, not {}",
+                nonce.as_bytes().len(),
+                self.nonce_len
+            );
+        }
+        let l = self.length_len();
+        if l < 8 && len as u64 >= 1 << (8 * l) {
+            anyhow::bail!("{} bytes is too long for a {} byte length", len, l);
+        }
+        Ok(())
+    }
+
+    /// The CBC-MAC of B_0, the encoded associated data and the plaintext,
+    /// each zero-padded to whole blocks, cut to `tag_len`.
+    fn mac(&self, nonce: &[u8], aad: &[u8], pt: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let l = self.length_len();
+        let adata = if aad.is_empty() { 0 } else { 0x40 };
+        let flags = adata | (((self.tag_len - 2) / 2) << 3) as u8 | (l - 1) as u8;
+
+        let mut blocks = vec![flags];
+        blocks.extend_from_slice(nonce);
+        blocks.extend_from_slice(&(pt.len() as u64).to_be_bytes()[8 - l..]);
+
+        if !aad.is_empty() {
+            let len = aad.len() as u64;
+            if len < 0xff00 {
+                blocks.extend_from_slice(&(len as u16).to_be_bytes());
+            } else if len <= u32::MAX as u64 {
+                blocks.extend_from_slice(&[0xff, 0xfe]);
+                blocks.extend_from_slice(&(len as u32).to_be_bytes());
+            } else {
+                blocks.extend_from_slice(&[0xff, 0xff]);
+                blocks.extend_from_slice(&len.to_be_bytes());
+            }
+            blocks.extend_from_slice(aad);
+            blocks.resize(blocks.len().div_ceil(16) * 16, 0);
+        }
+        blocks.extend_from_slice(pt);
+        blocks.resize(blocks.len().div_ceil(16) * 16, 0);
+
+        let macced = cbc_encrypt(self.cipher, &Iv::zero(16), &blocks)?;
+        Ok(macced[macced.len() - 16..][..self.tag_len].to_vec())
+    }
+
+    /// The CTR keystream: the counter blocks are a flags byte, the nonce,
+    /// and an `L`-byte big-endian counter. Block 0 masks the tag and the
+    /// message starts at block 1.
+    fn keystream(&self, nonce: &[u8]) -> anyhow::Result<CtrKeystream<'a, C>> {
+        let l = self.length_len();
+        let prefix = [&[(l - 1) as u8][..], nonce].concat();
+        let layout = CtrLayout {
+            nonce_len: 16 - l,
+            counter_len: l,
+            counter_endian: Endian::Big,
+        };
+        CtrKeystream::with_layout(self.cipher, Nonce::from_bytes(&prefix), layout, 0)
+    }
+
+    /// Returns the ciphertext with the tag appended.
+    pub fn seal(&self, nonce: Nonce, aad: &[u8], pt: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.check(&nonce, pt.len())?;
+        let mut tag = self.mac(nonce.as_bytes(), aad, pt)?;
+
+        let mut ks = self.keystream(nonce.as_bytes())?;
+        ks.apply(&mut tag);
+        ks.seek(16);
+        let mut out = pt.to_vec();
+        ks.apply(&mut out);
+        out.extend_from_slice(&tag);
+        Ok(out)
+    }
+
+    /// Checks the tag on `sealed` and returns the plaintext, or fails
+    /// without saying anything about it.
+    pub fn open(&self, nonce: &Nonce, aad: &[u8], sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if sealed.len() < self.tag_len {
+            anyhow::bail!("{} bytes is shorter than the tag", sealed.len());
+        }
+        let (ct, tag) = sealed.split_at(sealed.len() - self.tag_len);
+        self.check(nonce, ct.len())?;
+
+        let mut ks = self.keystream(nonce.as_bytes())?;
+        let mut expected = ks.take_bytes(self.tag_len);
+        ks.seek(16);
+        let mut pt = ct.to_vec();
+        ks.apply(&mut pt);
+
+        xor_into(&mut expected, &self.mac(nonce.as_bytes(), aad, &pt)?);
+        let diff = expected
+            .iter()
+            .zip(tag)
+            .fold(0, |acc, (a, b)| acc | (a ^ b));
+        if diff != 0 {
+            anyhow::bail!("CCM authentication failed");
+        }
+        Ok(pt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    fn rfc_3610_key() -> Aes {
+        Aes::new(&(0xc0..=0xcf).collect::<Vec<u8>>()).unwrap()
+    }
+
+    #[test]
+    fn test_rfc_3610_packet_vectors() {
+        let aes = rfc_3610_key();
+        let ccm = Ccm::new(&aes, 8, 13).unwrap();
+        let input: Vec<u8> = (0..32).collect();
+        for (nonce, len, expected) in [
+            // Packet vectors 1 and 2.
+            (
+                "00000003020100a0a1a2a3a4a5",
+                31,
+                "588c979a61c663d2f066d0c2c0f989806d5f6b61dac38417e8d12cfdf926e0",
+            ),
+            (
+                "00000004030201a0a1a2a3a4a5",
+                32,
+                "72c91a36e135f8cf291ca894085c87e3cc15c439c9e43a3ba091d56e10400916",
+            ),
+        ] {
+            let (aad, pt) = input[..len].split_at(8);
+            let sealed = ccm.seal(Nonce::from_bytes(&unhex(nonce)), aad, pt).unwrap();
+            assert_eq!(sealed, unhex(expected));
+            let opened = ccm.open(&Nonce::from_bytes(&unhex(nonce)), aad, &sealed);
+            assert_eq!(opened.unwrap(), pt);
+        }
+    }
+
+    #[test]
+    fn test_tag_and_nonce_lengths() {
+        let aes = rfc_3610_key();
+        let nonce = unhex("00112233445566");
+
+        let ccm = Ccm::new(&aes, 16, 7).unwrap();
+        let sealed = ccm
+            .seal(Nonce::from_bytes(&nonce), b"", b"no associated data here")
+            .unwrap();
+        assert_eq!(
+            sealed,
+            unhex("99947d7483b59f71565be7a25c20bd87f139345413d54c6937162c4da6bed5dcbd5d1bc52b57de")
+        );
+
+        // Nothing to encrypt: the sealed message is just the tag.
+        let ccm = Ccm::new(&aes, 4, 7).unwrap();
+        let sealed = ccm
+            .seal(Nonce::from_bytes(&nonce), b"only aad", b"")
+            .unwrap();
+        assert_eq!(sealed, unhex("66a04a54"));
+
+        assert!(Ccm::new(&aes, 5, 7).is_err());
+        assert!(Ccm::new(&aes, 8, 14).is_err());
+        assert!(ccm.seal(Nonce::from_bytes(&[0; 8]), b"", b"").is_err());
+    }
+
+    #[test]
+    fn test_open_rejects_tampering() {
+        let aes = rfc_3610_key();
+        let ccm = Ccm::new(&aes, 8, 12).unwrap();
+        let nonce = [7u8; 12];
+        let sealed = ccm
+            .seal(Nonce::from_bytes(&nonce), b"header", b"attack at dawn")
+            .unwrap();
+        let open = |aad: &[u8], sealed: &[u8]| ccm.open(&Nonce::from_bytes(&nonce), aad, sealed);
+        assert_eq!(open(b"header", &sealed).unwrap(), b"attack at dawn");
+
+        for i in 0..sealed.len() {
+            let mut bad = sealed.clone();
+            bad[i] ^= 1;
+            assert!(open(b"header", &bad).is_err());
+        }
+        assert!(open(b"headers", &sealed).is_err());
+        assert!(open(b"header", &sealed[..7]).is_err());
+    }
+
+    #[test]
+    fn test_forge_short_tag_by_brute_force() {
+        let aes = rfc_3610_key();
+        let ccm = Ccm::new(&aes, 4, 12).unwrap();
+        let nonce = [7u8; 12];
+        let sealed = ccm
+            .seal(Nonce::from_bytes(&nonce), b"header", b"attack at dawn")
+            .unwrap();
+
+        // CTR lets anyone change the plaintext; only the tag stops it.
+        let (ct, _) = sealed.split_at(sealed.len() - 4);
+        let mut ct = ct.to_vec();
+        xor_into(&mut ct[10..], b"dawn");
+        xor_into(&mut ct[10..], b"dusk");
+
+        // A 4-byte tag is 2^32 tries, too many for a test. Give the
+        // attacker the first two bytes of the right one (the key is only
+        // used to find them) and search the other 2^16 through `open`.
+        let right = ccm
+            .seal(Nonce::from_bytes(&nonce), b"header", b"attack at dusk")
+            .unwrap();
+        let known = &right[right.len() - 4..][..2];
+        let forged = (0..=u16::MAX)
+            .map(|guess| [&ct[..], known, &guess.to_be_bytes()].concat())
+            .find(|forged| {
+                ccm.open(&Nonce::from_bytes(&nonce), b"header", forged)
+                    .is_ok()
+            })
+            .expect("one of the guesses is the tag");
+        let opened = ccm.open(&Nonce::from_bytes(&nonce), b"header", &forged);
+        assert_eq!(opened.unwrap(), b"attack at dusk");
+        assert_eq!(forged, right);
+    }
+}
@@ -0,0 +1,173 @@
+#![allow(dead_code)]
+use crate::arx::*;
+use crate::iv::*;
+use crate::keystream::*;
+
+/// "expand 32-byte k", the first row of the state.
+const SIGMA: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+/// The ChaCha quarter round on words `a`, `b`, `c` and `d` of `state`.
+pub fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// The RFC 8439 block function: 20 rounds, alternating column and
+/// diagonal quarter rounds, over the constants, the key, the block counter
+/// and the 96-bit nonce, with the input added back in at the end.
+pub fn chacha20_block(key: &[u8; 32], counter: u32, nonce: &[u8; 12]) -> [u8; 64] {
+    let mut input = [0u32; 16];
+    input[..4].copy_from_slice(&SIGMA);
+    input[4..12].copy_from_slice(&le_words::<8>(key));
+    input[12] = counter;
+    input[13..].copy_from_slice(&le_words::<3>(nonce));
+
+    let mut state = input;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+
+    feed_forward(&state, &input)
+}
+
+/// The ChaCha20 keystream for one key and nonce, starting at block
+/// `initial_counter`. The 32-bit block counter wraps after 256 GiB, as far
+/// as RFC 8439 lets a single message go.
+pub struct ChaCha20 {
+    key: [u8; 32],
+    nonce: [u8; 12],
+    initial_counter: u32,
+    cursor: BlockCursor,
+}
+
+impl ChaCha20 {
+    pub fn new(key: &[u8], nonce: Nonce, initial_counter: u32) -> anyhow::Result<Self> {
+        if key.len() != 32 {
+            anyhow::bail!("ChaCha20 keys are 32 bytes, got {}", key.len());
+        }
+        if nonce.as_bytes().len() != 12 {
+            anyhow::bail!(
+                "ChaCha20 nonces are 12 bytes, got {}",
+                nonce.as_bytes().len()
+            );
+        }
+        let mut ans = Self {
+            key: [0; 32],
+            nonce: [0; 12],
+            initial_counter,
+            cursor: BlockCursor::new(),
+        };
+        ans.key.copy_from_slice(key);
+        ans.nonce.copy_from_slice(nonce.as_bytes());
+        Ok(ans)
+    }
+}
+
+impl KeystreamGenerator for ChaCha20 {
+    fn fill(&mut self, buf: &mut [u8]) {
+        let (key, nonce, initial) = (self.key, self.nonce, self.initial_counter);
+        self.cursor.fill(buf, |i| {
+            chacha20_block(&key, initial.wrapping_add(i as u32), &nonce)
+        });
+    }
+
+    fn seek(&mut self, pos: u64) {
+        self.cursor.seek(pos);
+    }
+
+    fn position(&self) -> u64 {
+        self.cursor.position()
+    }
+}
+
+/// ChaCha20 encryption or decryption of `data`, from block
+/// `initial_counter` on.
+pub fn chacha20_apply(
+    key: &[u8],
+    nonce: Nonce,
+    initial_counter: u32,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let mut out = data.to_vec();
+    ChaCha20::new(key, nonce, initial_counter)?.apply(&mut out);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_bytes::*;
+
+    fn nonce(s: &str) -> Nonce {
+        Nonce::from_bytes(&RawBytes::from_hex(s).bytes)
+    }
+
+    #[test]
+    fn test_quarter_round() {
+        // RFC 8439 2.2.1, on a state that is mostly zeros.
+        let mut state = [0u32; 16];
+        state[2] = 0x516461b1;
+        state[7] = 0x2a5f714c;
+        state[8] = 0x53372767;
+        state[13] = 0x3d631689;
+        quarter_round(&mut state, 2, 7, 8, 13);
+        assert_eq!(
+            [state[2], state[7], state[8], state[13]],
+            [0xbdb886dc, 0xcfacafd2, 0xe46bea80, 0xccc07c79]
+        );
+    }
+
+    #[test]
+    fn test_block_function() {
+        // RFC 8439 2.3.2.
+        let key: Vec<u8> = (0..32).collect();
+        let mut ks = ChaCha20::new(&key, nonce("000000090000004a00000000"), 1).unwrap();
+        assert_eq!(
+            RawBytes {
+                bytes: ks.take_bytes(64)
+            }
+            .to_hex(),
+            "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e\
+             d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+        );
+    }
+
+    #[test]
+    fn test_encryption() {
+        // RFC 8439 2.4.2.
+        let key: Vec<u8> = (0..32).collect();
+        let pt = "Ladies and Gentlemen of the class of '99: If I could offer you \
+                  only one tip for the future, sunscreen would be it.";
+        let ct = chacha20_apply(&key, nonce("000000000000004a00000000"), 1, pt.as_bytes()).unwrap();
+        assert_eq!(
+            RawBytes { bytes: ct.clone() }.to_hex(),
+            "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b\
+             f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8\
+             07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736\
+             5af90bbf74a35be6b40b8eedf2785e42874d"
+        );
+
+        // Reading from the middle matches.
+        let mut ks = ChaCha20::new(&key, nonce("000000000000004a00000000"), 1).unwrap();
+        ks.seek(70);
+        let mut tail = ct[70..].to_vec();
+        ks.apply(&mut tail);
+        assert_eq!(tail, pt.as_bytes()[70..]);
+
+        assert!(ChaCha20::new(&key[..16], nonce("000000000000004a00000000"), 0).is_err());
+        assert!(ChaCha20::new(&key, Nonce::fixed(0), 0).is_err());
+    }
+}
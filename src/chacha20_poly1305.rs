@@ -0,0 +1,248 @@
+#![allow(dead_code)]
+use crate::chacha20::*;
+use crate::iv::*;
+use crate::keystream::*;
+use crate::poly1305::*;
+use rand::prelude::*;
+use std::convert::TryInto;
+
+/// The one-time Poly1305 key: the first half of ChaCha20 block 0. The
+/// message itself is encrypted from block 1.
+pub fn poly1305_key_gen(key: &[u8; 32], nonce: &[u8; 12]) -> [u8; 32] {
+    chacha20_block(key, 0, nonce)[..32].try_into().unwrap()
+}
+
+/// What the tag covers: the associated data and the ciphertext, each
+/// zero-padded to 16 bytes, then both lengths as 64-bit little-endian.
+pub fn aead_mac_data(aad: &[u8], ct: &[u8]) -> Vec<u8> {
+    let mut data = aad.to_vec();
+    data.resize(aad.len().div_ceil(16) * 16, 0);
+    data.extend_from_slice(ct);
+    data.resize(data.len().div_ceil(16) * 16, 0);
+    data.extend_from_slice(&(aad.len() as u64).to_le_bytes());
+    data.extend_from_slice(&(ct.len() as u64).to_le_bytes());
+    data
+}
+
+fn check_key_and_nonce(key: &[u8], nonce: &Nonce) -> anyhow::Result<([u8; 32], [u8; 12])> {
+    let key: [u8; 32] = key
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("ChaCha20-Poly1305 keys are 32 bytes, got {}", key.len()))?;
+    let nonce: [u8; 12] = nonce.as_bytes().try_into().map_err(|_| {
+        anyhow::anyhow!(
+            "ChaCha20-Poly1305 nonces are 12 bytes, got {}",
+            nonce.as_bytes().len()
+        )
+    })?;
+    Ok((key, nonce))
+}
+
+/// The RFC 8439 AEAD. Returns the ciphertext with the 16-byte tag
+/// appended.
+pub fn chacha20_poly1305_seal(
+    key: &[u8],
+    nonce: Nonce,
+    aad: &[u8],
+    pt: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let (k, n) = check_key_and_nonce(key, &nonce)?;
+    let mut out = chacha20_apply(key, nonce, 1, pt)?;
+    let tag = poly1305(&poly1305_key_gen(&k, &n), &aead_mac_data(aad, &out));
+    out.extend_from_slice(&tag);
+    Ok(out)
+}
+
+/// Checks the tag and decrypts, or fails without saying anything more.
+pub fn chacha20_poly1305_open(
+    key: &[u8],
+    nonce: &Nonce,
+    aad: &[u8],
+    sealed: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let (k, n) = check_key_and_nonce(key, nonce)?;
+    if sealed.len() < 16 {
+        anyhow::bail!("{} bytes is shorter than the tag", sealed.len());
+    }
+    let (ct, tag) = sealed.split_at(sealed.len() - 16);
+    let expected = poly1305(&poly1305_key_gen(&k, &n), &aead_mac_data(aad, ct));
+    let diff = expected
+        .iter()
+        .zip(tag)
+        .fold(0, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        anyhow::bail!("ChaCha20-Poly1305 authentication failed");
+    }
+    let mut ks = ChaCha20::new(key, Nonce::from_bytes(&n), 1)?;
+    let mut pt = ct.to_vec();
+    ks.apply(&mut pt);
+    Ok(pt)
+}
+
+/// A deliberately broken sender that seals every message under the same
+/// nonce. Besides the usual keystream reuse, that gives every message the
+/// same one-time Poly1305 key, which `recover_poly1305_key` pulls out of
+/// two tags.
+pub struct NonceReusingSealer {
+    key: [u8; 32],
+    nonce: [u8; 12],
+}
+
+impl NonceReusingSealer {
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let mut ans = Self {
+            key: [0; 32],
+            nonce: [0; 12],
+        };
+        rng.fill_bytes(&mut ans.key);
+        rng.fill_bytes(&mut ans.nonce);
+        ans
+    }
+
+    pub fn seal(&self, aad: &[u8], pt: &[u8]) -> Vec<u8> {
+        chacha20_poly1305_seal(&self.key, Nonce::from_bytes(&self.nonce), aad, pt)
+            .expect("valid key and nonce")
+    }
+
+    pub fn open(&self, aad: &[u8], sealed: &[u8]) -> anyhow::Result<Vec<u8>> {
+        chacha20_poly1305_open(&self.key, &Nonce::from_bytes(&self.nonce), aad, sealed)
+    }
+
+    /// For checking a recovered key: the one-time key as Poly1305 uses it,
+    /// with `r` already clamped.
+    pub fn poly1305_key(&self) -> [u8; 32] {
+        let mut key = poly1305_key_gen(&self.key, &self.nonce);
+        let r = clamp_r(&key[..16]);
+        key[..16].copy_from_slice(&r.to_le_bytes());
+        key
+    }
+}
+
+/// Recovers the one-time Poly1305 key `r || s` from two messages sealed
+/// under the same key and nonce, with the same associated data and
+/// ciphertexts that differ only in their last 16-byte block.
+///
+/// Only that block and the length block after it are then multiplied in
+/// differently, so the tags differ by `(m1 - m2) * r^2`, up to the
+/// reduction mod p and the wrap mod 2^128 that `s` hides behind. Trying
+/// each of the few possible wraps gives candidates for `r^2`; a square
+/// root mod p and the clamping rules pick out `r`, and then either tag
+/// gives `s`.
+pub fn recover_poly1305_key(
+    aad: &[u8],
+    sealed1: &[u8],
+    sealed2: &[u8],
+) -> anyhow::Result<[u8; 32]> {
+    if sealed1.len() != sealed2.len() || sealed1.len() <= 16 {
+        anyhow::bail!("need two non-empty ciphertexts of the same length");
+    }
+    let split = |s: &[u8]| {
+        let (ct, tag) = s.split_at(s.len() - 16);
+        (ct.to_vec(), u128::from_le_bytes(tag.try_into().unwrap()))
+    };
+    let (ct1, t1) = split(sealed1);
+    let (ct2, t2) = split(sealed2);
+    let last = (ct1.len() - 1) / 16 * 16;
+    if ct1[..last] != ct2[..last] || ct1[last..] == ct2[last..] {
+        anyhow::bail!("the ciphertexts must differ in their last block and nowhere else");
+    }
+
+    let block = |ct: &[u8]| {
+        let mut b = ct[last..].to_vec();
+        b.resize(16, 0);
+        b.push(1);
+        Fe::from_le_bytes(&b)
+    };
+    let inv_diff = block(&ct1).sub(&block(&ct2)).invert();
+    let (data1, data2) = (aead_mac_data(aad, &ct1), aead_mac_data(aad, &ct2));
+    let tag_diff = Fe::from_u128(t1.wrapping_sub(t2));
+
+    for k in -4i64..=4 {
+        let mut delta = tag_diff;
+        for _ in 0..k.abs() {
+            delta = if k > 0 {
+                delta.add(&Fe::TWO_128)
+            } else {
+                delta.sub(&Fe::TWO_128)
+            };
+        }
+        let root = match delta.mul(&inv_diff).sqrt() {
+            Some(root) => root,
+            None => continue,
+        };
+        for r in [root, Fe::ZERO.sub(&root)] {
+            if r.high_bits() != 0 || clamp_r(&r.low_u128().to_le_bytes()) != r.low_u128() {
+                continue;
+            }
+            let r = r.low_u128();
+            let s = t1.wrapping_sub(poly1305_hash(r, &data1).low_u128());
+            if poly1305_hash(r, &data2).low_u128().wrapping_add(s) == t2 {
+                let mut key = [0u8; 32];
+                key[..16].copy_from_slice(&r.to_le_bytes());
+                key[16..].copy_from_slice(&s.to_le_bytes());
+                return Ok(key);
+            }
+        }
+    }
+    anyhow::bail!("no candidate for r fits both tags")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_bytes::*;
+
+    #[test]
+    fn test_rfc_8439_aead() {
+        // 2.8.2.
+        let key: Vec<u8> = (0x80..0xa0).collect();
+        let nonce = || Nonce::from_bytes(&RawBytes::from_hex("070000004041424344454647").bytes);
+        let aad = RawBytes::from_hex("50515253c0c1c2c3c4c5c6c7").bytes;
+        let pt = "Ladies and Gentlemen of the class of '99: If I could offer you \
+                  only one tip for the future, sunscreen would be it.";
+
+        let sealed = chacha20_poly1305_seal(&key, nonce(), &aad, pt.as_bytes()).unwrap();
+        assert_eq!(
+            RawBytes {
+                bytes: sealed.clone()
+            }
+            .to_hex(),
+            "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+             3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+             92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+             3ff4def08e4b7a9de576d26586cec64b6116\
+             1ae10b594f09e26a7e902ecbd0600691"
+        );
+        assert_eq!(
+            chacha20_poly1305_open(&key, &nonce(), &aad, &sealed).unwrap(),
+            pt.as_bytes()
+        );
+
+        let mut bad = sealed.clone();
+        bad[0] ^= 1;
+        assert!(chacha20_poly1305_open(&key, &nonce(), &aad, &bad).is_err());
+        assert!(chacha20_poly1305_open(&key, &nonce(), b"", &sealed).is_err());
+    }
+
+    #[test]
+    fn test_nonce_reuse_leaks_poly1305_key() {
+        let mut rng = StdRng::seed_from_u64(606);
+        let sender = NonceReusingSealer::new(&mut rng);
+        let aad = b"v1";
+        let sealed1 = sender.seal(aad, b"transfer $100 to alice");
+        let sealed2 = sender.seal(aad, b"transfer $100 to bobby");
+
+        let key = recover_poly1305_key(aad, &sealed1, &sealed2).unwrap();
+        assert_eq!(key, sender.poly1305_key());
+
+        // With the keystream from a known plaintext as well, any message
+        // of that length can be forged.
+        let known = b"transfer $100 to alice";
+        let forged_pt = b"transfer $999 to mallo";
+        let mut forged: Vec<u8> = (0..known.len())
+            .map(|i| sealed1[i] ^ known[i] ^ forged_pt[i])
+            .collect();
+        let tag = poly1305(&key, &aead_mac_data(aad, &forged));
+        forged.extend_from_slice(&tag);
+        assert_eq!(sender.open(aad, &forged).unwrap(), forged_pt);
+    }
+}
@@ -0,0 +1,744 @@
+#![allow(dead_code)]
+use crate::bitflip::*;
+use crate::block_cipher::*;
+use crate::cbc_mac::*;
+use crate::cbc_mac_forgery::*;
+use crate::cheap_hash::*;
+use crate::cipher::*;
+use crate::compression_oracle::*;
+use crate::ctr_attack::*;
+use crate::ecb_attack::*;
+use crate::expandable_message::*;
+use crate::input::*;
+use crate::iv::*;
+use crate::key_as_iv::*;
+use crate::length_extension::*;
+use crate::many_time_pad::*;
+use crate::md4::*;
+use crate::md4_collision::*;
+use crate::mode_io::*;
+use crate::mode_oracle::*;
+use crate::mt19937::*;
+use crate::mt_attack::*;
+use crate::multicollision::*;
+use crate::oracles::*;
+use crate::padding::*;
+use crate::padding_oracle::*;
+use crate::profile::*;
+use crate::raw_bytes::*;
+use crate::sha1_mac::*;
+use rand::prelude::*;
+use std::io::Read;
+
+/// Counts the work a challenge does so runs can be compared across commits.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Meter {
+    pub queries: u64,
+    pub bytes: u64,
+}
+
+impl Meter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records input handled directly rather than through an oracle.
+    pub fn process(&mut self, bytes: usize) {
+        self.bytes += bytes as u64;
+    }
+
+    /// Wraps an oracle so each call counts as a query and its input and
+    /// output as processed bytes.
+    pub fn oracle<'a, O: EncryptionOracle + 'a>(
+        &'a mut self,
+        mut oracle: O,
+    ) -> impl FnMut(&[u8]) -> Vec<u8> + 'a {
+        move |input| {
+            let out = oracle.encrypt(input);
+            self.queries += 1;
+            self.bytes += (input.len() + out.len()) as u64;
+            out
+        }
+    }
+}
+
+pub struct Challenge {
+    pub set: u8,
+    pub number: u8,
+    pub name: &'static str,
+    /// Solves the challenge and returns a short answer; fails if the answer
+    /// is wrong.
+    pub run: fn(&mut Meter) -> anyhow::Result<String>,
+}
+
+/// Everything `reader` has, such as a file through one of the streaming
+/// decryptors.
+fn read_all<R: Read>(mut reader: R) -> anyhow::Result<RawBytes> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(RawBytes { bytes })
+}
+
+fn load(path: &str, meter: &mut Meter) -> anyhow::Result<Vec<RawBytes>> {
+    let (_, data) = load_input(path)?;
+    meter.process(data.iter().map(|rb| rb.bytes.len()).sum());
+    Ok(data)
+}
+
+fn check(answer: String, expected: &str) -> anyhow::Result<String> {
+    if answer != expected {
+        anyhow::bail!("expected {:?}, got {:?}", expected, answer);
+    }
+    Ok(answer)
+}
+
+fn challenge_3(meter: &mut Meter) -> anyhow::Result<String> {
+    let rb =
+        RawBytes::from_hex("1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736");
+    meter.process(rb.bytes.len());
+    check(
+        single_char_xor_decrypt(&rb).to_str(),
+        "Cooking MC's like a pound of bacon",
+    )
+}
+
+fn challenge_4(meter: &mut Meter) -> anyhow::Result<String> {
+    let data = load("inputs/set4.txt", meter)?;
+    let ans = detect_single_char_xor_par(&data);
+    check(ans[0].2.to_str(), "Now that the party is jumping\n")
+}
+
+fn challenge_6(meter: &mut Meter) -> anyhow::Result<String> {
+    let data = load("inputs/set6.txt", meter)?;
+    let ans = repeating_key_xor_decrypt(&data[0]);
+    check(ans[0].key.to_str(), "Terminator X: Bring the noise")
+}
+
+fn challenge_7(meter: &mut Meter) -> anyhow::Result<String> {
+    let data = load("inputs/set7.txt", meter)?;
+    let aes = Aes::new(b"YELLOW SUBMARINE")?;
+    let ans = read_all(DecryptReader::ecb(&aes, Pkcs7, &data[0].bytes[..]))?;
+    let first = ans.to_str().lines().next().unwrap_or_default().to_string();
+    check(first, "I'm back and I'm ringin' the bell ")
+}
+
+fn challenge_8(meter: &mut Meter) -> anyhow::Result<String> {
+    let data = load("inputs/set8.txt", meter)?;
+    let ans = aes_128_ecb_detect_report(&data);
+    check(ans[0].index.to_string(), "132")
+}
+
+fn challenge_9(meter: &mut Meter) -> anyhow::Result<String> {
+    let rb = RawBytes::from_str("YELLOW SUBMARINE");
+    meter.process(rb.bytes.len());
+    let ans = RawBytes {
+        bytes: add_pkcs7_padding(&rb, 20).collect(),
+    };
+    check(ans.to_hex(), "59454c4c4f57205355424d4152494e4504040404")
+}
+
+fn challenge_10(meter: &mut Meter) -> anyhow::Result<String> {
+    let data = load("inputs/set10.txt", meter)?;
+    let aes = Aes::new(b"YELLOW SUBMARINE")?;
+    let iv = Iv::zero(16);
+    let ans = read_all(DecryptReader::cbc(&aes, &iv, Pkcs7, &data[0].bytes[..])?)?;
+    let first = ans.to_str().lines().next().unwrap_or_default().to_string();
+    check(first, "I'm back and I'm ringin' the bell ")
+}
+
+fn challenge_11(meter: &mut Meter) -> anyhow::Result<String> {
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut correct = 0;
+    for _ in 0..100 {
+        let mut mode = None;
+        let guess = detect_oracle_mode(meter.oracle(|input: &[u8]| {
+            let (ct, m) = encryption_oracle(input, &mut rng);
+            mode = Some(m);
+            ct.bytes
+        }));
+        correct += usize::from(Some(guess) == mode);
+    }
+    check(format!("{}/100", correct), "100/100")
+}
+
+const CHALLENGE_12_SECRET: &str = "Um9sbGluJyBpbiBteSA1LjAKV2l0aCBteSByYWctdG9wIGRvd24gc28gbXkgaGFpciBjYW4gYmxvdwpUaGUgZ2lybGllcyBvbiBzdGFuZGJ5IHdhdmluZyBqdXN0IHRvIHNheSBoaQpEaWQgeW91IHN0b3A/IE5vLCBJIGp1c3QgZHJvdmUgYnkK";
+
+fn challenge_12(meter: &mut Meter) -> anyhow::Result<String> {
+    let secret = RawBytes::from_base64(CHALLENGE_12_SECRET);
+    let mut oracle = EcbSuffixOracle::new(&secret.bytes, &mut StdRng::seed_from_u64(12));
+
+    let ans = recover_ecb_suffix(meter.oracle(|input: &[u8]| oracle.encrypt(input)))?;
+    let first = ans.to_str().lines().next().unwrap_or_default().to_string();
+    check(first, "Rollin' in my 5.0")
+}
+
+fn challenge_13(meter: &mut Meter) -> anyhow::Result<String> {
+    let mut key = [0u8; 16];
+    StdRng::seed_from_u64(13).fill_bytes(&mut key);
+    let server = ProfileServer::new(&key);
+
+    let forged = forge_admin_profile(meter.oracle(&server));
+    let profile = server.decrypt_profile(&forged)?;
+    check(role(&profile).unwrap_or_default().to_string(), "admin")
+}
+
+fn challenge_14(meter: &mut Meter) -> anyhow::Result<String> {
+    let secret = RawBytes::from_base64(CHALLENGE_12_SECRET);
+    let mut rng = StdRng::seed_from_u64(14);
+    let mut oracle = EcbSuffixOracle::with_random_prefix(&secret.bytes, &mut rng);
+
+    let ans = recover_ecb_suffix(meter.oracle(|input: &[u8]| oracle.encrypt(input)))?;
+    let first = ans.to_str().lines().next().unwrap_or_default().to_string();
+    check(first, "Rollin' in my 5.0")
+}
+
+fn challenge_16(meter: &mut Meter) -> anyhow::Result<String> {
+    let oracle = CbcCommentOracle::new(&mut StdRng::seed_from_u64(16));
+    let forged = cbc_bitflip_admin(meter.oracle(&oracle));
+    check(oracle.is_admin(&forged)?.to_string(), "true")
+}
+
+fn challenge_17(meter: &mut Meter) -> anyhow::Result<String> {
+    let mut rng = StdRng::seed_from_u64(17);
+    let server = PaddingOracleServer::new(&mut rng);
+    let (iv, ct) = server.encrypt(&mut rng);
+    meter.process(iv.len() + ct.len());
+
+    let ans = padding_oracle_decrypt(
+        |iv: &[u8], ct: &[u8]| {
+            meter.queries += 1;
+            meter.process(iv.len() + ct.len());
+            server.padding_valid(iv, ct)
+        },
+        &iv,
+        &ct,
+    )?;
+    let known = CHALLENGE_17_STRINGS
+        .iter()
+        .any(|s| RawBytes::from_base64(s) == ans);
+    check(known.to_string(), "true")
+}
+
+fn challenge_18(meter: &mut Meter) -> anyhow::Result<String> {
+    let ct = RawBytes::from_base64(
+        "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==",
+    );
+    meter.process(ct.bytes.len());
+    let key = RawBytes::from_str("YELLOW SUBMARINE");
+    let ans = aes_128_ctr(&ct, &key, Nonce::fixed(0))?;
+    check(
+        ans.to_str(),
+        "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ",
+    )
+}
+
+fn challenge_19(meter: &mut Meter) -> anyhow::Result<String> {
+    let data = load("inputs/set19.txt", meter)?;
+    let mut key = vec![0u8; 16];
+    StdRng::seed_from_u64(19).fill_bytes(&mut key);
+    let key = RawBytes { bytes: key };
+    let cts = data
+        .iter()
+        .map(|pt| aes_128_ctr(pt, &key, Nonce::fixed(0)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // Guess the opening line, then finish the longest one by hand.
+    let mut pad = ManyTimePad::new(&cts);
+    let opening = pad.drag(b"I have met them at close of day");
+    let best = opening
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("no placement for the crib"))?;
+    pad.accept(best);
+
+    let longest = (0..cts.len())
+        .max_by_key(|i| cts[*i].bytes.len())
+        .unwrap_or_default();
+    pad.set_plaintext(longest, 31, b"s turn,");
+    let ans = pad.plaintexts(b'?');
+    if ans != data {
+        anyhow::bail!("some plaintexts are still wrong");
+    }
+    check(
+        ans[longest].to_str(),
+        "He, too, has been changed in his turn,",
+    )
+}
+
+fn challenge_20(meter: &mut Meter) -> anyhow::Result<String> {
+    // The lyrics from challenge 7, a line per ciphertext, all under one
+    // fixed-nonce CTR keystream.
+    let data = load("inputs/set7.txt", meter)?;
+    let aes = Aes::new(b"YELLOW SUBMARINE")?;
+    let lyrics = read_all(DecryptReader::ecb(&aes, Pkcs7, &data[0].bytes[..]))?;
+    let mut key = vec![0u8; 16];
+    StdRng::seed_from_u64(20).fill_bytes(&mut key);
+    let key = RawBytes { bytes: key };
+    let cts = lyrics
+        .to_str()
+        .lines()
+        .filter(|l| l.len() >= 20)
+        .map(|l| aes_128_ctr(&RawBytes::from_str(l), &key, Nonce::fixed(0)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let ans = break_fixed_nonce(&cts)?;
+    check(ans.plaintexts[0].to_str(), "I'm back and I'm rin")
+}
+
+fn challenge_21(meter: &mut Meter) -> anyhow::Result<String> {
+    let mut mt = Mt19937::new(5489);
+    let ans = (0..10000).map(|_| mt.next_u32()).last().unwrap_or_default();
+    meter.process(10000 * 4);
+    check(ans.to_string(), "4123659995")
+}
+
+fn challenge_22(meter: &mut Meter) -> anyhow::Result<String> {
+    let mut rng = StdRng::seed_from_u64(22);
+    let mut now = 1_700_000_000;
+    let (output, seed) = timestamp_seeded_output(&mut now, &mut rng);
+    meter.process(4);
+
+    let ans = crack_timestamp_seed(output, now, 2000)
+        .ok_or_else(|| anyhow::anyhow!("no seed in the window"))?;
+    check((ans == seed).to_string(), "true")
+}
+
+fn challenge_24(meter: &mut Meter) -> anyhow::Result<String> {
+    let mut rng = StdRng::seed_from_u64(24);
+    let seed: u16 = rng.random();
+    let known = [b'A'; 14];
+    let ct = mt_stream_encrypt_with_prefix(&known, seed, &mut rng);
+    meter.queries += 1;
+    meter.process(known.len() + ct.len());
+
+    let ans =
+        recover_mt_stream_seed(&ct, &known).ok_or_else(|| anyhow::anyhow!("no seed matched"))?;
+    let now = 1_700_000_000;
+    let token = password_reset_token(now - 600);
+    let detected = is_time_seeded_token(&token, now, 3600);
+    check((ans == seed && detected).to_string(), "true")
+}
+
+fn challenge_25(meter: &mut Meter) -> anyhow::Result<String> {
+    let data = load("inputs/set7.txt", meter)?;
+    let aes = Aes::new(b"YELLOW SUBMARINE")?;
+    let pt = read_all(DecryptReader::ecb(&aes, Pkcs7, &data[0].bytes[..]))?;
+    let server = CtrEditServer::new(&mut StdRng::seed_from_u64(25));
+    let ct = server.encrypt(&pt.bytes);
+
+    let ans = recover_via_edit(&ct, |ct: &[u8], offset, new: &[u8]| {
+        meter.queries += 1;
+        meter.process(ct.len() + new.len());
+        server.edit(ct, offset, new)
+    })?;
+    let ans = RawBytes { bytes: ans };
+    if ans != pt {
+        anyhow::bail!("recovered plaintext differs");
+    }
+    let first = ans.to_str().lines().next().unwrap_or_default().to_string();
+    check(first, "I'm back and I'm ringin' the bell ")
+}
+
+fn challenge_26(meter: &mut Meter) -> anyhow::Result<String> {
+    let oracle = CtrCommentOracle::new(&mut StdRng::seed_from_u64(26));
+    let forged = ctr_bitflip_admin(meter.oracle(&oracle));
+    check(oracle.is_admin(&forged).to_string(), "true")
+}
+
+fn challenge_27(meter: &mut Meter) -> anyhow::Result<String> {
+    let oracle = KeyAsIvOracle::new(&mut StdRng::seed_from_u64(27));
+    let ct = oracle.encrypt("an ordinary comment");
+    meter.process(ct.len());
+
+    let key = recover_key_as_iv(&ct, |ct: &[u8]| {
+        meter.queries += 1;
+        meter.process(ct.len());
+        oracle.check_ascii(ct)
+    })?;
+    check((&key == oracle.key()).to_string(), "true")
+}
+
+fn challenge_28(meter: &mut Meter) -> anyhow::Result<String> {
+    let server = Sha1MacServer::new(&mut StdRng::seed_from_u64(28));
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let mac = server.sign(message);
+    meter.process(message.len());
+
+    let mut tampered = message.to_vec();
+    tampered[28] ^= 1;
+    let mut verifier = &server;
+    meter.queries += 2;
+    meter.process(message.len() + tampered.len());
+    let ok = verifier.verify(message, &mac) && !verifier.verify(&tampered, &mac);
+    check(ok.to_string(), "true")
+}
+
+fn challenge_29(meter: &mut Meter) -> anyhow::Result<String> {
+    let server = Sha1MacServer::new(&mut StdRng::seed_from_u64(29));
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let mac = server.sign(message);
+    meter.process(message.len());
+
+    let forged = forge_sha1_mac(
+        |m: &[u8], tag: &[u8]| {
+            meter.queries += 1;
+            meter.process(m.len());
+            let mut verifier = &server;
+            verifier.verify(m, tag)
+        },
+        message,
+        &mac,
+        b";admin=true",
+        64,
+    )?;
+    let (forged, _, _) = forged.ok_or_else(|| anyhow::anyhow!("no key length worked"))?;
+    check(forged.ends_with(b";admin=true").to_string(), "true")
+}
+
+fn challenge_30(meter: &mut Meter) -> anyhow::Result<String> {
+    let server = Md4MacServer::new(&mut StdRng::seed_from_u64(30));
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let mac = server.sign(message);
+    meter.process(message.len());
+
+    let forged = forge_md4_mac(
+        |m: &[u8], tag: &[u8]| {
+            meter.queries += 1;
+            meter.process(m.len());
+            let mut verifier = &server;
+            verifier.verify(m, tag)
+        },
+        message,
+        &mac,
+        b";admin=true",
+        64,
+    )?;
+    let (forged, _, _) = forged.ok_or_else(|| anyhow::anyhow!("no key length worked"))?;
+    check(forged.ends_with(b";admin=true").to_string(), "true")
+}
+
+fn challenge_49(meter: &mut Meter) -> anyhow::Result<String> {
+    let mut rng = StdRng::seed_from_u64(49);
+    let bank = CbcMacBank::new(&mut rng);
+    let (attacker, victim) = (2, 7);
+
+    // The client picks the IV: sign a transfer to ourselves and move it
+    // to the victim's account.
+    let own = bank.sign_transfer(attacker, attacker, 1_000_000, &mut rng);
+    meter.queries += 1;
+    meter.process(own.len());
+    let forged = forge_transfer_source(&own, victim)?;
+    let stolen = bank.process_transfer(&forged)?;
+
+    // The IV is fixed: splice our own list onto one of the victim's.
+    let captured = bank.sign_transfers(victim, &[(3, 25), (4, 1000)]);
+    meter.process(captured.len());
+    let forged = forge_transfer_list(&captured, attacker, 1_000_000, |txs: &[(u32, u64)]| {
+        meter.queries += 1;
+        let signed = bank.sign_transfers(attacker, txs);
+        meter.process(signed.len());
+        signed
+    })?;
+    let transfers = bank.process_transfers(&forged)?;
+
+    let wanted = Transfer {
+        from: victim,
+        to: attacker,
+        amount: 1_000_000,
+    };
+    check(
+        (stolen == wanted && transfers.contains(&wanted)).to_string(),
+        "true",
+    )
+}
+
+fn challenge_50(meter: &mut Meter) -> anyhow::Result<String> {
+    let key = b"YELLOW SUBMARINE";
+    let target = b"alert('MZA who was that?');\n";
+    let forged = forge_cbc_mac_hash_js(key, target, "alert('Ayo, the Wu is back!');")?;
+    meter.process(forged.len());
+    let hash = cbc_mac_hash(key, &forged)?;
+    check(
+        RawBytes { bytes: hash }.to_hex(),
+        "296b8d7cb78a243dda4d0a61d33bbdd1",
+    )
+}
+
+fn challenge_51(meter: &mut Meter) -> anyhow::Result<String> {
+    let mut found = Vec::new();
+    for cipher in [CompressionCipher::Stream, CompressionCipher::Block] {
+        let mut oracle = CompressionOracle::new(cipher, 51);
+        let secret = recover_secret(
+            |payload: &[u8]| {
+                meter.queries += 1;
+                meter.process(payload.len());
+                oracle.length(payload)
+            },
+            b"sessionid=",
+            BASE64_ALPHABET,
+            b'\n',
+            64,
+        )?;
+        found.push(String::from_utf8(secret)?);
+    }
+    if found[0] != found[1] {
+        anyhow::bail!("the two ciphers gave {:?} and {:?}", found[0], found[1]);
+    }
+    check(found.remove(0), SESSION_ID)
+}
+
+fn challenge_52(meter: &mut Meter) -> anyhow::Result<String> {
+    type F = AesCompression<2>;
+    type G = AesCompression<4>;
+    let found = cascade_collision::<F, G, _>(&mut StdRng::seed_from_u64(52))?;
+    meter.queries += found.f_calls + found.g_calls;
+    meter.process(found.a.len() + found.b.len());
+    let collides =
+        found.a != found.b && cascade_hash::<F, G>(&found.a) == cascade_hash::<F, G>(&found.b);
+    check(collides.to_string(), "true")
+}
+
+fn challenge_53(meter: &mut Meter) -> anyhow::Result<String> {
+    type C = AesCompression<3>;
+    let k = 12;
+    let mut rng = StdRng::seed_from_u64(53);
+    let mut target = vec![0u8; (1 << k) * 16];
+    rng.fill_bytes(&mut target);
+    let found = second_preimage::<C, _>(&target, k, &mut rng)?;
+    meter.queries += found.expandable.calls + found.bridge_calls;
+    meter.process(target.len());
+    let collides =
+        found.message != target && cheap_hash::<3>(&found.message) == cheap_hash::<3>(&target);
+    check(collides.to_string(), "true")
+}
+
+fn challenge_55(meter: &mut Meter) -> anyhow::Result<String> {
+    let found = find_md4_collision(&mut StdRng::seed_from_u64(55));
+    meter.queries += found.tries;
+    meter.process(found.a.len() + found.b.len());
+    let collides = found.a != found.b && md4(&found.a) == md4(&found.b);
+    check(collides.to_string(), "true")
+}
+
+/// Every implemented challenge, in order.
+pub fn registry() -> Vec<Challenge> {
+    vec![
+        Challenge {
+            set: 1,
+            number: 3,
+            name: "Single-byte XOR cipher",
+            run: challenge_3,
+        },
+        Challenge {
+            set: 1,
+            number: 4,
+            name: "Detect single-character XOR",
+            run: challenge_4,
+        },
+        Challenge {
+            set: 1,
+            number: 6,
+            name: "Break repeating-key XOR",
+            run: challenge_6,
+        },
+        Challenge {
+            set: 1,
+            number: 7,
+            name: "AES in ECB mode",
+            run: challenge_7,
+        },
+        Challenge {
+            set: 1,
+            number: 8,
+            name: "Detect AES in ECB mode",
+            run: challenge_8,
+        },
+        Challenge {
+            set: 2,
+            number: 9,
+            name: "Implement PKCS#7 padding",
+            run: challenge_9,
+        },
+        Challenge {
+            set: 2,
+            number: 10,
+            name: "Implement CBC mode",
+            run: challenge_10,
+        },
+        Challenge {
+            set: 2,
+            number: 11,
+            name: "An ECB/CBC detection oracle",
+            run: challenge_11,
+        },
+        Challenge {
+            set: 2,
+            number: 12,
+            name: "Byte-at-a-time ECB decryption (Simple)",
+            run: challenge_12,
+        },
+        Challenge {
+            set: 2,
+            number: 13,
+            name: "ECB cut-and-paste",
+            run: challenge_13,
+        },
+        Challenge {
+            set: 2,
+            number: 14,
+            name: "Byte-at-a-time ECB decryption (Harder)",
+            run: challenge_14,
+        },
+        Challenge {
+            set: 2,
+            number: 16,
+            name: "CBC bitflipping attacks",
+            run: challenge_16,
+        },
+        Challenge {
+            set: 3,
+            number: 17,
+            name: "The CBC padding oracle",
+            run: challenge_17,
+        },
+        Challenge {
+            set: 3,
+            number: 18,
+            name: "Implement CTR, the stream cipher mode",
+            run: challenge_18,
+        },
+        Challenge {
+            set: 3,
+            number: 19,
+            name: "Break fixed-nonce CTR mode using substitutions",
+            run: challenge_19,
+        },
+        Challenge {
+            set: 3,
+            number: 20,
+            name: "Break fixed-nonce CTR statistically",
+            run: challenge_20,
+        },
+        Challenge {
+            set: 3,
+            number: 21,
+            name: "Implement the MT19937 Mersenne Twister RNG",
+            run: challenge_21,
+        },
+        Challenge {
+            set: 3,
+            number: 22,
+            name: "Crack an MT19937 seed",
+            run: challenge_22,
+        },
+        Challenge {
+            set: 3,
+            number: 24,
+            name: "Create the MT19937 stream cipher and break it",
+            run: challenge_24,
+        },
+        Challenge {
+            set: 4,
+            number: 25,
+            name: "Break \"random access read/write\" AES CTR",
+            run: challenge_25,
+        },
+        Challenge {
+            set: 4,
+            number: 26,
+            name: "CTR bitflipping",
+            run: challenge_26,
+        },
+        Challenge {
+            set: 4,
+            number: 27,
+            name: "Recover the key from CBC with IV=Key",
+            run: challenge_27,
+        },
+        Challenge {
+            set: 4,
+            number: 28,
+            name: "Implement a SHA-1 keyed MAC",
+            run: challenge_28,
+        },
+        Challenge {
+            set: 4,
+            number: 29,
+            name: "Break a SHA-1 keyed MAC using length extension",
+            run: challenge_29,
+        },
+        Challenge {
+            set: 4,
+            number: 30,
+            name: "Break an MD4 keyed MAC using length extension",
+            run: challenge_30,
+        },
+        Challenge {
+            set: 7,
+            number: 49,
+            name: "CBC-MAC Message Forgery",
+            run: challenge_49,
+        },
+        Challenge {
+            set: 7,
+            number: 50,
+            name: "Hashing with CBC-MAC",
+            run: challenge_50,
+        },
+        Challenge {
+            set: 7,
+            number: 51,
+            name: "Compression Ratio Side-Channel Attacks",
+            run: challenge_51,
+        },
+        Challenge {
+            set: 7,
+            number: 52,
+            name: "Iterated Hash Function Multicollisions",
+            run: challenge_52,
+        },
+        Challenge {
+            set: 7,
+            number: 53,
+            name: "Kelsey and Schneier's Expandable Messages",
+            run: challenge_53,
+        },
+        Challenge {
+            set: 7,
+            number: 55,
+            name: "MD4 Collisions",
+            run: challenge_55,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        for c in registry() {
+            let mut meter = Meter::new();
+            let ans = (c.run)(&mut meter);
+            assert!(ans.is_ok(), "challenge {}: {:?}", c.number, ans);
+            assert!(meter.bytes > 0);
+        }
+    }
+
+    #[test]
+    fn test_meter_oracle() {
+        let mut meter = Meter::new();
+        {
+            let mut oracle = meter.oracle(|input: &[u8]| input.to_vec());
+            oracle(b"abc");
+            oracle(b"");
+        }
+        assert_eq!(
+            meter,
+            Meter {
+                queries: 2,
+                bytes: 6
+            }
+        );
+    }
+}
@@ -0,0 +1,84 @@
+#![allow(dead_code)]
+use crate::backend::*;
+use crate::merkle_damgard::*;
+use crate::modes::*;
+
+/// The chaining value every cheap hash starts from, cut to size.
+const INITIAL_STATE: [u8; 16] = *b"cheap hash state";
+
+/// Challenge 52's cheap compression function: the chaining value,
+/// zero-padded to an AES key, encrypts the message block, and the result
+/// is cut back down to `BYTES` bytes. With a state this small, birthday
+/// collisions take a couple of hundred calls for two bytes.
+#[derive(Debug, Clone)]
+pub struct AesCompression<const BYTES: usize>;
+
+impl<const BYTES: usize> Compression for AesCompression<BYTES> {
+    const BLOCK_SIZE: usize = 16;
+    const OUTPUT_SIZE: usize = BYTES;
+    const ENDIANNESS: Endian = Endian::Big;
+    type State = [u8; BYTES];
+
+    fn initial_state() -> [u8; BYTES] {
+        let mut state = [0u8; BYTES];
+        state.copy_from_slice(&INITIAL_STATE[..BYTES]);
+        state
+    }
+
+    fn compress(state: &mut [u8; BYTES], block: &[u8]) {
+        let mut key = [0u8; 16];
+        key[..BYTES].copy_from_slice(state);
+        let mut input = [0u8; 16];
+        input.copy_from_slice(block);
+        // The key is new every block, so there's nothing to keep.
+        let out = default_backend()
+            .keyed(&key)
+            .expect("16 byte key")
+            .encrypt_block(&input);
+        state.copy_from_slice(&out[..BYTES]);
+    }
+
+    fn state_to_bytes(state: &[u8; BYTES]) -> Vec<u8> {
+        state.to_vec()
+    }
+
+    fn state_from_bytes(bytes: &[u8]) -> [u8; BYTES] {
+        let mut state = [0u8; BYTES];
+        state.copy_from_slice(&bytes[..BYTES]);
+        state
+    }
+}
+
+/// A Merkle–Damgård hash with a `BYTES`-byte digest, cheap to break.
+pub type CheapHash<const BYTES: usize> = MerkleDamgard<AesCompression<BYTES>>;
+
+pub fn cheap_hash<const BYTES: usize>(data: &[u8]) -> Vec<u8> {
+    let mut h = CheapHash::<BYTES>::new();
+    h.update(data);
+    h.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cheap_hash() {
+        assert_eq!(cheap_hash::<2>(b"").len(), 2);
+        assert_eq!(cheap_hash::<5>(b"some message").len(), 5);
+        assert_ne!(
+            cheap_hash::<4>(b"some message"),
+            cheap_hash::<4>(b"some messagf")
+        );
+
+        // The state is a key: each block is AES under the one before.
+        let block = [7u8; 16];
+        let mut key = [0u8; 16];
+        key[..3].copy_from_slice(b"che");
+        let expected = default_backend().encrypt_block(&key, &block).unwrap();
+        assert_eq!(
+            compress_blocks::<AesCompression<3>>(AesCompression::<3>::initial_state(), &block),
+            expected[..3]
+        );
+    }
+}
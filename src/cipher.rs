@@ -1,8 +1,16 @@
 #![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::deadline::*;
+use crate::histogram::*;
+use crate::iv::*;
+use crate::modes::*;
+use crate::padding::*;
 use crate::raw_bytes::*;
 use itertools::Itertools;
-use openssl::symm::{Cipher, Crypter, Mode};
-use std::collections::HashSet;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::sync::OnceLock;
 
 pub fn single_key_xor(rb: &RawBytes, byte: u8) -> RawBytes {
     let data = rb.bytes.iter().map(|b| b ^ byte).collect();
@@ -24,54 +32,105 @@ pub fn all_xors(rb: &RawBytes) -> Vec<(u8, RawBytes)> {
         .collect()
 }
 
-pub fn score_for_english(rb: &RawBytes) -> i32 {
-    let mut score = 0;
+/// Anything that can rank a candidate plaintext; higher means more plausible.
+pub trait Scorer {
+    fn score(&self, bytes: &[u8]) -> f64;
+}
 
-    for b in &rb.bytes {
-        let ch = *b as char;
+impl<F: Fn(&[u8]) -> f64> Scorer for F {
+    fn score(&self, bytes: &[u8]) -> f64 {
+        self(bytes)
+    }
+}
 
-        if ch == 'e' {
-            score += 10;
-        } else if ch == 't' {
-            score += 9;
-        } else if ch == 'o' {
-            score += 8;
-        } else if ch == 'i' {
-            score += 7;
-        } else if ch == 'n' {
-            score += 6;
-        }
-        if ch.is_ascii_whitespace() {
-            score += 5;
-        } else if ch.is_ascii_lowercase() {
-            score += 4;
-        } else if ch.is_ascii_uppercase() {
-            score += 2;
-        } else if ch.is_numeric() {
-            score += 1;
-        } else {
-            score -= 2;
+pub fn score_for_english(rb: &RawBytes) -> f64 {
+    english_score(&rb.bytes)
+}
+
+// Probability floors for bytes the English reference never saw. Control
+// characters and high bytes essentially never occur in text, so a single one
+// costs more than a handful of unusual letters.
+const PRINTABLE_FLOOR: f64 = 1e-4;
+const NON_PRINTABLE_FLOOR: f64 = 1e-9;
+
+fn english_log_probs() -> &'static [f64; 256] {
+    static TABLE: OnceLock<[f64; 256]> = OnceLock::new();
+
+    TABLE.get_or_init(|| {
+        let freq = Histogram256::english().normalized();
+
+        let mut table = [0.0; 256];
+        for (b, (t, f)) in table.iter_mut().zip(freq.iter()).enumerate() {
+            let b = b as u8;
+            let floor = if b.is_ascii_graphic() || b.is_ascii_whitespace() {
+                PRINTABLE_FLOOR
+            } else {
+                NON_PRINTABLE_FLOOR
+            };
+            *t = f.max(floor).ln();
         }
-    }
-    score
+        table
+    })
+}
+
+/// Log-likelihood of `bytes` under the byte frequencies of English prose.
+pub fn english_score(bytes: &[u8]) -> f64 {
+    let table = english_log_probs();
+    bytes.iter().map(|b| table[*b as usize]).sum()
 }
 
 pub fn sort_by_english_score(mut rbs: Vec<RawBytes>) -> Vec<RawBytes> {
-    rbs.sort_by_key(|x| score_for_english(&x));
+    rbs.sort_by(|a, b| score_for_english(a).total_cmp(&score_for_english(b)));
     rbs
 }
 
-pub fn single_char_xor_decrypt_impl(rb: &RawBytes) -> (u8, RawBytes) {
-    let mut xors = all_xors(&rb);
-    xors.sort_by_key(|x| score_for_english(&x.1));
+/// Tries all 256 keys against `bytes`, decrypting into `scratch` so that no
+/// allocation happens per key. Returns the best key and its score; ties go to
+/// the larger key.
+pub fn single_char_xor_best_key<S: Scorer>(
+    bytes: &[u8],
+    scorer: &S,
+    scratch: &mut Vec<u8>,
+) -> (u8, f64) {
+    let mut best = (0, f64::NEG_INFINITY);
+
+    for key in 0..=255u8 {
+        scratch.clear();
+        scratch.extend(bytes.iter().map(|b| b ^ key));
+
+        let score = scorer.score(scratch);
+        if score >= best.1 {
+            best = (key, score);
+        }
+    }
+    best
+}
 
-    let b = xors.last().unwrap();
-    (
-        b.0,
-        RawBytes {
-            bytes: b.1.bytes.clone(),
-        },
-    )
+/// The `n` best `(key, plaintext, score)` guesses for `rb`, best first, for
+/// when the top scorer alone is not trustworthy (short or odd plaintexts).
+pub fn single_char_xor_top_candidates(rb: &RawBytes, n: usize) -> Vec<(u8, RawBytes, f64)> {
+    let mut scratch = Vec::with_capacity(rb.bytes.len());
+    let mut scores: Vec<(u8, f64)> = (0..=255u8)
+        .map(|key| {
+            scratch.clear();
+            scratch.extend(rb.bytes.iter().map(|b| b ^ key));
+            (key, english_score(&scratch))
+        })
+        .collect();
+
+    // Same tie-break as `single_char_xor_best_key`: larger keys first.
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.0.cmp(&a.0)));
+
+    scores
+        .into_iter()
+        .take(n)
+        .map(|(key, score)| (key, single_key_xor(rb, key), score))
+        .collect()
+}
+
+pub fn single_char_xor_decrypt_impl(rb: &RawBytes) -> (u8, RawBytes) {
+    let (key, _) = single_char_xor_best_key(&rb.bytes, &english_score, &mut Vec::new());
+    (key, single_key_xor(rb, key))
 }
 
 pub fn single_char_xor_decrypt(rb: &RawBytes) -> RawBytes {
@@ -79,6 +138,60 @@ pub fn single_char_xor_decrypt(rb: &RawBytes) -> RawBytes {
     b.1.clone()
 }
 
+/// Parallel "detect single-character XOR": cracks every line of `rbs`
+/// independently and returns `(line, key, plaintext, score)` ranked best
+/// first.
+pub fn detect_single_char_xor_par(rbs: &[RawBytes]) -> Vec<(usize, u8, RawBytes, f64)> {
+    let mut ans: Vec<(usize, u8, RawBytes, f64)> = rbs
+        .par_iter()
+        .enumerate()
+        .map_init(Vec::new, |scratch, (id, rb)| {
+            let (key, score) = single_char_xor_best_key(&rb.bytes, &english_score, scratch);
+            (id, key, single_key_xor(rb, key), score)
+        })
+        .collect();
+
+    ans.sort_by(|a, b| b.3.total_cmp(&a.3));
+    ans
+}
+
+/// Streaming "detect single-character XOR" over hex lines from `reader`,
+/// keeping only the `k` best `(line number, key, plaintext, score)` results,
+/// best first. Line numbers start at 1; blank lines are skipped but counted.
+pub fn detect_single_char_xor_stream<R: BufRead>(
+    mut reader: R,
+    k: usize,
+) -> anyhow::Result<Vec<(usize, u8, RawBytes, f64)>> {
+    let mut top: Vec<(usize, u8, RawBytes, f64)> = Vec::with_capacity(k + 1);
+    let mut line = String::new();
+    let mut scratch = Vec::new();
+    let mut line_no = 0;
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        line_no += 1;
+
+        let hex = line.trim();
+        if hex.is_empty() {
+            continue;
+        }
+
+        let rb = RawBytes::from_hex(hex);
+        let (key, score) = single_char_xor_best_key(&rb.bytes, &english_score, &mut scratch);
+        if top.len() == k && top.last().is_none_or(|worst| score <= worst.3) {
+            continue;
+        }
+
+        let at = top.partition_point(|x| x.3 >= score);
+        top.insert(at, (line_no, key, single_key_xor(&rb, key), score));
+        top.truncate(k);
+    }
+    Ok(top)
+}
+
 pub fn repeating_key_find_best_keysize(rb: &RawBytes) -> Vec<(usize, f64)> {
     let mut ans = Vec::new();
     for i in 2..40 {
@@ -99,48 +212,259 @@ pub fn repeating_key_find_best_keysize(rb: &RawBytes) -> Vec<(usize, f64)> {
             let a6 = RawBytes::hamming_distance_byte(c3, c4);
             hamming_dist += (a1 + a2 + a3 + a4 + a5 + a6) as f64 / 6.0;
         }
-        ans.push((i, hamming_dist as f64 / i as f64));
+        ans.push((i, hamming_dist / i as f64));
     }
     ans.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
     ans
 }
 
-pub fn repeating_key_xor_decrypt(rb: &RawBytes) -> Vec<RawBytes> {
+/// Kasiski examination: a repeated trigram usually means the same plaintext
+/// met the same key offset, so the distances between repeats are multiples
+/// of the key length. Each keysize `k` in `2..max_keysize` is scored by how
+/// far the fraction `f` of distances it divides beats the `1/k` a random
+/// distance would give, `(f - 1/k) / (1 - 1/k)`. Multiples of the key length
+/// only divide about half the distances and score low; factors of a
+/// composite key length score about as well as the key length itself.
+/// Best first.
+pub fn kasiski_keysizes(rb: &RawBytes, max_keysize: usize) -> Vec<(usize, f64)> {
+    let mut last_seen: HashMap<&[u8], usize> = HashMap::new();
+    let mut distances = Vec::new();
+
+    for (i, trigram) in rb.bytes.windows(3).enumerate() {
+        if let Some(prev) = last_seen.insert(trigram, i) {
+            distances.push(i - prev);
+        }
+    }
+
+    let total = distances.len().max(1) as f64;
+    let mut ans: Vec<(usize, f64)> = (2..max_keysize)
+        .map(|k| {
+            let divisible = distances.iter().filter(|d| *d % k == 0).count();
+            let chance = 1.0 / k as f64;
+            (k, (divisible as f64 / total - chance) / (1.0 - chance))
+        })
+        .collect();
+
+    ans.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    ans
+}
+
+/// Autocorrelation (Friedman) test: slides the ciphertext against itself and
+/// measures the fraction of positions where the bytes coincide. When the lag
+/// is a multiple of the key length every byte is XORed with the same key byte
+/// as its partner, so the rate jumps from the ~1/256 of random bytes to the
+/// plaintext's own coincidence rate. Returns `(lag, rate)` for each lag in
+/// `1..max_lag`, in lag order.
+pub fn autocorrelation_keysizes(rb: &RawBytes, max_lag: usize) -> Vec<(usize, f64)> {
+    (1..max_lag)
+        .filter(|lag| *lag < rb.bytes.len())
+        .map(|lag| {
+            let pairs = rb.bytes.len() - lag;
+            let same = rb
+                .bytes
+                .iter()
+                .zip(rb.bytes[lag..].iter())
+                .filter(|(a, b)| a == b)
+                .count();
+            (lag, same as f64 / pairs as f64)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeysizeEstimate {
+    pub keysize: usize,
+    pub hamming_rank: usize,
+    pub kasiski_rank: usize,
+}
+
+impl KeysizeEstimate {
+    /// Both estimators independently put this keysize first.
+    pub fn agrees(&self) -> bool {
+        self.hamming_rank == 0 && self.kasiski_rank == 0
+    }
+}
+
+/// Cross-checks the Hamming distance and Kasiski keysize estimators, ranking
+/// keysizes by the sum of their positions in both lists.
+pub fn estimate_keysize(rb: &RawBytes) -> Vec<KeysizeEstimate> {
+    let hamming = repeating_key_find_best_keysize(rb);
+    let kasiski = kasiski_keysizes(rb, 40);
+
+    let rank = |list: &[(usize, f64)], k| list.iter().position(|x| x.0 == k).unwrap();
+
+    let mut ans: Vec<KeysizeEstimate> = hamming
+        .iter()
+        .map(|(k, _)| KeysizeEstimate {
+            keysize: *k,
+            hamming_rank: rank(&hamming, *k),
+            kasiski_rank: rank(&kasiski, *k),
+        })
+        .collect();
+
+    ans.sort_by_key(|e| (e.hamming_rank + e.kasiski_rank, e.hamming_rank));
+    ans
+}
+
+/// Splits `rb` into `keysize` columns, column `i` holding every byte at an
+/// offset congruent to `i` mod `keysize`.
+pub fn transpose_blocks(rb: &RawBytes, keysize: usize) -> Vec<RawBytes> {
+    (0..keysize)
+        .map(|i| RawBytes {
+            bytes: rb.bytes.iter().skip(i).step_by(keysize).copied().collect(),
+        })
+        .collect()
+}
+
+/// Inverse of `transpose_blocks`: interleaves the columns back together.
+pub fn untranspose_blocks(columns: &[RawBytes]) -> RawBytes {
+    let len = columns.iter().map(|c| c.bytes.len()).sum();
+    let mut bytes = Vec::with_capacity(len);
+
+    for row in 0.. {
+        let before = bytes.len();
+        bytes.extend(columns.iter().filter_map(|c| c.bytes.get(row)));
+        if bytes.len() == before {
+            break;
+        }
+    }
+    RawBytes { bytes }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VigenereCandidate {
+    pub key: RawBytes,
+    pub plaintext: RawBytes,
+    pub keysize: usize,
+    pub score: f64,
+}
+
+/// Recovers a repeating XOR key of known size one column (key byte) per step.
+/// The partial result is the key prefix recovered so far.
+pub struct RepeatingKeyXorAttack {
+    columns: Vec<RawBytes>,
+    key: Vec<u8>,
+    scratch: Vec<u8>,
+}
+
+impl RepeatingKeyXorAttack {
+    pub fn new(rb: &RawBytes, keysize: usize) -> Self {
+        Self {
+            columns: transpose_blocks(rb, keysize),
+            key: Vec::with_capacity(keysize),
+            scratch: Vec::new(),
+        }
+    }
+}
+
+impl Checkpointable for RepeatingKeyXorAttack {
+    type Output = RawBytes;
+
+    fn step(&mut self) -> bool {
+        if let Some(column) = self.columns.get(self.key.len()) {
+            let b = single_char_xor_best_key(&column.bytes, &english_score, &mut self.scratch);
+            self.key.push(b.0);
+        }
+        self.key.len() == self.columns.len()
+    }
+
+    fn best_so_far(&self) -> RawBytes {
+        RawBytes {
+            bytes: self.key.clone(),
+        }
+    }
+}
+
+pub fn repeating_key_xor_decrypt(rb: &RawBytes) -> Vec<VigenereCandidate> {
     let key_sizes = repeating_key_find_best_keysize(rb);
 
     let mut ans = Vec::new();
 
     let experiments = 1;
     for (k, _) in key_sizes.iter().take(experiments) {
-        let mut key = Vec::new();
         //dbg!(*k);
-        for i in 0..*k {
-            let bytes: Vec<u8> = rb.bytes.iter().skip(i).step_by(*k).map(|x| *x).collect();
-
-            let rb = RawBytes { bytes };
-
-            let b = single_char_xor_decrypt_impl(&rb);
-            key.push(b.0);
-        }
-        let candidate = repeating_key_xor(rb, &RawBytes { bytes: key });
-        ans.push(candidate);
+        let mut attack = RepeatingKeyXorAttack::new(rb, *k);
+        while !attack.step() {}
+
+        let key = attack.best_so_far();
+        let plaintext = repeating_key_xor(rb, &key);
+        let score = score_for_english(&plaintext);
+        ans.push(VigenereCandidate {
+            key,
+            plaintext,
+            keysize: *k,
+            score,
+        });
     }
     ans
 }
 
+fn check_aes_128_key(key: &RawBytes) -> anyhow::Result<()> {
+    if key.bytes.len() != 16 {
+        anyhow::bail!("AES-128 needs a 16 byte key, got {}", key.bytes.len());
+    }
+    Ok(())
+}
+
 pub fn aes_128_ecb_decrypt_with_key(rb: &RawBytes, key: &RawBytes) -> anyhow::Result<RawBytes> {
-    let mut decrypter = Crypter::new(Cipher::aes_128_ecb(), Mode::Decrypt, &key.bytes, None)?;
-    decrypter.pad(false);
-    let mut decrypted = vec![0u8; 1024 * 3];
-    let mut bytes = decrypter.update(&rb.bytes, decrypted.as_mut_slice())?;
-    bytes += decrypter.finalize(decrypted.as_mut_slice())?;
+    check_aes_128_key(key)?;
+    let bytes = ecb_decrypt(&Aes::new(&key.bytes)?, &rb.bytes)?;
+    Ok(RawBytes { bytes })
+}
 
-    Ok(RawBytes {
-        bytes: decrypted[..bytes].into(),
-    })
+pub fn aes_128_ecb_encrypt_with_key(rb: &RawBytes, key: &RawBytes) -> anyhow::Result<RawBytes> {
+    check_aes_128_key(key)?;
+    let bytes = ecb_encrypt(&Aes::new(&key.bytes)?, &rb.bytes)?;
+    Ok(RawBytes { bytes })
+}
+
+pub fn aes_128_ecb_decrypt_with_padding<P: Padding>(
+    rb: &RawBytes,
+    key: &RawBytes,
+    padding: &P,
+) -> anyhow::Result<RawBytes> {
+    check_aes_128_key(key)?;
+    ecb_decrypt_with_padding(&Aes::new(&key.bytes)?, rb, padding)
 }
 
-pub fn aes_128_ecb_detect(rbs: &Vec<RawBytes>) -> Vec<(RawBytes, usize, i32)> {
+pub fn aes_128_ecb_encrypt_with_padding<P: Padding>(
+    rb: &RawBytes,
+    key: &RawBytes,
+    padding: &P,
+) -> anyhow::Result<RawBytes> {
+    check_aes_128_key(key)?;
+    ecb_encrypt_with_padding(&Aes::new(&key.bytes)?, rb, padding)
+}
+
+pub fn aes_128_cbc_decrypt_with_padding<P: Padding>(
+    rb: &RawBytes,
+    key: &RawBytes,
+    iv: &Iv,
+    padding: &P,
+) -> anyhow::Result<RawBytes> {
+    check_aes_128_key(key)?;
+    cbc_decrypt_with_padding(&Aes::new(&key.bytes)?, iv, rb, padding)
+}
+
+pub fn aes_128_cbc_encrypt_with_padding<P: Padding>(
+    rb: &RawBytes,
+    key: &RawBytes,
+    iv: &Iv,
+    padding: &P,
+) -> anyhow::Result<RawBytes> {
+    check_aes_128_key(key)?;
+    cbc_encrypt_with_padding(&Aes::new(&key.bytes)?, iv, rb, padding)
+}
+
+/// AES-128-CTR with the challenge 18 counter layout; encrypts and
+/// decrypts.
+pub fn aes_128_ctr(rb: &RawBytes, key: &RawBytes, nonce: Nonce) -> anyhow::Result<RawBytes> {
+    check_aes_128_key(key)?;
+    let bytes = ctr_apply(&Aes::new(&key.bytes)?, nonce, &rb.bytes)?;
+    Ok(RawBytes { bytes })
+}
+
+pub fn aes_128_ecb_detect(rbs: &[RawBytes]) -> Vec<(RawBytes, usize, i32)> {
     let block_size: usize = 16;
 
     let similar_score = |rb: &RawBytes| {
@@ -153,7 +477,7 @@ pub fn aes_128_ecb_detect(rbs: &Vec<RawBytes>) -> Vec<(RawBytes, usize, i32)> {
                 .iter()
                 .skip(block_size * i)
                 .take(block_size)
-                .map(|x| *x)
+                .copied()
                 .collect();
             different_blocks.insert(x);
         }
@@ -172,6 +496,68 @@ pub fn aes_128_ecb_detect(rbs: &Vec<RawBytes>) -> Vec<(RawBytes, usize, i32)> {
     ans
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct EcbDetection {
+    pub index: usize,
+    pub duplicates: usize,
+    /// Duplicate blocks a random ciphertext of this length would have on
+    /// average: the birthday bound `n(n - 1) / 2 / 2^128` for `n` blocks.
+    pub expected_duplicates: f64,
+    /// Chance that at least one random ciphertext of the corpus shows this
+    /// many duplicates; tiny values mean a genuine ECB hit.
+    pub p_value: f64,
+}
+
+// P(X >= k) for X ~ Poisson(lambda), summed from the tail so that tiny
+// lambdas don't vanish in `1 - P(X < k)`.
+fn poisson_tail(lambda: f64, k: usize) -> f64 {
+    if k == 0 {
+        return 1.0;
+    }
+
+    let mut term = (-lambda).exp();
+    for i in 1..=k {
+        term *= lambda / i as f64;
+    }
+
+    let mut tail = 0.0;
+    let mut i = k;
+    while term > tail * f64::EPSILON && term > 0.0 {
+        tail += term;
+        i += 1;
+        term *= lambda / i as f64;
+    }
+    tail.min(1.0)
+}
+
+/// `aes_128_ecb_detect` with statistical context, ranked most significant
+/// first.
+pub fn aes_128_ecb_detect_report(rbs: &[RawBytes]) -> Vec<EcbDetection> {
+    let block_space = 2f64.powi(128);
+    let lines = rbs.len() as f64;
+
+    let mut ans: Vec<EcbDetection> = aes_128_ecb_detect(rbs)
+        .into_iter()
+        .map(|(rb, index, duplicates)| {
+            let n = (rb.bytes.len() / 16) as f64;
+            let expected_duplicates = n * (n - 1.0) / 2.0 / block_space;
+
+            let p_line = poisson_tail(expected_duplicates, duplicates as usize);
+            let p_value = -(lines * (-p_line).ln_1p()).exp_m1();
+
+            EcbDetection {
+                index,
+                duplicates: duplicates as usize,
+                expected_duplicates,
+                p_value,
+            }
+        })
+        .collect();
+
+    ans.sort_by(|a, b| a.p_value.partial_cmp(&b.p_value).unwrap());
+    ans
+}
+
 pub fn add_pkcs7_padding<'a>(rbs: &'a RawBytes, block_len: usize) -> impl Iterator<Item = u8> + 'a {
     let len = rbs.bytes.len();
 
@@ -179,13 +565,57 @@ pub fn add_pkcs7_padding<'a>(rbs: &'a RawBytes, block_len: usize) -> impl Iterat
 
     rbs.bytes
         .iter()
-        .map(|x| *x)
-        .chain(std::iter::repeat(pad_len as u8).take(pad_len).into_iter())
+        .copied()
+        .chain(std::iter::repeat_n(pad_len as u8, pad_len))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaddingError {
+    /// The input is empty or not a whole number of blocks.
+    BadLength(usize),
+    /// The final byte is zero or claims more than a block of padding.
+    BadPadLength(u8),
+    /// Not every byte in the padding run equals the pad length.
+    InconsistentPadding,
+}
+
+impl std::fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PaddingError::BadLength(len) => write!(f, "invalid padded length {}", len),
+            PaddingError::BadPadLength(n) => write!(f, "invalid pad length {}", n),
+            PaddingError::InconsistentPadding => write!(f, "inconsistent padding bytes"),
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
+pub fn strip_pkcs7_padding(rb: &RawBytes, block_len: usize) -> Result<RawBytes, PaddingError> {
+    let len = rb.bytes.len();
+    if len == 0 || !len.is_multiple_of(block_len) {
+        return Err(PaddingError::BadLength(len));
+    }
+
+    let pad_len = rb.bytes[len - 1];
+    if pad_len == 0 || pad_len as usize > block_len {
+        return Err(PaddingError::BadPadLength(pad_len));
+    }
+
+    let data_len = len - pad_len as usize;
+    if rb.bytes[data_len..].iter().any(|b| *b != pad_len) {
+        return Err(PaddingError::InconsistentPadding);
+    }
+
+    Ok(RawBytes {
+        bytes: rb.bytes[..data_len].to_vec(),
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::input::*;
 
     #[test]
     fn test_single_xor_decrypt() {
@@ -199,13 +629,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scorer_closure_and_fn() {
+        let english: &dyn Scorer = &english_score;
+        assert!(english.score(b"the cat") > english.score(b"\x01\x02\x03"));
+
+        let count_a = |b: &[u8]| b.iter().filter(|c| **c == b'a').count() as f64;
+        let data = single_key_xor(&RawBytes::from_str("aaab"), 0x42);
+        let (key, score) = single_char_xor_best_key(&data.bytes, &count_a, &mut Vec::new());
+        assert_eq!((key, score), (0x42, 3.0));
+    }
+
+    #[test]
+    fn test_english_score_ranking() {
+        let plain = b"Cooking MC's like a pound of bacon";
+        let score = english_score(plain);
+
+        // Every other single-byte XOR of the plaintext is a near miss,
+        // including the all-printable case flip under 0x20.
+        for key in 1..=255u8 {
+            let garbage: Vec<u8> = plain.iter().map(|b| b ^ key).collect();
+            assert!(english_score(&garbage) < score, "key {:#x}", key);
+        }
+
+        assert!(english_score(b"the cat sat") > english_score(b"xqz#jv!@kw$"));
+        assert!(english_score(b"hello world") > english_score(b"hello\x00world"));
+        assert_eq!(english_score(b""), 0.0);
+    }
+
+    #[test]
+    fn test_single_char_xor_top_candidates() {
+        let rb = RawBytes::from_hex(
+            "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736",
+        );
+
+        let ans = single_char_xor_top_candidates(&rb, 5);
+        assert_eq!(ans.len(), 5);
+        assert_eq!(ans[0].0, single_char_xor_decrypt_impl(&rb).0);
+        assert_eq!(ans[0].1.to_str(), "Cooking MC's like a pound of bacon");
+        assert!(ans.windows(2).all(|w| w[0].2 >= w[1].2));
+
+        assert_eq!(single_char_xor_top_candidates(&rb, 1000).len(), 256);
+    }
+
     #[test]
     fn test_decrypt_single_file() {
-        let data = std::fs::read_to_string("inputs/set4.txt").unwrap();
+        let (_, data) = load_input("inputs/set4.txt").unwrap();
         let decryted_strings: Vec<RawBytes> = data
-            .lines()
-            .map(|s| RawBytes::from_hex(s))
-            .map(|b| single_char_xor_decrypt(&b))
+            .iter()
+            .map(single_char_xor_decrypt)
             //.inspect(|s| println!("{:?}", s))
             .collect();
         let decryted_strings = sort_by_english_score(decryted_strings);
@@ -220,6 +692,40 @@ mod tests {
         assert_eq!(best_str[0], "Now that the party is jumping\n");
     }
 
+    #[test]
+    fn test_detect_single_char_xor_par() {
+        let (_, data) = load_input("inputs/set4.txt").unwrap();
+
+        let ans = detect_single_char_xor_par(&data);
+        assert_eq!(ans.len(), data.len());
+        assert_eq!(ans[0].2.to_str(), "Now that the party is jumping\n");
+        assert_eq!(ans[0].2, single_key_xor(&data[ans[0].0], ans[0].1));
+        assert!(ans.windows(2).all(|w| w[0].3 >= w[1].3));
+    }
+
+    #[test]
+    fn test_detect_single_char_xor_stream() {
+        let file = std::fs::File::open("inputs/set4.txt").unwrap();
+        let ans = detect_single_char_xor_stream(std::io::BufReader::new(file), 3).unwrap();
+
+        let (_, data) = load_input("inputs/set4.txt").unwrap();
+        let expected = detect_single_char_xor_par(&data);
+
+        assert_eq!(ans.len(), 3);
+        for (a, e) in ans.iter().zip(expected.iter()) {
+            assert_eq!((a.0, a.1, &a.2), (e.0 + 1, e.1, &e.2));
+        }
+        assert_eq!(ans[0].2.to_str(), "Now that the party is jumping\n");
+
+        let text = "\n1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736\n";
+        let ans = detect_single_char_xor_stream(text.as_bytes(), 10).unwrap();
+        assert_eq!(ans.len(), 1);
+        assert_eq!(ans[0].0, 2);
+        assert!(detect_single_char_xor_stream(text.as_bytes(), 0)
+            .unwrap()
+            .is_empty());
+    }
+
     #[test]
     fn test_repeating_key_xor() {
         let data = "Burning 'em, if you ain't quick and nimble
@@ -235,22 +741,35 @@ I go crazy when I hear a cymbal";
     }
 
     #[test]
-    fn test_repeating_key_find_best_keysize() {
-        let data = std::fs::read_to_string("./inputs/set6.txt").unwrap();
+    fn test_transpose_blocks() {
+        let rb = RawBytes::from_str("abcdefgh");
+
+        let columns = transpose_blocks(&rb, 3);
+        let columns: Vec<String> = columns.iter().map(|c| c.to_str()).collect();
+        assert_eq!(columns, vec!["adg", "beh", "cf"]);
 
-        let data: String = data.lines().join("");
+        for keysize in 1..10 {
+            assert_eq!(untranspose_blocks(&transpose_blocks(&rb, keysize)), rb);
+        }
+    }
 
-        let rb = RawBytes::from_base64(&data);
+    #[test]
+    fn test_repeating_key_find_best_keysize() {
+        let (_, data) = load_input("./inputs/set6.txt").unwrap();
+        let rb = &data[0];
 
-        let x = repeating_key_find_best_keysize(&rb);
+        let x = repeating_key_find_best_keysize(rb);
 
         //dbg!(rb.to_str());
         dbg!(&x);
         assert_eq!(29, x[0].0);
 
-        let ans = repeating_key_xor_decrypt(&rb);
+        let ans = repeating_key_xor_decrypt(rb);
+        assert_eq!(ans[0].keysize, 29);
+        assert_eq!(ans[0].key.to_str(), "Terminator X: Bring the noise");
+        assert_eq!(repeating_key_xor(rb, &ans[0].key), ans[0].plaintext);
 
-        let ans: Vec<String> = ans.iter().map(|x| x.to_str()).collect();
+        let ans: Vec<String> = ans.iter().map(|x| x.plaintext.to_str()).collect();
         dbg!(&ans);
         assert_eq!(
             "I\'m back and I\'m ringin\' the bell ",
@@ -259,30 +778,121 @@ I go crazy when I hear a cymbal";
     }
 
     #[test]
-    fn test_aes_128_ecb() {
-        let key = RawBytes::from_str("YELLOW SUBMARINE");
-        let data = std::fs::read_to_string("./inputs/set7.txt").unwrap();
+    fn test_kasiski_keysizes() {
+        let (_, data) = load_input("./inputs/set6.txt").unwrap();
+        assert_eq!(kasiski_keysizes(&data[0], 40)[0].0, 29);
+
+        let (_, data) = load_input("./inputs/set7.txt").unwrap();
+        let plain = aes_128_ecb_decrypt_with_key(&data[0], &RawBytes::from_str("YELLOW SUBMARINE"))
+            .unwrap();
+        let rb = repeating_key_xor(&plain, &RawBytes::from_str("ICE"));
+        assert_eq!(kasiski_keysizes(&rb, 40)[0].0, 3);
+    }
 
-        let data: String = data.lines().join("");
+    #[test]
+    fn test_autocorrelation_keysizes() {
+        let (_, data) = load_input("./inputs/set6.txt").unwrap();
 
-        let rb = RawBytes::from_base64(&data);
+        let ans = autocorrelation_keysizes(&data[0], 40);
+        assert_eq!(ans.len(), 39);
+        assert_eq!(ans[0].0, 1);
 
-        let ans = aes_128_ecb_decrypt_with_key(&rb, &key).unwrap();
+        let best = ans.iter().max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        assert_eq!(best.unwrap().0, 29);
+
+        let plain = repeating_key_xor(
+            &data[0],
+            &RawBytes::from_str("Terminator X: Bring the noise"),
+        );
+        let rb = repeating_key_xor(&plain, &RawBytes::from_str("ICE"));
+        let ans = autocorrelation_keysizes(&rb, 10);
+        let (aligned, other): (Vec<&(usize, f64)>, Vec<_>) = ans.iter().partition(|x| x.0 % 3 == 0);
+        let lowest_aligned = aligned.iter().map(|x| x.1).fold(1.0, f64::min);
+        assert!(other.iter().all(|x| x.1 < lowest_aligned), "{:?}", ans);
+    }
+
+    #[test]
+    fn test_estimate_keysize() {
+        let (_, data) = load_input("./inputs/set6.txt").unwrap();
+
+        let ans = estimate_keysize(&data[0]);
+        assert_eq!(ans[0].keysize, 29);
+        assert!(ans[0].agrees());
+        assert!(!ans[1].agrees());
+    }
+
+    #[test]
+    fn test_repeating_key_xor_attack_deadline() {
+        let (_, data) = load_input("./inputs/set6.txt").unwrap();
+
+        let attack = RepeatingKeyXorAttack::new(&data[0], 29);
+        let ans = run_with_deadline(attack, std::time::Duration::from_secs(60));
+        assert_eq!(
+            ans,
+            DeadlineResult::Complete(RawBytes::from_str("Terminator X: Bring the noise"))
+        );
+
+        let attack = RepeatingKeyXorAttack::new(&data[0], 29);
+        let ans = run_with_deadline(attack, std::time::Duration::ZERO);
+        assert_eq!(ans, DeadlineResult::Partial(RawBytes::new()));
+    }
+
+    #[test]
+    fn test_aes_128_ecb() {
+        let key = RawBytes::from_str("YELLOW SUBMARINE");
+        let (_, data) = load_input("./inputs/set7.txt").unwrap();
+        let rb = &data[0];
+
+        let ans = aes_128_ecb_decrypt_with_key(rb, &key).unwrap();
 
         println!("{:?}", rb.to_hex());
         println!("{}", ans.to_str());
 
         assert_eq!(
             "I'm back and I'm ringin' the bell ",
-            ans.to_str().lines().nth(0).unwrap()
+            ans.to_str().lines().next().unwrap()
         );
     }
 
+    #[test]
+    fn test_aes_128_ecb_with_padding() {
+        let key = RawBytes::from_str("YELLOW SUBMARINE");
+        let (_, data) = load_input("./inputs/set7.txt").unwrap();
+
+        let raw = aes_128_ecb_decrypt_with_key(&data[0], &key).unwrap();
+        let ans = aes_128_ecb_decrypt_with_padding(&data[0], &key, &Pkcs7).unwrap();
+
+        assert_eq!(raw.bytes.len() - ans.bytes.len(), 4);
+        assert!(ans.to_str().ends_with("Play that funky music \n"));
+
+        assert!(aes_128_ecb_decrypt_with_padding(&data[0], &key, &Iso7816).is_err());
+
+        let ct = aes_128_ecb_encrypt_with_padding(&ans, &key, &Pkcs7).unwrap();
+        assert_eq!(ct, data[0]);
+    }
+
+    #[test]
+    fn test_aes_128_ecb_encrypt() {
+        // FIPS-197 appendix C.1, twice over to check the blocks don't chain.
+        let key = RawBytes::from_hex("000102030405060708090a0b0c0d0e0f");
+        let pt = RawBytes::from_hex(&"00112233445566778899aabbccddeeff".repeat(2));
+        let ct = aes_128_ecb_encrypt_with_key(&pt, &key).unwrap();
+        assert_eq!(ct.to_hex(), "69c4e0d86a7b0430d8cdb78070b4c55a".repeat(2));
+        assert_eq!(aes_128_ecb_decrypt_with_key(&ct, &key).unwrap(), pt);
+
+        // Without padding the input has to be whole blocks.
+        let short = RawBytes::from_str("YELLOW SUBMARIN");
+        assert!(aes_128_ecb_encrypt_with_key(&short, &key).is_err());
+        let padded = aes_128_ecb_encrypt_with_padding(&short, &key, &Pkcs7).unwrap();
+        assert_eq!(padded.bytes.len(), 16);
+        assert!(aes_128_ecb_encrypt_with_key(&pt, &RawBytes::from_str("short key")).is_err());
+    }
+
     #[test]
     fn test_detect_aes_128_ecb() {
         let data = std::fs::read_to_string("./inputs/set8.txt").unwrap();
 
-        let data: Vec<RawBytes> = data.lines().map(|s| RawBytes::from_base64(s)).collect();
+        let data: Vec<RawBytes> = data.lines().map(RawBytes::from_base64).collect();
 
         let ans = aes_128_ecb_detect(&data);
 
@@ -290,6 +900,30 @@ I go crazy when I hear a cymbal";
         assert_eq!(ans[0].2, 3);
     }
 
+    #[test]
+    fn test_detect_aes_128_ecb_report() {
+        let (_, data) = load_input("./inputs/set8.txt").unwrap();
+
+        let ans = aes_128_ecb_detect_report(&data);
+
+        assert_eq!(ans[0].index, 132);
+        assert_eq!(ans[0].duplicates, 3);
+        assert!(ans[0].p_value < 1e-80);
+        assert!(ans[0].expected_duplicates < 1e-35);
+
+        // Everything else looks exactly like random data.
+        assert!(ans[1..]
+            .iter()
+            .all(|d| d.duplicates == 0 && d.p_value == 1.0));
+    }
+
+    #[test]
+    fn test_poisson_tail() {
+        assert_eq!(poisson_tail(0.5, 0), 1.0);
+        assert!((poisson_tail(1.0, 1) - (1.0 - (-1.0f64).exp())).abs() < 1e-12);
+        assert!((poisson_tail(1e-30, 2) - 0.5e-60).abs() < 1e-72);
+    }
+
     #[test]
     fn test_pkcs7_padding() {
         let data = RawBytes::from_str("YELLOW SUBMARINE");
@@ -306,4 +940,45 @@ I go crazy when I hear a cymbal";
         assert_eq!(ans[..data.bytes.len()], data.bytes[..]);
         assert_eq!(ans[data.bytes.len()..], vec![0x03, 0x03, 0x03][..]);
     }
+
+    #[test]
+    fn test_strip_pkcs7_padding() {
+        let data = RawBytes::from_str("ICE ICE BABY\x04\x04\x04\x04");
+        let ans = strip_pkcs7_padding(&data, 16).unwrap();
+        assert_eq!(ans.to_str(), "ICE ICE BABY");
+
+        let data = RawBytes::from_str("ICE ICE BABY\x05\x05\x05\x05");
+        assert_eq!(
+            strip_pkcs7_padding(&data, 16),
+            Err(PaddingError::InconsistentPadding)
+        );
+
+        let data = RawBytes::from_str("ICE ICE BABY\x01\x02\x03\x04");
+        assert_eq!(
+            strip_pkcs7_padding(&data, 16),
+            Err(PaddingError::InconsistentPadding)
+        );
+
+        let data = RawBytes::from_str("ICE ICE BABY\x04\x04\x04\x00");
+        assert_eq!(
+            strip_pkcs7_padding(&data, 16),
+            Err(PaddingError::BadPadLength(0))
+        );
+
+        let data = RawBytes::from_str("ICE ICE BABY\x04\x04\x04");
+        assert_eq!(
+            strip_pkcs7_padding(&data, 16),
+            Err(PaddingError::BadLength(15))
+        );
+
+        for len in 0..40 {
+            let data = RawBytes {
+                bytes: (0..len).collect(),
+            };
+            let padded = RawBytes {
+                bytes: add_pkcs7_padding(&data, 16).collect(),
+            };
+            assert_eq!(strip_pkcs7_padding(&padded, 16).unwrap(), data);
+        }
+    }
 }
@@ -0,0 +1,688 @@
+#![allow(dead_code)]
+use crate::cipher::*;
+use crate::raw_bytes::*;
+use rand::prelude::*;
+use std::sync::OnceLock;
+
+/// The ordered symbol set a classical cipher shifts or substitutes over.
+/// Bytes outside the alphabet pass through untouched and do not consume key
+/// material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alphabet {
+    symbols: Vec<u8>,
+    index: [Option<u8>; 256],
+}
+
+impl Alphabet {
+    pub fn new(symbols: &str) -> Self {
+        let symbols: Vec<u8> = symbols.bytes().collect();
+        assert!(symbols.len() <= 256, "alphabet too large");
+
+        let mut index = [None; 256];
+        for (i, s) in symbols.iter().enumerate() {
+            assert!(index[*s as usize].is_none(), "repeated symbol in alphabet");
+            index[*s as usize] = Some(i as u8);
+        }
+        Self { symbols, index }
+    }
+
+    pub fn lowercase() -> Self {
+        Self::new("abcdefghijklmnopqrstuvwxyz")
+    }
+
+    pub fn uppercase() -> Self {
+        Self::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ")
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn position(&self, b: u8) -> Option<usize> {
+        self.index[b as usize].map(|i| i as usize)
+    }
+
+    pub fn symbol(&self, i: usize) -> u8 {
+        self.symbols[i % self.len()]
+    }
+}
+
+fn shift_bytes(rb: &RawBytes, shifts: &[usize], alphabet: &Alphabet) -> anyhow::Result<RawBytes> {
+    if alphabet.is_empty() {
+        anyhow::bail!("empty alphabet");
+    }
+    if shifts.is_empty() {
+        anyhow::bail!("empty key");
+    }
+    let n = alphabet.len();
+    let mut used = 0;
+
+    let bytes = rb
+        .bytes
+        .iter()
+        .map(|b| match alphabet.position(*b) {
+            Some(i) => {
+                let shift = shifts[used % shifts.len()];
+                used += 1;
+                alphabet.symbol(i + shift % n)
+            }
+            None => *b,
+        })
+        .collect();
+    Ok(RawBytes { bytes })
+}
+
+pub fn caesar_encrypt(
+    rb: &RawBytes,
+    shift: usize,
+    alphabet: &Alphabet,
+) -> anyhow::Result<RawBytes> {
+    shift_bytes(rb, &[shift], alphabet)
+}
+
+pub fn caesar_decrypt(
+    rb: &RawBytes,
+    shift: usize,
+    alphabet: &Alphabet,
+) -> anyhow::Result<RawBytes> {
+    if alphabet.is_empty() {
+        anyhow::bail!("empty alphabet");
+    }
+    let n = alphabet.len();
+    shift_bytes(rb, &[n - shift % n], alphabet)
+}
+
+/// Tries every shift and keeps the one `scorer` likes best.
+pub fn caesar_crack<S: Scorer>(
+    rb: &RawBytes,
+    alphabet: &Alphabet,
+    scorer: &S,
+) -> anyhow::Result<(usize, RawBytes)> {
+    let mut best: Option<(usize, f64, RawBytes)> = None;
+    for shift in 0..alphabet.len() {
+        let p = caesar_decrypt(rb, shift, alphabet)?;
+        let score = scorer.score(&p.bytes);
+        if best.as_ref().is_none_or(|b| score > b.1) {
+            best = Some((shift, score, p));
+        }
+    }
+    best.map(|(shift, _, p)| (shift, p))
+        .ok_or_else(|| anyhow::anyhow!("empty alphabet"))
+}
+
+fn vigenere_shifts(key: &RawBytes, alphabet: &Alphabet) -> anyhow::Result<Vec<usize>> {
+    key.bytes
+        .iter()
+        .map(|b| {
+            alphabet
+                .position(*b)
+                .ok_or_else(|| anyhow::anyhow!("key symbol {:#04x} is outside the alphabet", b))
+        })
+        .collect()
+}
+
+pub fn vigenere_encrypt(
+    rb: &RawBytes,
+    key: &RawBytes,
+    alphabet: &Alphabet,
+) -> anyhow::Result<RawBytes> {
+    shift_bytes(rb, &vigenere_shifts(key, alphabet)?, alphabet)
+}
+
+pub fn vigenere_decrypt(
+    rb: &RawBytes,
+    key: &RawBytes,
+    alphabet: &Alphabet,
+) -> anyhow::Result<RawBytes> {
+    let n = alphabet.len();
+    let shifts: Vec<usize> = vigenere_shifts(key, alphabet)?
+        .iter()
+        .map(|s| (n - s) % n)
+        .collect();
+    shift_bytes(rb, &shifts, alphabet)
+}
+
+/// `key[i]` is the ciphertext symbol that `alphabet` symbol `i` maps to.
+pub fn substitution_encrypt(rb: &RawBytes, key: &[u8], alphabet: &Alphabet) -> RawBytes {
+    let bytes = rb
+        .bytes
+        .iter()
+        .map(|b| alphabet.position(*b).map_or(*b, |i| key[i]))
+        .collect();
+    RawBytes { bytes }
+}
+
+pub fn substitution_decrypt(rb: &RawBytes, key: &[u8], alphabet: &Alphabet) -> RawBytes {
+    // Where each ciphertext byte sits in the key, straight from the bytes.
+    let mut inverse = [None; 256];
+    for (i, c) in key.iter().enumerate() {
+        inverse[*c as usize] = Some(i);
+    }
+    let bytes = rb
+        .bytes
+        .iter()
+        .map(|b| inverse[*b as usize].map_or(*b, |i| alphabet.symbol(i)))
+        .collect();
+    RawBytes { bytes }
+}
+
+// The commonest letter pairs inside English words, in percent.
+const ENGLISH_BIGRAMS: [(&[u8; 2], f64); 257] = [
+    (b"th", 3.56),
+    (b"he", 3.07),
+    (b"in", 2.43),
+    (b"er", 2.05),
+    (b"an", 1.99),
+    (b"re", 1.85),
+    (b"on", 1.76),
+    (b"at", 1.49),
+    (b"en", 1.45),
+    (b"nd", 1.35),
+    (b"ti", 1.34),
+    (b"es", 1.34),
+    (b"or", 1.28),
+    (b"te", 1.20),
+    (b"of", 1.17),
+    (b"ed", 1.17),
+    (b"is", 1.13),
+    (b"it", 1.12),
+    (b"al", 1.09),
+    (b"ar", 1.07),
+    (b"st", 1.05),
+    (b"to", 1.04),
+    (b"nt", 1.04),
+    (b"ng", 0.95),
+    (b"se", 0.93),
+    (b"ha", 0.93),
+    (b"as", 0.87),
+    (b"ou", 0.87),
+    (b"io", 0.83),
+    (b"le", 0.83),
+    (b"ve", 0.83),
+    (b"co", 0.79),
+    (b"me", 0.79),
+    (b"de", 0.76),
+    (b"hi", 0.76),
+    (b"ri", 0.73),
+    (b"ro", 0.73),
+    (b"ic", 0.70),
+    (b"ne", 0.69),
+    (b"ea", 0.69),
+    (b"ra", 0.69),
+    (b"ce", 0.65),
+    (b"li", 0.62),
+    (b"ch", 0.60),
+    (b"ll", 0.58),
+    (b"be", 0.58),
+    (b"ma", 0.57),
+    (b"si", 0.55),
+    (b"om", 0.55),
+    (b"ur", 0.54),
+    (b"ca", 0.54),
+    (b"el", 0.53),
+    (b"ta", 0.53),
+    (b"la", 0.53),
+    (b"ns", 0.51),
+    (b"di", 0.50),
+    (b"fo", 0.50),
+    (b"ho", 0.50),
+    (b"pe", 0.49),
+    (b"ec", 0.48),
+    (b"pr", 0.47),
+    (b"no", 0.47),
+    (b"ct", 0.46),
+    (b"us", 0.45),
+    (b"ac", 0.45),
+    (b"ot", 0.44),
+    (b"il", 0.43),
+    (b"tr", 0.43),
+    (b"ly", 0.43),
+    (b"nc", 0.42),
+    (b"et", 0.42),
+    (b"ut", 0.41),
+    (b"ss", 0.41),
+    (b"so", 0.40),
+    (b"rs", 0.40),
+    (b"un", 0.39),
+    (b"lo", 0.39),
+    (b"wa", 0.38),
+    (b"ge", 0.38),
+    (b"ie", 0.38),
+    (b"wh", 0.38),
+    (b"ee", 0.38),
+    (b"wi", 0.37),
+    (b"em", 0.37),
+    (b"ad", 0.37),
+    (b"ol", 0.36),
+    (b"rt", 0.36),
+    (b"po", 0.35),
+    (b"we", 0.35),
+    (b"na", 0.35),
+    (b"ul", 0.34),
+    (b"ni", 0.34),
+    (b"ts", 0.34),
+    (b"mo", 0.34),
+    (b"ow", 0.33),
+    (b"pa", 0.32),
+    (b"im", 0.32),
+    (b"mi", 0.32),
+    (b"ai", 0.32),
+    (b"sh", 0.31),
+    (b"ir", 0.31),
+    (b"su", 0.31),
+    (b"id", 0.30),
+    (b"os", 0.30),
+    (b"iv", 0.29),
+    (b"ia", 0.29),
+    (b"am", 0.29),
+    (b"fi", 0.28),
+    (b"ci", 0.28),
+    (b"vi", 0.27),
+    (b"pl", 0.26),
+    (b"ig", 0.26),
+    (b"tu", 0.26),
+    (b"ev", 0.25),
+    (b"ld", 0.25),
+    (b"ry", 0.25),
+    (b"mp", 0.24),
+    (b"fe", 0.24),
+    (b"bl", 0.24),
+    (b"ab", 0.23),
+    (b"gh", 0.23),
+    (b"ty", 0.23),
+    (b"op", 0.23),
+    (b"wo", 0.22),
+    (b"sa", 0.22),
+    (b"ay", 0.22),
+    (b"ex", 0.21),
+    (b"ke", 0.21),
+    (b"fr", 0.21),
+    (b"oo", 0.21),
+    (b"av", 0.20),
+    (b"ag", 0.20),
+    (b"if", 0.20),
+    (b"ap", 0.20),
+    (b"gr", 0.20),
+    (b"od", 0.20),
+    (b"bo", 0.19),
+    (b"sp", 0.19),
+    (b"rd", 0.19),
+    (b"do", 0.19),
+    (b"uc", 0.19),
+    (b"bu", 0.19),
+    (b"ei", 0.18),
+    (b"ov", 0.18),
+    (b"by", 0.18),
+    (b"rm", 0.18),
+    (b"ep", 0.17),
+    (b"tt", 0.17),
+    (b"oc", 0.17),
+    (b"fa", 0.17),
+    (b"ef", 0.17),
+    (b"cu", 0.16),
+    (b"rn", 0.16),
+    (b"sc", 0.16),
+    (b"gi", 0.15),
+    (b"da", 0.15),
+    (b"yo", 0.15),
+    (b"cr", 0.15),
+    (b"cl", 0.15),
+    (b"du", 0.15),
+    (b"ga", 0.15),
+    (b"qu", 0.15),
+    (b"ue", 0.15),
+    (b"ff", 0.15),
+    (b"ba", 0.15),
+    (b"ey", 0.14),
+    (b"ls", 0.14),
+    (b"va", 0.14),
+    (b"um", 0.14),
+    (b"pp", 0.14),
+    (b"ua", 0.14),
+    (b"up", 0.14),
+    (b"lu", 0.14),
+    (b"go", 0.13),
+    (b"ht", 0.13),
+    (b"ru", 0.13),
+    (b"ug", 0.13),
+    (b"ds", 0.13),
+    (b"lt", 0.13),
+    (b"pi", 0.13),
+    (b"rc", 0.12),
+    (b"rr", 0.12),
+    (b"eg", 0.12),
+    (b"au", 0.12),
+    (b"ck", 0.12),
+    (b"ew", 0.12),
+    (b"mu", 0.12),
+    (b"br", 0.12),
+    (b"bi", 0.11),
+    (b"pt", 0.11),
+    (b"ak", 0.11),
+    (b"pu", 0.11),
+    (b"ui", 0.11),
+    (b"rg", 0.11),
+    (b"ib", 0.11),
+    (b"tl", 0.11),
+    (b"ny", 0.11),
+    (b"ki", 0.11),
+    (b"rk", 0.10),
+    (b"ys", 0.10),
+    (b"ob", 0.10),
+    (b"mm", 0.10),
+    (b"fu", 0.10),
+    (b"ph", 0.10),
+    (b"og", 0.10),
+    (b"ms", 0.10),
+    (b"ye", 0.10),
+    (b"ud", 0.10),
+    (b"mb", 0.09),
+    (b"ip", 0.09),
+    (b"ub", 0.09),
+    (b"oi", 0.09),
+    (b"rl", 0.09),
+    (b"gu", 0.09),
+    (b"dr", 0.09),
+    (b"hr", 0.09),
+    (b"cc", 0.09),
+    (b"tw", 0.09),
+    (b"ft", 0.09),
+    (b"wn", 0.09),
+    (b"nu", 0.08),
+    (b"af", 0.08),
+    (b"hu", 0.08),
+    (b"nn", 0.08),
+    (b"eo", 0.08),
+    (b"vo", 0.08),
+    (b"rv", 0.08),
+    (b"nf", 0.08),
+    (b"xp", 0.08),
+    (b"gn", 0.07),
+    (b"sm", 0.07),
+    (b"fl", 0.07),
+    (b"iz", 0.07),
+    (b"ok", 0.07),
+    (b"nl", 0.07),
+    (b"my", 0.07),
+    (b"gl", 0.07),
+    (b"aw", 0.07),
+    (b"ju", 0.07),
+    (b"oa", 0.07),
+    (b"eq", 0.07),
+    (b"sy", 0.06),
+    (b"sl", 0.06),
+    (b"ps", 0.06),
+    (b"jo", 0.06),
+    (b"lf", 0.06),
+    (b"nv", 0.06),
+    (b"je", 0.06),
+    (b"nk", 0.06),
+    (b"kn", 0.06),
+    (b"gs", 0.06),
+    (b"dy", 0.06),
+    (b"hy", 0.06),
+    (b"ze", 0.06),
+    (b"ks", 0.06),
+    (b"xt", 0.06),
+    (b"bs", 0.05),
+];
+
+// How often English words start and end with each letter, in percent.
+const WORD_STARTS: [f64; 26] = [
+    11.7, 4.7, 5.2, 3.0, 2.8, 4.0, 1.6, 4.2, 7.0, 0.6, 0.6, 2.4, 4.3, 2.4, 7.6, 4.0, 0.2, 2.8, 7.8,
+    16.0, 1.2, 0.8, 5.5, 0.05, 0.8, 0.05,
+];
+const WORD_ENDS: [f64; 26] = [
+    2.5, 0.1, 0.5, 9.2, 19.0, 4.1, 2.6, 3.4, 0.5, 0.02, 1.0, 4.6, 1.5, 7.9, 4.7, 1.0, 0.02, 6.9,
+    14.0, 8.6, 0.3, 0.05, 0.8, 0.1, 7.3, 0.02,
+];
+
+// What any other pair gets, in percent.
+const BIGRAM_FLOOR: f64 = 0.01;
+
+/// Log-likelihoods of letter pairs, with index 26 standing for anything
+/// that isn't a letter, so word starts and ends count too.
+fn english_bigram_log_probs() -> &'static [[f64; 27]; 27] {
+    static TABLE: OnceLock<[[f64; 27]; 27]> = OnceLock::new();
+
+    TABLE.get_or_init(|| {
+        let mut table = [[BIGRAM_FLOOR.ln(); 27]; 27];
+        for (pair, f) in ENGLISH_BIGRAMS.iter() {
+            table[(pair[0] - b'a') as usize][(pair[1] - b'a') as usize] = f.ln();
+        }
+        for (i, (start, end)) in WORD_STARTS.iter().zip(WORD_ENDS.iter()).enumerate() {
+            table[26][i] = start.ln();
+            table[i][26] = end.ln();
+        }
+        table[26][26] = 0.0;
+        table
+    })
+}
+
+fn letter_index(b: u8) -> usize {
+    let b = b.to_ascii_lowercase();
+    if b.is_ascii_lowercase() {
+        (b - b'a') as usize
+    } else {
+        26
+    }
+}
+
+// The commonest English words, which settle what letter pairs can't.
+const COMMON_WORDS: [&str; 100] = [
+    "the", "of", "and", "to", "a", "in", "is", "you", "that", "it", "he", "was", "for", "on",
+    "are", "as", "with", "his", "they", "i", "at", "be", "this", "have", "from", "or", "one",
+    "had", "by", "word", "but", "not", "what", "all", "were", "we", "when", "your", "can", "said",
+    "there", "use", "an", "each", "which", "she", "do", "how", "their", "if", "will", "up",
+    "other", "about", "out", "many", "then", "them", "these", "so", "some", "her", "would", "make",
+    "like", "him", "into", "time", "has", "look", "two", "more", "write", "go", "see", "number",
+    "no", "way", "could", "people", "my", "than", "first", "water", "been", "call", "who", "oil",
+    "its", "now", "find", "long", "down", "day", "did", "get", "come", "made", "may", "part",
+];
+
+// What each letter of a common word is worth, in log-likelihood.
+const COMMON_WORD_BONUS: f64 = 0.5;
+
+/// `english_bigram_score` plus a bonus for every common word.
+pub fn english_text_score(bytes: &[u8]) -> f64 {
+    let words: f64 = bytes
+        .split(|b| !b.is_ascii_alphabetic())
+        .filter(|w| {
+            COMMON_WORDS
+                .iter()
+                .any(|c| c.as_bytes().eq_ignore_ascii_case(w))
+        })
+        .map(|w| w.len() as f64 * COMMON_WORD_BONUS)
+        .sum();
+    english_bigram_score(bytes) + words
+}
+
+/// `english_score` plus the log-likelihood of every pair of adjacent
+/// letters, and of the letters words start and end with. Single-letter
+/// frequencies can only sort a substitution key by how common each letter
+/// is; pairs tell `th` from `ht`.
+pub fn english_bigram_score(bytes: &[u8]) -> f64 {
+    let table = english_bigram_log_probs();
+    let padded = std::iter::once(b' ')
+        .chain(bytes.iter().copied())
+        .chain(std::iter::once(b' '));
+    let pairs: f64 = padded
+        .clone()
+        .zip(padded.skip(1))
+        .map(|(a, b)| table[letter_index(a)][letter_index(b)])
+        .sum();
+    english_score(bytes) + pairs
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubstitutionSolution {
+    pub key: Vec<u8>,
+    pub plaintext: RawBytes,
+    pub score: f64,
+}
+
+/// Simulated annealing over substitution keys: swap two key symbols, keep the
+/// swap if `scorer` improves or, with a probability that shrinks as the
+/// temperature cools, even if it does not. The best key seen is returned.
+pub fn solve_substitution<S: Scorer, R: Rng>(
+    rb: &RawBytes,
+    alphabet: &Alphabet,
+    scorer: &S,
+    rng: &mut R,
+    iterations: usize,
+) -> SubstitutionSolution {
+    let n = alphabet.len();
+    let mut key: Vec<u8> = (0..n).map(|i| alphabet.symbol(i)).collect();
+    key.shuffle(rng);
+
+    let score = |key: &[u8]| scorer.score(&substitution_decrypt(rb, key, alphabet).bytes);
+
+    let mut current = score(&key);
+    let mut best = (key.clone(), current);
+
+    let start_temp = rb.bytes.len().max(1) as f64 / 4.0;
+    for step in 0..iterations {
+        let temp = start_temp * (1.0 - step as f64 / iterations as f64) + 1e-3;
+
+        let (a, b) = (rng.random_range(0..n), rng.random_range(0..n));
+        key.swap(a, b);
+
+        let candidate = score(&key);
+        let delta = candidate - current;
+        if delta >= 0.0 || rng.random::<f64>() < (delta / temp).exp() {
+            current = candidate;
+            if current > best.1 {
+                best = (key.clone(), current);
+            }
+        } else {
+            key.swap(a, b);
+        }
+    }
+
+    let (key, score) = best;
+    SubstitutionSolution {
+        plaintext: substitution_decrypt(rb, &key, alphabet),
+        key,
+        score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::histogram::*;
+
+    #[test]
+    fn test_caesar() {
+        let abc = Alphabet::lowercase();
+        let rb = RawBytes::from_str("the quick brown fox, jumps!");
+
+        let ans = caesar_encrypt(&rb, 3, &abc).unwrap();
+        assert_eq!(ans.to_str(), "wkh txlfn eurzq ira, mxpsv!");
+        assert_eq!(caesar_decrypt(&ans, 3, &abc).unwrap(), rb);
+
+        // A pangram is useless for frequency analysis; use ordinary prose.
+        let rb = RawBytes::from_str("it was the best of times, it was the worst of times");
+        let ans = caesar_encrypt(&rb, 11, &abc).unwrap();
+
+        let (shift, plain) = caesar_crack(&ans, &abc, &chi_squared_score).unwrap();
+        assert_eq!(shift, 11);
+        assert_eq!(plain, rb);
+    }
+
+    #[test]
+    fn test_vigenere() {
+        let abc = Alphabet::uppercase();
+        let rb = RawBytes::from_str("ATTACK AT DAWN");
+        let key = RawBytes::from_str("LEMON");
+
+        let ans = vigenere_encrypt(&rb, &key, &abc).unwrap();
+        assert_eq!(ans.to_str(), "LXFOPV EF RNHR");
+        assert_eq!(vigenere_decrypt(&ans, &key, &abc).unwrap(), rb);
+    }
+
+    #[test]
+    fn test_custom_alphabet() {
+        let abc = Alphabet::new("0123456789");
+        let rb = RawBytes::from_str("pin 1979");
+
+        let ans = caesar_encrypt(&rb, 5, &abc).unwrap();
+        assert_eq!(ans.to_str(), "pin 6424");
+        assert_eq!(caesar_decrypt(&ans, 5, &abc).unwrap(), rb);
+    }
+
+    #[test]
+    fn test_substitution_round_trip() {
+        let abc = Alphabet::lowercase();
+        let key = b"qwertyuiopasdfghjklzxcvbnm";
+        let rb = RawBytes::from_str("hello world");
+
+        let ans = substitution_encrypt(&rb, key, &abc);
+        assert_eq!(ans.to_str(), "itssg vgksr");
+        assert_eq!(substitution_decrypt(&ans, key, &abc), rb);
+    }
+
+    #[test]
+    fn test_bad_keys_and_alphabets() {
+        let abc = Alphabet::uppercase();
+        let rb = RawBytes::from_str("ATTACK AT DAWN");
+        assert!(vigenere_encrypt(&rb, &RawBytes::from_str(""), &abc).is_err());
+        assert!(vigenere_decrypt(&rb, &RawBytes::from_str("lemon"), &abc).is_err());
+
+        let empty = Alphabet::new("");
+        assert!(caesar_encrypt(&rb, 3, &empty).is_err());
+        assert!(caesar_decrypt(&rb, 3, &empty).is_err());
+        assert!(caesar_crack(&rb, &empty, &chi_squared_score).is_err());
+    }
+
+    #[test]
+    fn test_substitution_key_with_high_bytes() {
+        let abc = Alphabet::new("abc");
+        let key = [0xe9, 0x80, 0xff];
+        let rb = RawBytes::from_str("cab, bac");
+
+        let ans = substitution_encrypt(&rb, &key, &abc);
+        assert_eq!(ans.bytes, [0xff, 0xe9, 0x80, b',', b' ', 0x80, 0xe9, 0xff]);
+        assert_eq!(substitution_decrypt(&ans, &key, &abc), rb);
+    }
+
+    #[test]
+    fn test_solve_substitution() {
+        let abc = Alphabet::lowercase();
+        let text = "it was the best of times it was the worst of times it was the age of \
+                    wisdom it was the age of foolishness it was the epoch of belief it was \
+                    the epoch of incredulity it was the season of light it was the season \
+                    of darkness it was the spring of hope it was the winter of despair we \
+                    had everything before us we had nothing before us we were all going \
+                    direct to heaven we were all going direct the other way";
+        let rb = RawBytes::from_str(text);
+        let ct = substitution_encrypt(&rb, b"qwertyuiopasdfghjklzxcvbnm", &abc);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let ans = solve_substitution(&ct, &abc, &english_text_score, &mut rng, 60000);
+
+        // The solver can only be as good as its scorer: it must find a key at
+        // least as convincing as the real one.
+        assert!(ans.score >= english_text_score(&rb.bytes));
+        assert_eq!(substitution_decrypt(&ct, &ans.key, &abc), ans.plaintext);
+
+        // And on a text this long that means nearly all of it reads right;
+        // what's left are rarer letters that could swap places unnoticed.
+        let right = ans
+            .plaintext
+            .bytes
+            .iter()
+            .zip(rb.bytes.iter())
+            .filter(|(a, b)| a == b)
+            .count();
+        assert!(
+            right * 100 >= rb.bytes.len() * 90,
+            "{}",
+            ans.plaintext.to_str()
+        );
+    }
+}
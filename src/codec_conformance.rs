@@ -0,0 +1,74 @@
+//! Differential tests of the hand-rolled hex and base64 codecs in `raw_bytes`
+//! against the `hex` and `base64` crates.
+use crate::raw_bytes::*;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use rand::prelude::*;
+
+fn edge_cases() -> Vec<Vec<u8>> {
+    let mut cases = vec![
+        vec![],
+        vec![0x00],
+        vec![0xff],
+        vec![0xff; 2],
+        vec![0xff; 3],
+        vec![0xff; 64],
+        vec![0x00; 64],
+        (0..=255).collect(),
+    ];
+    // Every remainder mod 3, so each base64 padding case comes up.
+    for len in 1..=9 {
+        cases.push((0..len as u8).map(|b| b.wrapping_mul(0x9d)).collect());
+    }
+    cases
+}
+
+fn random_cases() -> Vec<Vec<u8>> {
+    let mut rng = StdRng::seed_from_u64(556);
+    (0..500)
+        .map(|_| {
+            let mut bytes = vec![0u8; rng.random_range(0..200)];
+            rng.fill_bytes(&mut bytes);
+            bytes
+        })
+        .collect()
+}
+
+fn check(bytes: &[u8]) {
+    let rb = RawBytes {
+        bytes: bytes.to_vec(),
+    };
+
+    let h = hex::encode(bytes);
+    assert_eq!(rb.to_hex(), h);
+    assert_eq!(RawBytes::from_hex(&h).bytes, bytes);
+    assert_eq!(RawBytes::from_hex(&h.to_uppercase()).bytes, bytes);
+
+    let b64 = STANDARD.encode(bytes);
+    assert_eq!(rb.to_base64(), b64, "{:02x?}", bytes);
+    assert_eq!(RawBytes::from_base64(&b64).bytes, bytes, "{}", b64);
+}
+
+#[test]
+fn test_edge_cases() {
+    for bytes in edge_cases() {
+        check(&bytes);
+    }
+}
+
+#[test]
+fn test_random_inputs() {
+    for bytes in random_cases() {
+        check(&bytes);
+    }
+}
+
+#[test]
+fn test_our_output_decodes() {
+    for bytes in edge_cases().into_iter().chain(random_cases()) {
+        let rb = RawBytes {
+            bytes: bytes.clone(),
+        };
+        assert_eq!(hex::decode(rb.to_hex()).unwrap(), bytes);
+        assert_eq!(STANDARD.decode(rb.to_base64()).unwrap(), bytes);
+    }
+}
@@ -0,0 +1,189 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::deflate::*;
+use crate::iv::*;
+use crate::modes::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+use rand::prelude::*;
+
+/// The session cookie challenge 51 hides in every request.
+pub const SESSION_ID: &str = "TmV2ZXIgcmV2ZWFsIHRoZSBXdS1UYW5nIFNlY3JldCE=";
+
+/// The request the client sends with `payload` as its body.
+pub fn format_request(payload: &[u8]) -> Vec<u8> {
+    let head = format!(
+        "POST / HTTP/1.1\nHost: hapless.com\nCookie: sessionid={}\nContent-Length: {}\n",
+        SESSION_ID,
+        payload.len()
+    );
+    [head.as_bytes(), payload].concat()
+}
+
+/// How the compressed request is encrypted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompressionCipher {
+    /// AES-CTR: the ciphertext is exactly as long as the compressed request.
+    Stream,
+    /// AES-CBC with PKCS#7: lengths only move in whole blocks.
+    Block,
+}
+
+/// The challenge 51 oracle: compresses the request carrying `payload`,
+/// encrypts it under a fresh key (and nonce or IV), and gives away only
+/// the length.
+pub struct CompressionOracle {
+    cipher: CompressionCipher,
+    rng: StdRng,
+}
+
+impl CompressionOracle {
+    pub fn new(cipher: CompressionCipher, seed: u64) -> Self {
+        Self {
+            cipher,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn length(&mut self, payload: &[u8]) -> usize {
+        let compressed = deflate(&format_request(payload));
+        let mut key = [0u8; 16];
+        self.rng.fill_bytes(&mut key);
+        let aes = Aes::new(&key).expect("16 byte key");
+        let ct = match self.cipher {
+            CompressionCipher::Stream => {
+                ctr_apply(&aes, Nonce::random(&mut self.rng), &compressed).expect("any length")
+            }
+            CompressionCipher::Block => {
+                let iv = Iv::random(&mut self.rng, 16);
+                cbc_encrypt_with_padding(&aes, &iv, &RawBytes { bytes: compressed }, &Pkcs7)
+                    .expect("padded")
+                    .bytes
+            }
+        };
+        ct.len()
+    }
+}
+
+/// Bytes that appear nowhere in the request, to put in front of a guess
+/// without giving the compressor anything to match: `low` of them with
+/// 8-bit literal codes, then `high` with 9-bit ones.
+fn filler(low: usize, high: usize) -> Vec<u8> {
+    (0x0eu8..0x20)
+        .take(low)
+        .chain((0x90u8..0xb0).take(high))
+        .collect()
+}
+
+/// The challenge 51 attack (CRIME): recovers what follows `prefix` in the
+/// request from `length(payload)` alone, a character at a time. A payload
+/// of `prefix`, what is known so far and a right guess compresses into one
+/// match; a wrong guess costs a literal more.
+///
+/// A literal is only a byte or so, which the cipher's lengths may round
+/// away, so each candidate is tried behind filler of different bit lengths
+/// until one of them pushes every wrong candidate over a byte (or block)
+/// boundary that the right one stays under. A candidate longer than the
+/// shortest for any filler is out. `terminator` ends the secret, and no
+/// byte of the alphabet may be zero.
+pub fn recover_secret<O: FnMut(&[u8]) -> usize>(
+    mut length: O,
+    prefix: &[u8],
+    alphabet: &[u8],
+    terminator: u8,
+    max_len: usize,
+) -> anyhow::Result<Vec<u8>> {
+    let fillers: Vec<Vec<u8>> = (0..=16)
+        .flat_map(|low| (0..8).map(move |high| filler(low, high)))
+        .collect();
+
+    let mut known = Vec::new();
+    while known.len() < max_len {
+        let mut candidates: Vec<u8> = alphabet.to_vec();
+        candidates.push(terminator);
+        for fill in &fillers {
+            // Most fillers leave every guess in the same block. Appending a
+            // byte that can't be right, against appending nothing, shows
+            // in two queries whether this one might not.
+            let bare = [fill, prefix, &known].concat();
+            if length(&[&bare[..], &[0]].concat()) == length(&bare) {
+                continue;
+            }
+            let lengths: Vec<usize> = candidates
+                .iter()
+                .map(|c| length(&[fill, prefix, &known, &[*c]].concat()))
+                .collect();
+            let shortest = *lengths.iter().min().expect("candidates");
+            candidates = candidates
+                .iter()
+                .zip(&lengths)
+                .filter(|(_, l)| **l == shortest)
+                .map(|(c, _)| *c)
+                .collect();
+            if candidates.len() == 1 {
+                break;
+            }
+        }
+        match candidates[..] {
+            [c] if c == terminator => return Ok(known),
+            [c] => known.push(c),
+            _ => anyhow::bail!(
+                "couldn't tell {:?} apart after {:?}",
+                String::from_utf8_lossy(&candidates),
+                String::from_utf8_lossy(&known)
+            ),
+        }
+    }
+    anyhow::bail!("no terminator within {} bytes", max_len)
+}
+
+pub const BASE64_ALPHABET: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+
+/// Recovers the session cookie through `oracle`.
+pub fn recover_session_id(oracle: &mut CompressionOracle) -> anyhow::Result<String> {
+    let secret = recover_secret(
+        |payload: &[u8]| oracle.length(payload),
+        b"sessionid=",
+        BASE64_ALPHABET,
+        b'\n',
+        64,
+    )?;
+    Ok(String::from_utf8(secret)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filler_is_not_in_the_request() {
+        let request = format_request(b"");
+        let fill = filler(16, 7);
+        assert_eq!(fill.len(), 23);
+        assert!(fill.iter().all(|b| !request.contains(b)));
+        assert!(fill.iter().all(|b| !BASE64_ALPHABET.contains(b)));
+    }
+
+    #[test]
+    fn test_oracle_lengths() {
+        let mut stream = CompressionOracle::new(CompressionCipher::Stream, 51);
+        let mut block = CompressionOracle::new(CompressionCipher::Block, 51);
+        let payload = b"sessionid=TmV2";
+        let compressed = deflate(&format_request(payload)).len();
+        assert_eq!(stream.length(payload), compressed);
+        assert_eq!(block.length(payload), (compressed / 16 + 1) * 16);
+    }
+
+    #[test]
+    fn test_recovers_session_id_through_stream_cipher() {
+        let mut oracle = CompressionOracle::new(CompressionCipher::Stream, 51);
+        assert_eq!(recover_session_id(&mut oracle).unwrap(), SESSION_ID);
+    }
+
+    #[test]
+    fn test_recovers_session_id_through_block_cipher() {
+        let mut oracle = CompressionOracle::new(CompressionCipher::Block, 51);
+        assert_eq!(recover_session_id(&mut oracle).unwrap(), SESSION_ID);
+    }
+}
@@ -0,0 +1,78 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::iv::*;
+use crate::modes::*;
+use crate::oracles::*;
+use rand::prelude::*;
+
+/// The challenge 25 server: CTR under a key and nonce the attacker never
+/// sees, with an API that edits ciphertext in place.
+pub struct CtrEditServer {
+    aes: Aes,
+    nonce: u64,
+}
+
+impl CtrEditServer {
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        Self {
+            aes: Aes::new(&key).expect("16 byte key"),
+            nonce: rng.random(),
+        }
+    }
+
+    pub fn encrypt(&self, pt: &[u8]) -> Vec<u8> {
+        ctr_apply(&self.aes, Nonce::fixed(self.nonce), pt).expect("16 byte block")
+    }
+
+    pub fn edit(&self, ct: &[u8], offset: usize, new_plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        ctr_edit(
+            ct,
+            &self.aes,
+            Nonce::fixed(self.nonce),
+            offset,
+            new_plaintext,
+        )
+    }
+}
+
+impl EditOracle for &CtrEditServer {
+    fn edit(&mut self, ct: &[u8], offset: usize, new_plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        CtrEditServer::edit(self, ct, offset, new_plaintext)
+    }
+}
+
+/// Recovers the plaintext under `ct` from an edit oracle. Writing zeros
+/// over the whole thing hands back the keystream itself.
+pub fn recover_via_edit<O: EditOracle>(ct: &[u8], mut oracle: O) -> anyhow::Result<Vec<u8>> {
+    let keystream = oracle.edit(ct, 0, &vec![0u8; ct.len()])?;
+    Ok(ct
+        .iter()
+        .zip(keystream.iter())
+        .map(|(c, k)| c ^ k)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recover_via_edit() {
+        let server = CtrEditServer::new(&mut StdRng::seed_from_u64(25));
+        let pt = b"random access read/write, all in one";
+        let ct = server.encrypt(pt);
+
+        let mut queries = 0;
+        let ans = recover_via_edit(&ct, |ct: &[u8], offset, new: &[u8]| {
+            queries += 1;
+            server.edit(ct, offset, new)
+        })
+        .unwrap();
+        assert_eq!(ans, pt);
+        assert_eq!(queries, 1);
+
+        assert_eq!(recover_via_edit(&ct, &server).unwrap(), pt);
+    }
+}
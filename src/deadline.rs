@@ -0,0 +1,88 @@
+#![allow(dead_code)]
+use std::time::{Duration, Instant};
+
+/// An attack that makes progress in small steps and can report its best
+/// answer at any point in between.
+pub trait Checkpointable {
+    type Output;
+
+    /// Does one unit of work; returns true once the attack is finished.
+    fn step(&mut self) -> bool;
+
+    /// The full result once finished, otherwise whatever has been recovered.
+    fn best_so_far(&self) -> Self::Output;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeadlineResult<T> {
+    Complete(T),
+    /// Time ran out; holds the best partial result.
+    Partial(T),
+}
+
+impl<T> DeadlineResult<T> {
+    pub fn is_complete(&self) -> bool {
+        matches!(self, DeadlineResult::Complete(_))
+    }
+
+    pub fn into_inner(self) -> T {
+        match self {
+            DeadlineResult::Complete(t) | DeadlineResult::Partial(t) => t,
+        }
+    }
+}
+
+/// Steps `attack` until it finishes or `duration` has passed. The deadline is
+/// checked between steps, so a single slow step can overrun it.
+pub fn run_with_deadline<A: Checkpointable>(
+    mut attack: A,
+    duration: Duration,
+) -> DeadlineResult<A::Output> {
+    let start = Instant::now();
+
+    while start.elapsed() < duration {
+        if attack.step() {
+            return DeadlineResult::Complete(attack.best_so_far());
+        }
+    }
+    DeadlineResult::Partial(attack.best_so_far())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountTo {
+        n: usize,
+        at: usize,
+    }
+
+    impl Checkpointable for CountTo {
+        type Output = usize;
+
+        fn step(&mut self) -> bool {
+            self.at += 1;
+            if self.at == 3 {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            self.at == self.n
+        }
+
+        fn best_so_far(&self) -> usize {
+            self.at
+        }
+    }
+
+    #[test]
+    fn test_run_with_deadline() {
+        let ans = run_with_deadline(CountTo { n: 10, at: 0 }, Duration::from_secs(10));
+        assert_eq!(ans, DeadlineResult::Complete(10));
+
+        let ans = run_with_deadline(CountTo { n: 10, at: 0 }, Duration::from_millis(10));
+        assert_eq!(ans, DeadlineResult::Partial(3));
+
+        let ans = run_with_deadline(CountTo { n: 10, at: 0 }, Duration::ZERO);
+        assert!(!ans.is_complete());
+        assert_eq!(ans.into_inner(), 0);
+    }
+}
@@ -0,0 +1,310 @@
+#![allow(dead_code)]
+use std::collections::HashMap;
+
+/// The window LZ77 matches may reach back into.
+const WINDOW: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// How many earlier positions with the same three bytes are tried.
+const MAX_CHAIN: usize = 128;
+
+/// Base lengths of length codes 257 to 285, and their extra bits.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances of distance codes 0 to 29, and their extra bits.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Packs bits least significant first, as DEFLATE does.
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    nbits: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            out: Vec::new(),
+            acc: 0,
+            nbits: 0,
+        }
+    }
+
+    fn bits(&mut self, value: u32, n: u32) {
+        self.acc |= value << self.nbits;
+        self.nbits += n;
+        while self.nbits >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.nbits -= 8;
+        }
+    }
+
+    /// Huffman codes go in most significant bit first.
+    fn code(&mut self, code: u32, len: u32) {
+        self.bits(code.reverse_bits() >> (32 - len), len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+/// The fixed literal/length code (RFC 1951, 3.2.6).
+fn fixed_code(symbol: u16) -> (u32, u32) {
+    let s = symbol as u32;
+    match symbol {
+        0..=143 => (0x30 + s, 8),
+        144..=255 => (0x190 + s - 144, 9),
+        256..=279 => (s - 256, 7),
+        _ => (0xc0 + s - 280, 8),
+    }
+}
+
+/// The code for a length or distance, and its extra bits.
+fn bucket(value: usize, base: &[u16], extra: &[u8]) -> (usize, u32, u32) {
+    let i = base
+        .iter()
+        .rposition(|b| *b as usize <= value)
+        .expect("in range");
+    (i, (value - base[i] as usize) as u32, extra[i] as u32)
+}
+
+/// The longest earlier match for the bytes at `pos`, as (length,
+/// distance). Ties go to the nearest.
+fn longest_match(data: &[u8], pos: usize, candidates: &[usize]) -> Option<(usize, usize)> {
+    let max = (data.len() - pos).min(MAX_MATCH);
+    let mut best: Option<(usize, usize)> = None;
+    for &start in candidates.iter().rev().take(MAX_CHAIN) {
+        if pos - start > WINDOW {
+            break;
+        }
+        let len = data[start..]
+            .iter()
+            .zip(&data[pos..pos + max])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if len >= MIN_MATCH && best.is_none_or(|(l, _)| len > l) {
+            best = Some((len, pos - start));
+        }
+    }
+    best
+}
+
+/// Compresses `data` as a single DEFLATE block with the fixed Huffman
+/// codes, after a greedy LZ77 pass. Not zlib's ratio, but what a
+/// compression side channel needs is there: repeated strings get shorter.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut w = BitWriter::new();
+    // Last block, fixed codes.
+    w.bits(1, 1);
+    w.bits(1, 2);
+
+    let mut seen: HashMap<[u8; 3], Vec<usize>> = HashMap::new();
+    let remember = |seen: &mut HashMap<[u8; 3], Vec<usize>>, i: usize| {
+        if i + MIN_MATCH <= data.len() {
+            seen.entry([data[i], data[i + 1], data[i + 2]])
+                .or_default()
+                .push(i);
+        }
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let found = if pos + MIN_MATCH <= data.len() {
+            seen.get(&[data[pos], data[pos + 1], data[pos + 2]])
+                .and_then(|c| longest_match(data, pos, c))
+        } else {
+            None
+        };
+        match found {
+            Some((len, dist)) => {
+                let (i, extra, n) = bucket(len, &LENGTH_BASE, &LENGTH_EXTRA);
+                let (code, code_len) = fixed_code(257 + i as u16);
+                w.code(code, code_len);
+                w.bits(extra, n);
+                let (i, extra, n) = bucket(dist, &DIST_BASE, &DIST_EXTRA);
+                w.code(i as u32, 5);
+                w.bits(extra, n);
+                for i in pos..pos + len {
+                    remember(&mut seen, i);
+                }
+                pos += len;
+            }
+            None => {
+                let (code, code_len) = fixed_code(data[pos] as u16);
+                w.code(code, code_len);
+                remember(&mut seen, pos);
+                pos += 1;
+            }
+        }
+    }
+
+    let (code, code_len) = fixed_code(256);
+    w.code(code, code_len);
+    w.finish()
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> anyhow::Result<u32> {
+        let byte = self
+            .data
+            .get(self.pos / 8)
+            .ok_or_else(|| anyhow::anyhow!("compressed data ends early"))?;
+        let bit = (byte >> (self.pos % 8)) & 1;
+        self.pos += 1;
+        Ok(bit as u32)
+    }
+
+    fn bits(&mut self, n: u32) -> anyhow::Result<u32> {
+        let mut value = 0;
+        for i in 0..n {
+            value |= self.bit()? << i;
+        }
+        Ok(value)
+    }
+
+    /// `n` bits of a Huffman code, most significant first.
+    fn code(&mut self, n: u32) -> anyhow::Result<u32> {
+        let mut value = 0;
+        for _ in 0..n {
+            value = (value << 1) | self.bit()?;
+        }
+        Ok(value)
+    }
+
+    fn fixed_symbol(&mut self) -> anyhow::Result<u16> {
+        let c = self.code(7)?;
+        if c <= 0x17 {
+            return Ok(256 + c as u16);
+        }
+        let c = (c << 1) | self.bit()?;
+        match c {
+            0x30..=0xbf => return Ok((c - 0x30) as u16),
+            0xc0..=0xc7 => return Ok((280 + c - 0xc0) as u16),
+            _ => {}
+        }
+        let c = (c << 1) | self.bit()?;
+        Ok((144 + c - 0x190) as u16)
+    }
+}
+
+/// Decompresses DEFLATE made of stored and fixed-code blocks, which is all
+/// `deflate` writes. Dynamic-code blocks are an error.
+pub fn inflate(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut r = BitReader { data, pos: 0 };
+    let mut out = Vec::new();
+    loop {
+        let last = r.bits(1)? == 1;
+        match r.bits(2)? {
+            0 => {
+                r.pos = r.pos.div_ceil(8) * 8;
+                let len = r.bits(16)? as usize;
+                if r.bits(16)? as usize != !len & 0xffff {
+                    anyhow::bail!("stored block length doesn't match its complement");
+                }
+                for _ in 0..len {
+                    out.push(r.bits(8)? as u8);
+                }
+            }
+            1 => loop {
+                let symbol = r.fixed_symbol()?;
+                match symbol {
+                    0..=255 => out.push(symbol as u8),
+                    256 => break,
+                    257..=285 => {
+                        let i = (symbol - 257) as usize;
+                        let len =
+                            LENGTH_BASE[i] as usize + r.bits(LENGTH_EXTRA[i] as u32)? as usize;
+                        let i = r.code(5)? as usize;
+                        if i >= DIST_BASE.len() {
+                            anyhow::bail!("bad distance code {}", i);
+                        }
+                        let dist = DIST_BASE[i] as usize + r.bits(DIST_EXTRA[i] as u32)? as usize;
+                        if dist > out.len() {
+                            anyhow::bail!("distance {} reaches before the start", dist);
+                        }
+                        for _ in 0..len {
+                            out.push(out[out.len() - dist]);
+                        }
+                    }
+                    _ => anyhow::bail!("bad length code {}", symbol),
+                }
+            },
+            2 => anyhow::bail!("dynamic Huffman blocks aren't supported"),
+            _ => anyhow::bail!("reserved block type"),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = StdRng::seed_from_u64(624);
+        let mut random = vec![0u8; 5000];
+        rng.fill_bytes(&mut random);
+        let long_run = vec![b'a'; 1000];
+        let text = b"Never reveal the Wu-Tang Secret! Never reveal the Wu-Tang Secret!".repeat(40);
+        for data in [&b""[..], b"a", b"abcabcabcabc", &random, &long_run, &text] {
+            assert_eq!(inflate(&deflate(data)).unwrap(), data);
+        }
+        assert!(deflate(&text).len() < text.len() / 10);
+        assert!(deflate(&long_run).len() < 20);
+    }
+
+    #[test]
+    fn test_reference_streams() {
+        // zlib's raw DEFLATE, which picks fixed codes for these,
+        // of "hello hello hello" and of a stored block.
+        assert_eq!(
+            inflate(&[0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00]).unwrap(),
+            b"hello hello hello"
+        );
+        assert_eq!(
+            inflate(&[0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c']).unwrap(),
+            b"abc"
+        );
+        assert!(inflate(&[0x05]).is_err());
+        // A byte shorter than zlib's, since the whole repeat goes in one
+        // match, and zlib reads it back all the same.
+        assert_eq!(
+            deflate(b"hello hello hello"),
+            [0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0x40, 0x22, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_repeats_compress() {
+        let secret = b"sessionid=TmV2ZXIgcmV2ZWFs";
+        let with = |guess: &[u8]| deflate(&[&secret[..], b"\n", guess].concat()).len();
+        assert!(with(b"sessionid=TmV2") < with(b"sessionid=Xq9z"));
+    }
+}
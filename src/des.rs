@@ -0,0 +1,256 @@
+#![allow(dead_code)]
+
+// The FIPS 46-3 tables. Bits are numbered from 1 at the most significant
+// end, as in the standard.
+
+const IP: [u8; 64] = [
+    58, 50, 42, 34, 26, 18, 10, 2, 60, 52, 44, 36, 28, 20, 12, 4, 62, 54, 46, 38, 30, 22, 14, 6,
+    64, 56, 48, 40, 32, 24, 16, 8, 57, 49, 41, 33, 25, 17, 9, 1, 59, 51, 43, 35, 27, 19, 11, 3, 61,
+    53, 45, 37, 29, 21, 13, 5, 63, 55, 47, 39, 31, 23, 15, 7,
+];
+
+const FP: [u8; 64] = [
+    40, 8, 48, 16, 56, 24, 64, 32, 39, 7, 47, 15, 55, 23, 63, 31, 38, 6, 46, 14, 54, 22, 62, 30,
+    37, 5, 45, 13, 53, 21, 61, 29, 36, 4, 44, 12, 52, 20, 60, 28, 35, 3, 43, 11, 51, 19, 59, 27,
+    34, 2, 42, 10, 50, 18, 58, 26, 33, 1, 41, 9, 49, 17, 57, 25,
+];
+
+/// Expands the 32-bit half to 48 bits, each 4-bit group borrowing the bits
+/// either side of it.
+const E: [u8; 48] = [
+    32, 1, 2, 3, 4, 5, 4, 5, 6, 7, 8, 9, 8, 9, 10, 11, 12, 13, 12, 13, 14, 15, 16, 17, 16, 17, 18,
+    19, 20, 21, 20, 21, 22, 23, 24, 25, 24, 25, 26, 27, 28, 29, 28, 29, 30, 31, 32, 1,
+];
+
+const P: [u8; 32] = [
+    16, 7, 20, 21, 29, 12, 28, 17, 1, 15, 23, 26, 5, 18, 31, 10, 2, 8, 24, 14, 32, 27, 3, 9, 19,
+    13, 30, 6, 22, 11, 4, 25,
+];
+
+/// Picks the 56 key bits, dropping every eighth (parity) bit.
+const PC1: [u8; 56] = [
+    57, 49, 41, 33, 25, 17, 9, 1, 58, 50, 42, 34, 26, 18, 10, 2, 59, 51, 43, 35, 27, 19, 11, 3, 60,
+    52, 44, 36, 63, 55, 47, 39, 31, 23, 15, 7, 62, 54, 46, 38, 30, 22, 14, 6, 61, 53, 45, 37, 29,
+    21, 13, 5, 28, 20, 12, 4,
+];
+
+const PC2: [u8; 48] = [
+    14, 17, 11, 24, 1, 5, 3, 28, 15, 6, 21, 10, 23, 19, 12, 4, 26, 8, 16, 7, 27, 20, 13, 2, 41, 52,
+    31, 37, 47, 55, 30, 40, 51, 45, 33, 48, 44, 49, 39, 56, 34, 53, 46, 42, 50, 36, 29, 32,
+];
+
+const SHIFTS: [u32; 16] = [1, 1, 2, 2, 2, 2, 2, 2, 1, 2, 2, 2, 2, 2, 2, 1];
+
+const S: [[u8; 64]; 8] = [
+    [
+        14, 4, 13, 1, 2, 15, 11, 8, 3, 10, 6, 12, 5, 9, 0, 7, 0, 15, 7, 4, 14, 2, 13, 1, 10, 6, 12,
+        11, 9, 5, 3, 8, 4, 1, 14, 8, 13, 6, 2, 11, 15, 12, 9, 7, 3, 10, 5, 0, 15, 12, 8, 2, 4, 9,
+        1, 7, 5, 11, 3, 14, 10, 0, 6, 13,
+    ],
+    [
+        15, 1, 8, 14, 6, 11, 3, 4, 9, 7, 2, 13, 12, 0, 5, 10, 3, 13, 4, 7, 15, 2, 8, 14, 12, 0, 1,
+        10, 6, 9, 11, 5, 0, 14, 7, 11, 10, 4, 13, 1, 5, 8, 12, 6, 9, 3, 2, 15, 13, 8, 10, 1, 3, 15,
+        4, 2, 11, 6, 7, 12, 0, 5, 14, 9,
+    ],
+    [
+        10, 0, 9, 14, 6, 3, 15, 5, 1, 13, 12, 7, 11, 4, 2, 8, 13, 7, 0, 9, 3, 4, 6, 10, 2, 8, 5,
+        14, 12, 11, 15, 1, 13, 6, 4, 9, 8, 15, 3, 0, 11, 1, 2, 12, 5, 10, 14, 7, 1, 10, 13, 0, 6,
+        9, 8, 7, 4, 15, 14, 3, 11, 5, 2, 12,
+    ],
+    [
+        7, 13, 14, 3, 0, 6, 9, 10, 1, 2, 8, 5, 11, 12, 4, 15, 13, 8, 11, 5, 6, 15, 0, 3, 4, 7, 2,
+        12, 1, 10, 14, 9, 10, 6, 9, 0, 12, 11, 7, 13, 15, 1, 3, 14, 5, 2, 8, 4, 3, 15, 0, 6, 10, 1,
+        13, 8, 9, 4, 5, 11, 12, 7, 2, 14,
+    ],
+    [
+        2, 12, 4, 1, 7, 10, 11, 6, 8, 5, 3, 15, 13, 0, 14, 9, 14, 11, 2, 12, 4, 7, 13, 1, 5, 0, 15,
+        10, 3, 9, 8, 6, 4, 2, 1, 11, 10, 13, 7, 8, 15, 9, 12, 5, 6, 3, 0, 14, 11, 8, 12, 7, 1, 14,
+        2, 13, 6, 15, 0, 9, 10, 4, 5, 3,
+    ],
+    [
+        12, 1, 10, 15, 9, 2, 6, 8, 0, 13, 3, 4, 14, 7, 5, 11, 10, 15, 4, 2, 7, 12, 9, 5, 6, 1, 13,
+        14, 0, 11, 3, 8, 9, 14, 15, 5, 2, 8, 12, 3, 7, 0, 4, 10, 1, 13, 11, 6, 4, 3, 2, 12, 9, 5,
+        15, 10, 11, 14, 1, 7, 6, 0, 8, 13,
+    ],
+    [
+        4, 11, 2, 14, 15, 0, 8, 13, 3, 12, 9, 7, 5, 10, 6, 1, 13, 0, 11, 7, 4, 9, 1, 10, 14, 3, 5,
+        12, 2, 15, 8, 6, 1, 4, 11, 13, 12, 3, 7, 14, 10, 15, 6, 8, 0, 5, 9, 2, 6, 11, 13, 8, 1, 4,
+        10, 7, 9, 5, 0, 15, 14, 2, 3, 12,
+    ],
+    [
+        13, 2, 8, 4, 6, 15, 11, 1, 10, 9, 3, 14, 5, 0, 12, 7, 1, 15, 13, 8, 10, 3, 7, 4, 12, 5, 6,
+        11, 0, 14, 9, 2, 7, 11, 4, 1, 9, 12, 14, 2, 0, 6, 10, 13, 15, 3, 5, 8, 2, 1, 14, 7, 4, 10,
+        8, 13, 15, 12, 9, 0, 3, 5, 6, 11,
+    ],
+];
+
+/// The four weak keys, whose sixteen subkeys are all the same, so that
+/// encryption is its own inverse.
+pub const WEAK_KEYS: [u64; 4] = [
+    0x0101010101010101,
+    0xfefefefefefefefe,
+    0xe0e0e0e0f1f1f1f1,
+    0x1f1f1f1f0e0e0e0e,
+];
+
+/// The six pairs of semi-weak keys: encrypting under one of a pair
+/// decrypts under the other.
+pub const SEMI_WEAK_KEY_PAIRS: [(u64, u64); 6] = [
+    (0x011f011f010e010e, 0x1f011f010e010e01),
+    (0x01e001e001f101f1, 0xe001e001f101f101),
+    (0x01fe01fe01fe01fe, 0xfe01fe01fe01fe01),
+    (0x1fe01fe00ef10ef1, 0xe01fe01ff10ef10e),
+    (0x1ffe1ffe0efe0efe, 0xfe1ffe1ffe0efe0e),
+    (0xe0fee0fef1fef1fe, 0xfee0fee0fef1fef1),
+];
+
+/// The parity bits, which DES ignores.
+const PARITY_MASK: u64 = 0x0101010101010101;
+
+/// Gathers the bits of `input`, a `width`-bit value, that `table` names,
+/// into a value `table.len()` bits wide.
+fn permute(input: u64, width: u32, table: &[u8]) -> u64 {
+    table.iter().fold(0, |acc, &bit| {
+        (acc << 1) | ((input >> (width - bit as u32)) & 1)
+    })
+}
+
+/// The round function: expand, mix in the subkey, substitute through the
+/// S-boxes, permute.
+fn feistel(half: u32, subkey: u64) -> u32 {
+    let x = permute(half as u64, 32, &E) ^ subkey;
+    let mut out = 0u32;
+    for (i, sbox) in S.iter().enumerate() {
+        let six = ((x >> (42 - 6 * i)) & 0x3f) as usize;
+        // The outer two bits pick the row, the middle four the column.
+        let row = ((six >> 4) & 2) | (six & 1);
+        let col = (six >> 1) & 0xf;
+        out = (out << 4) | sbox[row * 16 + col] as u32;
+    }
+    permute(out as u64, 32, &P) as u32
+}
+
+/// The sixteen 48-bit round keys: PC-1, then the two 28-bit halves rotated
+/// left by the schedule, each round's selected by PC-2.
+pub fn key_schedule(key: u64) -> [u64; 16] {
+    let cd = permute(key, 64, &PC1);
+    let rotl28 = |x: u64, n: u32| ((x << n) | (x >> (28 - n))) & 0x0fff_ffff;
+    let (mut c, mut d) = (cd >> 28, cd & 0x0fff_ffff);
+    let mut out = [0u64; 16];
+    for (k, shift) in out.iter_mut().zip(SHIFTS) {
+        c = rotl28(c, shift);
+        d = rotl28(d, shift);
+        *k = permute((c << 28) | d, 56, &PC2);
+    }
+    out
+}
+
+fn crypt(subkeys: impl Iterator<Item = u64>, block: u64) -> u64 {
+    let x = permute(block, 64, &IP);
+    let (mut l, mut r) = ((x >> 32) as u32, x as u32);
+    for k in subkeys {
+        let next = l ^ feistel(r, k);
+        l = r;
+        r = next;
+    }
+    // The halves are swapped once more before the final permutation.
+    permute(((r as u64) << 32) | l as u64, 64, &FP)
+}
+
+/// DES under one 64-bit key (56 bits of key and 8 parity bits). Far too
+/// small a key for anything but exercises, which is the point: it is the
+/// cipher for meet-in-the-middle and brute-force work.
+#[derive(Debug, Clone)]
+pub struct Des {
+    subkeys: [u64; 16],
+}
+
+impl Des {
+    pub fn new(key: &[u8]) -> anyhow::Result<Self> {
+        if key.len() != 8 {
+            anyhow::bail!("DES keys are 8 bytes, got {}", key.len());
+        }
+        let mut k = [0u8; 8];
+        k.copy_from_slice(key);
+        Ok(Self::from_u64(u64::from_be_bytes(k)))
+    }
+
+    pub fn from_u64(key: u64) -> Self {
+        Self {
+            subkeys: key_schedule(key),
+        }
+    }
+
+    pub fn encrypt_u64(&self, block: u64) -> u64 {
+        crypt(self.subkeys.iter().copied(), block)
+    }
+
+    pub fn decrypt_u64(&self, block: u64) -> u64 {
+        crypt(self.subkeys.iter().rev().copied(), block)
+    }
+}
+
+/// Whether `key` is one of the four weak keys, parity bits aside.
+pub fn is_weak_key(key: u64) -> bool {
+    WEAK_KEYS.iter().any(|w| (w ^ key) & !PARITY_MASK == 0)
+}
+
+/// Whether `key` is one of the twelve semi-weak keys, parity bits aside.
+pub fn is_semi_weak_key(key: u64) -> bool {
+    SEMI_WEAK_KEY_PAIRS
+        .iter()
+        .any(|(a, b)| (a ^ key) & !PARITY_MASK == 0 || (b ^ key) & !PARITY_MASK == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_cipher::*;
+
+    #[test]
+    fn test_known_vectors() {
+        for (key, pt, ct) in [
+            (0x133457799bbcdff1, 0x0123456789abcdef, 0x85e813540f0ab405),
+            (0x0e329232ea6d0d73, 0x8787878787878787, 0x0000000000000000),
+            (0x0123456789abcdef, 0x4e6f772069732074, 0x3fa40e8a984d4815),
+        ] {
+            let des = Des::from_u64(key);
+            assert_eq!(des.encrypt_u64(pt), ct);
+            assert_eq!(des.decrypt_u64(ct), pt);
+        }
+
+        // The same through the byte-oriented interface.
+        let des = Des::new(&0x133457799bbcdff1u64.to_be_bytes()).unwrap();
+        let mut block = 0x0123456789abcdefu64.to_be_bytes();
+        des.encrypt_block(&mut block);
+        assert_eq!(block, 0x85e813540f0ab405u64.to_be_bytes());
+        assert!(Des::new(&[0; 7]).is_err());
+    }
+
+    #[test]
+    fn test_parity_bits_are_ignored() {
+        let a = Des::from_u64(0x133457799bbcdff1);
+        let b = Des::from_u64(0x133457799bbcdff1 ^ PARITY_MASK);
+        assert_eq!(a.encrypt_u64(42), b.encrypt_u64(42));
+    }
+
+    #[test]
+    fn test_weak_and_semi_weak_keys() {
+        let x = 0x0123456789abcdef;
+        for &k in &WEAK_KEYS {
+            assert!(is_weak_key(k) && !is_semi_weak_key(k));
+            let des = Des::from_u64(k);
+            assert!(key_schedule(k).iter().all(|s| *s == key_schedule(k)[0]));
+            assert_eq!(des.encrypt_u64(des.encrypt_u64(x)), x);
+        }
+        for &(a, b) in &SEMI_WEAK_KEY_PAIRS {
+            assert!(is_semi_weak_key(a) && is_semi_weak_key(b) && !is_weak_key(a));
+            let (da, db) = (Des::from_u64(a), Des::from_u64(b));
+            assert_eq!(db.encrypt_u64(da.encrypt_u64(x)), x);
+        }
+        // Flipping parity bits leaves a weak key weak.
+        assert!(is_weak_key(0x0000000000000000));
+        assert!(!is_weak_key(0x133457799bbcdff1));
+        assert!(!is_semi_weak_key(0x133457799bbcdff1));
+    }
+}
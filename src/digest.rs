@@ -0,0 +1,78 @@
+#![allow(dead_code)]
+use crate::merkle_damgard::*;
+
+/// An incremental hash function, so constructions built on one (HMAC,
+/// the length extension and the like) can take any of them.
+pub trait Digest: Clone {
+    fn new() -> Self;
+
+    fn update(&mut self, data: &[u8]);
+
+    fn finalize(self) -> Vec<u8>;
+
+    /// The length of the digest, in bytes.
+    fn output_size(&self) -> usize;
+
+    /// The size of the blocks the compression function takes, in bytes.
+    fn block_size(&self) -> usize;
+
+    /// Hashes `data` in one go.
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut h = Self::new();
+        h.update(data);
+        h.finalize()
+    }
+}
+
+/// SHA-1, MD4 and MD5 all come from here; SHA-3 is a sponge and has its
+/// own.
+impl<C: Compression + Clone> Digest for MerkleDamgard<C> {
+    fn new() -> Self {
+        MerkleDamgard::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        MerkleDamgard::update(self, data);
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        MerkleDamgard::finalize(self)
+    }
+
+    fn output_size(&self) -> usize {
+        C::OUTPUT_SIZE
+    }
+
+    fn block_size(&self) -> usize {
+        C::BLOCK_SIZE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md4::*;
+    use crate::md5::*;
+    use crate::sha1::*;
+    use crate::sha3::*;
+
+    fn check<D: Digest>(data: &[u8], one_shot: &[u8], block_size: usize) {
+        assert_eq!(D::digest(data), one_shot);
+
+        let mut h = D::new();
+        assert_eq!(h.output_size(), one_shot.len());
+        assert_eq!(h.block_size(), block_size);
+        h.update(&data[..10]);
+        h.update(&data[10..]);
+        assert_eq!(h.finalize(), one_shot);
+    }
+
+    #[test]
+    fn test_all_hashes() {
+        let data: Vec<u8> = (0..200u8).collect();
+        check::<Sha1>(&data, &sha1(&data), 64);
+        check::<Md4>(&data, &md4(&data), 64);
+        check::<Md5>(&data, &md5(&data), 64);
+        check::<Sha3_256>(&data, &sha3_256(&data), 136);
+    }
+}
@@ -0,0 +1,380 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::histogram::*;
+use crate::modes::*;
+use crate::oracles::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+use rand::prelude::*;
+use std::collections::HashMap;
+
+/// How a black-box block cipher oracle lays out `prefix || input || suffix`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockLayout {
+    pub block_size: usize,
+    pub prefix_len: usize,
+    pub suffix_len: usize,
+}
+
+fn block(ct: &[u8], block_size: usize, i: usize) -> &[u8] {
+    &ct[i * block_size..(i + 1) * block_size]
+}
+
+/// Infers the block size and the lengths of the hidden prefix and suffix of
+/// a deterministic, padded block cipher oracle such as ECB with PKCS#7.
+///
+/// The block size shows up as the jump in ciphertext length while growing
+/// the input. The prefix is located by watching which ciphertext block
+/// changes with the first input byte, and how much input it takes before
+/// that block no longer depends on the next one. Neither step compares the
+/// input against the hidden bytes, so their contents can't confuse it.
+pub fn discover_block_layout<O: EncryptionOracle>(mut oracle: O) -> anyhow::Result<BlockLayout> {
+    find_block_layout(&mut oracle)
+}
+
+fn find_block_layout<O: EncryptionOracle + ?Sized>(oracle: &mut O) -> anyhow::Result<BlockLayout> {
+    let base = oracle.encrypt(&[]).len();
+
+    let (block_size, first_jump) = (1..=256)
+        .find_map(|i| {
+            let len = oracle.encrypt(&vec![0u8; i]).len();
+            if len > base {
+                Some((len - base, i))
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| anyhow::anyhow!("ciphertext length never grew"))?;
+    if block_size < 2 {
+        anyhow::bail!("ciphertext grows byte by byte; not a padded block cipher");
+    }
+    let hidden_len = base - first_jump;
+
+    let first_changed = {
+        let a = oracle.encrypt(&[0]);
+        let b = oracle.encrypt(&[1]);
+        (0..a.len() / block_size)
+            .find(|i| block(&a, block_size, *i) != block(&b, block_size, *i))
+            .ok_or_else(|| anyhow::anyhow!("input does not affect the ciphertext"))?
+    };
+
+    // Smallest amount of input that completes the block the prefix ends in.
+    let mut fill = block_size;
+    for i in 0..block_size {
+        let mut input = vec![0u8; i + 1];
+        let a = oracle.encrypt(&input);
+        input[i] = 1;
+        let b = oracle.encrypt(&input);
+        if block(&a, block_size, first_changed) == block(&b, block_size, first_changed) {
+            fill = i;
+            break;
+        }
+    }
+
+    let prefix_len = first_changed * block_size + (block_size - fill) % block_size;
+    if prefix_len > hidden_len {
+        anyhow::bail!("inconsistent layout: prefix longer than all hidden bytes");
+    }
+
+    Ok(BlockLayout {
+        block_size,
+        prefix_len,
+        suffix_len: hidden_len - prefix_len,
+    })
+}
+
+/// The order the byte-at-a-time attack tries guesses in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CandidateOrder {
+    /// 0x00 through 0xff.
+    Sequential,
+    /// Printable ASCII from most to least common in English, then the rest.
+    /// Roughly halves the oracle queries when the secret is text.
+    PrintableFirst,
+}
+
+pub fn candidate_bytes(order: CandidateOrder) -> Vec<u8> {
+    let mut bytes: Vec<u8> = (0..=255).collect();
+
+    if order == CandidateOrder::PrintableFirst {
+        let english = Histogram256::english();
+        bytes.sort_by_key(|b| {
+            let printable = b.is_ascii_graphic() || b.is_ascii_whitespace();
+            (!printable, std::cmp::Reverse(english.count(*b)))
+        });
+    }
+    bytes
+}
+
+const FILLER: u8 = b'A';
+
+/// The output of the byte-at-a-time attack along with what it learned about
+/// the oracle on the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredSecret {
+    pub bytes: RawBytes,
+    /// The secret length implied by the ciphertext length transitions; the
+    /// attack decrypts exactly this many bytes.
+    pub length: usize,
+    pub block_size: usize,
+    pub prefix_len: usize,
+}
+
+/// Recovers the secret suffix an ECB oracle appends to attacker input
+/// (challenges 12 and 14), one byte at a time. Each unknown byte is lined up
+/// as the last byte of a block and guesses are tried in `order` until one
+/// encrypts to the same block.
+///
+/// The suffix length is worked out from where the ciphertext grows before
+/// any decryption, so the attack stops exactly at the end of the secret
+/// instead of decoding padding bytes as garbage.
+pub fn byte_at_a_time_ecb<O: EncryptionOracle>(
+    mut oracle: O,
+    order: CandidateOrder,
+) -> anyhow::Result<RecoveredSecret> {
+    let layout = find_block_layout(&mut oracle)?;
+    let bs = layout.block_size;
+
+    // Enough filler to finish the prefix's last block; attacker blocks start
+    // at `first_block`.
+    let align = (bs - layout.prefix_len % bs) % bs;
+    let first_block = (layout.prefix_len + align) / bs;
+
+    // Two identical attacker blocks encrypt identically only under ECB.
+    let probe = oracle.encrypt(&vec![FILLER; align + 2 * bs]);
+    if block(&probe, bs, first_block) != block(&probe, bs, first_block + 1) {
+        anyhow::bail!("not an ECB oracle: equal plaintext blocks encrypt differently");
+    }
+
+    let candidates = candidate_bytes(order);
+    let mut targets: HashMap<usize, Vec<u8>> = HashMap::new();
+    let mut known: Vec<u8> = Vec::with_capacity(layout.suffix_len);
+
+    for i in 0..layout.suffix_len {
+        let pad = bs - 1 - i % bs;
+        let target = targets
+            .entry(pad)
+            .or_insert_with(|| oracle.encrypt(&vec![FILLER; align + pad]));
+        let target = block(target, bs, first_block + i / bs).to_vec();
+
+        let mut window = vec![FILLER; pad];
+        window.extend_from_slice(&known);
+        let window = &window[window.len() - (bs - 1)..];
+
+        let mut probe = vec![FILLER; align];
+        probe.extend_from_slice(window);
+        probe.push(0);
+
+        let found = candidates.iter().find(|c| {
+            probe[align + bs - 1] = **c;
+            block(&oracle.encrypt(&probe), bs, first_block) == &target[..]
+        });
+        match found {
+            Some(c) => known.push(*c),
+            None => anyhow::bail!("no guess matched suffix byte {}", i),
+        }
+    }
+
+    Ok(RecoveredSecret {
+        bytes: RawBytes { bytes: known },
+        length: layout.suffix_len,
+        block_size: bs,
+        prefix_len: layout.prefix_len,
+    })
+}
+
+/// Challenges 12 and 14: the secret an ECB oracle appends to attacker
+/// input, with the guesses ordered for text. Any prefix the oracle adds in
+/// front is measured and stepped over first.
+pub fn recover_ecb_suffix<O: EncryptionOracle>(oracle: O) -> anyhow::Result<RawBytes> {
+    Ok(byte_at_a_time_ecb(oracle, CandidateOrder::PrintableFirst)?.bytes)
+}
+
+/// `prefix || input || secret` under AES-128-ECB and a fixed key: the
+/// oracle of challenge 12 without a prefix, and of challenge 14 with a
+/// random one.
+pub struct EcbSuffixOracle {
+    aes: Aes,
+    prefix: Vec<u8>,
+    secret: Vec<u8>,
+}
+
+impl EcbSuffixOracle {
+    pub fn new<R: Rng>(secret: &[u8], rng: &mut R) -> Self {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        Self {
+            aes: Aes::new(&key).expect("16 byte key"),
+            prefix: Vec::new(),
+            secret: secret.to_vec(),
+        }
+    }
+
+    /// Also puts 1 to 64 random bytes, the same on every call, in front.
+    pub fn with_random_prefix<R: Rng>(secret: &[u8], rng: &mut R) -> Self {
+        let mut oracle = Self::new(secret, rng);
+        oracle.prefix = vec![0u8; rng.random_range(1..=64)];
+        rng.fill_bytes(&mut oracle.prefix);
+        oracle
+    }
+}
+
+impl EncryptionOracle for EcbSuffixOracle {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        let data = RawBytes {
+            bytes: [&self.prefix[..], input, &self.secret[..]].concat(),
+        };
+        ecb_encrypt_with_padding(&self.aes, &data, &Pkcs7)
+            .expect("padded")
+            .bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::*;
+    use crate::iv::*;
+
+    fn ecb_oracle(prefix: Vec<u8>, suffix: Vec<u8>) -> impl FnMut(&[u8]) -> Vec<u8> {
+        let key = RawBytes::from_str("YELLOW SUBMARINE");
+        move |input| {
+            let data = RawBytes {
+                bytes: [&prefix[..], input, &suffix[..]].concat(),
+            };
+            aes_128_ecb_encrypt_with_padding(&data, &key, &Pkcs7)
+                .unwrap()
+                .bytes
+        }
+    }
+
+    #[test]
+    fn test_discover_block_layout() {
+        for prefix_len in [0, 1, 5, 15, 16, 21, 40] {
+            for suffix_len in [0, 3, 16, 33] {
+                // Hidden bytes equal to the probe bytes must not matter.
+                let oracle = ecb_oracle(vec![0u8; prefix_len], vec![1u8; suffix_len]);
+
+                let layout = discover_block_layout(oracle).unwrap();
+                assert_eq!(
+                    layout,
+                    BlockLayout {
+                        block_size: 16,
+                        prefix_len,
+                        suffix_len,
+                    }
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_candidate_bytes() {
+        let ans = candidate_bytes(CandidateOrder::PrintableFirst);
+        assert_eq!(&ans[..3], b" et");
+
+        let mut sorted = ans.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, candidate_bytes(CandidateOrder::Sequential));
+    }
+
+    #[test]
+    fn test_byte_at_a_time_ecb() {
+        let secret = b"Rollin' in my 5.0\nWith my rag-top down so my hair can blow\n".to_vec();
+
+        let mut counts = Vec::new();
+        for order in [CandidateOrder::Sequential, CandidateOrder::PrintableFirst] {
+            for prefix_len in [0, 7, 16] {
+                let mut oracle = ecb_oracle(vec![0x10; prefix_len], secret.clone());
+                let mut queries = 0;
+                let counted = |input: &[u8]| {
+                    queries += 1;
+                    oracle(input)
+                };
+
+                let ans = byte_at_a_time_ecb(counted, order).unwrap();
+                assert_eq!(
+                    ans,
+                    RecoveredSecret {
+                        bytes: RawBytes {
+                            bytes: secret.clone()
+                        },
+                        length: secret.len(),
+                        block_size: 16,
+                        prefix_len,
+                    }
+                );
+                counts.push(queries);
+            }
+        }
+        assert!(counts[3] * 2 < counts[0], "{:?}", counts);
+    }
+
+    #[test]
+    fn test_byte_at_a_time_ecb_binary_secret() {
+        let secret: Vec<u8> = (0..=255u8).rev().step_by(7).collect();
+        let oracle = ecb_oracle(vec![], secret.clone());
+
+        let ans = byte_at_a_time_ecb(oracle, CandidateOrder::PrintableFirst).unwrap();
+        assert_eq!(ans.bytes.bytes, secret);
+        assert_eq!(ans.length, secret.len());
+    }
+
+    #[test]
+    fn test_byte_at_a_time_ecb_stops_at_secret_end() {
+        // Secrets that end in pad-like bytes or exactly on a block boundary.
+        for secret in [&b"ends in one\x01"[..], b"YELLOW SUBMARINE", b""] {
+            let oracle = ecb_oracle(b"xyz".to_vec(), secret.to_vec());
+
+            let ans = byte_at_a_time_ecb(oracle, CandidateOrder::Sequential).unwrap();
+            assert_eq!(ans.bytes.bytes, secret);
+            assert_eq!(ans.length, secret.len());
+        }
+    }
+
+    #[test]
+    fn test_recover_ecb_suffix() {
+        let secret = b"Did you stop? No, I just drove by\n".to_vec();
+        let ans = recover_ecb_suffix(ecb_oracle(vec![], secret.clone())).unwrap();
+        assert_eq!(ans.bytes, secret);
+    }
+
+    #[test]
+    fn test_random_prefix_oracle() {
+        let secret = b"Rollin' in my 5.0\nWith my rag-top down".to_vec();
+        let mut rng = StdRng::seed_from_u64(14);
+
+        for _ in 0..5 {
+            let mut oracle = EcbSuffixOracle::with_random_prefix(&secret, &mut rng);
+            let prefix_len = oracle.prefix.len();
+            let attack = |input: &[u8]| oracle.encrypt(input);
+            let ans = byte_at_a_time_ecb(attack, CandidateOrder::PrintableFirst).unwrap();
+            assert_eq!(ans.bytes.bytes, secret);
+            assert_eq!(ans.prefix_len, prefix_len);
+        }
+
+        let oracle = EcbSuffixOracle::new(&secret, &mut rng);
+        assert_eq!(recover_ecb_suffix(oracle).unwrap().bytes, secret);
+    }
+
+    #[test]
+    fn test_byte_at_a_time_rejects_cbc() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let oracle = |input: &[u8]| {
+            let data = RawBytes {
+                bytes: [input, b"secret"].concat(),
+            };
+            cbc_encrypt_with_padding(&aes, &Iv::zero(16), &data, &Pkcs7)
+                .unwrap()
+                .bytes
+        };
+        let err = recover_ecb_suffix(oracle).unwrap_err();
+        assert!(err.to_string().contains("not an ECB oracle"), "{}", err);
+    }
+
+    #[test]
+    fn test_discover_block_layout_rejects_stream() {
+        let oracle = |input: &[u8]| input.iter().map(|b| b ^ 0x5a).collect();
+        assert!(discover_block_layout(oracle).is_err());
+    }
+}
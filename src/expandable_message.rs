@@ -0,0 +1,253 @@
+#![allow(dead_code)]
+use crate::merkle_damgard::*;
+use crate::multicollision::random_block;
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// One step of an expandable message: a single block, and `dummies`
+/// fixed blocks followed by one more, that both take the state before to
+/// `state`.
+#[derive(Debug, Clone)]
+pub struct ExpandablePiece<C: Compression> {
+    pub short: Vec<u8>,
+    pub long: Vec<u8>,
+    pub state: C::State,
+}
+
+/// A collision between a one-block message and one of `dummies + 1`
+/// blocks, both from `state`, found by a birthday search between the two
+/// sides. Returns the piece and the compression calls it took.
+pub fn find_length_collision<C: Compression, R: Rng>(
+    state: C::State,
+    dummies: usize,
+    rng: &mut R,
+) -> (ExpandablePiece<C>, u64)
+where
+    C::State: Hash + Eq,
+{
+    let dummy = vec![0u8; dummies * C::BLOCK_SIZE];
+    let after_dummies = compress_blocks::<C>(state, &dummy);
+    let mut calls = dummies as u64;
+
+    let mut short: HashMap<C::State, Vec<u8>> = HashMap::new();
+    let mut long: HashMap<C::State, Vec<u8>> = HashMap::new();
+    loop {
+        let block = random_block(rng, C::BLOCK_SIZE);
+        let mut s = state;
+        C::compress(&mut s, &block);
+        let mut l = after_dummies;
+        C::compress(&mut l, &block);
+        calls += 2;
+
+        if let Some(other) = long.get(&s) {
+            return (
+                ExpandablePiece {
+                    short: block,
+                    long: [&dummy[..], other].concat(),
+                    state: s,
+                },
+                calls,
+            );
+        }
+        short.insert(s, block.clone());
+        if let Some(other) = short.get(&l) {
+            return (
+                ExpandablePiece {
+                    short: other.clone(),
+                    long: [&dummy[..], &block].concat(),
+                    state: l,
+                },
+                calls,
+            );
+        }
+        long.insert(l, block);
+    }
+}
+
+/// Kelsey and Schneier's expandable message: `k` pieces, piece `i` a
+/// choice between 1 block and 2^i + 1, all landing on the same state. Any
+/// length from `k` to `k + 2^k - 1` blocks can be had by picking the long
+/// side of the pieces for the bits of `length - k`, for about `k` birthday
+/// searches and 2^k extra calls.
+#[derive(Debug, Clone)]
+pub struct ExpandableMessage<C: Compression> {
+    pub pieces: Vec<ExpandablePiece<C>>,
+    pub calls: u64,
+}
+
+impl<C: Compression> ExpandableMessage<C>
+where
+    C::State: Hash + Eq,
+{
+    pub fn new<R: Rng>(k: usize, rng: &mut R) -> Self {
+        let mut state = C::initial_state();
+        let mut pieces = Vec::with_capacity(k);
+        let mut calls = 0;
+        for i in 0..k {
+            let (piece, c) = find_length_collision::<C, R>(state, 1 << i, rng);
+            state = piece.state;
+            pieces.push(piece);
+            calls += c;
+        }
+        Self { pieces, calls }
+    }
+
+    /// The state every expansion ends on.
+    pub fn state(&self) -> C::State {
+        self.pieces
+            .last()
+            .map_or_else(C::initial_state, |p| p.state)
+    }
+
+    /// The shortest and longest expansions, in blocks.
+    pub fn min_blocks(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn max_blocks(&self) -> usize {
+        self.pieces.len() + (1 << self.pieces.len()) - 1
+    }
+
+    /// The expansion `blocks` blocks long.
+    pub fn expand(&self, blocks: usize) -> anyhow::Result<Vec<u8>> {
+        if blocks < self.min_blocks() || blocks > self.max_blocks() {
+            anyhow::bail!(
+                "{} blocks is outside {}..={}",
+                blocks,
+                self.min_blocks(),
+                self.max_blocks()
+            );
+        }
+        let extra = blocks - self.min_blocks();
+        Ok(self
+            .pieces
+            .iter()
+            .enumerate()
+            .flat_map(|(i, p)| {
+                if extra >> i & 1 == 1 {
+                    p.long.clone()
+                } else {
+                    p.short.clone()
+                }
+            })
+            .collect())
+    }
+}
+
+/// What the second-preimage search found, kept so it can be looked at.
+#[derive(Debug, Clone)]
+pub struct SecondPreimage<C: Compression> {
+    /// A different message with the target's hash.
+    pub message: Vec<u8>,
+    /// The block that takes the expandable message's state onto one of
+    /// the target's intermediate states.
+    pub bridge: Vec<u8>,
+    /// How many of the target's blocks the bridge stands in for: the
+    /// forged message takes the target's blocks from here on.
+    pub bridge_index: usize,
+    pub expandable: ExpandableMessage<C>,
+    /// Compression calls the search for the bridge took.
+    pub bridge_calls: u64,
+}
+
+/// The long-message second-preimage attack (challenge 53). The target's
+/// intermediate states are all places a forgery could join it, so a 2^k
+/// block target makes finding one about 2^k times easier than a plain
+/// preimage. Merkle–Damgård strengthening would give a forgery of the
+/// wrong length away, which is what the expandable message is for: it
+/// fills the forgery out to just the length that puts the bridge block
+/// where the target's block was.
+pub fn second_preimage<C: Compression, R: Rng>(
+    target: &[u8],
+    k: usize,
+    rng: &mut R,
+) -> anyhow::Result<SecondPreimage<C>>
+where
+    C::State: Hash + Eq,
+{
+    let bs = C::BLOCK_SIZE;
+    let whole = target.len() / bs;
+
+    // The state after `i` blocks, for each `i` the forgery can join at.
+    let mut states: HashMap<C::State, usize> = HashMap::new();
+    let mut state = C::initial_state();
+    for i in 1..=whole {
+        C::compress(&mut state, &target[(i - 1) * bs..i * bs]);
+        if i > k && i <= k + (1 << k) {
+            states.entry(state).or_insert(i);
+        }
+    }
+    if states.is_empty() {
+        anyhow::bail!("the target needs more than {} whole blocks", k);
+    }
+
+    let expandable = ExpandableMessage::<C>::new(k, rng);
+    let mut bridge_calls = 0;
+    let (bridge, bridge_index) = loop {
+        let block = random_block(rng, bs);
+        let mut s = expandable.state();
+        C::compress(&mut s, &block);
+        bridge_calls += 1;
+        if let Some(&i) = states.get(&s) {
+            break (block, i);
+        }
+    };
+
+    let message = [
+        &expandable.expand(bridge_index - 1)?[..],
+        &bridge,
+        &target[bridge_index * bs..],
+    ]
+    .concat();
+    Ok(SecondPreimage {
+        message,
+        bridge,
+        bridge_index,
+        expandable,
+        bridge_calls,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cheap_hash::*;
+
+    type C = AesCompression<2>;
+
+    #[test]
+    fn test_expandable_message() {
+        let mut rng = StdRng::seed_from_u64(53);
+        let em = ExpandableMessage::<C>::new(5, &mut rng);
+        assert_eq!((em.min_blocks(), em.max_blocks()), (5, 36));
+        for blocks in em.min_blocks()..=em.max_blocks() {
+            let m = em.expand(blocks).unwrap();
+            assert_eq!(m.len(), blocks * 16);
+            assert_eq!(compress_blocks::<C>(C::initial_state(), &m), em.state());
+        }
+        assert!(em.expand(4).is_err());
+        assert!(em.expand(37).is_err());
+    }
+
+    #[test]
+    fn test_second_preimage() {
+        let mut rng = StdRng::seed_from_u64(53);
+        let k = 8;
+        let mut target = vec![0u8; (1 << k) * 16 + 5];
+        rng.fill_bytes(&mut target);
+
+        let found = second_preimage::<C, _>(&target, k, &mut rng).unwrap();
+        assert_ne!(found.message, target);
+        assert_eq!(found.message.len(), target.len());
+        assert_eq!(cheap_hash::<2>(&found.message), cheap_hash::<2>(&target));
+        assert_eq!(
+            found.message[found.bridge_index * 16..],
+            target[found.bridge_index * 16..]
+        );
+        // 2^16 states, 2^8 of them targets: a few hundred tries.
+        assert!(found.bridge_calls < 5000);
+
+        assert!(second_preimage::<C, _>(&target[..8 * 16], k, &mut rng).is_err());
+    }
+}
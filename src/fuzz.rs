@@ -0,0 +1,263 @@
+#![allow(dead_code)]
+use rand::prelude::*;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// A big-endian length field inside a protocol message, so the fuzzer can
+/// lie about lengths rather than only flipping random bits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthField {
+    pub offset: usize,
+    pub width: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mutation {
+    Truncate(usize),
+    BitFlip { byte: usize, bit: u8 },
+    LengthLie { field: LengthField, value: u64 },
+    Append(usize),
+}
+
+impl Mutation {
+    pub fn apply(&self, msg: &[u8]) -> Vec<u8> {
+        let mut out = msg.to_vec();
+        match self {
+            Mutation::Truncate(len) => out.truncate(*len),
+            Mutation::BitFlip { byte, bit } => out[*byte] ^= 1 << bit,
+            Mutation::LengthLie { field, value } => {
+                let bytes = value.to_be_bytes();
+                out[field.offset..field.offset + field.width]
+                    .copy_from_slice(&bytes[8 - field.width..]);
+            }
+            Mutation::Append(n) => out.extend(std::iter::repeat_n(0x41, *n)),
+        }
+        out
+    }
+}
+
+fn random_mutation<R: Rng>(msg: &[u8], length_fields: &[LengthField], rng: &mut R) -> Mutation {
+    let fields: Vec<&LengthField> = length_fields
+        .iter()
+        .filter(|f| f.width <= 8 && f.offset + f.width <= msg.len())
+        .collect();
+
+    loop {
+        match rng.random_range(0..4) {
+            0 => return Mutation::Truncate(rng.random_range(0..msg.len().max(1))),
+            1 if !msg.is_empty() => {
+                return Mutation::BitFlip {
+                    byte: rng.random_range(0..msg.len()),
+                    bit: rng.random_range(0..8),
+                }
+            }
+            2 if !fields.is_empty() => {
+                let field = *fields[rng.random_range(0..fields.len())];
+                let max = if field.width == 8 {
+                    u64::MAX
+                } else {
+                    (1u64 << (8 * field.width)) - 1
+                };
+                // Favour the boundary values parsers tend to get wrong.
+                let value = match rng.random_range(0..4) {
+                    0 => 0,
+                    1 => max,
+                    2 => msg.len() as u64 + 1,
+                    _ => rng.random::<u64>() & max,
+                };
+                return Mutation::LengthLie { field, value };
+            }
+            3 => return Mutation::Append(rng.random_range(1..64)),
+            _ => {}
+        }
+    }
+}
+
+/// A mutated input that made the target panic instead of returning an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzFailure {
+    pub seed_index: usize,
+    pub mutation: Mutation,
+    pub input: Vec<u8>,
+    pub message: String,
+}
+
+/// Mutates each valid `seeds` message `iterations` times and feeds the result
+/// to `target`, collecting every input that panicked. Returning `Err` is the
+/// expected way to reject garbage; the run is reproducible from `rng_seed`.
+pub fn fuzz_fails_closed<T, E, F: Fn(&[u8]) -> Result<T, E>>(
+    seeds: &[Vec<u8>],
+    length_fields: &[LengthField],
+    iterations: usize,
+    rng_seed: u64,
+    target: F,
+) -> Vec<FuzzFailure> {
+    let mut rng = StdRng::seed_from_u64(rng_seed);
+    let mut failures = Vec::new();
+
+    for (seed_index, msg) in seeds.iter().enumerate() {
+        for _ in 0..iterations {
+            let mutation = random_mutation(msg, length_fields, &mut rng);
+            let input = mutation.apply(msg);
+
+            if let Err(e) = catch_unwind(AssertUnwindSafe(|| target(&input).is_ok())) {
+                let message = e
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| e.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                failures.push(FuzzFailure {
+                    seed_index,
+                    mutation,
+                    input,
+                    message,
+                });
+            }
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::*;
+    use crate::padding::*;
+    use crate::raw_bytes::*;
+
+    #[test]
+    fn test_mutations() {
+        let msg = [0, 3, b'a', b'b', b'c'];
+        let field = LengthField {
+            offset: 0,
+            width: 2,
+        };
+
+        assert_eq!(Mutation::Truncate(2).apply(&msg), vec![0, 3]);
+        assert_eq!(
+            Mutation::BitFlip { byte: 2, bit: 1 }.apply(&msg),
+            vec![0, 3, b'c', b'b', b'c']
+        );
+        assert_eq!(
+            Mutation::LengthLie {
+                field,
+                value: 0x1234
+            }
+            .apply(&msg),
+            vec![0x12, 0x34, b'a', b'b', b'c']
+        );
+    }
+
+    #[test]
+    fn test_padding_fails_closed() {
+        let seeds: Vec<Vec<u8>> = ["", "YELLOW", "YELLOW SUBMARINE"]
+            .iter()
+            .map(|s| Pkcs7.pad(&RawBytes::from_str(s), 16).bytes)
+            .collect();
+
+        let failures = fuzz_fails_closed(&seeds, &[], 500, 7, |msg| {
+            strip_pkcs7_padding(
+                &RawBytes {
+                    bytes: msg.to_vec(),
+                },
+                16,
+            )
+        });
+        assert!(failures.is_empty(), "{:?}", failures);
+
+        let failures = fuzz_fails_closed(&seeds, &[], 500, 7, |msg| {
+            AnsiX923.unpad(
+                &RawBytes {
+                    bytes: msg.to_vec(),
+                },
+                16,
+            )
+        });
+        assert!(failures.is_empty(), "{:?}", failures);
+    }
+
+    #[test]
+    fn test_bank_fails_closed() {
+        use crate::cbc_mac::*;
+
+        let mut rng = StdRng::seed_from_u64(551);
+        let bank = CbcMacBank::new(&mut rng);
+        let seeds = [
+            bank.sign_transfer(2, 3, 100, &mut rng),
+            bank.sign_transfers(2, &[(3, 100), (4, 5)]),
+        ];
+
+        let failures =
+            fuzz_fails_closed(&seeds[..1], &[], 500, 1, |msg| bank.process_transfer(msg));
+        assert!(failures.is_empty(), "{:?}", failures);
+        let failures =
+            fuzz_fails_closed(&seeds[1..], &[], 500, 2, |msg| bank.process_transfers(msg));
+        assert!(failures.is_empty(), "{:?}", failures);
+    }
+
+    #[test]
+    fn test_padding_oracle_server_fails_closed() {
+        use crate::padding_oracle::*;
+
+        let mut rng = StdRng::seed_from_u64(551);
+        let server = PaddingOracleServer::new(&mut rng);
+        // The IV goes in front, as it would on the wire.
+        let seeds: Vec<Vec<u8>> = (0..3)
+            .map(|_| {
+                let (iv, ct) = server.encrypt(&mut rng);
+                [iv.as_bytes(), &ct[..]].concat()
+            })
+            .collect();
+
+        let failures = fuzz_fails_closed(&seeds, &[], 500, 3, |msg| {
+            let (iv, ct) = msg.split_at(msg.len().min(16));
+            if server.padding_valid(iv, ct) {
+                Ok(())
+            } else {
+                Err(())
+            }
+        });
+        assert!(failures.is_empty(), "{:?}", failures);
+    }
+
+    #[test]
+    fn test_profile_server_fails_closed() {
+        use crate::profile::*;
+
+        let server = ProfileServer::new(b"YELLOW SUBMARINE");
+        let seeds = [
+            server.encrypt_profile("foo@bar.com"),
+            server.encrypt_profile("a=b&role=admin"),
+        ];
+
+        let failures = fuzz_fails_closed(&seeds, &[], 500, 4, |msg| server.decrypt_profile(msg));
+        assert!(failures.is_empty(), "{:?}", failures);
+    }
+
+    #[test]
+    fn test_finds_trusting_parser() {
+        // Reads a two byte length and slices without checking it.
+        let parse = |msg: &[u8]| -> Result<Vec<u8>, String> {
+            if msg.len() < 2 {
+                return Err("short".to_string());
+            }
+            let len = u16::from_be_bytes([msg[0], msg[1]]) as usize;
+            Ok(msg[2..2 + len].to_vec())
+        };
+        let seeds = vec![vec![0, 3, b'a', b'b', b'c']];
+        let fields = [LengthField {
+            offset: 0,
+            width: 2,
+        }];
+
+        let failures = fuzz_fails_closed(&seeds, &fields, 200, 1, parse);
+        assert!(failures
+            .iter()
+            .any(|f| matches!(f.mutation, Mutation::LengthLie { .. })));
+        assert!(failures
+            .iter()
+            .any(|f| matches!(f.mutation, Mutation::Truncate(_))));
+
+        // Same seed, same findings.
+        assert_eq!(failures, fuzz_fails_closed(&seeds, &fields, 200, 1, parse));
+    }
+}
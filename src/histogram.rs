@@ -0,0 +1,200 @@
+#![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// Occurrence counts for every byte value.
+///
+/// Serializes as a plain array of 256 counts so reference distributions can
+/// be stored as JSON assets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<u64>", into = "Vec<u64>")]
+pub struct Histogram256 {
+    counts: [u64; 256],
+}
+
+// Letter frequencies of English in percent; `english` scales these and the
+// other counts to roughly 100000 characters of prose.
+const ENGLISH_LETTERS: [f64; 26] = [
+    8.167, 1.492, 2.782, 4.253, 12.702, 2.228, 2.015, 6.094, 6.966, 0.153, 0.772, 4.025, 2.406,
+    6.749, 7.507, 1.929, 0.095, 5.987, 6.327, 9.056, 2.758, 0.978, 2.360, 0.150, 1.974, 0.074,
+];
+const ENGLISH_OTHER: [(u8, u64); 9] = [
+    (b' ', 15000),
+    (b'.', 600),
+    (b',', 600),
+    (b'\n', 300),
+    (b'\'', 200),
+    (b'"', 100),
+    (b'-', 100),
+    (b'?', 50),
+    (b'!', 50),
+];
+
+impl Histogram256 {
+    pub fn new() -> Self {
+        Self { counts: [0; 256] }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut h = Self::new();
+        h.extend(bytes);
+        h
+    }
+
+    /// A reference distribution for English text.
+    pub fn english() -> Self {
+        let mut h = Self::new();
+        for (i, f) in ENGLISH_LETTERS.iter().enumerate() {
+            h.counts[b'a' as usize + i] = (f * 800.0) as u64;
+            h.counts[b'A' as usize + i] = (f * 30.0) as u64;
+        }
+        for (b, c) in ENGLISH_OTHER.iter() {
+            h.counts[*b as usize] = *c;
+        }
+        h
+    }
+
+    pub fn add(&mut self, byte: u8) {
+        self.counts[byte as usize] += 1;
+    }
+
+    pub fn extend(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.add(*b);
+        }
+    }
+
+    pub fn merge(&mut self, other: &Histogram256) {
+        for (c, o) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *c += o;
+        }
+    }
+
+    pub fn count(&self, byte: u8) -> u64 {
+        self.counts[byte as usize]
+    }
+
+    pub fn counts(&self) -> &[u64; 256] {
+        &self.counts
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Relative frequencies; all zero for an empty histogram.
+    pub fn normalized(&self) -> [f64; 256] {
+        let total = self.total().max(1) as f64;
+
+        let mut freq = [0.0; 256];
+        for (f, c) in freq.iter_mut().zip(self.counts.iter()) {
+            *f = *c as f64 / total;
+        }
+        freq
+    }
+
+    /// Pearson's chi-squared statistic of these counts against the
+    /// distribution of `reference`. Every reference bin gets half a pseudo
+    /// count so that bytes the reference never saw are penalized heavily
+    /// rather than infinitely.
+    pub fn chi_squared(&self, reference: &Histogram256) -> f64 {
+        let total = self.total() as f64;
+        let ref_total = reference.total() as f64 + 128.0;
+
+        self.counts
+            .iter()
+            .zip(reference.counts.iter())
+            .map(|(o, r)| {
+                let expected = total * (*r as f64 + 0.5) / ref_total;
+                let diff = *o as f64 - expected;
+                diff * diff / expected
+            })
+            .sum()
+    }
+}
+
+impl Default for Histogram256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TryFrom<Vec<u64>> for Histogram256 {
+    type Error = String;
+
+    fn try_from(v: Vec<u64>) -> Result<Self, Self::Error> {
+        let counts = <[u64; 256]>::try_from(v)
+            .map_err(|v| format!("expected 256 counts, got {}", v.len()))?;
+        Ok(Self { counts })
+    }
+}
+
+impl From<Histogram256> for Vec<u64> {
+    fn from(h: Histogram256) -> Self {
+        h.counts.to_vec()
+    }
+}
+
+/// Scorer ranking bytes by closeness to English; the negated chi-squared
+/// distance to `Histogram256::english`.
+pub fn chi_squared_score(bytes: &[u8]) -> f64 {
+    -Histogram256::from_bytes(bytes).chi_squared(&Histogram256::english())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::*;
+    use crate::raw_bytes::*;
+
+    #[test]
+    fn test_counts_and_merge() {
+        let mut h = Histogram256::from_bytes(b"hello");
+        assert_eq!(h.count(b'l'), 2);
+        assert_eq!(h.total(), 5);
+
+        h.merge(&Histogram256::from_bytes(b"world"));
+        assert_eq!(h.count(b'l'), 3);
+        assert_eq!(h.count(b'o'), 2);
+        assert_eq!(h.total(), 10);
+
+        let freq = h.normalized();
+        assert!((freq.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert_eq!(freq[b'l' as usize], 0.3);
+    }
+
+    #[test]
+    fn test_chi_squared() {
+        let english = Histogram256::english();
+        let text = Histogram256::from_bytes(b"Now that the party is jumping");
+        let noise = Histogram256::from_bytes(&[0x9c, 0x01, 0xff, 0x7f, 0x13, 0x80, 0x42]);
+
+        assert!(text.chi_squared(&english) < noise.chi_squared(&english));
+        assert!(english.chi_squared(&english) < english.total() as f64 * 0.01);
+    }
+
+    #[test]
+    fn test_chi_squared_scorer() {
+        let rb = RawBytes::from_hex(
+            "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736",
+        );
+
+        let (key, _) = single_char_xor_best_key(&rb.bytes, &chi_squared_score, &mut Vec::new());
+        assert_eq!(
+            single_key_xor(&rb, key).to_str(),
+            "Cooking MC's like a pound of bacon"
+        );
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let h = Histogram256::from_bytes(b"YELLOW SUBMARINE");
+
+        let json = serde_json::to_string(&h).unwrap();
+        assert!(json.starts_with('['));
+        let back: Histogram256 = serde_json::from_str(&json).unwrap();
+        assert_eq!(h, back);
+
+        assert!(serde_json::from_str::<Histogram256>("[1, 2, 3]").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+#![allow(dead_code)]
+use crate::digest::*;
+use crate::sha1::*;
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+/// HMAC (RFC 2104) over any `Digest`: `H((K ^ opad) || H((K ^ ipad) ||
+/// message))`, with `K` the key hashed if it is longer than a block and
+/// zero-padded to one. Unlike the secret-prefix MAC, the outer hash hides
+/// the inner state, so there is nothing to extend.
+#[derive(Debug, Clone)]
+pub struct Hmac<D: Digest> {
+    inner: D,
+    outer: D,
+}
+
+impl<D: Digest> Hmac<D> {
+    pub fn new(key: &[u8]) -> Self {
+        let mut inner = D::new();
+        let mut outer = D::new();
+        let block_size = inner.block_size();
+
+        let mut k = if key.len() > block_size {
+            D::digest(key)
+        } else {
+            key.to_vec()
+        };
+        k.resize(block_size, 0);
+
+        inner.update(&k.iter().map(|b| b ^ IPAD).collect::<Vec<u8>>());
+        outer.update(&k.iter().map(|b| b ^ OPAD).collect::<Vec<u8>>());
+        Self { inner, outer }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        let mut outer = self.outer;
+        outer.update(&self.inner.finalize());
+        outer.finalize()
+    }
+}
+
+pub fn hmac<D: Digest>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut h = Hmac::<D>::new(key);
+    h.update(message);
+    h.finalize()
+}
+
+pub fn hmac_sha1(key: &[u8], message: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut out = [0u8; DIGEST_SIZE];
+    out.copy_from_slice(&hmac::<Sha1>(key, message));
+    out
+}
+
+/// Checks `mac` against the HMAC of `message` without stopping at the
+/// first wrong byte.
+pub fn hmac_verify<D: Digest>(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    let expected = hmac::<D>(key, message);
+    mac.len() == expected.len()
+        && expected
+            .iter()
+            .zip(mac)
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md4::*;
+    use crate::md5::*;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_rfc_2202_hmac_sha1() {
+        for (key, data, expected) in [
+            (
+                vec![0x0b; 20],
+                b"Hi There".to_vec(),
+                "b617318655057264e28bc0b6fb378c8ef146be00",
+            ),
+            (
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?".to_vec(),
+                "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            ),
+            (
+                vec![0xaa; 20],
+                vec![0xdd; 50],
+                "125d7342b9ac11cd91a39af48aa17b4f63f175d3",
+            ),
+            // A key longer than the block is hashed first.
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+                "aa4ae5e15272d00e95705637ce8a3b55ed402112",
+            ),
+        ] {
+            assert_eq!(hex(&hmac_sha1(&key, &data)), expected);
+            assert!(hmac_verify::<Sha1>(&key, &data, &hmac_sha1(&key, &data)));
+        }
+    }
+
+    #[test]
+    fn test_incremental_and_other_digests() {
+        let mut h = Hmac::<Sha1>::new(b"key");
+        h.update(b"The quick brown fox ");
+        h.update(b"jumps over the lazy dog");
+        assert_eq!(
+            hex(&h.finalize()),
+            "de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9"
+        );
+
+        let mac = hmac::<Md4>(b"key", b"message");
+        assert_eq!(mac.len(), 16);
+        assert!(hmac_verify::<Md4>(b"key", b"message", &mac));
+        assert!(!hmac_verify::<Md4>(b"key", b"massage", &mac));
+        assert!(!hmac_verify::<Md4>(b"key", b"message", &mac[..15]));
+
+        // RFC 2104's own example.
+        assert_eq!(
+            hex(&hmac::<Md5>(b"Jefe", b"what do ya want for nothing?")),
+            "750c783e6ab0b503eaa86e310a5db738"
+        );
+    }
+}
@@ -0,0 +1,422 @@
+#![allow(dead_code)]
+use crate::deadline::*;
+
+/// The median of `xs`, which must not be empty.
+pub fn median(xs: &[f64]) -> f64 {
+    let mut v = xs.to_vec();
+    v.sort_by(|a, b| a.total_cmp(b));
+    let mid = v.len() / 2;
+    if v.len().is_multiple_of(2) {
+        (v[mid - 1] + v[mid]) / 2.0
+    } else {
+        v[mid]
+    }
+}
+
+/// The median absolute deviation, scaled by 1.4826 so that for normal
+/// noise it estimates the standard deviation. Unlike the standard
+/// deviation it shrugs off the odd request that got stuck behind
+/// something else.
+pub fn mad(xs: &[f64]) -> f64 {
+    let m = median(xs);
+    let deviations: Vec<f64> = xs.iter().map(|x| (x - m).abs()).collect();
+    1.4826 * median(&deviations)
+}
+
+#[derive(Debug, Clone)]
+pub struct TimingAttackConfig {
+    /// Timings taken for every candidate byte before anything is decided.
+    pub initial_samples: usize,
+    /// The most timings any one candidate gets before the leader is taken
+    /// as it is.
+    pub max_samples: usize,
+    /// How many of the slowest-rejected candidates get the extra samples
+    /// when the leader isn't clear.
+    pub contenders: usize,
+    /// How many standard errors the leader has to be ahead of the runner-up
+    /// by.
+    pub confidence: f64,
+    /// How many times the attack may go back a byte because the one after
+    /// it showed no leader at all.
+    pub max_backtracks: usize,
+}
+
+impl Default for TimingAttackConfig {
+    fn default() -> Self {
+        Self {
+            initial_samples: 5,
+            max_samples: 160,
+            contenders: 16,
+            confidence: 4.0,
+            max_backtracks: 8,
+        }
+    }
+}
+
+/// The challenge 31 and 32 attack: finds a valid MAC for some fixed
+/// message a byte at a time, from how long the server takes to reject
+/// guesses. `probe(signature)` submits a guess and returns whether it was
+/// accepted and how long that took, in nanoseconds, or an error if the
+/// request didn't get an answer. An error stops the attack: a refused
+/// connection comes back fast, and timing it would look like a rejection.
+///
+/// For each byte every candidate is timed a few times, and candidates are
+/// compared by their median, so a request that stalls once doesn't decide
+/// anything. When the leader isn't ahead of the runner-up by `confidence`
+/// standard errors (estimated from the MAD), the closest contenders are
+/// sampled again, doubling up to `max_samples`; they always get at least
+/// one more round, to confirm the first impression. A wrong byte shows up one
+/// position later, when no candidate stands out from the rest: the attack
+/// then drops the byte and redoes it. The last byte needs no timing, since
+/// the server says whether the whole MAC is right.
+pub struct HmacTimingAttack<F> {
+    probe: F,
+    mac_len: usize,
+    config: TimingAttackConfig,
+    known: Vec<u8>,
+    /// How far each accepted byte's leader stood above the other
+    /// candidates, in nanoseconds.
+    gaps: Vec<f64>,
+    backtracks: usize,
+    found: bool,
+    failed: bool,
+    /// Why the probe stopped working, if it did.
+    error: Option<anyhow::Error>,
+    probes: u64,
+}
+
+impl<F: FnMut(&[u8]) -> anyhow::Result<(bool, f64)>> HmacTimingAttack<F> {
+    pub fn new(probe: F, mac_len: usize, config: TimingAttackConfig) -> Self {
+        Self {
+            probe,
+            mac_len,
+            config,
+            known: Vec::new(),
+            gaps: Vec::new(),
+            backtracks: 0,
+            found: false,
+            failed: false,
+            error: None,
+            probes: 0,
+        }
+    }
+
+    pub fn probes(&self) -> u64 {
+        self.probes
+    }
+
+    /// The probe's error, if that's what ended the attack.
+    pub fn error(&self) -> Option<&anyhow::Error> {
+        self.error.as_ref()
+    }
+
+    /// The MAC, once one has been accepted.
+    pub fn mac(&self) -> Option<&[u8]> {
+        if self.found {
+            Some(&self.known)
+        } else {
+            None
+        }
+    }
+
+    fn guess(&self, byte: u8) -> Vec<u8> {
+        let mut sig = self.known.clone();
+        sig.push(byte);
+        sig.resize(self.mac_len, 0);
+        sig
+    }
+
+    /// Times `n` more guesses of `byte`. Returns whether the guess was
+    /// accepted, in which case there is nothing left to time.
+    fn sample(&mut self, byte: u8, times: &mut Vec<f64>, n: usize) -> anyhow::Result<bool> {
+        let sig = self.guess(byte);
+        for _ in 0..n {
+            let (accepted, t) = (self.probe)(&sig)?;
+            self.probes += 1;
+            if accepted {
+                return Ok(true);
+            }
+            times.push(t);
+        }
+        Ok(false)
+    }
+
+    /// The next byte, and how far its median stands above the median of
+    /// all candidates. The gap is infinite if the zero-padded guess was
+    /// accepted outright.
+    fn time_next_byte(&mut self) -> anyhow::Result<(u8, f64)> {
+        let mut times: Vec<Vec<f64>> = vec![Vec::new(); 256];
+        // Round-robin, so slow drift in the server's speed hits every
+        // candidate alike.
+        for _ in 0..self.config.initial_samples {
+            for b in 0..=255u8 {
+                let mut t = std::mem::take(&mut times[b as usize]);
+                if self.sample(b, &mut t, 1)? {
+                    return Ok((b, f64::INFINITY));
+                }
+                times[b as usize] = t;
+            }
+        }
+
+        let mut per_candidate = self.config.initial_samples;
+        loop {
+            let medians: Vec<f64> = times.iter().map(|t| median(t)).collect();
+            let mut order: Vec<usize> = (0..256).collect();
+            order.sort_by(|a, b| medians[*b].total_cmp(&medians[*a]));
+            let (leader, runner_up) = (order[0], order[1]);
+
+            let noise = median(&times.iter().map(|t| mad(t)).collect::<Vec<f64>>());
+            let stderr = 1.2533 * noise / (per_candidate as f64).sqrt();
+            let gap = medians[leader] - medians[runner_up];
+            let clear = gap > self.config.confidence * stderr * std::f64::consts::SQRT_2;
+
+            // The first pass only picks the contenders: a candidate can
+            // lead it just by having most of its few requests stall.
+            let confirmed = per_candidate > self.config.initial_samples;
+            if confirmed && (clear || per_candidate >= self.config.max_samples) {
+                return Ok((leader as u8, medians[leader] - median(&medians)));
+            }
+
+            let extra = per_candidate;
+            for &c in &order[..self.config.contenders.min(256)] {
+                let mut t = std::mem::take(&mut times[c]);
+                if self.sample(c as u8, &mut t, extra)? {
+                    return Ok((c as u8, f64::INFINITY));
+                }
+                times[c] = t;
+            }
+            per_candidate += extra;
+        }
+    }
+
+    /// Ends the attack on a probe that stopped working.
+    fn fail(&mut self, err: anyhow::Error) -> bool {
+        self.error = Some(err);
+        self.failed = true;
+        true
+    }
+}
+
+impl<F: FnMut(&[u8]) -> anyhow::Result<(bool, f64)>> Checkpointable for HmacTimingAttack<F> {
+    type Output = Vec<u8>;
+
+    /// Settles one byte, or goes back one.
+    fn step(&mut self) -> bool {
+        if self.found || self.failed {
+            return true;
+        }
+
+        if self.known.len() + 1 == self.mac_len {
+            for b in 0..=255u8 {
+                let sig = self.guess(b);
+                self.probes += 1;
+                match (self.probe)(&sig) {
+                    Ok((true, _)) => {
+                        self.known.push(b);
+                        self.found = true;
+                        return true;
+                    }
+                    Ok((false, _)) => {}
+                    Err(err) => return self.fail(err),
+                }
+            }
+        } else {
+            let (byte, gap) = match self.time_next_byte() {
+                Ok(next) => next,
+                Err(err) => return self.fail(err),
+            };
+            let typical = if self.gaps.is_empty() {
+                None
+            } else {
+                Some(median(&self.gaps))
+            };
+            if gap.is_infinite() {
+                // Accepted outright.
+                self.known.push(byte);
+                self.known.resize(self.mac_len, 0);
+                self.found = true;
+                return true;
+            }
+            // Only the right prefix makes one candidate stand out.
+            if typical.is_none_or(|t| gap > t / 2.0) {
+                self.known.push(byte);
+                self.gaps.push(gap);
+                return false;
+            }
+        }
+
+        // Nothing stood out, so the byte before was wrong.
+        if self.known.is_empty() || self.backtracks >= self.config.max_backtracks {
+            self.failed = true;
+            return true;
+        }
+        self.backtracks += 1;
+        self.known.pop();
+        self.gaps.pop();
+        false
+    }
+
+    fn best_so_far(&self) -> Vec<u8> {
+        self.known.clone()
+    }
+}
+
+/// Runs the attack to the end. Returns the MAC, if one was found, or the
+/// probe's error.
+pub fn recover_mac_by_timing<F: FnMut(&[u8]) -> anyhow::Result<(bool, f64)>>(
+    probe: F,
+    mac_len: usize,
+    config: TimingAttackConfig,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut attack = HmacTimingAttack::new(probe, mac_len, config);
+    while !attack.step() {}
+    if let Some(err) = attack.error.take() {
+        return Err(err);
+    }
+    Ok(attack.mac().map(|m| m.to_vec()))
+}
+
+/// Times requests to a `TimingServer` asking about `file`, with the finest
+/// timer the machine has.
+#[cfg(feature = "timing-server")]
+pub fn http_probe(
+    addr: std::net::SocketAddr,
+    file: &str,
+) -> impl FnMut(&[u8]) -> anyhow::Result<(bool, f64)> + '_ {
+    use crate::timer::*;
+    use crate::timing_server::*;
+    let timer = best_timer();
+    move |sig: &[u8]| {
+        let path = test_path(file, sig);
+        let start = timer.now();
+        let status = http_get_status(addr, &path)?;
+        Ok((status == 200, timer.elapsed_ns(start)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    /// A simulated server: a fixed cost, `delay_ns` per matching leading
+    /// byte, roughly normal jitter of `jitter_ns`, and one request in
+    /// twenty held up by an extra 20 ms.
+    fn simulated<'a>(
+        mac: &'a [u8],
+        delay_ns: f64,
+        jitter_ns: f64,
+        rng: &'a mut StdRng,
+    ) -> impl FnMut(&[u8]) -> anyhow::Result<(bool, f64)> + 'a {
+        move |sig: &[u8]| {
+            let matched = mac.iter().zip(sig).take_while(|(a, b)| a == b).count();
+            let normal: f64 = (0..12).map(|_| rng.random::<f64>()).sum::<f64>() - 6.0;
+            let stall = if rng.random_range(0..20) == 0 {
+                20e6
+            } else {
+                0.0
+            };
+            let t = 200e3 + matched as f64 * delay_ns + normal * jitter_ns + stall;
+            Ok((sig == mac, t.max(0.0)))
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        assert_eq!(median(&[3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&[4.0, 1.0, 2.0, 3.0]), 2.5);
+        // One wild value moves neither.
+        assert_eq!(median(&[1.0, 2.0, 3.0, 1e9]), 2.5);
+        assert!((mad(&[1.0, 2.0, 3.0, 4.0, 1e9]) - 1.4826).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_recovers_mac_with_50ms_delay() {
+        let mut rng = StdRng::seed_from_u64(617);
+        let mac: Vec<u8> = (0..20).map(|_| rng.random()).collect();
+        let mut noise = StdRng::seed_from_u64(31);
+        let probe = simulated(&mac, 50e6, 100e3, &mut noise);
+        let found = recover_mac_by_timing(probe, 20, TimingAttackConfig::default()).unwrap();
+        assert_eq!(found.as_deref(), Some(&mac[..]));
+    }
+
+    #[test]
+    fn test_recovers_mac_with_1ms_delay_and_noise() {
+        let mut rng = StdRng::seed_from_u64(617);
+        let mac: Vec<u8> = (0..20).map(|_| rng.random()).collect();
+        let mut noise = StdRng::seed_from_u64(32);
+        let probe = simulated(&mac, 1e6, 600e3, &mut noise);
+        let mut attack = HmacTimingAttack::new(probe, 20, TimingAttackConfig::default());
+        while !attack.step() {}
+        assert_eq!(attack.mac(), Some(&mac[..]));
+        assert!(attack.probes() < 200_000);
+    }
+
+    #[test]
+    fn test_gives_up_without_a_leak() {
+        let mut rng = StdRng::seed_from_u64(617);
+        let mac: Vec<u8> = (0..20).map(|_| rng.random()).collect();
+        let mut noise = StdRng::seed_from_u64(33);
+        let probe = simulated(&mac, 0.0, 600e3, &mut noise);
+        let config = TimingAttackConfig {
+            max_samples: 10,
+            ..TimingAttackConfig::default()
+        };
+        assert_eq!(recover_mac_by_timing(probe, 20, config).unwrap(), None);
+    }
+
+    #[test]
+    fn test_accepted_early_guess() {
+        // A MAC ending in zeros is accepted as soon as the zero-padded
+        // guess for its last non-zero byte is tried.
+        let mut mac = [0u8; 20];
+        mac[0] = 0x42;
+        let probe = |sig: &[u8]| Ok((sig == &mac[..], 1000.0));
+        let found = recover_mac_by_timing(probe, 20, TimingAttackConfig::default()).unwrap();
+        assert_eq!(found.as_deref(), Some(&mac[..]));
+    }
+
+    #[test]
+    fn test_probe_error_stops_the_attack() {
+        let mut rng = StdRng::seed_from_u64(617);
+        let mac: Vec<u8> = (0..20).map(|_| rng.random()).collect();
+        let mut noise = StdRng::seed_from_u64(34);
+        let mut timed = simulated(&mac, 50e6, 100e3, &mut noise);
+        let mut calls = 0;
+        // The connection goes away partway through the first byte.
+        let probe = |sig: &[u8]| {
+            calls += 1;
+            if calls > 300 {
+                anyhow::bail!("connection refused");
+            }
+            timed(sig)
+        };
+        let mut attack = HmacTimingAttack::new(probe, 20, TimingAttackConfig::default());
+        assert!(attack.step());
+        assert_eq!(attack.mac(), None);
+        assert!(attack.best_so_far().is_empty());
+        assert_eq!(attack.probes(), 300);
+        assert!(attack.error().unwrap().to_string().contains("refused"));
+    }
+
+    #[cfg(feature = "timing-server")]
+    #[test]
+    fn test_first_bytes_over_http() {
+        use crate::hmac::*;
+        use crate::timing_server::*;
+        use std::time::Duration;
+
+        let key = b"timing key";
+        let server = TimingServer::start(key, Duration::from_millis(2)).unwrap();
+        let mac = hmac_sha1(key, b"foo");
+        let mut attack = HmacTimingAttack::new(
+            http_probe(server.addr(), "foo"),
+            20,
+            TimingAttackConfig::default(),
+        );
+        while attack.best_so_far().len() < 2 {
+            assert!(!attack.step());
+        }
+        assert_eq!(attack.best_so_far(), mac[..2]);
+    }
+}
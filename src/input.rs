@@ -0,0 +1,174 @@
+#![allow(dead_code)]
+use crate::raw_bytes::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputFormat {
+    /// One base64 payload wrapped over several lines (challenges 6 and 7).
+    Base64Blob,
+    /// An independent base64 record on every line.
+    Base64Lines,
+    /// An independent hex record on every line (challenges 4 and 8).
+    HexLines,
+}
+
+/// Strips a leading BOM, CRLF line endings, surrounding whitespace and
+/// trailing blank lines, returning the remaining lines.
+pub fn normalize_lines(text: &str) -> Vec<&str> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    let mut lines: Vec<&str> = text.lines().map(|l| l.trim()).collect();
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Hex,
+    Base64,
+    /// Anything else, taken byte for byte.
+    Text,
+}
+
+fn is_base64(s: &str) -> bool {
+    let body = s.trim_end_matches('=');
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '+' || c == '/';
+
+    !s.is_empty()
+        && s.len().is_multiple_of(4)
+        && s.len() - body.len() <= 2
+        && body.chars().all(valid)
+}
+
+/// Guesses how `s` is encoded, ignoring line breaks and surrounding
+/// whitespace. Short strings can be valid in more than one encoding; hex wins
+/// over base64, which wins over text.
+pub fn detect_encoding(s: &str) -> Encoding {
+    let joined = normalize_lines(s).concat();
+
+    if !joined.is_empty()
+        && joined.len().is_multiple_of(2)
+        && joined.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        Encoding::Hex
+    } else if is_base64(&joined) {
+        Encoding::Base64
+    } else {
+        Encoding::Text
+    }
+}
+
+pub fn detect_format(lines: &[&str]) -> InputFormat {
+    let is_hex = |l: &&str| l.len().is_multiple_of(2) && l.chars().all(|c| c.is_ascii_hexdigit());
+
+    if !lines.is_empty() && lines.iter().all(is_hex) {
+        return InputFormat::HexLines;
+    }
+
+    // A wrapped blob has every line but the last cut at the same width, and
+    // only the very end of the payload may carry '=' padding.
+    let (last, body) = match lines.split_last() {
+        Some(x) => x,
+        None => return InputFormat::Base64Blob,
+    };
+    let width = body.first().map_or(last.len(), |l| l.len());
+    let same_width = body.iter().all(|l| l.len() == width) && last.len() <= width;
+    let padded_early = body.iter().any(|l| l.contains('='));
+
+    if same_width && !padded_early {
+        InputFormat::Base64Blob
+    } else {
+        InputFormat::Base64Lines
+    }
+}
+
+pub fn parse_input(text: &str) -> (InputFormat, Vec<RawBytes>) {
+    let lines = normalize_lines(text);
+    let format = detect_format(&lines);
+
+    let records = match format {
+        InputFormat::Base64Blob => vec![RawBytes::from_base64(&lines.concat())],
+        InputFormat::Base64Lines => lines.iter().map(|l| RawBytes::from_base64(l)).collect(),
+        InputFormat::HexLines => lines.iter().map(|l| RawBytes::from_hex(l)).collect(),
+    };
+
+    (format, records)
+}
+
+pub fn load_input(path: &str) -> anyhow::Result<(InputFormat, Vec<RawBytes>)> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(parse_input(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_lines() {
+        let text = "\u{feff}abcd\r\n  ef01 \r\n\r\n\n";
+        assert_eq!(normalize_lines(text), vec!["abcd", "ef01"]);
+
+        assert!(normalize_lines("\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            detect_format(&["0e3647e8", "334b041d", "40e1"]),
+            InputFormat::HexLines
+        );
+        assert_eq!(
+            detect_format(&["SSdtIGJh", "Y2sgYW5k", "IEk="]),
+            InputFormat::Base64Blob
+        );
+        assert_eq!(
+            detect_format(&["TWE=", "TWFu", "TQ=="]),
+            InputFormat::Base64Lines
+        );
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(detect_encoding("49276d206b696c6c696e67"), Encoding::Hex);
+        assert_eq!(detect_encoding("SSdtIGtpbGxpbmc="), Encoding::Base64);
+        assert_eq!(
+            detect_encoding("SSdtIGJh\r\nY2sgYW5k\r\nIEk=\n"),
+            Encoding::Base64
+        );
+        assert_eq!(detect_encoding("YELLOW SUBMARINE"), Encoding::Text);
+        assert_eq!(detect_encoding("abc"), Encoding::Text);
+        assert_eq!(detect_encoding("ab=c"), Encoding::Text);
+        assert_eq!(detect_encoding(""), Encoding::Text);
+
+        let (_, data) = load_input("inputs/set6.txt").unwrap();
+        let text = std::fs::read_to_string("inputs/set6.txt").unwrap();
+        assert_eq!(detect_encoding(&text), Encoding::Base64);
+        assert_eq!(RawBytes::from_auto(&text), data[0]);
+    }
+
+    #[test]
+    fn test_load_challenge_inputs() {
+        let expected = vec![
+            ("inputs/set4.txt", InputFormat::HexLines, 327),
+            ("inputs/set6.txt", InputFormat::Base64Blob, 1),
+            ("inputs/set7.txt", InputFormat::Base64Blob, 1),
+            ("inputs/set8.txt", InputFormat::HexLines, 204),
+        ];
+
+        for (path, format, records) in expected {
+            let (f, data) = load_input(path).unwrap();
+            assert_eq!(f, format, "{}", path);
+            assert_eq!(data.len(), records, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_parse_input_crlf_blob() {
+        let (format, data) = parse_input("SSdtIGJh\r\nY2sgYW5k\r\nIEk=\r\n\r\n");
+
+        assert_eq!(format, InputFormat::Base64Blob);
+        assert_eq!(data[0].to_str(), "I'm back and I");
+    }
+}
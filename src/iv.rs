@@ -0,0 +1,113 @@
+#![allow(dead_code)]
+use rand::prelude::*;
+
+/// An initialization vector for the block modes that take one (CBC, CFB,
+/// OFB). The mode checks the length against the block size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Iv(Vec<u8>);
+
+impl Iv {
+    /// A fresh IV from `rng`, which is what a message should get.
+    pub fn random<R: Rng>(rng: &mut R, len: usize) -> Self {
+        let mut bytes = vec![0u8; len];
+        rng.fill_bytes(&mut bytes);
+        Self(bytes)
+    }
+
+    /// An IV with chosen bytes: test vectors, IVs read off the wire, and
+    /// whatever an attacker forges.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+
+    pub fn zero(len: usize) -> Self {
+        Self(vec![0; len])
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A CTR nonce. Deliberately neither `Copy` nor `Clone`: each one is used
+/// for a single message, and getting the same value again takes an explicit
+/// `Nonce::fixed` or `Nonce::from_bytes`.
+#[derive(Debug, PartialEq)]
+pub struct Nonce(Vec<u8>);
+
+impl Nonce {
+    /// A random 64-bit nonce, the size challenge 18's layout uses.
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        Self::fixed(rng.random())
+    }
+
+    /// A 64-bit nonce with a chosen value, stored little-endian, for test
+    /// vectors, for decrypting, and for the challenges that reuse one on
+    /// purpose.
+    pub fn fixed(value: u64) -> Self {
+        Self(value.to_le_bytes().to_vec())
+    }
+
+    /// A nonce of any length with chosen bytes, such as the 96-bit nonces
+    /// of NIST-style CTR.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Hands out nonces by counting, so a sender never repeats one.
+#[derive(Debug, Clone)]
+pub struct NonceSequence {
+    next: Option<u64>,
+}
+
+impl NonceSequence {
+    pub fn new(start: u64) -> Self {
+        Self { next: Some(start) }
+    }
+
+    /// The next nonce, or an error once the counter has run out.
+    pub fn next_nonce(&mut self) -> anyhow::Result<Nonce> {
+        let value = self
+            .next
+            .ok_or_else(|| anyhow::anyhow!("nonce sequence exhausted"))?;
+        self.next = value.checked_add(1);
+        Ok(Nonce::fixed(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_iv() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let a = Iv::random(&mut rng, 16);
+        let b = Iv::random(&mut rng, 16);
+        assert_eq!(a.len(), 16);
+        assert_ne!(a, b);
+        assert_eq!(Iv::zero(8).as_bytes(), &[0; 8]);
+    }
+
+    #[test]
+    fn test_nonce_sequence() {
+        let mut seq = NonceSequence::new(u64::MAX - 2);
+        let got: Vec<Nonce> = (0..3).map(|_| seq.next_nonce().unwrap()).collect();
+        assert_eq!(got[0], Nonce::fixed(u64::MAX - 2));
+        assert_eq!(got[2].as_bytes(), &[0xff; 8]);
+        assert!(seq.next_nonce().is_err());
+    }
+}
@@ -0,0 +1,192 @@
+#![allow(dead_code)]
+use crate::digest::*;
+use crate::hmac::*;
+
+/// PBKDF2 (RFC 8018, 5.2) with HMAC over `D`: each `output_size` chunk of
+/// the key is `iterations` chained HMACs of the salt and the chunk's
+/// number, XORed together, so every guess at the password costs as much
+/// as deriving the key did.
+pub fn pbkdf2<D: Digest>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    key_len: usize,
+) -> anyhow::Result<Vec<u8>> {
+    if iterations == 0 {
+        anyhow::bail!("PBKDF2 needs at least one iteration");
+    }
+    // Blocks are numbered with 32 bits, so the key runs out at
+    // (2^32 - 1) hashes' worth.
+    let max_len = u32::MAX as u64 * D::new().output_size() as u64;
+    if key_len as u64 > max_len {
+        anyhow::bail!("PBKDF2 can give at most {} bytes, not {}", max_len, key_len);
+    }
+    // Keyed once; every HMAC below starts from a copy.
+    let prf = Hmac::<D>::new(password);
+
+    let mut key = Vec::with_capacity(key_len);
+    let mut block = 1u32;
+    while key.len() < key_len {
+        let mut h = prf.clone();
+        h.update(salt);
+        h.update(&block.to_be_bytes());
+        let mut u = h.finalize();
+        let mut t = u.clone();
+        for _ in 1..iterations {
+            let mut h = prf.clone();
+            h.update(&u);
+            u = h.finalize();
+            for (t, u) in t.iter_mut().zip(&u) {
+                *t ^= u;
+            }
+        }
+        key.extend_from_slice(&t);
+        block += 1;
+    }
+    key.truncate(key_len);
+    Ok(key)
+}
+
+/// HKDF's extract step (RFC 5869, 2.2): concentrates whatever entropy `ikm`
+/// has, a Diffie–Hellman shared secret say, into a pseudorandom key. No
+/// salt means a string of zeros as long as the hash.
+pub fn hkdf_extract<D: Digest>(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    if salt.is_empty() {
+        let zeros = vec![0u8; D::new().output_size()];
+        hmac::<D>(&zeros, ikm)
+    } else {
+        hmac::<D>(salt, ikm)
+    }
+}
+
+/// HKDF's expand step (RFC 5869, 2.3): `len` bytes of key from `prk`, with
+/// `info` tying them to what they're for. At most 255 hashes' worth.
+pub fn hkdf_expand<D: Digest>(prk: &[u8], info: &[u8], len: usize) -> anyhow::Result<Vec<u8>> {
+    let hash_len = D::new().output_size();
+    if len > 255 * hash_len {
+        anyhow::bail!(
+            "HKDF can give at most {} bytes, not {}",
+            255 * hash_len,
+            len
+        );
+    }
+
+    let mut okm = Vec::with_capacity(len);
+    let mut t = Vec::new();
+    for i in 1..=len.div_ceil(hash_len) {
+        let mut h = Hmac::<D>::new(prk);
+        h.update(&t);
+        h.update(info);
+        h.update(&[i as u8]);
+        t = h.finalize();
+        okm.extend_from_slice(&t);
+    }
+    okm.truncate(len);
+    Ok(okm)
+}
+
+/// Extract then expand.
+pub fn hkdf<D: Digest>(
+    salt: &[u8],
+    ikm: &[u8],
+    info: &[u8],
+    len: usize,
+) -> anyhow::Result<Vec<u8>> {
+    hkdf_expand::<D>(&hkdf_extract::<D>(salt, ikm), info, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha1::*;
+    use crate::sha3::*;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_pbkdf2_rfc_6070_vectors() {
+        for (password, salt, iterations, expected) in [
+            (
+                &b"password"[..],
+                &b"salt"[..],
+                1,
+                "0c60c80f961f0e71f3a9b524af6012062fe037a6",
+            ),
+            (
+                b"password",
+                b"salt",
+                2,
+                "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957",
+            ),
+            (
+                b"password",
+                b"salt",
+                4096,
+                "4b007901b765489abead49d926f721d065a429c1",
+            ),
+            (
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                4096,
+                "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038",
+            ),
+            (
+                b"pass\0word",
+                b"sa\0lt",
+                4096,
+                "56fa6aa75548099dcc37d7f03425e0c3",
+            ),
+        ] {
+            let key = pbkdf2::<Sha1>(password, salt, iterations, expected.len() / 2).unwrap();
+            assert_eq!(hex(&key), expected);
+        }
+        assert!(pbkdf2::<Sha1>(b"password", b"salt", 0, 20).is_err());
+    }
+
+    #[test]
+    fn test_pbkdf2_output_limit() {
+        // Refused before anything is derived or allocated.
+        let too_long = (u32::MAX as u64 * 20 + 1) as usize;
+        assert!(pbkdf2::<Sha1>(b"password", b"salt", 1, too_long).is_err());
+        assert!(pbkdf2::<Sha1>(b"password", b"salt", 1, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_hkdf_rfc_5869_vectors() {
+        // Test cases 4 and 7, the SHA-1 ones.
+        let ikm = [0x0b; 11];
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        let prk = hkdf_extract::<Sha1>(&salt, &ikm);
+        assert_eq!(hex(&prk), "9b6c18c432a7bf8f0e71c8eb88f4b30baa2ba243");
+        assert_eq!(
+            hex(&hkdf_expand::<Sha1>(&prk, &info, 42).unwrap()),
+            "085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896"
+        );
+
+        let ikm = [0x0c; 22];
+        assert_eq!(
+            hex(&hkdf_extract::<Sha1>(b"", &ikm)),
+            "2adccada18779e7c2077ad2eb19d3f3e731385dd"
+        );
+        assert_eq!(
+            hex(&hkdf::<Sha1>(b"", &ikm, b"", 42).unwrap()),
+            "2c91117204d745f3500d636a62f64f0ab3bae548aa53d423b0d1f27ebba6f5e5673a081d70cce7acfc48"
+        );
+    }
+
+    #[test]
+    fn test_hkdf_output_limit() {
+        let prk = hkdf_extract::<Sha3_256>(b"salt", b"shared secret");
+        assert_eq!(
+            hkdf_expand::<Sha3_256>(&prk, b"", 255 * 32).unwrap().len(),
+            255 * 32
+        );
+        assert!(hkdf_expand::<Sha3_256>(&prk, b"", 255 * 32 + 1).is_err());
+        // Shorter outputs are prefixes of longer ones.
+        let long = hkdf_expand::<Sha3_256>(&prk, b"key", 100).unwrap();
+        assert_eq!(
+            hkdf_expand::<Sha3_256>(&prk, b"key", 40).unwrap(),
+            long[..40]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+#![allow(dead_code)]
+use crate::bitflip::*;
+use crate::block_cipher::*;
+use crate::iv::*;
+use crate::modes::*;
+use crate::oracles::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+use rand::prelude::*;
+
+/// The receiver found high-ASCII bytes and, unhelpfully, says what it
+/// decrypted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComplianceError {
+    pub plaintext: Vec<u8>,
+}
+
+impl std::fmt::Display for ComplianceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid ASCII in message: {}",
+            RawBytes {
+                bytes: self.plaintext.clone()
+            }
+            .to_hex()
+        )
+    }
+}
+
+impl std::error::Error for ComplianceError {}
+
+/// The challenge 27 server: the challenge 16 comment strings under
+/// AES-128-CBC, with the key reused as the IV.
+pub struct KeyAsIvOracle {
+    aes: Aes,
+    key: [u8; 16],
+}
+
+impl KeyAsIvOracle {
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        Self {
+            aes: Aes::new(&key).expect("16 byte key"),
+            key,
+        }
+    }
+
+    pub fn encrypt(&self, userdata: &str) -> Vec<u8> {
+        let pt = RawBytes::from_str(&comment_string(userdata));
+        cbc_encrypt_with_padding(&self.aes, &Iv::from_bytes(&self.key), &pt, &Pkcs7)
+            .expect("padded")
+            .bytes
+    }
+
+    /// Decrypts `ct` without checking padding, and complains if any byte is
+    /// outside 7-bit ASCII.
+    pub fn check_ascii(&self, ct: &[u8]) -> anyhow::Result<()> {
+        let pt = cbc_decrypt(&self.aes, &Iv::from_bytes(&self.key), ct)?;
+        if pt.iter().any(|b| *b >= 0x80) {
+            return Err(ComplianceError { plaintext: pt }.into());
+        }
+        Ok(())
+    }
+
+    /// For checking a recovered key.
+    pub fn key(&self) -> &[u8; 16] {
+        &self.key
+    }
+}
+
+impl DecryptionOracle<()> for &KeyAsIvOracle {
+    fn decrypt(&mut self, ct: &[u8]) -> anyhow::Result<()> {
+        self.check_ascii(ct)
+    }
+}
+
+/// Recovers the key of a CBC oracle that uses it as the IV. Resending a
+/// ciphertext as `C1, 0, C1` decrypts to `P1' = D(C1) ^ K` and
+/// `P3' = D(C1) ^ 0`, so `P1' ^ P3'` is the key. The receiver must leak
+/// the plaintext in its error.
+pub fn recover_key_as_iv<O: DecryptionOracle<()>>(
+    ct: &[u8],
+    mut receiver: O,
+) -> anyhow::Result<[u8; 16]> {
+    if ct.len() < 3 * 16 {
+        anyhow::bail!("need at least three blocks, got {} bytes", ct.len());
+    }
+
+    let mut forged = ct.to_vec();
+    forged[16..32].fill(0);
+    forged.copy_within(0..16, 32);
+
+    let err = match receiver.decrypt(&forged) {
+        Ok(()) => anyhow::bail!("the receiver accepted the forged message"),
+        Err(err) => err,
+    };
+    let pt = match err.downcast_ref::<ComplianceError>() {
+        Some(e) => &e.plaintext,
+        None => return Err(err),
+    };
+    if pt.len() < 48 {
+        anyhow::bail!(
+            "the receiver leaked {} bytes of plaintext, not the three blocks needed",
+            pt.len()
+        );
+    }
+
+    let mut key = [0u8; 16];
+    for (i, k) in key.iter_mut().enumerate() {
+        *k = pt[i] ^ pt[32 + i];
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recover_key_as_iv() {
+        let oracle = KeyAsIvOracle::new(&mut StdRng::seed_from_u64(27));
+        let ct = oracle.encrypt("an ordinary comment");
+        assert!(oracle.check_ascii(&ct).is_ok());
+
+        let key = recover_key_as_iv(&ct, &oracle).unwrap();
+        assert_eq!(&key, oracle.key());
+    }
+
+    #[test]
+    fn test_recover_key_as_iv_needs_a_leak() {
+        let oracle = KeyAsIvOracle::new(&mut StdRng::seed_from_u64(27));
+        let ct = oracle.encrypt("x");
+
+        assert!(recover_key_as_iv(&ct[..32], &oracle).is_err());
+        assert!(recover_key_as_iv(&ct, |_: &[u8]| Ok(())).is_err());
+        assert!(recover_key_as_iv(&ct, |_: &[u8]| anyhow::bail!("rejected")).is_err());
+
+        // A receiver that cuts the plaintext short before reporting it.
+        let truncating = |ct: &[u8]| match oracle.check_ascii(ct) {
+            Err(err) => match err.downcast::<ComplianceError>() {
+                Ok(e) => Err(ComplianceError {
+                    plaintext: e.plaintext[..40].to_vec(),
+                }
+                .into()),
+                Err(err) => Err(err),
+            },
+            ok => ok,
+        };
+        let err = recover_key_as_iv(&ct, truncating).unwrap_err();
+        assert!(err.to_string().contains("40 bytes"), "{}", err);
+    }
+}
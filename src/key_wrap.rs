@@ -0,0 +1,250 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+
+/// The RFC 3394 initial value.
+const DEFAULT_IV: [u8; 8] = [0xa6; 8];
+/// The first half of the RFC 5649 alternative initial value; the length
+/// of the key data follows.
+const PADDED_IV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyWrapError {
+    /// The input is too short or not a whole number of 8-byte halves.
+    BadLength(usize),
+    /// The unwrapped integrity check value, or with RFC 5649 the length and
+    /// padding it describes, is wrong: the wrong KEK or a tampered blob.
+    IntegrityCheck,
+}
+
+impl std::fmt::Display for KeyWrapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KeyWrapError::BadLength(len) => write!(f, "invalid key wrap length {}", len),
+            KeyWrapError::IntegrityCheck => write!(f, "key unwrap integrity check failed"),
+        }
+    }
+}
+
+impl std::error::Error for KeyWrapError {}
+
+/// The wrapping function W: six passes over the 64-bit halves `r`, each
+/// step encrypting `a | r[i]` and folding the step number into `a`.
+fn wrap_rounds<C: BlockCipher + ?Sized>(kek: &C, a: [u8; 8], r: &[u8]) -> Vec<u8> {
+    let n = r.len() / 8;
+    let mut a = a;
+    let mut r = r.to_vec();
+    let mut b = [0u8; 16];
+    for j in 0..6 {
+        for i in 0..n {
+            b[..8].copy_from_slice(&a);
+            b[8..].copy_from_slice(&r[i * 8..i * 8 + 8]);
+            kek.encrypt_block(&mut b);
+            let t = (n * j + i + 1) as u64;
+            for (x, y) in a.iter_mut().zip(b[..8].iter().zip(t.to_be_bytes())) {
+                *x = y.0 ^ y.1;
+            }
+            r[i * 8..i * 8 + 8].copy_from_slice(&b[8..]);
+        }
+    }
+    [&a[..], &r].concat()
+}
+
+/// W⁻¹, returning the recovered `a` and halves.
+fn unwrap_rounds<C: BlockCipher + ?Sized>(kek: &C, c: &[u8]) -> ([u8; 8], Vec<u8>) {
+    let n = c.len() / 8 - 1;
+    let mut a = [0u8; 8];
+    a.copy_from_slice(&c[..8]);
+    let mut r = c[8..].to_vec();
+    let mut b = [0u8; 16];
+    for j in (0..6).rev() {
+        for i in (0..n).rev() {
+            let t = (n * j + i + 1) as u64;
+            for (x, y) in b[..8].iter_mut().zip(a.iter().zip(t.to_be_bytes())) {
+                *x = y.0 ^ y.1;
+            }
+            b[8..].copy_from_slice(&r[i * 8..i * 8 + 8]);
+            kek.decrypt_block(&mut b);
+            a.copy_from_slice(&b[..8]);
+            r[i * 8..i * 8 + 8].copy_from_slice(&b[8..]);
+        }
+    }
+    (a, r)
+}
+
+fn check_kek<C: BlockCipher + ?Sized>(kek: &C) -> anyhow::Result<()> {
+    if kek.block_size() != 16 {
+        anyhow::bail!("key wrap needs a 16 byte block cipher");
+    }
+    Ok(())
+}
+
+/// RFC 3394 key wrap. `key` must be at least two 8-byte halves.
+pub fn key_wrap<C: BlockCipher + ?Sized>(kek: &C, key: &[u8]) -> anyhow::Result<Vec<u8>> {
+    check_kek(kek)?;
+    if key.len() < 16 || !key.len().is_multiple_of(8) {
+        return Err(KeyWrapError::BadLength(key.len()).into());
+    }
+    Ok(wrap_rounds(kek, DEFAULT_IV, key))
+}
+
+/// RFC 3394 key unwrap. Fails with `KeyWrapError::IntegrityCheck` unless
+/// the initial value comes back out.
+pub fn key_unwrap<C: BlockCipher + ?Sized>(kek: &C, wrapped: &[u8]) -> anyhow::Result<Vec<u8>> {
+    check_kek(kek)?;
+    if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+        return Err(KeyWrapError::BadLength(wrapped.len()).into());
+    }
+    let (a, r) = unwrap_rounds(kek, wrapped);
+    if a != DEFAULT_IV {
+        return Err(KeyWrapError::IntegrityCheck.into());
+    }
+    Ok(r)
+}
+
+/// RFC 5649 key wrap with padding, for key data of any nonzero length.
+pub fn key_wrap_with_padding<C: BlockCipher + ?Sized>(
+    kek: &C,
+    key: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    check_kek(kek)?;
+    if key.is_empty() || key.len() > u32::MAX as usize {
+        return Err(KeyWrapError::BadLength(key.len()).into());
+    }
+
+    let mut a = [0u8; 8];
+    a[..4].copy_from_slice(&PADDED_IV_PREFIX);
+    a[4..].copy_from_slice(&(key.len() as u32).to_be_bytes());
+    let mut padded = key.to_vec();
+    padded.resize(key.len().div_ceil(8) * 8, 0);
+
+    // A single half is just encrypted along with the initial value.
+    if padded.len() == 8 {
+        let mut b = [&a[..], &padded].concat();
+        kek.encrypt_block(&mut b);
+        return Ok(b);
+    }
+    Ok(wrap_rounds(kek, a, &padded))
+}
+
+/// RFC 5649 key unwrap. The initial value, the length it gives and the
+/// zero padding all have to check out.
+pub fn key_unwrap_with_padding<C: BlockCipher + ?Sized>(
+    kek: &C,
+    wrapped: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    check_kek(kek)?;
+    if wrapped.len() < 16 || !wrapped.len().is_multiple_of(8) {
+        return Err(KeyWrapError::BadLength(wrapped.len()).into());
+    }
+
+    let (a, r) = if wrapped.len() == 16 {
+        let mut b = wrapped.to_vec();
+        kek.decrypt_block(&mut b);
+        let mut a = [0u8; 8];
+        a.copy_from_slice(&b[..8]);
+        (a, b[8..].to_vec())
+    } else {
+        unwrap_rounds(kek, wrapped)
+    };
+
+    let len = u32::from_be_bytes([a[4], a[5], a[6], a[7]]) as usize;
+    let ok = a[..4] == PADDED_IV_PREFIX
+        && len + 8 > r.len()
+        && len <= r.len()
+        && r[len..].iter().all(|b| *b == 0);
+    if !ok {
+        return Err(KeyWrapError::IntegrityCheck.into());
+    }
+    Ok(r[..len].to_vec())
+}
+
+/// `key_wrap` under an AES key-encryption key of any AES key size.
+pub fn aes_key_wrap(kek: &[u8], key: &[u8]) -> anyhow::Result<Vec<u8>> {
+    key_wrap(&Aes::new(kek)?, key)
+}
+
+pub fn aes_key_unwrap(kek: &[u8], wrapped: &[u8]) -> anyhow::Result<Vec<u8>> {
+    key_unwrap(&Aes::new(kek)?, wrapped)
+}
+
+pub fn aes_key_wrap_with_padding(kek: &[u8], key: &[u8]) -> anyhow::Result<Vec<u8>> {
+    key_wrap_with_padding(&Aes::new(kek)?, key)
+}
+
+pub fn aes_key_unwrap_with_padding(kek: &[u8], wrapped: &[u8]) -> anyhow::Result<Vec<u8>> {
+    key_unwrap_with_padding(&Aes::new(kek)?, wrapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_rfc_3394_vectors() {
+        // 4.1: 128 bits of key data under a 128-bit KEK.
+        let kek = unhex("000102030405060708090a0b0c0d0e0f");
+        let key = unhex("00112233445566778899aabbccddeeff");
+        let wrapped = aes_key_wrap(&kek, &key).unwrap();
+        assert_eq!(
+            wrapped,
+            unhex("1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5")
+        );
+        assert_eq!(aes_key_unwrap(&kek, &wrapped).unwrap(), key);
+
+        // 4.6: 256 bits of key data under a 256-bit KEK.
+        let kek = unhex("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+        let key = unhex("00112233445566778899aabbccddeeff000102030405060708090a0b0c0d0e0f");
+        let wrapped = aes_key_wrap(&kek, &key).unwrap();
+        assert_eq!(
+            wrapped,
+            unhex(
+                "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43bfb988b9b7a02dd21"
+            )
+        );
+        assert_eq!(aes_key_unwrap(&kek, &wrapped).unwrap(), key);
+    }
+
+    #[test]
+    fn test_rfc_5649_vectors() {
+        let kek = unhex("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8");
+        for (key, expected) in [
+            (
+                "c37b7e6492584340bed12207808941155068f738",
+                "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a",
+            ),
+            ("466f7250617369", "afbeb0f07dfbf5419200f2ccb50bb24f"),
+        ] {
+            let wrapped = aes_key_wrap_with_padding(&kek, &unhex(key)).unwrap();
+            assert_eq!(wrapped, unhex(expected));
+            assert_eq!(
+                aes_key_unwrap_with_padding(&kek, &wrapped).unwrap(),
+                unhex(key)
+            );
+        }
+    }
+
+    #[test]
+    fn test_unwrap_integrity_failures() {
+        let kek = unhex("000102030405060708090a0b0c0d0e0f");
+        let key = unhex("00112233445566778899aabbccddeeff");
+        let integrity = |r: anyhow::Result<Vec<u8>>| {
+            r.unwrap_err().downcast_ref::<KeyWrapError>() == Some(&KeyWrapError::IntegrityCheck)
+        };
+
+        let mut wrapped = aes_key_wrap(&kek, &key).unwrap();
+        wrapped[20] ^= 1;
+        assert!(integrity(aes_key_unwrap(&kek, &wrapped)));
+        let wrapped = aes_key_wrap(&kek, &key).unwrap();
+        assert!(integrity(aes_key_unwrap(&[0u8; 16], &wrapped)));
+        assert!(integrity(aes_key_unwrap_with_padding(&kek, &wrapped)));
+
+        let mut wrapped = aes_key_wrap_with_padding(&kek, b"seven b").unwrap();
+        wrapped[3] ^= 0x80;
+        assert!(integrity(aes_key_unwrap_with_padding(&kek, &wrapped)));
+
+        assert!(aes_key_wrap(&kek, &key[..12]).is_err());
+        assert!(aes_key_unwrap(&kek, &[0u8; 16]).is_err());
+        assert!(aes_key_wrap_with_padding(&kek, b"").is_err());
+    }
+}
@@ -0,0 +1,150 @@
+#![allow(dead_code)]
+
+/// The separators of a flat key-value encoding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KvFormat {
+    pub pair_sep: u8,
+    pub kv_sep: u8,
+}
+
+/// `k1=v1&k2=v2`, as in challenge 13's profiles.
+pub const QUERY: KvFormat = KvFormat {
+    pair_sep: b'&',
+    kv_sep: b'=',
+};
+
+/// `k1=v1;k2=v2`, as in the comment strings of challenges 16 and 26.
+pub const COOKIE: KvFormat = KvFormat {
+    pair_sep: b';',
+    kv_sep: b'=',
+};
+
+impl KvFormat {
+    fn must_escape(&self, b: u8) -> bool {
+        b == self.pair_sep || b == self.kv_sep || b == b'%' || b == b' '
+    }
+
+    /// Percent-encodes the separators and `%`, so `s` can't start a field
+    /// of its own, and spaces, as the challenge strings do. Other bytes go
+    /// through as they are, non-ASCII characters included.
+    pub fn escape(&self, s: &str) -> String {
+        let mut ans = String::with_capacity(s.len());
+        for c in s.chars() {
+            if c.is_ascii() && self.must_escape(c as u8) {
+                ans.push_str(&format!("%{:02X}", c as u8));
+            } else {
+                ans.push(c);
+            }
+        }
+        ans
+    }
+
+    pub fn serialize<K: AsRef<str>, V: AsRef<str>>(&self, pairs: &[(K, V)]) -> String {
+        pairs
+            .iter()
+            .map(|(k, v)| {
+                format!(
+                    "{}{}{}",
+                    self.escape(k.as_ref()),
+                    self.kv_sep as char,
+                    self.escape(v.as_ref())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(&(self.pair_sep as char).to_string())
+    }
+
+    /// Parses every pair, in order, undoing the escaping. Any pair without a
+    /// key-value separator, or with a bad escape, is an error.
+    pub fn parse(&self, s: &str) -> anyhow::Result<Vec<(String, String)>> {
+        s.split(self.pair_sep as char)
+            .map(|pair| match pair.split_once(self.kv_sep as char) {
+                Some((k, v)) => Ok((unescape(k.as_bytes())?, unescape(v.as_bytes())?)),
+                None => anyhow::bail!("no {:?} in {:?}", self.kv_sep as char, pair),
+            })
+            .collect()
+    }
+
+    /// Parses whatever pairs it can out of bytes that may be partly garbage,
+    /// such as a plaintext with a block scrambled by bitflipping. Pairs
+    /// that don't parse are skipped, and values are left escaped.
+    pub fn parse_lenient<'a>(&self, bytes: &'a [u8]) -> Vec<(&'a [u8], &'a [u8])> {
+        bytes
+            .split(|b| *b == self.pair_sep)
+            .filter_map(|pair| {
+                let at = pair.iter().position(|b| *b == self.kv_sep)?;
+                Some((&pair[..at], &pair[at + 1..]))
+            })
+            .collect()
+    }
+}
+
+fn unescape(s: &[u8]) -> anyhow::Result<String> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        if s[i] == b'%' {
+            let hex = s
+                .get(i + 1..i + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok());
+            match hex {
+                Some(b) => out.push(b),
+                None => anyhow::bail!("bad escape at {} in {:?}", i, String::from_utf8_lossy(s)),
+            }
+            i += 3;
+        } else {
+            out.push(s[i]);
+            i += 1;
+        }
+    }
+    Ok(String::from_utf8(out)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_serialize() {
+        let pairs = QUERY.parse("foo=bar&baz=qux&zap=zazzle").unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                ("foo".to_string(), "bar".to_string()),
+                ("baz".to_string(), "qux".to_string()),
+                ("zap".to_string(), "zazzle".to_string()),
+            ]
+        );
+        assert_eq!(QUERY.serialize(&pairs), "foo=bar&baz=qux&zap=zazzle");
+        assert!(QUERY.parse("foo=bar&baz").is_err());
+        assert!(QUERY.parse("foo=%4").is_err());
+    }
+
+    #[test]
+    fn test_escaping_round_trips() {
+        let pairs = [("user data", "a;b=c&d%e"), ("k", "")];
+        let s = COOKIE.serialize(&pairs);
+        assert_eq!(s, "user%20data=a%3Bb%3Dc&d%25e;k=");
+        let back = COOKIE.parse(&s).unwrap();
+        assert_eq!(back[0], ("user data".to_string(), "a;b=c&d%e".to_string()));
+
+        // The other format's separators are left alone.
+        assert_eq!(QUERY.escape("a;b&c"), "a;b%26c");
+    }
+
+    #[test]
+    fn test_non_ascii_round_trips() {
+        let pairs = [("naïve user", "café=☕;ÿ")];
+        let s = COOKIE.serialize(&pairs);
+        assert_eq!(s, "naïve%20user=café%3D☕%3Bÿ");
+        let back = COOKIE.parse(&s).unwrap();
+        assert_eq!(back[0], (pairs[0].0.to_string(), pairs[0].1.to_string()));
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let pairs = COOKIE.parse_lenient(b"\xff\x00garbage;admin=true;x=1=2");
+        assert_eq!(pairs, vec![(&b"admin"[..], &b"true"[..]), (b"x", b"1=2")]);
+    }
+}
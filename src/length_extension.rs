@@ -0,0 +1,196 @@
+#![allow(dead_code)]
+use crate::digest::*;
+use crate::md4::*;
+use crate::merkle_damgard::*;
+use crate::oracles::*;
+use crate::sha1::*;
+
+/// What a length extension needs from a hash: a way back into the state a
+/// digest was written from, and the padding the hash appended, which ends
+/// up in the middle of the forged message. Only hashes whose digest is
+/// their whole state can do this; a sponge like SHA-3 keeps most of its
+/// state back.
+pub trait LengthExtendable: Digest {
+    /// Carries on from the state a digest was written from, as though
+    /// `processed` bytes (a whole number of blocks) had already gone in.
+    fn from_state(digest: &[u8], processed: u64) -> Self;
+
+    /// The padding after a `message_len`-byte input.
+    fn padding(message_len: u64) -> Vec<u8>;
+}
+
+impl<C: Compression + Clone> LengthExtendable for MerkleDamgard<C> {
+    fn from_state(digest: &[u8], processed: u64) -> Self {
+        MerkleDamgard::from_digest(digest, processed)
+    }
+
+    fn padding(message_len: u64) -> Vec<u8> {
+        crate::merkle_damgard::md_padding(message_len, C::BLOCK_SIZE, C::ENDIANNESS)
+    }
+}
+
+/// Extends a secret-prefix MAC without the key. `mac` is the tag on
+/// `message` under a key of `key_len` bytes. The hash of `key || message`
+/// ended with the state in `mac`, after hashing that input and its
+/// padding; setting the registers to it and carrying on gives the hash of
+/// `key || message || glue || suffix`, where `glue` is that padding.
+///
+/// Returns the forged message, which is everything after the key, and its
+/// tag, or an error if `mac` isn't a whole digest of `H`.
+pub fn length_extension<H: LengthExtendable>(
+    mac: &[u8],
+    message: &[u8],
+    key_len: usize,
+    suffix: &[u8],
+) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let digest_size = H::new().output_size();
+    if mac.len() != digest_size {
+        anyhow::bail!("MAC is {} bytes, not {}", mac.len(), digest_size);
+    }
+    let glue = H::padding((key_len + message.len()) as u64);
+    let processed = (key_len + message.len() + glue.len()) as u64;
+    let mut h = H::from_state(mac, processed);
+    h.update(suffix);
+    let tag = h.finalize();
+    Ok(([message, &glue, suffix].concat(), tag))
+}
+
+/// A forged message, its tag and the key length it was made for.
+pub type Forgery = (Vec<u8>, Vec<u8>, usize);
+
+/// The challenge 29 and 30 attack: the key length isn't known, so try
+/// each one up to `max_key_len` against `verifier` until a forgery is
+/// accepted. Returns the forged message, its tag and the key length that
+/// worked, or `None` if no length did.
+pub fn forge_secret_prefix_mac<H: LengthExtendable, V: MacVerifier>(
+    mut verifier: V,
+    message: &[u8],
+    mac: &[u8],
+    suffix: &[u8],
+    max_key_len: usize,
+) -> anyhow::Result<Option<Forgery>> {
+    for key_len in 0..=max_key_len {
+        let (forged, tag) = length_extension::<H>(mac, message, key_len, suffix)?;
+        if verifier.verify(&forged, &tag) {
+            return Ok(Some((forged, tag, key_len)));
+        }
+    }
+    Ok(None)
+}
+
+pub fn sha1_length_extension(
+    mac: &[u8; crate::sha1::DIGEST_SIZE],
+    message: &[u8],
+    key_len: usize,
+    suffix: &[u8],
+) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    length_extension::<Sha1>(mac, message, key_len, suffix)
+}
+
+pub fn forge_sha1_mac<V: MacVerifier>(
+    verifier: V,
+    message: &[u8],
+    mac: &[u8; crate::sha1::DIGEST_SIZE],
+    suffix: &[u8],
+    max_key_len: usize,
+) -> anyhow::Result<Option<Forgery>> {
+    forge_secret_prefix_mac::<Sha1, V>(verifier, message, mac, suffix, max_key_len)
+}
+
+pub fn forge_md4_mac<V: MacVerifier>(
+    verifier: V,
+    message: &[u8],
+    mac: &[u8; crate::md4::DIGEST_SIZE],
+    suffix: &[u8],
+    max_key_len: usize,
+) -> anyhow::Result<Option<Forgery>> {
+    forge_secret_prefix_mac::<Md4, V>(verifier, message, mac, suffix, max_key_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md5::*;
+    use crate::sha1_mac::*;
+    use rand::prelude::*;
+
+    const MESSAGE: &[u8] =
+        b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+
+    #[test]
+    fn test_extension_with_known_key_length() {
+        let key = b"not so secret";
+        let mac = sha1_mac(key, MESSAGE);
+        let (forged, tag) =
+            sha1_length_extension(&mac, MESSAGE, key.len(), b";admin=true").unwrap();
+        assert_eq!(tag, sha1_mac(key, &forged));
+        assert!(forged.starts_with(MESSAGE) && forged.ends_with(b";admin=true"));
+
+        let mac = md4_mac(key, MESSAGE);
+        let (forged, tag) =
+            length_extension::<Md4>(&mac, MESSAGE, key.len(), b";admin=true").unwrap();
+        assert_eq!(tag, md4_mac(key, &forged));
+    }
+
+    #[test]
+    fn test_wrong_length_mac_is_an_error() {
+        let mac = sha1_mac(b"not so secret", MESSAGE);
+        assert!(length_extension::<Sha1>(&mac[..19], MESSAGE, 13, b";admin=true").is_err());
+        assert!(length_extension::<Md4>(&mac, MESSAGE, 13, b";admin=true").is_err());
+        assert!(forge_secret_prefix_mac::<Md4, _>(
+            |_: &[u8], _: &[u8]| true,
+            MESSAGE,
+            &[],
+            b";admin=true",
+            64
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_forgery_against_server() {
+        let mut rng = StdRng::seed_from_u64(613);
+        let server = Sha1MacServer::new(&mut rng);
+        let mac = server.sign(MESSAGE);
+
+        let (forged, tag, key_len) = forge_sha1_mac(&server, MESSAGE, &mac, b";admin=true", 64)
+            .unwrap()
+            .expect("some key length works");
+        assert_eq!(key_len, server.key_len());
+        let mut verifier = &server;
+        assert!(verifier.verify(&forged, &tag));
+        assert!(forged.ends_with(b";admin=true"));
+    }
+
+    #[test]
+    fn test_md4_forgery() {
+        let mut rng = StdRng::seed_from_u64(614);
+        let server = Md4MacServer::new(&mut rng);
+        let mac = server.sign(MESSAGE);
+
+        let (forged, tag, key_len) = forge_md4_mac(&server, MESSAGE, &mac, b";admin=true", 64)
+            .unwrap()
+            .expect("some key length works");
+        assert_eq!(key_len, server.key_len());
+        let mut verifier = &server;
+        assert!(verifier.verify(&forged, &tag));
+        assert!(forged.ends_with(b";admin=true"));
+    }
+
+    #[test]
+    fn test_md5_forgery() {
+        let mut rng = StdRng::seed_from_u64(618);
+        let mut key = vec![0u8; rng.random_range(8..=32)];
+        rng.fill_bytes(&mut key);
+        let md5_mac = |m: &[u8]| md5(&[&key[..], m].concat());
+        let mac = md5_mac(MESSAGE);
+
+        let verify = |m: &[u8], tag: &[u8]| md5_mac(m)[..] == *tag;
+        let (forged, tag, key_len) =
+            forge_secret_prefix_mac::<Md5, _>(verify, MESSAGE, &mac, b";admin=true", 64)
+                .unwrap()
+                .expect("some key length works");
+        assert_eq!(key_len, key.len());
+        assert_eq!(tag, md5_mac(&forged));
+    }
+}
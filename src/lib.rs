@@ -1,6 +1,80 @@
-mod aes;
+pub mod aes;
+mod aes_bitsliced;
+#[cfg(test)]
+mod aes_conformance;
+#[cfg(target_arch = "x86_64")]
+mod aes_ni;
+mod arx;
+pub mod backend;
+mod bitflip;
+pub mod block_cipher;
+mod cache_timing;
+mod cbc_mac;
+mod cbc_mac_forgery;
+mod ccm;
+mod chacha20;
+mod chacha20_poly1305;
+pub mod challenges;
+mod cheap_hash;
 mod cipher;
+mod classical;
+#[cfg(test)]
+mod codec_conformance;
+mod compression_oracle;
+mod ctr_attack;
+mod deadline;
+mod deflate;
+mod des;
+mod digest;
+mod ecb_attack;
+mod expandable_message;
+mod fuzz;
+mod histogram;
+mod hmac;
+mod hmac_timing;
+mod input;
+pub mod iv;
+mod kdf;
+mod key_as_iv;
+mod key_wrap;
+mod keystream;
+mod kv;
+mod length_extension;
+mod many_time_pad;
+mod md4;
+mod md4_collision;
+mod md5;
+mod merkle_damgard;
+mod mode_io;
+mod mode_oracle;
+pub mod modes;
+mod mt19937;
+mod mt_attack;
+mod multicollision;
+mod oracles;
+mod padding;
+mod padding_oracle;
+mod poly1305;
+mod predictable_iv;
+mod profile;
 mod raw_bytes;
+mod rc4;
+mod rc4_bias;
+pub mod report;
+mod salsa20;
+mod sha1;
+mod sha1_mac;
+mod sha3;
+mod spn;
+mod spn_attack;
+mod tea;
+#[cfg(test)]
+mod test_helpers;
+mod timer;
+#[cfg(feature = "timing-server")]
+mod timing_server;
+mod whitebox;
+mod xor_io;
 
 #[cfg(test)]
 mod tests {
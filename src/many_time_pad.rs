@@ -0,0 +1,356 @@
+#![allow(dead_code)]
+use crate::cipher::*;
+use crate::deadline::*;
+use crate::raw_bytes::*;
+
+/// What the other ciphertexts decrypt to if `crib` sits in ciphertext
+/// `source` at `position`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CribMatch {
+    pub source: usize,
+    pub position: usize,
+    pub keystream: RawBytes,
+    /// `(ciphertext index, plaintext fragment)` for every other ciphertext
+    /// long enough to cover the crib.
+    pub fragments: Vec<(usize, RawBytes)>,
+    /// Mean English log-likelihood per fragment byte.
+    pub score: f64,
+}
+
+fn plausible(b: u8) -> bool {
+    b.is_ascii_graphic() || b == b' '
+}
+
+/// Ciphertexts encrypted under one reused keystream, with whatever keystream
+/// bytes have been worked out so far. Drag cribs, accept the matches that
+/// read well and watch the plaintexts fill in.
+#[derive(Debug, Clone)]
+pub struct ManyTimePad {
+    ciphertexts: Vec<RawBytes>,
+    keystream: Vec<Option<u8>>,
+}
+
+impl ManyTimePad {
+    pub fn new(ciphertexts: &[RawBytes]) -> Self {
+        let len = ciphertexts.iter().map(|c| c.bytes.len()).max().unwrap_or(0);
+        Self {
+            ciphertexts: ciphertexts.to_vec(),
+            keystream: vec![None; len],
+        }
+    }
+
+    /// Slides `crib` across every position of every ciphertext and keeps the
+    /// placements under which all other overlapping fragments are printable
+    /// text, best first.
+    pub fn drag(&self, crib: &[u8]) -> Vec<CribMatch> {
+        let mut ans = Vec::new();
+
+        for (source, ct) in self.ciphertexts.iter().enumerate() {
+            for position in 0..(ct.bytes.len() + 1).saturating_sub(crib.len()) {
+                if let Some(m) = self.try_crib(source, position, crib) {
+                    ans.push(m);
+                }
+            }
+        }
+
+        ans.sort_by(|a, b| b.score.total_cmp(&a.score));
+        ans
+    }
+
+    fn try_crib(&self, source: usize, position: usize, crib: &[u8]) -> Option<CribMatch> {
+        let range = position..position + crib.len();
+        let keystream: Vec<u8> = self.ciphertexts[source].bytes[range.clone()]
+            .iter()
+            .zip(crib.iter())
+            .map(|(c, p)| c ^ p)
+            .collect();
+
+        let mut fragments = Vec::new();
+        let mut total = 0.0;
+        for (i, ct) in self.ciphertexts.iter().enumerate() {
+            if i == source || ct.bytes.len() < range.end {
+                continue;
+            }
+
+            let bytes: Vec<u8> = ct.bytes[range.clone()]
+                .iter()
+                .zip(keystream.iter())
+                .map(|(c, k)| c ^ k)
+                .collect();
+            if !bytes.iter().all(|b| plausible(*b)) {
+                return None;
+            }
+            total += english_score(&bytes);
+            fragments.push((i, RawBytes { bytes }));
+        }
+
+        // Nothing else to check the placement against.
+        if fragments.is_empty() || crib.is_empty() {
+            return None;
+        }
+
+        let score = total / (fragments.len() * crib.len()) as f64;
+        Some(CribMatch {
+            source,
+            position,
+            keystream: RawBytes { bytes: keystream },
+            fragments,
+            score,
+        })
+    }
+
+    /// Records the keystream bytes implied by `m`.
+    pub fn accept(&mut self, m: &CribMatch) {
+        for (i, k) in m.keystream.bytes.iter().enumerate() {
+            self.keystream[m.position + i] = Some(*k);
+        }
+    }
+
+    /// Records the keystream implied by ciphertext `source` decrypting to
+    /// `plaintext` from `position` on, for fixing up guesses by hand.
+    pub fn set_plaintext(&mut self, source: usize, position: usize, plaintext: &[u8]) {
+        let ct = &self.ciphertexts[source].bytes[position..position + plaintext.len()];
+        for (i, (c, p)) in ct.iter().zip(plaintext.iter()).enumerate() {
+            self.keystream[position + i] = Some(c ^ p);
+        }
+    }
+
+    /// Records keystream bytes worked out some other way.
+    pub fn set_keystream(&mut self, position: usize, keystream: &[u8]) {
+        for (i, k) in keystream.iter().enumerate() {
+            self.keystream[position + i] = Some(*k);
+        }
+    }
+
+    /// Forgets the keystream over `range`, to back out a bad guess.
+    pub fn forget(&mut self, range: std::ops::Range<usize>) {
+        for k in &mut self.keystream[range] {
+            *k = None;
+        }
+    }
+
+    /// The plaintexts as they would read if ciphertext `source` decrypted
+    /// to `guess` from `position` on, on top of what is already known.
+    /// Nothing is recorded; follow up with `set_plaintext` if it reads well.
+    pub fn preview(
+        &self,
+        source: usize,
+        position: usize,
+        guess: &[u8],
+        unknown: u8,
+    ) -> Vec<RawBytes> {
+        let mut trial = self.clone();
+        trial.set_plaintext(source, position, guess);
+        trial.plaintexts(unknown)
+    }
+
+    /// How many keystream bytes are known.
+    pub fn known(&self) -> usize {
+        self.keystream.iter().filter(|k| k.is_some()).count()
+    }
+
+    pub fn keystream(&self) -> &[Option<u8>] {
+        &self.keystream
+    }
+
+    /// The plaintexts as far as they are known, with `unknown` in the gaps.
+    pub fn plaintexts(&self, unknown: u8) -> Vec<RawBytes> {
+        self.ciphertexts
+            .iter()
+            .map(|ct| {
+                let bytes = ct
+                    .bytes
+                    .iter()
+                    .zip(self.keystream.iter())
+                    .map(|(c, k)| k.map_or(unknown, |k| c ^ k))
+                    .collect();
+                RawBytes { bytes }
+            })
+            .collect()
+    }
+}
+
+/// What `break_fixed_nonce` recovers: the keystream over the common
+/// length, and every ciphertext decrypted that far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedNonceBreak {
+    pub keystream: RawBytes,
+    pub plaintexts: Vec<RawBytes>,
+}
+
+/// Breaks ciphertexts that share a keystream (fixed-nonce CTR, challenge
+/// 20) without any guessing. Truncated to the shortest one and laid end to
+/// end they are repeating-key XOR with the keystream as the key, so each
+/// keystream byte falls to the single-byte solver on its column.
+///
+/// Flipping the case of a whole column scores nearly the same, and the
+/// first column is all line starts, so there the keystream byte that
+/// makes more of them capitals wins.
+pub fn break_fixed_nonce(ciphertexts: &[RawBytes]) -> anyhow::Result<FixedNonceBreak> {
+    let len = ciphertexts.iter().map(|c| c.bytes.len()).min().unwrap_or(0);
+    if len == 0 {
+        anyhow::bail!("need at least one non-empty ciphertext");
+    }
+
+    let joined = RawBytes {
+        bytes: ciphertexts
+            .iter()
+            .flat_map(|c| c.bytes[..len].iter().copied())
+            .collect(),
+    };
+    let mut attack = RepeatingKeyXorAttack::new(&joined, len);
+    while !attack.step() {}
+    let mut keystream = attack.best_so_far();
+
+    let (upper, lower) = ciphertexts.iter().fold((0, 0), |(u, l), c| {
+        let first = c.bytes[0] ^ keystream.bytes[0];
+        (
+            u + first.is_ascii_uppercase() as usize,
+            l + first.is_ascii_lowercase() as usize,
+        )
+    });
+    if lower > upper {
+        keystream.bytes[0] ^= 0x20;
+    }
+
+    let plaintexts = ciphertexts
+        .iter()
+        .map(|c| {
+            repeating_key_xor(
+                &RawBytes {
+                    bytes: c.bytes[..len].to_vec(),
+                },
+                &keystream,
+            )
+        })
+        .collect();
+    Ok(FixedNonceBreak {
+        keystream,
+        plaintexts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_cipher::*;
+    use crate::input::*;
+    use crate::iv::*;
+    use crate::modes::*;
+    use crate::padding::*;
+    use rand::prelude::*;
+
+    const PLAINTEXTS: [&str; 5] = [
+        "i have met them at close of day",
+        "coming with vivid faces",
+        "from counter or desk among grey",
+        "eighteenth-century houses",
+        "i have passed with a nod of the head",
+    ];
+
+    fn encrypt_all() -> Vec<RawBytes> {
+        let mut rng = StdRng::seed_from_u64(19);
+        let mut keystream = vec![0u8; 64];
+        rng.fill_bytes(&mut keystream);
+        let keystream = RawBytes { bytes: keystream };
+
+        PLAINTEXTS
+            .iter()
+            .map(|p| repeating_key_xor(&RawBytes::from_str(p), &keystream))
+            .collect()
+    }
+
+    #[test]
+    fn test_crib_drag() {
+        let pad = ManyTimePad::new(&encrypt_all());
+
+        let matches = pad.drag(b"i have ");
+        assert!(!matches.is_empty());
+        assert!(matches.windows(2).all(|w| w[0].score >= w[1].score));
+
+        let best = &matches[0];
+        assert_eq!(best.position, 0);
+        assert!(best.source == 0 || best.source == 4);
+        assert!(best
+            .fragments
+            .iter()
+            .any(|(i, f)| *i == 1 && f.to_str() == "coming "));
+    }
+
+    #[test]
+    fn test_accept_and_plaintexts() {
+        let mut pad = ManyTimePad::new(&encrypt_all());
+
+        let matches = pad.drag(b"eighteenth-century");
+        let m = matches.iter().find(|m| m.source == 3).unwrap();
+        assert_eq!(m.position, 0);
+        pad.accept(m);
+
+        let ans = pad.plaintexts(b'_');
+        assert_eq!(ans[1].to_str(), "coming with vivid _____");
+        assert!(pad.keystream()[18].is_none());
+
+        pad.set_plaintext(1, 18, b"faces");
+        assert_eq!(pad.plaintexts(b'_')[1].to_str(), PLAINTEXTS[1]);
+    }
+
+    #[test]
+    fn test_preview_and_forget() {
+        let mut pad = ManyTimePad::new(&encrypt_all());
+        pad.set_plaintext(4, 0, b"i have passed with a nod");
+        assert_eq!(pad.known(), 24);
+
+        let before = pad.plaintexts(b'_');
+        let preview = pad.preview(4, 24, b" of the head", b'_');
+        assert_eq!(pad.plaintexts(b'_'), before);
+        assert_eq!(preview[0].to_str(), "i have met them at close of day");
+        assert_eq!(preview[4].to_str(), PLAINTEXTS[4]);
+
+        // A wrong guess reads badly elsewhere and can be backed out.
+        pad.set_plaintext(4, 7, b"gone   ");
+        assert_ne!(pad.plaintexts(b'_')[0].to_str(), &PLAINTEXTS[0][..24]);
+        pad.forget(7..14);
+        assert_eq!(
+            pad.plaintexts(b'_')[0].to_str(),
+            "i have _______m at close_______"
+        );
+
+        let keystream: Vec<u8> = pad.keystream()[..7].iter().map(|k| k.unwrap()).collect();
+        let mut other = ManyTimePad::new(&encrypt_all());
+        other.set_keystream(0, &keystream);
+        assert_eq!(other.plaintexts(b'_')[2].to_str()[..7], PLAINTEXTS[2][..7]);
+    }
+
+    #[test]
+    fn test_break_fixed_nonce() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let (_, data) = load_input("./inputs/set7.txt").unwrap();
+        let lyrics = ecb_decrypt_with_padding(&aes, &data[0], &Pkcs7).unwrap();
+        let lines: Vec<RawBytes> = lyrics
+            .to_str()
+            .lines()
+            .filter(|l| l.len() >= 20)
+            .map(RawBytes::from_str)
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(20);
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        let aes = Aes::new(&key).unwrap();
+        let cts: Vec<RawBytes> = lines
+            .iter()
+            .map(|l| RawBytes {
+                bytes: ctr_apply(&aes, Nonce::fixed(0), &l.bytes).unwrap(),
+            })
+            .collect();
+
+        let ans = break_fixed_nonce(&cts).unwrap();
+        let keystream = ctr_apply(&aes, Nonce::fixed(0), &[0; 20]).unwrap();
+        assert_eq!(ans.keystream.bytes, keystream);
+        for (pt, line) in ans.plaintexts.iter().zip(lines.iter()) {
+            assert_eq!(pt.bytes, line.bytes[..20]);
+        }
+
+        assert!(break_fixed_nonce(&[]).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+#![allow(dead_code)]
+use crate::merkle_damgard::*;
+use crate::modes::*;
+use crate::oracles::*;
+use rand::prelude::*;
+
+pub const BLOCK_SIZE: usize = 64;
+pub const DIGEST_SIZE: usize = 16;
+
+pub(crate) const INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+/// The MD4 padding for a `message_len`-byte message: as SHA-1's, but with
+/// the bit length little-endian.
+pub fn md_padding(message_len: u64) -> Vec<u8> {
+    crate::merkle_damgard::md_padding(message_len, BLOCK_SIZE, Endian::Little)
+}
+
+pub(crate) fn f(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (!x & z)
+}
+
+pub(crate) fn g(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | (x & z) | (y & z)
+}
+
+fn h(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+/// The compression function (RFC 1320): three rounds of sixteen steps,
+/// each round with its own boolean function, word order and additive
+/// constant.
+pub fn compress(state: &mut [u32; 4], block: &[u8]) {
+    let mut x = [0u32; 16];
+    for (i, chunk) in block.chunks(4).enumerate() {
+        x[i] = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+
+    let mut v = *state;
+    // Step i updates word -i mod 4 of the state from the other three.
+    let step = |v: &mut [u32; 4], i: usize, func: fn(u32, u32, u32) -> u32, k: usize, s: u32, c| {
+        let (a, b, cc, d) = (
+            (4 - i % 4) % 4,
+            (5 - i % 4) % 4,
+            (6 - i % 4) % 4,
+            (7 - i % 4) % 4,
+        );
+        v[a] = v[a]
+            .wrapping_add(func(v[b], v[cc], v[d]))
+            .wrapping_add(x[k])
+            .wrapping_add(c)
+            .rotate_left(s);
+    };
+
+    const S1: [u32; 4] = [3, 7, 11, 19];
+    for i in 0..16 {
+        step(&mut v, i, f, i, S1[i % 4], 0);
+    }
+    const S2: [u32; 4] = [3, 5, 9, 13];
+    for i in 0..16 {
+        step(&mut v, i, g, (i % 4) * 4 + i / 4, S2[i % 4], 0x5a827999);
+    }
+    const S3: [u32; 4] = [3, 9, 11, 15];
+    const ORDER3: [usize; 16] = [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15];
+    for i in 0..16 {
+        step(&mut v, i, h, ORDER3[i], S3[i % 4], 0x6ed9eba1);
+    }
+
+    for (s, x) in state.iter_mut().zip(v) {
+        *s = s.wrapping_add(x);
+    }
+}
+
+/// The MD4 compression function, for `MerkleDamgard`.
+#[derive(Debug, Clone)]
+pub struct Md4Compression;
+
+impl Compression for Md4Compression {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const OUTPUT_SIZE: usize = DIGEST_SIZE;
+    const ENDIANNESS: Endian = Endian::Little;
+    type State = [u32; 4];
+
+    fn initial_state() -> [u32; 4] {
+        INITIAL_STATE
+    }
+
+    fn compress(state: &mut [u32; 4], block: &[u8]) {
+        compress(state, block);
+    }
+
+    fn state_to_bytes(state: &[u32; 4]) -> Vec<u8> {
+        words_to_bytes(state, Endian::Little)
+    }
+
+    fn state_from_bytes(bytes: &[u8]) -> [u32; 4] {
+        let mut state = [0u32; 4];
+        state.copy_from_slice(&words_from_bytes(&bytes[..DIGEST_SIZE], Endian::Little));
+        state
+    }
+}
+
+/// Incremental MD4 (RFC 1320). Broken as a hash since 1995, fully so since
+/// 2004, and still here because of challenges 30 and 55.
+pub type Md4 = MerkleDamgard<Md4Compression>;
+
+pub fn md4(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut h = Md4::new();
+    h.update(data);
+    let mut out = [0u8; DIGEST_SIZE];
+    out.copy_from_slice(&h.finalize());
+    out
+}
+
+/// The challenge 30 MAC: MD4 over the key followed by the message.
+pub fn md4_mac(key: &[u8], message: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut h = Md4::new();
+    h.update(key);
+    h.update(message);
+    let mut out = [0u8; DIGEST_SIZE];
+    out.copy_from_slice(&h.finalize());
+    out
+}
+
+pub fn md4_mac_verify(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    let expected = md4_mac(key, message);
+    mac.len() == expected.len()
+        && expected
+            .iter()
+            .zip(mac)
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// The challenge 30 server: `Sha1MacServer` with MD4 in place of SHA-1.
+pub struct Md4MacServer {
+    key: Vec<u8>,
+}
+
+impl Md4MacServer {
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let mut key = vec![0u8; rng.random_range(8..=32)];
+        rng.fill_bytes(&mut key);
+        Self { key }
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; DIGEST_SIZE] {
+        md4_mac(&self.key, message)
+    }
+
+    /// For checking guesses in tests.
+    pub fn key_len(&self) -> usize {
+        self.key.len()
+    }
+}
+
+impl MacVerifier for &Md4MacServer {
+    fn verify(&mut self, message: &[u8], mac: &[u8]) -> bool {
+        md4_mac_verify(&self.key, message, mac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_bytes::*;
+
+    #[test]
+    fn test_rfc_1320_vectors() {
+        for (input, expected) in [
+            ("", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+            ("a", "bde52cb31de33e46245e05fbdbd6fb24"),
+            ("abc", "a448017aaf21d8525fc10ae87aa6729d"),
+            ("message digest", "d9130a8164549fe818874806e1c7014b"),
+            (
+                "abcdefghijklmnopqrstuvwxyz",
+                "d79e1c308aa5bbcdeea8ed63df412da9",
+            ),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "043f8582f241db351ce627e153e7f0e4",
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "e33b4ddc9c38f2199c3e7b164fcc0536",
+            ),
+        ] {
+            let digest = md4(input.as_bytes());
+            assert_eq!(
+                RawBytes {
+                    bytes: digest.to_vec()
+                }
+                .to_hex(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_incremental_matches_one_shot() {
+        let data: Vec<u8> = (0..300u32).map(|i| (i * 13) as u8).collect();
+        for split in [0, 1, 55, 56, 64, 65, 299] {
+            let mut h = Md4::new();
+            h.update(&data[..split]);
+            h.update(&data[split..]);
+            assert_eq!(h.finalize(), md4(&data), "split at {}", split);
+        }
+        assert_eq!(md4_mac(b"YELLOW ", b"SUBMARINE"), md4(b"YELLOW SUBMARINE"));
+    }
+}
@@ -0,0 +1,325 @@
+#![allow(dead_code)]
+use crate::md4::*;
+use rand::prelude::*;
+
+/// A condition Wang et al. put on one bit of a step's output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+    Zero(u32),
+    One(u32),
+    /// The bit equals the same bit of the output `n` steps before.
+    Equal(u32, usize),
+}
+
+use Condition::*;
+
+impl Condition {
+    pub fn bit(self) -> u32 {
+        match self {
+            Zero(b) | One(b) | Equal(b, _) => b,
+        }
+    }
+
+    /// `v`, as output `i` of `outputs`, with the condition made to hold.
+    fn enforce(self, v: u32, outputs: &[u32], i: usize) -> u32 {
+        match self {
+            Zero(b) => v & !(1 << b),
+            One(b) => v | 1 << b,
+            Equal(b, n) => v & !(1 << b) | outputs[i - n] & 1 << b,
+        }
+    }
+
+    fn holds(self, outputs: &[u32], i: usize) -> bool {
+        self.enforce(outputs[i], outputs, i) == outputs[i]
+    }
+}
+
+/// The sufficient conditions on round 1's outputs a1, d1, c1, b1, a2, ...,
+/// b4 (Wang et al., table 6), with bits counted from zero.
+pub const ROUND1_CONDITIONS: [&[Condition]; 16] = [
+    &[Equal(6, 1)],
+    &[Zero(6), Equal(7, 1), Equal(10, 1)],
+    &[One(6), One(7), Zero(10), Equal(25, 1)],
+    &[One(6), Zero(7), Zero(10), Zero(25)],
+    &[One(7), One(10), Zero(25), Equal(13, 1)],
+    &[
+        Zero(13),
+        Equal(18, 1),
+        Equal(19, 1),
+        Equal(20, 1),
+        Equal(21, 1),
+        One(25),
+    ],
+    &[
+        Equal(12, 1),
+        Zero(13),
+        Equal(14, 1),
+        Zero(18),
+        Zero(19),
+        One(20),
+        Zero(21),
+    ],
+    &[
+        One(12),
+        One(13),
+        Zero(14),
+        Equal(16, 1),
+        Zero(18),
+        Zero(19),
+        Zero(20),
+        Zero(21),
+    ],
+    &[
+        One(12),
+        One(13),
+        One(14),
+        Zero(16),
+        Zero(18),
+        Zero(19),
+        Zero(20),
+        Equal(22, 1),
+        One(21),
+        Equal(25, 1),
+    ],
+    &[
+        One(12),
+        One(13),
+        One(14),
+        Zero(16),
+        Zero(19),
+        One(20),
+        One(21),
+        Zero(22),
+        One(25),
+        Equal(29, 1),
+    ],
+    &[
+        One(16),
+        Zero(19),
+        Zero(20),
+        Zero(21),
+        Zero(22),
+        Zero(25),
+        One(29),
+        Equal(31, 1),
+    ],
+    &[
+        Zero(19),
+        One(20),
+        One(21),
+        Equal(22, 1),
+        One(25),
+        Zero(29),
+        Zero(31),
+    ],
+    &[
+        Zero(22),
+        Zero(25),
+        Equal(26, 1),
+        Equal(28, 1),
+        One(29),
+        Zero(31),
+    ],
+    &[Zero(22), Zero(25), One(26), One(28), Zero(29), One(31)],
+    &[Equal(18, 1), One(22), One(25), Zero(26), Zero(28), Zero(29)],
+    &[Zero(18), One(25), One(26), One(28), Zero(29)],
+];
+
+/// The conditions on a5 and d5, the first two outputs of round 2, which
+/// message modification can still fix without undoing round 1.
+pub const A5_CONDITIONS: [Condition; 5] = [Equal(18, 2), One(25), Zero(26), One(28), One(31)];
+pub const D5_CONDITIONS: [Condition; 5] = [
+    Equal(18, 1),
+    Equal(25, 2),
+    Equal(26, 2),
+    Equal(28, 2),
+    Equal(31, 2),
+];
+
+const S1: [u32; 4] = [3, 7, 11, 19];
+const S2: [u32; 4] = [3, 5, 9, 13];
+const K2: u32 = 0x5a827999;
+
+// Step outputs are kept in one sequence after the initial a0, d0, c0, b0,
+// so step j of round 1 is output j + 4 and works from the four before it.
+
+fn initial_outputs() -> Vec<u32> {
+    let [a, b, c, d] = INITIAL_STATE;
+    vec![a, d, c, b]
+}
+
+fn round1(q: &[u32], j: usize, m: u32) -> u32 {
+    q[j].wrapping_add(f(q[j + 3], q[j + 2], q[j + 1]))
+        .wrapping_add(m)
+        .rotate_left(S1[j % 4])
+}
+
+/// The message word that makes round 1's step `j` output `v`.
+fn round1_word(q: &[u32], j: usize, v: u32) -> u32 {
+    v.rotate_right(S1[j % 4])
+        .wrapping_sub(q[j])
+        .wrapping_sub(f(q[j + 3], q[j + 2], q[j + 1]))
+}
+
+fn round2(q: &[u32], j: usize, m: u32) -> u32 {
+    q[j + 16]
+        .wrapping_add(g(q[j + 19], q[j + 18], q[j + 17]))
+        .wrapping_add(m)
+        .wrapping_add(K2)
+        .rotate_left(S2[j % 4])
+}
+
+/// The initial values, round 1's outputs, and a5 and d5.
+pub fn step_outputs(m: &[u32; 16]) -> Vec<u32> {
+    let mut q = initial_outputs();
+    for (j, word) in m.iter().enumerate() {
+        q.push(round1(&q, j, *word));
+    }
+    q.push(round2(&q, 0, m[0]));
+    q.push(round2(&q, 1, m[4]));
+    q
+}
+
+/// Whether every round-1 condition holds for `m`.
+pub fn round1_holds(m: &[u32; 16]) -> bool {
+    let q = step_outputs(m);
+    ROUND1_CONDITIONS
+        .iter()
+        .enumerate()
+        .all(|(j, conds)| conds.iter().all(|c| c.holds(&q, j + 4)))
+}
+
+/// Whether the a5 and d5 conditions hold as well.
+pub fn round2_start_holds(m: &[u32; 16]) -> bool {
+    let q = step_outputs(m);
+    A5_CONDITIONS.iter().all(|c| c.holds(&q, 20)) && D5_CONDITIONS.iter().all(|c| c.holds(&q, 21))
+}
+
+/// Message modification: rewrites `m` so that round 1's conditions all
+/// hold, and most likely a5's and d5's too.
+///
+/// Round 1 uses each word once, in order, so each output can be set to
+/// what the conditions want and the word solved for. Round 2 starts over
+/// with m0 and m4, so a wrong bit of a5 is fixed by flipping the bit of
+/// a1 that m0 carries into it, then solving m0 to m4 again so that d1 to
+/// a2 don't move; d5 the same way through m4, a2 and m4 to m8. A carry
+/// can still spoil an earlier fix, so a5 and d5 aren't guaranteed.
+pub fn massage(m: &mut [u32; 16]) {
+    let mut q = initial_outputs();
+    for (j, conds) in ROUND1_CONDITIONS.iter().enumerate() {
+        let v = conds
+            .iter()
+            .fold(round1(&q, j, m[j]), |v, c| c.enforce(v, &q, j + 4));
+        m[j] = round1_word(&q, j, v);
+        q.push(v);
+    }
+
+    // A bad bit of the round-2 output of `step` is flipped through the
+    // round-1 output that word `word` made, `lag` bits lower: m0 reaches
+    // a1 and a5 under the same rotation, while m4 is rotated two bits
+    // less into a2 than into d5.
+    let mut fix = |q: &mut Vec<u32>, step: usize, word: usize, conds: &[Condition], lag: u32| {
+        for c in conds {
+            let v = round2(q, step, m[word]);
+            if c.enforce(v, q, step + 20) == v {
+                continue;
+            }
+            q[word + 4] ^= 1 << ((c.bit() + 32 - lag) % 32);
+            for j in word..word + 5 {
+                m[j] = round1_word(q, j, q[j + 4]);
+            }
+        }
+        let v = round2(q, step, m[word]);
+        q.push(v);
+    };
+    fix(&mut q, 0, 0, &A5_CONDITIONS, 0);
+    fix(&mut q, 1, 4, &D5_CONDITIONS, 2);
+}
+
+/// Wang's differential: the other message of the pair, which collides
+/// with `m` when the conditions all hold.
+pub fn differential(m: &[u32; 16]) -> [u32; 16] {
+    let mut other = *m;
+    other[1] = other[1].wrapping_add(1 << 31);
+    other[2] = other[2].wrapping_add(1 << 31).wrapping_sub(1 << 28);
+    other[12] = other[12].wrapping_sub(1 << 16);
+    other
+}
+
+fn words_to_block(m: &[u32; 16]) -> Vec<u8> {
+    m.iter().flat_map(|w| w.to_le_bytes().to_vec()).collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Md4Collision {
+    pub a: Vec<u8>,
+    pub b: Vec<u8>,
+    /// Messages massaged before one collided.
+    pub tries: u64,
+}
+
+/// Challenge 55: random blocks, massaged, until one and its differential
+/// partner compress to the same state. The later round-2 and round-3
+/// conditions are left to chance, a few hundred thousand tries.
+pub fn find_md4_collision<R: Rng>(rng: &mut R) -> Md4Collision {
+    let mut tries = 0;
+    loop {
+        tries += 1;
+        let mut m = [0u32; 16];
+        for w in m.iter_mut() {
+            *w = rng.random();
+        }
+        massage(&mut m);
+        let other = differential(&m);
+
+        let (a, b) = (words_to_block(&m), words_to_block(&other));
+        let (mut sa, mut sb) = (INITIAL_STATE, INITIAL_STATE);
+        compress(&mut sa, &a);
+        compress(&mut sb, &b);
+        if sa == sb {
+            return Md4Collision { a, b, tries };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_massage_enforces_round_1() {
+        let mut rng = StdRng::seed_from_u64(55);
+        let mut round2 = 0;
+        for _ in 0..1000 {
+            let mut m = [0u32; 16];
+            for w in m.iter_mut() {
+                *w = rng.random();
+            }
+            massage(&mut m);
+            assert!(round1_holds(&m));
+            if round2_start_holds(&m) {
+                round2 += 1;
+            }
+        }
+        // Left alone, a5 and d5 would hold one time in 2^10.
+        assert!(round2 > 500, "{}", round2);
+    }
+
+    #[test]
+    fn test_differential() {
+        let m = [0u32; 16];
+        let other = differential(&m);
+        assert_eq!(
+            (other[1], other[2], other[12]),
+            (0x80000000, 0x70000000, 0xffff0000)
+        );
+        assert_eq!(other.iter().filter(|w| **w != 0).count(), 3);
+    }
+
+    #[test]
+    fn test_find_md4_collision() {
+        let found = find_md4_collision(&mut StdRng::seed_from_u64(55));
+        assert_ne!(found.a, found.b);
+        assert_eq!(md4(&found.a), md4(&found.b));
+    }
+}
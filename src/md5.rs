@@ -0,0 +1,155 @@
+#![allow(dead_code)]
+use crate::merkle_damgard::*;
+use crate::modes::*;
+
+pub const BLOCK_SIZE: usize = 64;
+pub const DIGEST_SIZE: usize = 16;
+
+const INITIAL_STATE: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+/// `floor(abs(sin(i + 1)) * 2^32)`.
+const K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/// The rotation for each step; each round cycles through four.
+const S: [[u32; 4]; 4] = [
+    [7, 12, 17, 22],
+    [5, 9, 14, 20],
+    [4, 11, 16, 23],
+    [6, 10, 15, 21],
+];
+
+/// The MD5 padding for a `message_len`-byte message, the same as MD4's.
+pub fn md_padding(message_len: u64) -> Vec<u8> {
+    crate::merkle_damgard::md_padding(message_len, BLOCK_SIZE, Endian::Little)
+}
+
+/// The compression function (RFC 1321): four rounds of sixteen steps, each
+/// step with its own constant, and each adding in the result of the step
+/// before, which MD4 didn't.
+pub fn compress(state: &mut [u32; 4], block: &[u8]) {
+    let mut m = [0u32; 16];
+    for (i, chunk) in block.chunks(4).enumerate() {
+        m[i] = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+
+    let [mut a, mut b, mut c, mut d] = *state;
+    for i in 0..64 {
+        let (f, g) = match i / 16 {
+            0 => ((b & c) | (!b & d), i),
+            1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+            2 => (b ^ c ^ d, (3 * i + 5) % 16),
+            _ => (c ^ (b | !d), (7 * i) % 16),
+        };
+        let t = a
+            .wrapping_add(f)
+            .wrapping_add(K[i])
+            .wrapping_add(m[g])
+            .rotate_left(S[i / 16][i % 4]);
+        a = d;
+        d = c;
+        c = b;
+        b = b.wrapping_add(t);
+    }
+
+    for (s, x) in state.iter_mut().zip([a, b, c, d]) {
+        *s = s.wrapping_add(x);
+    }
+}
+
+/// The MD5 compression function, for `MerkleDamgard`.
+#[derive(Debug, Clone)]
+pub struct Md5Compression;
+
+impl Compression for Md5Compression {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const OUTPUT_SIZE: usize = DIGEST_SIZE;
+    const ENDIANNESS: Endian = Endian::Little;
+    type State = [u32; 4];
+
+    fn initial_state() -> [u32; 4] {
+        INITIAL_STATE
+    }
+
+    fn compress(state: &mut [u32; 4], block: &[u8]) {
+        compress(state, block);
+    }
+
+    fn state_to_bytes(state: &[u32; 4]) -> Vec<u8> {
+        words_to_bytes(state, Endian::Little)
+    }
+
+    fn state_from_bytes(bytes: &[u8]) -> [u32; 4] {
+        let mut state = [0u32; 4];
+        state.copy_from_slice(&words_from_bytes(&bytes[..DIGEST_SIZE], Endian::Little));
+        state
+    }
+}
+
+/// Incremental MD5 (RFC 1321). Collisions take seconds on a laptop, but it
+/// is still everywhere, and it extends just like SHA-1 and MD4.
+pub type Md5 = MerkleDamgard<Md5Compression>;
+
+pub fn md5(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut h = Md5::new();
+    h.update(data);
+    let mut out = [0u8; DIGEST_SIZE];
+    out.copy_from_slice(&h.finalize());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_bytes::*;
+
+    #[test]
+    fn test_rfc_1321_vectors() {
+        for (input, expected) in [
+            ("", "d41d8cd98f00b204e9800998ecf8427e"),
+            ("a", "0cc175b9c0f1b6a831c399e269772661"),
+            ("abc", "900150983cd24fb0d6963f7d28e17f72"),
+            ("message digest", "f96b697d7cb7938d525a2f31aaf161d0"),
+            (
+                "abcdefghijklmnopqrstuvwxyz",
+                "c3fcd3d76192e4007dfb496cca67e13b",
+            ),
+            (
+                "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                "d174ab98d277d9f5a5611c2c9f419d9f",
+            ),
+            (
+                "12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                "57edf4a22be3c955ac49da2e2107b67a",
+            ),
+        ] {
+            let digest = md5(input.as_bytes());
+            assert_eq!(
+                RawBytes {
+                    bytes: digest.to_vec()
+                }
+                .to_hex(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_incremental_matches_one_shot() {
+        let data: Vec<u8> = (0..300u32).map(|i| (i * 17) as u8).collect();
+        for split in [0, 1, 55, 56, 64, 65, 299] {
+            let mut h = Md5::new();
+            h.update(&data[..split]);
+            h.update(&data[split..]);
+            assert_eq!(h.finalize(), md5(&data), "split at {}", split);
+        }
+    }
+}
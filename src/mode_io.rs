@@ -0,0 +1,404 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::iv::*;
+use crate::keystream::*;
+use crate::modes::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+use std::io::{self, Read, Write};
+
+/// How much ciphertext `DecryptReader` asks its source for at a time.
+const CHUNK: usize = 4096;
+
+/// How consecutive blocks are tied together, with whatever state that
+/// carries from one call to the next.
+#[derive(Debug, Clone)]
+enum Chaining {
+    Ecb,
+    /// The previous ciphertext block, the IV to begin with.
+    Cbc(Vec<u8>),
+}
+
+impl Chaining {
+    fn cbc<C: BlockCipher + ?Sized>(cipher: &C, iv: &Iv) -> anyhow::Result<Self> {
+        check_iv(cipher, iv)?;
+        Ok(Chaining::Cbc(iv.as_bytes().to_vec()))
+    }
+
+    fn encrypt<C: BlockCipher + ?Sized>(&mut self, cipher: &C, data: &mut [u8]) {
+        match self {
+            Chaining::Ecb => cipher.encrypt_blocks(data),
+            Chaining::Cbc(prev) => {
+                for block in data.chunks_mut(cipher.block_size()) {
+                    xor_into(block, prev);
+                    cipher.encrypt_block(block);
+                    prev.copy_from_slice(block);
+                }
+            }
+        }
+    }
+
+    fn decrypt<C: BlockCipher + ?Sized>(&mut self, cipher: &C, data: &mut [u8]) {
+        match self {
+            Chaining::Ecb => cipher.decrypt_blocks(data),
+            Chaining::Cbc(prev) => {
+                for block in data.chunks_mut(cipher.block_size()) {
+                    let ct = block.to_vec();
+                    cipher.decrypt_block(block);
+                    xor_into(block, prev);
+                    *prev = ct;
+                }
+            }
+        }
+    }
+}
+
+/// Encrypts everything written to it with ECB or CBC and passes the
+/// ciphertext on to `inner` a whole block at a time. At most one partial
+/// block is held back, so input of any size goes through in constant
+/// memory. Call `finish` to pad and write the last block; dropping the
+/// writer loses it.
+pub struct EncryptWriter<'a, C: BlockCipher + ?Sized, P: Padding, W: Write> {
+    cipher: &'a C,
+    chaining: Chaining,
+    padding: P,
+    inner: W,
+    pending: Vec<u8>,
+}
+
+impl<'a, C: BlockCipher + ?Sized, P: Padding, W: Write> EncryptWriter<'a, C, P, W> {
+    pub fn ecb(cipher: &'a C, padding: P, inner: W) -> Self {
+        Self {
+            cipher,
+            chaining: Chaining::Ecb,
+            padding,
+            inner,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn cbc(cipher: &'a C, iv: &Iv, padding: P, inner: W) -> anyhow::Result<Self> {
+        Ok(Self {
+            cipher,
+            chaining: Chaining::cbc(cipher, iv)?,
+            padding,
+            inner,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Pads and writes the last block, and hands back `inner`.
+    pub fn finish(mut self) -> io::Result<W> {
+        let tail = RawBytes {
+            bytes: std::mem::take(&mut self.pending),
+        };
+        let mut last = self.padding.pad(&tail, self.cipher.block_size()).bytes;
+        self.chaining.encrypt(self.cipher, &mut last);
+        self.inner.write_all(&last)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<'a, C: BlockCipher + ?Sized, P: Padding, W: Write> Write for EncryptWriter<'a, C, P, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let bs = self.cipher.block_size();
+        let whole = self.pending.len() / bs * bs;
+        if whole > 0 {
+            let mut blocks: Vec<u8> = self.pending.drain(..whole).collect();
+            self.chaining.encrypt(self.cipher, &mut blocks);
+            self.inner.write_all(&blocks)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads ECB or CBC ciphertext from `inner` and yields the plaintext, a
+/// chunk at a time. The last block is held back until `inner` runs out so
+/// its padding can be stripped. Bad padding or a ragged length only shows
+/// up then, as an `InvalidData` error after the rest has been read.
+pub struct DecryptReader<'a, C: BlockCipher + ?Sized, P: Padding, R: Read> {
+    cipher: &'a C,
+    chaining: Chaining,
+    padding: P,
+    inner: R,
+    held: Vec<u8>,
+    ready: Vec<u8>,
+    ready_pos: usize,
+    done: bool,
+}
+
+impl<'a, C: BlockCipher + ?Sized, P: Padding, R: Read> DecryptReader<'a, C, P, R> {
+    pub fn ecb(cipher: &'a C, padding: P, inner: R) -> Self {
+        Self::with_chaining(cipher, Chaining::Ecb, padding, inner)
+    }
+
+    pub fn cbc(cipher: &'a C, iv: &Iv, padding: P, inner: R) -> anyhow::Result<Self> {
+        Ok(Self::with_chaining(
+            cipher,
+            Chaining::cbc(cipher, iv)?,
+            padding,
+            inner,
+        ))
+    }
+
+    fn with_chaining(cipher: &'a C, chaining: Chaining, padding: P, inner: R) -> Self {
+        Self {
+            cipher,
+            chaining,
+            padding,
+            inner,
+            held: Vec::new(),
+            ready: Vec::new(),
+            ready_pos: 0,
+            done: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads another chunk and decrypts all but the last whole block of
+    /// what is held, or finishes off at the end of `inner`.
+    fn fill(&mut self) -> io::Result<()> {
+        let bs = self.cipher.block_size();
+        let mut chunk = [0u8; CHUNK];
+        let n = self.inner.read(&mut chunk)?;
+        if n == 0 {
+            self.done = true;
+            return self.finish();
+        }
+
+        self.held.extend_from_slice(&chunk[..n]);
+        if self.held.len() > bs {
+            let take = (self.held.len() - 1) / bs * bs;
+            let mut blocks: Vec<u8> = self.held.drain(..take).collect();
+            self.chaining.decrypt(self.cipher, &mut blocks);
+            self.ready = blocks;
+            self.ready_pos = 0;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let bs = self.cipher.block_size();
+        if self.held.len() != bs {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "ciphertext ends {} bytes into a {} byte block",
+                    self.held.len(),
+                    bs
+                ),
+            ));
+        }
+
+        let mut last = std::mem::take(&mut self.held);
+        self.chaining.decrypt(self.cipher, &mut last);
+        let unpadded = self
+            .padding
+            .unpad(&RawBytes { bytes: last }, bs)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.ready = unpadded.bytes;
+        self.ready_pos = 0;
+        Ok(())
+    }
+}
+
+impl<'a, C: BlockCipher + ?Sized, P: Padding, R: Read> Read for DecryptReader<'a, C, P, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.ready_pos == self.ready.len() && !self.done {
+            self.fill()?;
+        }
+        let n = buf.len().min(self.ready.len() - self.ready_pos);
+        buf[..n].copy_from_slice(&self.ready[self.ready_pos..self.ready_pos + n]);
+        self.ready_pos += n;
+        Ok(n)
+    }
+}
+
+/// Reads from `inner`, XORing with `keystream` from its current position.
+/// With a `CtrKeystream` this is CTR in either direction.
+pub struct KeystreamReader<K: KeystreamGenerator, R: Read> {
+    keystream: K,
+    inner: R,
+}
+
+impl<K: KeystreamGenerator, R: Read> KeystreamReader<K, R> {
+    pub fn new(keystream: K, inner: R) -> Self {
+        Self { keystream, inner }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<K: KeystreamGenerator, R: Read> Read for KeystreamReader<K, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.keystream.apply(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Writes to `inner`, XORing with `keystream` from its current position.
+pub struct KeystreamWriter<K: KeystreamGenerator, W: Write> {
+    keystream: K,
+    inner: W,
+    scratch: Vec<u8>,
+}
+
+impl<K: KeystreamGenerator, W: Write> KeystreamWriter<K, W> {
+    pub fn new(keystream: K, inner: W) -> Self {
+        Self {
+            keystream,
+            inner,
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<K: KeystreamGenerator, W: Write> Write for KeystreamWriter<K, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.scratch.clear();
+        self.scratch.extend_from_slice(buf);
+
+        // Only move past the keystream that `inner` actually took.
+        let start = self.keystream.position();
+        self.keystream.apply(&mut self.scratch);
+        let n = self.inner.write(&self.scratch)?;
+        if n < buf.len() {
+            self.keystream.seek(start + n as u64);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    /// Hands out at most `max` bytes per call.
+    struct Dribble<'a> {
+        data: &'a [u8],
+        max: usize,
+    }
+
+    impl<'a> Read for Dribble<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.max).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; len];
+        StdRng::seed_from_u64(595).fill_bytes(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn test_cbc_streams_match_one_shot() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let iv = Iv::from_bytes(b"0123456789abcdef");
+        for len in [0, 1, 15, 16, 17, 4095, 4096, 4097, 20000] {
+            let pt = random_bytes(len);
+            let expected =
+                cbc_encrypt_with_padding(&aes, &iv, &RawBytes { bytes: pt.clone() }, &Pkcs7)
+                    .unwrap()
+                    .bytes;
+
+            let mut writer = EncryptWriter::cbc(&aes, &iv, Pkcs7, Vec::new()).unwrap();
+            for piece in pt.chunks(7) {
+                writer.write_all(piece).unwrap();
+            }
+            let ct = writer.finish().unwrap();
+            assert_eq!(ct, expected, "{} bytes", len);
+
+            let mut back = Vec::new();
+            let mut reader =
+                DecryptReader::cbc(&aes, &iv, Pkcs7, Dribble { data: &ct, max: 5 }).unwrap();
+            reader.read_to_end(&mut back).unwrap();
+            assert_eq!(back, pt, "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn test_ecb_streams_round_trip() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let pt = random_bytes(10000);
+
+        let mut writer = EncryptWriter::ecb(&aes, AnsiX923, Vec::new());
+        io::copy(&mut &pt[..], &mut writer).unwrap();
+        let ct = writer.finish().unwrap();
+        let expected = ecb_encrypt_with_padding(&aes, &RawBytes { bytes: pt.clone() }, &AnsiX923)
+            .unwrap()
+            .bytes;
+        assert_eq!(ct, expected);
+
+        let mut back = Vec::new();
+        DecryptReader::ecb(&aes, AnsiX923, &ct[..])
+            .read_to_end(&mut back)
+            .unwrap();
+        assert_eq!(back, pt);
+    }
+
+    #[test]
+    fn test_decrypt_reader_rejects_bad_endings() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let iv = Iv::zero(16);
+        let ct = cbc_encrypt(&aes, &iv, &[0x41; 32]).unwrap();
+
+        for bad in [&ct[..], &ct[..31], &[][..]] {
+            let mut reader = DecryptReader::cbc(&aes, &iv, Pkcs7, bad).unwrap();
+            let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+        assert!(DecryptReader::cbc(&aes, &Iv::zero(8), Pkcs7, &ct[..]).is_err());
+    }
+
+    #[test]
+    fn test_ctr_streams_match_one_shot() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let pt = random_bytes(9000);
+        let expected = ctr_apply(&aes, Nonce::fixed(7), &pt).unwrap();
+
+        let ks = CtrKeystream::new(&aes, Nonce::fixed(7)).unwrap();
+        let mut writer = KeystreamWriter::new(ks, Vec::new());
+        for piece in pt.chunks(1000) {
+            writer.write_all(piece).unwrap();
+        }
+        assert_eq!(writer.into_inner(), expected);
+
+        let ks = CtrKeystream::new(&aes, Nonce::fixed(7)).unwrap();
+        let mut back = Vec::new();
+        KeystreamReader::new(
+            ks,
+            Dribble {
+                data: &expected,
+                max: 13,
+            },
+        )
+        .read_to_end(&mut back)
+        .unwrap();
+        assert_eq!(back, pt);
+    }
+}
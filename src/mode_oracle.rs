@@ -0,0 +1,106 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::iv::*;
+use crate::modes::*;
+use crate::oracles::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+use rand::prelude::*;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockMode {
+    Ecb,
+    Cbc,
+}
+
+/// The challenge 11 oracle: 5 to 10 random bytes on each side of `input`,
+/// PKCS#7, then AES-128 under a fresh random key in ECB or CBC (random IV),
+/// picked by coin flip. Returns the mode too so callers can check a guess.
+/// Everything random comes from `rng`, so a seeded one replays exactly.
+pub fn encryption_oracle<R: Rng>(input: &[u8], rng: &mut R) -> (RawBytes, BlockMode) {
+    let random_bytes = |rng: &mut R, len: usize| {
+        let mut bytes = vec![0u8; len];
+        rng.fill_bytes(&mut bytes);
+        bytes
+    };
+
+    let key = random_bytes(rng, 16);
+    let prefix_len = rng.random_range(5..=10);
+    let prefix = random_bytes(rng, prefix_len);
+    let suffix_len = rng.random_range(5..=10);
+    let suffix = random_bytes(rng, suffix_len);
+    let data = RawBytes {
+        bytes: [&prefix[..], input, &suffix[..]].concat(),
+    };
+
+    let aes = Aes::new(&key).expect("16 byte key");
+    if rng.random::<bool>() {
+        let ct = ecb_encrypt_with_padding(&aes, &data, &Pkcs7).expect("padded");
+        (ct, BlockMode::Ecb)
+    } else {
+        let iv = Iv::random(rng, 16);
+        let ct = cbc_encrypt_with_padding(&aes, &iv, &data, &Pkcs7).expect("padded");
+        (ct, BlockMode::Cbc)
+    }
+}
+
+/// ECB if any two blocks of `ct` are equal. Only meaningful when the
+/// plaintext had repeated blocks, which is what `detect_oracle_mode` sends.
+pub fn classify_block_mode(ct: &[u8], block_size: usize) -> BlockMode {
+    let mut seen = HashSet::new();
+    if ct.chunks(block_size).all(|b| seen.insert(b)) {
+        BlockMode::Cbc
+    } else {
+        BlockMode::Ecb
+    }
+}
+
+/// Asks `oracle` to encrypt enough identical bytes that, whatever prefix of
+/// up to a block it adds, two whole plaintext blocks are equal; under ECB
+/// their ciphertexts are too.
+pub fn detect_oracle_mode<O: EncryptionOracle>(mut oracle: O) -> BlockMode {
+    let ct = oracle.encrypt(&[b'A'; 3 * 16]);
+    classify_block_mode(&ct, 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_oracle_mode() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut counts = [0; 2];
+
+        for _ in 0..200 {
+            let mut mode = None;
+            let guess = detect_oracle_mode(|input: &[u8]| {
+                let (ct, m) = encryption_oracle(input, &mut rng);
+                mode = Some(m);
+                ct.bytes
+            });
+            assert_eq!(Some(guess), mode);
+            counts[(guess == BlockMode::Cbc) as usize] += 1;
+        }
+        // Both modes come up.
+        assert!(counts.iter().all(|c| *c > 50), "{:?}", counts);
+    }
+
+    #[test]
+    fn test_encryption_oracle_is_reproducible() {
+        let run = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..5)
+                .map(|_| encryption_oracle(b"YELLOW SUBMARINE", &mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(1), run(1));
+        assert_ne!(run(1), run(2));
+
+        for (ct, _) in run(3) {
+            // 16 bytes of input plus 10 to 20 random ones, padded.
+            assert!(ct.bytes.len() == 32 || ct.bytes.len() == 48);
+        }
+    }
+}
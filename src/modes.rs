@@ -0,0 +1,851 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::iv::*;
+use crate::keystream::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+
+fn check_blocks<C: BlockCipher + ?Sized>(cipher: &C, data: &[u8]) -> anyhow::Result<()> {
+    if !data.len().is_multiple_of(cipher.block_size()) {
+        anyhow::bail!(
+            "{} bytes is not a whole number of {} byte blocks",
+            data.len(),
+            cipher.block_size()
+        );
+    }
+    Ok(())
+}
+
+/// ECB without padding; `data` must be a whole number of blocks.
+pub fn ecb_encrypt<C: BlockCipher + ?Sized>(cipher: &C, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    check_blocks(cipher, data)?;
+    let mut out = data.to_vec();
+    cipher.encrypt_blocks(&mut out);
+    Ok(out)
+}
+
+pub fn ecb_decrypt<C: BlockCipher + ?Sized>(cipher: &C, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    check_blocks(cipher, data)?;
+    let mut out = data.to_vec();
+    cipher.decrypt_blocks(&mut out);
+    Ok(out)
+}
+
+pub fn ecb_encrypt_with_padding<C: BlockCipher + ?Sized, P: Padding>(
+    cipher: &C,
+    rb: &RawBytes,
+    padding: &P,
+) -> anyhow::Result<RawBytes> {
+    let padded = padding.pad(rb, cipher.block_size());
+    Ok(RawBytes {
+        bytes: ecb_encrypt(cipher, &padded.bytes)?,
+    })
+}
+
+pub fn ecb_decrypt_with_padding<C: BlockCipher + ?Sized, P: Padding>(
+    cipher: &C,
+    rb: &RawBytes,
+    padding: &P,
+) -> anyhow::Result<RawBytes> {
+    let decrypted = RawBytes {
+        bytes: ecb_decrypt(cipher, &rb.bytes)?,
+    };
+    Ok(padding.unpad(&decrypted, cipher.block_size())?)
+}
+
+pub fn check_iv<C: BlockCipher + ?Sized>(cipher: &C, iv: &Iv) -> anyhow::Result<()> {
+    if iv.len() != cipher.block_size() {
+        anyhow::bail!(
+            "IV is {} bytes, the block size is {}",
+            iv.len(),
+            cipher.block_size()
+        );
+    }
+    Ok(())
+}
+
+pub fn xor_into(dst: &mut [u8], src: &[u8]) {
+    for (d, s) in dst.iter_mut().zip(src.iter()) {
+        *d ^= s;
+    }
+}
+
+/// CBC without padding: each plaintext block is XORed with the previous
+/// ciphertext block (the IV for the first) before it is encrypted.
+pub fn cbc_encrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &Iv,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    check_iv(cipher, iv)?;
+    check_blocks(cipher, data)?;
+
+    let bs = cipher.block_size();
+    let mut out = data.to_vec();
+    let mut prev = iv.as_bytes().to_vec();
+    for block in out.chunks_mut(bs) {
+        xor_into(block, &prev);
+        cipher.encrypt_block(block);
+        prev.copy_from_slice(block);
+    }
+    Ok(out)
+}
+
+pub fn cbc_decrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &Iv,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    check_iv(cipher, iv)?;
+    check_blocks(cipher, data)?;
+
+    // Every block decrypts independently, so do them in bulk and XOR the
+    // chain in afterwards.
+    let bs = cipher.block_size();
+    let mut out = data.to_vec();
+    cipher.decrypt_blocks(&mut out);
+    for (i, block) in out.chunks_mut(bs).enumerate() {
+        let prev = if i == 0 {
+            iv.as_bytes()
+        } else {
+            &data[(i - 1) * bs..i * bs]
+        };
+        xor_into(block, prev);
+    }
+    Ok(out)
+}
+
+pub fn cbc_encrypt_with_padding<C: BlockCipher + ?Sized, P: Padding>(
+    cipher: &C,
+    iv: &Iv,
+    rb: &RawBytes,
+    padding: &P,
+) -> anyhow::Result<RawBytes> {
+    let padded = padding.pad(rb, cipher.block_size());
+    Ok(RawBytes {
+        bytes: cbc_encrypt(cipher, iv, &padded.bytes)?,
+    })
+}
+
+pub fn cbc_decrypt_with_padding<C: BlockCipher + ?Sized, P: Padding>(
+    cipher: &C,
+    iv: &Iv,
+    rb: &RawBytes,
+    padding: &P,
+) -> anyhow::Result<RawBytes> {
+    let decrypted = RawBytes {
+        bytes: cbc_decrypt(cipher, iv, &rb.bytes)?,
+    };
+    Ok(padding.unpad(&decrypted, cipher.block_size())?)
+}
+
+/// CFB with full-block feedback: each block of keystream is the encryption
+/// of the previous ciphertext block (the IV for the first). `data` can be
+/// any length; a short last block uses as much keystream as it needs.
+pub fn cfb_encrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &Iv,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    check_iv(cipher, iv)?;
+    let mut out = data.to_vec();
+    let mut feedback = iv.as_bytes().to_vec();
+    for block in out.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block(&mut feedback);
+        xor_into(block, &feedback);
+        feedback[..block.len()].copy_from_slice(block);
+    }
+    Ok(out)
+}
+
+pub fn cfb_decrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &Iv,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    check_iv(cipher, iv)?;
+    let mut out = data.to_vec();
+    let mut feedback = iv.as_bytes().to_vec();
+    for block in out.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block(&mut feedback);
+        let ct = block.to_vec();
+        xor_into(block, &feedback);
+        feedback[..ct.len()].copy_from_slice(&ct);
+    }
+    Ok(out)
+}
+
+/// Shifts a ciphertext byte into the 8-bit CFB register.
+fn shift_in(register: &mut [u8], ct: u8) {
+    register.rotate_left(1);
+    let last = register.len() - 1;
+    register[last] = ct;
+}
+
+/// CFB with 8-bit feedback: one block cipher call per byte.
+pub fn cfb8_encrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &Iv,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    check_iv(cipher, iv)?;
+    let mut register = iv.as_bytes().to_vec();
+    let mut scratch = vec![0u8; iv.len()];
+    let mut out = Vec::with_capacity(data.len());
+    for p in data {
+        scratch.copy_from_slice(&register);
+        cipher.encrypt_block(&mut scratch);
+        let c = p ^ scratch[0];
+        shift_in(&mut register, c);
+        out.push(c);
+    }
+    Ok(out)
+}
+
+pub fn cfb8_decrypt<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &Iv,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    check_iv(cipher, iv)?;
+    let mut register = iv.as_bytes().to_vec();
+    let mut scratch = vec![0u8; iv.len()];
+    let mut out = Vec::with_capacity(data.len());
+    for c in data {
+        scratch.copy_from_slice(&register);
+        cipher.encrypt_block(&mut scratch);
+        out.push(c ^ scratch[0]);
+        shift_in(&mut register, *c);
+    }
+    Ok(out)
+}
+
+/// OFB: the keystream is the IV encrypted over and over, independent of
+/// the data, so encryption and decryption are the same.
+pub fn ofb_apply<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    iv: &Iv,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    check_iv(cipher, iv)?;
+    let mut out = data.to_vec();
+    let mut state = iv.as_bytes().to_vec();
+    for block in out.chunks_mut(cipher.block_size()) {
+        cipher.encrypt_block(&mut state);
+        xor_into(block, &state);
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// How a CTR counter block is put together: the nonce, then a counter of
+/// `counter_len` bytes in the given byte order. Only the counter changes
+/// from block to block, and it wraps within its own bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CtrLayout {
+    pub nonce_len: usize,
+    pub counter_len: usize,
+    pub counter_endian: Endian,
+}
+
+impl CtrLayout {
+    /// Challenge 18: 64-bit little-endian nonce, 64-bit little-endian
+    /// counter.
+    pub const CRYPTOPALS: CtrLayout = CtrLayout {
+        nonce_len: 8,
+        counter_len: 8,
+        counter_endian: Endian::Little,
+    };
+
+    /// SP 800-38A's usual choice, and GCM's: 96-bit nonce, 32-bit
+    /// big-endian counter.
+    pub const NIST: CtrLayout = CtrLayout {
+        nonce_len: 12,
+        counter_len: 4,
+        counter_endian: Endian::Big,
+    };
+
+    fn check(&self, block_size: usize, nonce: &Nonce) -> anyhow::Result<()> {
+        if self.nonce_len + self.counter_len != block_size {
+            anyhow::bail!(
+                "{} byte nonce and {} byte counter don't fill a {} byte block",
+                self.nonce_len,
+                self.counter_len,
+                block_size
+            );
+        }
+        if self.counter_len == 0 || self.counter_len > 8 {
+            anyhow::bail!("counters are 1 to 8 bytes, not {}", self.counter_len);
+        }
+        if nonce.as_bytes().len() != self.nonce_len {
+            anyhow::bail!(
+                "nonce is {} bytes, the layout wants {}",
+                nonce.as_bytes().len(),
+                self.nonce_len
+            );
+        }
+        Ok(())
+    }
+
+    /// Writes the counter block for `counter`, truncated to the counter's
+    /// width, into `block`.
+    fn counter_block(&self, nonce: &[u8], counter: u64, block: &mut [u8]) {
+        block[..self.nonce_len].copy_from_slice(nonce);
+        let out = &mut block[self.nonce_len..];
+        match self.counter_endian {
+            Endian::Little => out.copy_from_slice(&counter.to_le_bytes()[..self.counter_len]),
+            Endian::Big => out.copy_from_slice(&counter.to_be_bytes()[8 - self.counter_len..]),
+        }
+    }
+}
+
+/// The CTR keystream of a 16-byte block cipher: block `i` is the
+/// encryption of the counter block for `initial_counter + i` (see
+/// `CtrLayout`). Any byte can be reached directly with `seek`, so it can be
+/// read from the middle.
+pub struct CtrKeystream<'a, C: BlockCipher + ?Sized> {
+    cipher: &'a C,
+    nonce: Nonce,
+    layout: CtrLayout,
+    initial_counter: u64,
+    pos: u64,
+    block: [u8; 16],
+    block_index: Option<u64>,
+}
+
+impl<'a, C: BlockCipher + ?Sized> CtrKeystream<'a, C> {
+    /// Challenge 18's layout, counting from zero.
+    pub fn new(cipher: &'a C, nonce: Nonce) -> anyhow::Result<Self> {
+        Self::with_layout(cipher, nonce, CtrLayout::CRYPTOPALS, 0)
+    }
+
+    pub fn with_layout(
+        cipher: &'a C,
+        nonce: Nonce,
+        layout: CtrLayout,
+        initial_counter: u64,
+    ) -> anyhow::Result<Self> {
+        if cipher.block_size() != 16 {
+            anyhow::bail!(
+                "CTR needs a 16 byte block, the block size is {}",
+                cipher.block_size()
+            );
+        }
+        layout.check(16, &nonce)?;
+        Ok(Self {
+            cipher,
+            nonce,
+            layout,
+            initial_counter,
+            pos: 0,
+            block: [0; 16],
+            block_index: None,
+        })
+    }
+
+    fn load_block(&mut self, index: u64) {
+        if self.block_index != Some(index) {
+            let counter = self.initial_counter.wrapping_add(index);
+            self.layout
+                .counter_block(self.nonce.as_bytes(), counter, &mut self.block);
+            self.cipher.encrypt_block(&mut self.block);
+            self.block_index = Some(index);
+        }
+    }
+}
+
+impl<'a, C: BlockCipher + ?Sized> KeystreamGenerator for CtrKeystream<'a, C> {
+    fn fill(&mut self, buf: &mut [u8]) {
+        let mut done = 0;
+        while done < buf.len() {
+            self.load_block(self.pos / 16);
+            let offset = (self.pos % 16) as usize;
+            let n = (16 - offset).min(buf.len() - done);
+            buf[done..done + n].copy_from_slice(&self.block[offset..offset + n]);
+            done += n;
+            self.pos += n as u64;
+        }
+    }
+
+    fn seek(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    fn position(&self) -> u64 {
+        self.pos
+    }
+}
+
+/// CTR from the start of the keystream. Encryption and decryption are the
+/// same XOR, and `data` can be any length.
+pub fn ctr_apply<C: BlockCipher + ?Sized>(
+    cipher: &C,
+    nonce: Nonce,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let mut out = data.to_vec();
+    CtrKeystream::new(cipher, nonce)?.apply(&mut out);
+    Ok(out)
+}
+
+/// `stream_edit` with the CTR keystream.
+pub fn ctr_edit<C: BlockCipher + ?Sized>(
+    ciphertext: &[u8],
+    cipher: &C,
+    nonce: Nonce,
+    offset: usize,
+    new_plaintext: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    stream_edit(
+        &mut CtrKeystream::new(cipher, nonce)?,
+        ciphertext,
+        offset,
+        new_plaintext,
+    )
+}
+
+/// Multiplies an XTS tweak by the primitive element of GF(2^128), with the
+/// bytes taken little-endian as IEEE 1619 does.
+fn xts_double(tweak: &mut [u8]) {
+    let mut carry = 0u8;
+    for b in tweak.iter_mut() {
+        let next = *b >> 7;
+        *b = (*b << 1) | carry;
+        carry = next;
+    }
+    if carry != 0 {
+        tweak[0] ^= 0x87;
+    }
+}
+
+/// XTS processes one data unit (a disk sector, say) at a time, under two
+/// keys: `tweak_cipher` turns the sector number into the first block's
+/// tweak, and `data_cipher` encrypts each block between two XORs with its
+/// tweak. A short final block borrows the tail of the one before
+/// (ciphertext stealing), so the ciphertext is exactly as long as `data`,
+/// which must be at least a block.
+struct Xts<'a, C: BlockCipher + ?Sized> {
+    data_cipher: &'a C,
+    tweaks: Vec<Vec<u8>>,
+}
+
+impl<'a, C: BlockCipher + ?Sized> Xts<'a, C> {
+    fn new(data_cipher: &'a C, tweak_cipher: &C, sector: u128, len: usize) -> anyhow::Result<Self> {
+        if data_cipher.block_size() != 16 || tweak_cipher.block_size() != 16 {
+            anyhow::bail!("XTS needs a 16 byte block cipher");
+        }
+        if len < 16 {
+            anyhow::bail!("XTS needs at least one whole block, got {} bytes", len);
+        }
+
+        let mut tweak = sector.to_le_bytes().to_vec();
+        tweak_cipher.encrypt_block(&mut tweak);
+        let mut tweaks = Vec::with_capacity(len.div_ceil(16));
+        for _ in 0..len.div_ceil(16) {
+            tweaks.push(tweak.clone());
+            xts_double(&mut tweak);
+        }
+        Ok(Self {
+            data_cipher,
+            tweaks,
+        })
+    }
+
+    fn block(&self, block: &mut [u8], j: usize, encrypt: bool) {
+        xor_into(block, &self.tweaks[j]);
+        if encrypt {
+            self.data_cipher.encrypt_block(block);
+        } else {
+            self.data_cipher.decrypt_block(block);
+        }
+        xor_into(block, &self.tweaks[j]);
+    }
+
+    fn apply(&self, data: &[u8], encrypt: bool) -> Vec<u8> {
+        let mut out = data.to_vec();
+        let full = data.len() / 16;
+        let r = data.len() % 16;
+        let plain = if r == 0 { full } else { full - 1 };
+        for (j, block) in out[..plain * 16].chunks_mut(16).enumerate() {
+            self.block(block, j, encrypt);
+        }
+        if r == 0 {
+            return out;
+        }
+
+        // Ciphertext stealing: the last whole block goes first, under tweak
+        // m when encrypting but m + 1 when decrypting, and the tail of the
+        // result fills out the short block.
+        let m = full - 1;
+        let (first, second) = if encrypt { (m, m + 1) } else { (m + 1, m) };
+        let mut last = data[m * 16..(m + 1) * 16].to_vec();
+        self.block(&mut last, first, encrypt);
+        let mut stolen = data[(m + 1) * 16..].to_vec();
+        stolen.extend_from_slice(&last[r..]);
+        self.block(&mut stolen, second, encrypt);
+        out[m * 16..(m + 1) * 16].copy_from_slice(&stolen);
+        out[(m + 1) * 16..].copy_from_slice(&last[..r]);
+        out
+    }
+}
+
+/// XTS encryption of one data unit, numbered `sector`; see `Xts`.
+pub fn xts_encrypt<C: BlockCipher + ?Sized>(
+    data_cipher: &C,
+    tweak_cipher: &C,
+    sector: u128,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    Ok(Xts::new(data_cipher, tweak_cipher, sector, data.len())?.apply(data, true))
+}
+
+pub fn xts_decrypt<C: BlockCipher + ?Sized>(
+    data_cipher: &C,
+    tweak_cipher: &C,
+    sector: u128,
+    data: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    Ok(Xts::new(data_cipher, tweak_cipher, sector, data.len())?.apply(data, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::*;
+
+    /// A (very) toy 8-byte block cipher: add the key bytewise, then rotate.
+    struct Toy([u8; 8]);
+
+    impl BlockCipher for Toy {
+        fn block_size(&self) -> usize {
+            8
+        }
+
+        fn encrypt_block(&self, block: &mut [u8]) {
+            for (b, k) in block.iter_mut().zip(self.0.iter()) {
+                *b = b.wrapping_add(*k);
+            }
+            block.rotate_left(3);
+        }
+
+        fn decrypt_block(&self, block: &mut [u8]) {
+            block.rotate_right(3);
+            for (b, k) in block.iter_mut().zip(self.0.iter()) {
+                *b = b.wrapping_sub(*k);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ecb_generic_over_cipher() {
+        let toy = Toy(*b"toy key!");
+        let rb = RawBytes::from_str("twenty-one bytes long");
+
+        let ct = ecb_encrypt_with_padding(&toy, &rb, &Pkcs7).unwrap();
+        assert_eq!(ct.bytes.len(), 24);
+        // Identical plaintext blocks stay identical under ECB.
+        let same = ecb_encrypt(&toy, b"abcdefghabcdefgh").unwrap();
+        assert_eq!(same[..8], same[8..]);
+
+        assert_eq!(ecb_decrypt_with_padding(&toy, &ct, &Pkcs7).unwrap(), rb);
+        assert!(ecb_encrypt(&toy, b"short").is_err());
+    }
+
+    #[test]
+    fn test_ecb_aes() {
+        let key = RawBytes::from_str("YELLOW SUBMARINE");
+        let aes = Aes::new(&key.bytes).unwrap();
+        let rb = RawBytes::from_str("I'm back and I'm ringin' the bell");
+
+        let ct = ecb_encrypt_with_padding(&aes, &rb, &Pkcs7).unwrap();
+        let boxed: Box<dyn BlockCipher> = Box::new(aes);
+        assert_eq!(ecb_decrypt_with_padding(&*boxed, &ct, &Pkcs7).unwrap(), rb);
+    }
+
+    #[test]
+    fn test_cbc_sp800_38a() {
+        // NIST SP 800-38A F.2.1, first two blocks.
+        let key = RawBytes::from_hex("2b7e151628aed2a6abf7158809cf4f3c");
+        let iv = Iv::from_bytes(&RawBytes::from_hex("000102030405060708090a0b0c0d0e0f").bytes);
+        let pt =
+            RawBytes::from_hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51");
+        let aes = Aes::new(&key.bytes).unwrap();
+
+        let ct = cbc_encrypt(&aes, &iv, &pt.bytes).unwrap();
+        assert_eq!(
+            RawBytes { bytes: ct.clone() }.to_hex(),
+            "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2"
+        );
+        assert_eq!(cbc_decrypt(&aes, &iv, &ct).unwrap(), pt.bytes);
+
+        assert!(cbc_encrypt(&aes, &Iv::zero(8), &pt.bytes).is_err());
+        assert!(cbc_decrypt(&aes, &iv, &ct[..20]).is_err());
+    }
+
+    #[test]
+    fn test_cbc_challenge_10() {
+        let (_, data) = load_input("./inputs/set10.txt").unwrap();
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let iv = Iv::zero(16);
+
+        let ans = cbc_decrypt_with_padding(&aes, &iv, &data[0], &Pkcs7).unwrap();
+        let text = ans.to_str();
+        assert_eq!(
+            text.lines().next().unwrap(),
+            "I'm back and I'm ringin' the bell "
+        );
+        assert!(text.ends_with("Play that funky music \n"));
+
+        let ct = cbc_encrypt_with_padding(&aes, &iv, &ans, &Pkcs7).unwrap();
+        assert_eq!(ct, data[0]);
+    }
+
+    #[test]
+    fn test_cbc_chains_blocks() {
+        let toy = Toy(*b"toy key!");
+        let iv = Iv::from_bytes(&[7u8; 8]);
+        let ct = cbc_encrypt(&toy, &iv, b"abcdefghabcdefgh").unwrap();
+        assert_ne!(ct[..8], ct[8..]);
+
+        // A flipped ciphertext bit garbles its own block and flips the same
+        // bit of the next plaintext block.
+        let mut tampered = ct.clone();
+        tampered[2] ^= 0x01;
+        let pt = cbc_decrypt(&toy, &iv, &tampered).unwrap();
+        assert_ne!(&pt[..8], b"abcdefgh");
+        assert_eq!(&pt[8..], b"abcdefgh".map(|b| b ^ u8::from(b == b'c')));
+    }
+
+    #[test]
+    fn test_ctr_challenge_18() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let ct = RawBytes::from_base64(
+            "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==",
+        );
+
+        let pt = ctr_apply(&aes, Nonce::fixed(0), &ct.bytes).unwrap();
+        assert_eq!(
+            RawBytes { bytes: pt.clone() }.to_str(),
+            "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby "
+        );
+        assert_eq!(ctr_apply(&aes, Nonce::fixed(0), &pt).unwrap(), ct.bytes);
+        assert_ne!(ctr_apply(&aes, Nonce::fixed(1), &ct.bytes).unwrap(), pt);
+    }
+
+    #[test]
+    fn test_ctr_keystream_seek() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let mut ks = CtrKeystream::new(&aes, Nonce::fixed(0)).unwrap();
+        let all = ks.take_bytes(48);
+        assert_eq!(
+            RawBytes { bytes: all.clone() }.to_hex(),
+            "76d1cb4bafa246e2e3af035d6c13c372d2ec6cdc986d12decfda1f93afee7318\
+             2da08ecb117b374bc3dab726b2fc84cd"
+        );
+        assert_eq!(ks.position(), 48);
+
+        // Reading in odd pieces from odd places gives the same bytes.
+        ks.seek(13);
+        assert_eq!(ks.take_bytes(7), all[13..20]);
+        assert_eq!(ks.take_bytes(21), all[20..41]);
+        ks.seek(3);
+        assert_eq!(ks.take_bytes(1), all[3..4]);
+
+        assert!(CtrKeystream::new(&Toy(*b"toy key!"), Nonce::fixed(0)).is_err());
+    }
+
+    #[test]
+    fn test_ctr_nist_layout() {
+        // SP 800-38A F.5.1 CTR-AES128.Encrypt: the initial counter block
+        // f0f1...feff split into a 96-bit nonce and 32-bit counter.
+        let (aes, _, pt) = sp800_38a();
+        let nonce = Nonce::from_bytes(&RawBytes::from_hex("f0f1f2f3f4f5f6f7f8f9fafb").bytes);
+        let mut ks = CtrKeystream::with_layout(&aes, nonce, CtrLayout::NIST, 0xfcfdfeff).unwrap();
+        let mut ct = pt.clone();
+        ks.apply(&mut ct);
+        assert_eq!(
+            RawBytes { bytes: ct }.to_hex(),
+            "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+             5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee"
+        );
+    }
+
+    #[test]
+    fn test_ctr_counter_wraps_in_place() {
+        // A 32-bit counter wraps to zero without carrying into the nonce.
+        let (aes, _, _) = sp800_38a();
+        let nonce = Nonce::from_bytes(&(0..12).collect::<Vec<u8>>());
+        let mut ks = CtrKeystream::with_layout(&aes, nonce, CtrLayout::NIST, 0xffffffff).unwrap();
+        assert_eq!(
+            RawBytes {
+                bytes: ks.take_bytes(32)
+            }
+            .to_hex(),
+            "bdb7c0ef49717942fc68eeb17692fcf494193f8116eb745cfe7465d70c756236"
+        );
+    }
+
+    #[test]
+    fn test_ctr_layout_checks() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let with = |nonce, layout| CtrKeystream::with_layout(&aes, nonce, layout, 0).is_ok();
+        assert!(with(Nonce::fixed(0), CtrLayout::CRYPTOPALS));
+        assert!(!with(Nonce::fixed(0), CtrLayout::NIST));
+        assert!(with(Nonce::from_bytes(&[0; 12]), CtrLayout::NIST));
+
+        let odd = CtrLayout {
+            nonce_len: 4,
+            counter_len: 12,
+            counter_endian: Endian::Big,
+        };
+        assert!(!with(Nonce::from_bytes(&[0; 4]), odd));
+        let short = CtrLayout {
+            nonce_len: 8,
+            counter_len: 4,
+            counter_endian: Endian::Little,
+        };
+        assert!(!with(Nonce::fixed(0), short));
+    }
+
+    #[test]
+    fn test_ctr_edit() {
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let pt = b"the quick brown fox jumps over the lazy dog";
+        let ct = ctr_apply(&aes, Nonce::fixed(7), pt).unwrap();
+
+        let edited = ctr_edit(&ct, &aes, Nonce::fixed(7), 10, b"green").unwrap();
+        assert_eq!(edited[..10], ct[..10]);
+        assert_eq!(edited[15..], ct[15..]);
+        assert_eq!(
+            ctr_apply(&aes, Nonce::fixed(7), &edited).unwrap(),
+            b"the quick green fox jumps over the lazy dog"
+        );
+
+        // Running off the end extends the ciphertext.
+        let longer = ctr_edit(&ct, &aes, Nonce::fixed(7), 40, b"dogs!").unwrap();
+        assert_eq!(
+            ctr_apply(&aes, Nonce::fixed(7), &longer).unwrap(),
+            b"the quick brown fox jumps over the lazy dogs!"
+        );
+        assert!(ctr_edit(&ct, &aes, Nonce::fixed(7), ct.len() + 1, b"x").is_err());
+    }
+
+    const SP800_38A_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const SP800_38A_IV: &str = "000102030405060708090a0b0c0d0e0f";
+    const SP800_38A_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                                       30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    fn sp800_38a() -> (Aes, Iv, Vec<u8>) {
+        let key = RawBytes::from_hex(SP800_38A_KEY);
+        let iv = RawBytes::from_hex(SP800_38A_IV);
+        let pt = RawBytes::from_hex(SP800_38A_PLAINTEXT);
+        (
+            Aes::new(&key.bytes).unwrap(),
+            Iv::from_bytes(&iv.bytes),
+            pt.bytes,
+        )
+    }
+
+    #[test]
+    fn test_cfb128_sp800_38a() {
+        // F.3.13 CFB128-AES128.Encrypt.
+        let (aes, iv, pt) = sp800_38a();
+        let ct = cfb_encrypt(&aes, &iv, &pt).unwrap();
+        assert_eq!(
+            RawBytes { bytes: ct.clone() }.to_hex(),
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+             26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6"
+        );
+        assert_eq!(cfb_decrypt(&aes, &iv, &ct).unwrap(), pt);
+
+        // Stopping part way through a block is fine.
+        assert_eq!(cfb_encrypt(&aes, &iv, &pt[..21]).unwrap(), ct[..21]);
+        assert_eq!(cfb_decrypt(&aes, &iv, &ct[..21]).unwrap(), pt[..21]);
+    }
+
+    #[test]
+    fn test_cfb8_sp800_38a() {
+        // F.3.7 CFB8-AES128.Encrypt.
+        let (aes, iv, pt) = sp800_38a();
+        let ct = cfb8_encrypt(&aes, &iv, &pt[..18]).unwrap();
+        assert_eq!(
+            RawBytes { bytes: ct.clone() }.to_hex(),
+            "3b79424c9c0dd436bace9e0ed4586a4f32b9"
+        );
+        assert_eq!(cfb8_decrypt(&aes, &iv, &ct).unwrap(), pt[..18]);
+        assert!(cfb8_encrypt(&aes, &Iv::zero(8), &pt).is_err());
+    }
+
+    #[test]
+    fn test_ofb_sp800_38a() {
+        // F.4.1 OFB-AES128.Encrypt.
+        let (aes, iv, pt) = sp800_38a();
+        let ct = ofb_apply(&aes, &iv, &pt).unwrap();
+        assert_eq!(
+            RawBytes { bytes: ct.clone() }.to_hex(),
+            "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+             9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e"
+        );
+        assert_eq!(ofb_apply(&aes, &iv, &ct).unwrap(), pt);
+        assert_eq!(ofb_apply(&aes, &iv, &pt[..7]).unwrap(), ct[..7]);
+    }
+
+    fn xts_keys(k1: &str, k2: &str) -> (Aes, Aes) {
+        (
+            Aes::new(&RawBytes::from_hex(k1).bytes).unwrap(),
+            Aes::new(&RawBytes::from_hex(k2).bytes).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_xts_ieee_1619() {
+        // Vectors 1 and 2: whole blocks.
+        let (k1, k2) = xts_keys(&"00".repeat(16), &"00".repeat(16));
+        let ct = xts_encrypt(&k1, &k2, 0, &[0u8; 32]).unwrap();
+        assert_eq!(
+            RawBytes { bytes: ct }.to_hex(),
+            "917cf69ebd68b2ec9b9fe9a3eadda692cd43d2f59598ed858c02c2652fbf922e"
+        );
+
+        let (k1, k2) = xts_keys(&"11".repeat(16), &"22".repeat(16));
+        let ct = xts_encrypt(&k1, &k2, 0x3333333333, &[0x44u8; 32]).unwrap();
+        assert_eq!(
+            RawBytes { bytes: ct.clone() }.to_hex(),
+            "c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0"
+        );
+        assert_eq!(
+            xts_decrypt(&k1, &k2, 0x3333333333, &ct).unwrap(),
+            [0x44u8; 32]
+        );
+    }
+
+    #[test]
+    fn test_xts_ciphertext_stealing() {
+        // Vectors 15 and 18: 17 and 20 byte data units.
+        let (k1, k2) = xts_keys(
+            "fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0",
+            "bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0",
+        );
+        for (len, expected) in [
+            (17, "6c1625db4671522d3d7599601de7ca09ed"),
+            (20, "9d84c813f719aa2c7be3f66171c7c5c2edbf9dac"),
+        ] {
+            let pt: Vec<u8> = (0..len as u8).collect();
+            let ct = xts_encrypt(&k1, &k2, 0x123456789a, &pt).unwrap();
+            assert_eq!(RawBytes { bytes: ct.clone() }.to_hex(), expected);
+            assert_eq!(xts_decrypt(&k1, &k2, 0x123456789a, &ct).unwrap(), pt);
+        }
+
+        // Longer odd lengths round trip, and each sector encrypts differently.
+        let pt: Vec<u8> = (0..100).collect();
+        let ct = xts_encrypt(&k1, &k2, 7, &pt).unwrap();
+        assert_eq!(xts_decrypt(&k1, &k2, 7, &ct).unwrap(), pt);
+        assert_ne!(xts_encrypt(&k1, &k2, 8, &pt).unwrap(), ct);
+        assert!(xts_encrypt(&k1, &k2, 0, &pt[..15]).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+#![allow(dead_code)]
+use crate::keystream::*;
+
+const N: usize = 624;
+const M: usize = 397;
+const MATRIX_A: u32 = 0x9908_b0df;
+const UPPER_MASK: u32 = 0x8000_0000;
+const LOWER_MASK: u32 = 0x7fff_ffff;
+
+/// The output transformation applied to each state word.
+pub fn temper(mut y: u32) -> u32 {
+    y ^= y >> 11;
+    y ^= (y << 7) & 0x9d2c_5680;
+    y ^= (y << 15) & 0xefc6_0000;
+    y ^= y >> 18;
+    y
+}
+
+/// The 32-bit Mersenne Twister, as in Matsumoto and Nishimura's reference
+/// `mt19937ar.c` seeded with `init_genrand`.
+#[derive(Debug, Clone)]
+pub struct Mt19937 {
+    state: [u32; N],
+    index: usize,
+}
+
+impl Mt19937 {
+    pub fn new(seed: u32) -> Self {
+        let mut state = [0u32; N];
+        state[0] = seed;
+        for i in 1..N {
+            let prev = state[i - 1];
+            state[i] = 1_812_433_253u32
+                .wrapping_mul(prev ^ (prev >> 30))
+                .wrapping_add(i as u32);
+        }
+        Self { state, index: N }
+    }
+
+    /// A generator that continues from an already twisted state, with the
+    /// next output coming from `state[0]`.
+    pub fn from_state(state: [u32; N]) -> Self {
+        Self { state, index: 0 }
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N {
+            let y = (self.state[i] & UPPER_MASK) | (self.state[(i + 1) % N] & LOWER_MASK);
+            let mag = if y & 1 == 1 { MATRIX_A } else { 0 };
+            self.state[i] = self.state[(i + M) % N] ^ (y >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        if self.index >= N {
+            self.twist();
+        }
+        let y = self.state[self.index];
+        self.index += 1;
+        temper(y)
+    }
+}
+
+/// The challenge 24 stream cipher's keystream: MT19937 seeded with a
+/// 16-bit key, four bytes (little-endian) per output.
+#[derive(Debug, Clone)]
+pub struct MtKeystream {
+    seed: u16,
+    mt: Mt19937,
+    word: [u8; 4],
+    pos: u64,
+}
+
+impl MtKeystream {
+    pub fn new(seed: u16) -> Self {
+        Self {
+            seed,
+            mt: Mt19937::new(seed as u32),
+            word: [0; 4],
+            pos: 0,
+        }
+    }
+}
+
+impl KeystreamGenerator for MtKeystream {
+    fn fill(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            if self.pos.is_multiple_of(4) {
+                self.word = self.mt.next_u32().to_le_bytes();
+            }
+            *b = self.word[(self.pos % 4) as usize];
+            self.pos += 1;
+        }
+    }
+
+    fn seek(&mut self, pos: u64) {
+        if pos < self.pos {
+            *self = Self::new(self.seed);
+        }
+        let mut skip = [0u8; 256];
+        while self.pos < pos {
+            let n = (pos - self.pos).min(skip.len() as u64) as usize;
+            self.fill(&mut skip[..n]);
+        }
+    }
+
+    fn position(&self) -> u64 {
+        self.pos
+    }
+}
+
+/// XORs `data` with the `MtKeystream` for `seed`. Encryption and
+/// decryption are the same.
+pub fn mt_stream_apply(seed: u16, data: &[u8]) -> Vec<u8> {
+    stream_apply(&mut MtKeystream::new(seed), data)
+}
+
+const N64: usize = 312;
+const M64: usize = 156;
+const MATRIX_A64: u64 = 0xb502_6f5a_a966_19e9;
+const UPPER_MASK64: u64 = 0xffff_ffff_8000_0000;
+const LOWER_MASK64: u64 = 0x7fff_ffff;
+
+/// The 64-bit Mersenne Twister (`mt19937-64.c`), seeded with
+/// `init_genrand64`.
+#[derive(Debug, Clone)]
+pub struct Mt19937_64 {
+    state: [u64; N64],
+    index: usize,
+}
+
+impl Mt19937_64 {
+    pub fn new(seed: u64) -> Self {
+        let mut state = [0u64; N64];
+        state[0] = seed;
+        for i in 1..N64 {
+            let prev = state[i - 1];
+            state[i] = 6_364_136_223_846_793_005u64
+                .wrapping_mul(prev ^ (prev >> 62))
+                .wrapping_add(i as u64);
+        }
+        Self { state, index: N64 }
+    }
+
+    fn twist(&mut self) {
+        for i in 0..N64 {
+            let x = (self.state[i] & UPPER_MASK64) | (self.state[(i + 1) % N64] & LOWER_MASK64);
+            let mag = if x & 1 == 1 { MATRIX_A64 } else { 0 };
+            self.state[i] = self.state[(i + M64) % N64] ^ (x >> 1) ^ mag;
+        }
+        self.index = 0;
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        if self.index >= N64 {
+            self.twist();
+        }
+        let mut x = self.state[self.index];
+        self.index += 1;
+
+        x ^= (x >> 29) & 0x5555_5555_5555_5555;
+        x ^= (x << 17) & 0x71d6_7fff_eda6_0000;
+        x ^= (x << 37) & 0xfff7_eee0_0000_0000;
+        x ^= x >> 43;
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mt19937_reference_outputs() {
+        // The default seed used by mt19937ar.c's init_genrand and C++'s
+        // std::mt19937.
+        let mut mt = Mt19937::new(5489);
+        let first: Vec<u32> = (0..5).map(|_| mt.next_u32()).collect();
+        assert_eq!(
+            first,
+            vec![3499211612, 581869302, 3890346734, 3586334585, 545404204]
+        );
+
+        // The standard requires the 10000th output of a default-constructed
+        // std::mt19937 to be 4123659995.
+        let mut mt = Mt19937::new(5489);
+        let ans = (0..10000).map(|_| mt.next_u32()).last();
+        assert_eq!(ans, Some(4123659995));
+    }
+
+    #[test]
+    fn test_mt19937_64_reference_outputs() {
+        let mut mt = Mt19937_64::new(5489);
+        assert_eq!(mt.next_u64(), 14514284786278117030);
+
+        // Likewise 9981545732273789042 for std::mt19937_64.
+        let mut mt = Mt19937_64::new(5489);
+        let ans = (0..10000).map(|_| mt.next_u64()).last();
+        assert_eq!(ans, Some(9981545732273789042));
+    }
+
+    #[test]
+    fn test_mt_stream_apply() {
+        let pt = b"the keystream is only sixteen bits of key";
+        let ct = mt_stream_apply(0xbeef, pt);
+        assert_ne!(&ct[..], &pt[..]);
+        assert_eq!(mt_stream_apply(0xbeef, &ct), pt);
+
+        let mut mt = Mt19937::new(0xbeef);
+        let first = mt.next_u32().to_le_bytes();
+        assert_eq!(mt_stream_apply(0xbeef, &[0; 4]), first);
+    }
+
+    #[test]
+    fn test_mt19937_seeds_differ() {
+        let mut a = Mt19937::new(1);
+        let mut b = Mt19937::new(2);
+        assert_ne!(a.next_u32(), b.next_u32());
+
+        let mut a = Mt19937::new(1);
+        let mut c = a.clone();
+        for _ in 0..1000 {
+            assert_eq!(a.next_u32(), c.next_u32());
+        }
+    }
+}
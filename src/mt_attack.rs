@@ -0,0 +1,104 @@
+#![allow(dead_code)]
+use crate::mt19937::*;
+use rand::prelude::*;
+
+/// The challenge 22 routine, run against a simulated clock instead of
+/// sleeping: wait 40 to 1000 seconds, seed MT19937 with the Unix time,
+/// wait again, and return the first output. `now` is advanced past both
+/// waits; the seed is returned too so callers can check a guess.
+pub fn timestamp_seeded_output<R: Rng>(now: &mut u64, rng: &mut R) -> (u32, u32) {
+    *now += rng.random_range(40..=1000);
+    let seed = *now as u32;
+    let output = Mt19937::new(seed).next_u32();
+    *now += rng.random_range(40..=1000);
+    (output, seed)
+}
+
+/// Finds the seed behind `output`, the first output of an MT19937 seeded
+/// with a timestamp no more than `window` seconds before `now`. Tries the
+/// most recent seconds first.
+pub fn crack_timestamp_seed(output: u32, now: u64, window: u64) -> Option<u32> {
+    (now.saturating_sub(window)..=now)
+        .rev()
+        .map(|t| t as u32)
+        .find(|seed| Mt19937::new(*seed).next_u32() == output)
+}
+
+/// The challenge 24 oracle: 5 to 20 random bytes then `known`, encrypted
+/// with `mt_stream_apply` under `seed`.
+pub fn mt_stream_encrypt_with_prefix<R: Rng>(known: &[u8], seed: u16, rng: &mut R) -> Vec<u8> {
+    let mut pt = vec![0u8; rng.random_range(5..=20)];
+    rng.fill_bytes(&mut pt);
+    pt.extend_from_slice(known);
+    mt_stream_apply(seed, &pt)
+}
+
+/// Recovers the 16-bit seed of an MT19937 stream ciphertext that ends in
+/// `known` by trying all of them.
+pub fn recover_mt_stream_seed(ct: &[u8], known: &[u8]) -> Option<u16> {
+    if known.len() > ct.len() {
+        return None;
+    }
+    let start = ct.len() - known.len();
+    (0..=u16::MAX).find(|seed| mt_stream_apply(*seed, ct)[start..] == *known)
+}
+
+/// A password reset token: 16 bytes of MT19937 output seeded with the
+/// current Unix time.
+pub fn password_reset_token(now: u64) -> [u8; 16] {
+    let mut mt = Mt19937::new(now as u32);
+    let mut token = [0u8; 16];
+    for chunk in token.chunks_mut(4) {
+        chunk.copy_from_slice(&mt.next_u32().to_le_bytes());
+    }
+    token
+}
+
+/// Whether `token` came from `password_reset_token` at some time no more
+/// than `window` seconds before `now`.
+pub fn is_time_seeded_token(token: &[u8], now: u64, window: u64) -> bool {
+    (now.saturating_sub(window)..=now).any(|t| password_reset_token(t) == token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crack_timestamp_seed() {
+        let mut rng = StdRng::seed_from_u64(22);
+        let mut now = 1_700_000_000;
+
+        for _ in 0..5 {
+            let (output, seed) = timestamp_seeded_output(&mut now, &mut rng);
+            assert_eq!(crack_timestamp_seed(output, now, 2000), Some(seed));
+        }
+
+        // Seeded long before the window: not found.
+        let output = Mt19937::new(1_600_000_000).next_u32();
+        assert_eq!(crack_timestamp_seed(output, now, 2000), None);
+    }
+
+    #[test]
+    fn test_recover_mt_stream_seed() {
+        let mut rng = StdRng::seed_from_u64(24);
+        let known = [b'A'; 14];
+        for seed in [0u16, 0x1234, rng.random()] {
+            let ct = mt_stream_encrypt_with_prefix(&known, seed, &mut rng);
+            assert_eq!(recover_mt_stream_seed(&ct, &known), Some(seed));
+        }
+        assert_eq!(recover_mt_stream_seed(b"short", &known), None);
+    }
+
+    #[test]
+    fn test_is_time_seeded_token() {
+        let now = 1_700_000_000;
+        let token = password_reset_token(now - 300);
+        assert!(is_time_seeded_token(&token, now, 3600));
+        assert!(!is_time_seeded_token(&token, now, 60));
+
+        let mut random = [0u8; 16];
+        StdRng::seed_from_u64(24).fill_bytes(&mut random);
+        assert!(!is_time_seeded_token(&random, now, 3600));
+    }
+}
@@ -0,0 +1,279 @@
+#![allow(dead_code)]
+use crate::merkle_damgard::*;
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+pub(crate) fn random_block<R: Rng>(rng: &mut R, block_size: usize) -> Vec<u8> {
+    let mut block = vec![0u8; block_size];
+    rng.fill_bytes(&mut block);
+    block
+}
+
+/// Two different blocks that take `state` to the same next state.
+#[derive(Debug, Clone)]
+pub struct BlockCollision<C: Compression> {
+    pub a: Vec<u8>,
+    pub b: Vec<u8>,
+    pub state: C::State,
+    /// Compression function calls it took.
+    pub calls: u64,
+}
+
+/// A birthday search: random blocks from `state` until two of them
+/// compress to the same thing, about 2^(n/2) calls for an n-bit state.
+pub fn find_block_collision<C: Compression, R: Rng>(
+    state: C::State,
+    rng: &mut R,
+) -> BlockCollision<C>
+where
+    C::State: Hash + Eq,
+{
+    let mut seen: HashMap<C::State, Vec<u8>> = HashMap::new();
+    let mut calls = 0;
+    loop {
+        let block = random_block(rng, C::BLOCK_SIZE);
+        let mut next = state;
+        C::compress(&mut next, &block);
+        calls += 1;
+        match seen.get(&next) {
+            Some(other) if *other != block => {
+                return BlockCollision {
+                    a: other.clone(),
+                    b: block,
+                    state: next,
+                    calls,
+                }
+            }
+            _ => {
+                seen.insert(next, block);
+            }
+        }
+    }
+}
+
+/// The most pairs a `Multicollision` holds: a message is chosen by the
+/// bits of a `u64`, one per pair.
+pub const MAX_PAIRS: usize = u64::BITS as usize;
+
+/// Joux's multicollision: block collisions one after another, each from
+/// the state the one before led to. Picking either block of each pair
+/// gives 2^t messages of t blocks, all with the same chaining value, for
+/// only t times the work of one collision.
+#[derive(Debug, Clone)]
+pub struct Multicollision<C: Compression> {
+    pub pairs: Vec<(Vec<u8>, Vec<u8>)>,
+    /// The chaining value every message leads to.
+    pub state: C::State,
+    pub calls: u64,
+}
+
+impl<C: Compression> Default for Multicollision<C>
+where
+    C::State: Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Compression> Multicollision<C>
+where
+    C::State: Hash + Eq,
+{
+    /// No pairs yet, from the hash's initial state.
+    pub fn new() -> Self {
+        Self::from_state(C::initial_state())
+    }
+
+    pub fn from_state(state: C::State) -> Self {
+        Self {
+            pairs: Vec::new(),
+            state,
+            calls: 0,
+        }
+    }
+
+    /// Doubles the number of messages, or fails if there are already
+    /// `MAX_PAIRS` pairs.
+    pub fn extend<R: Rng>(&mut self, rng: &mut R) -> anyhow::Result<()> {
+        if self.pairs.len() == MAX_PAIRS {
+            anyhow::bail!("a multicollision can't have more than {} pairs", MAX_PAIRS);
+        }
+        let collision = find_block_collision::<C, R>(self.state, rng);
+        self.pairs.push((collision.a, collision.b));
+        self.state = collision.state;
+        self.calls += collision.calls;
+        Ok(())
+    }
+
+    /// The message where bit `i` of `choice` picks the second block of
+    /// pair `i`.
+    pub fn message(&self, choice: u64) -> Vec<u8> {
+        self.pairs
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (a, b))| {
+                if choice >> i & 1 == 1 {
+                    b.clone()
+                } else {
+                    a.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Two of `mc`'s messages that collide under `G` too, as choices for
+/// `Multicollision::message`, and how many `G` calls the search took.
+/// The messages share prefixes, so they are walked as a tree: 2^(t+1)
+/// calls for all 2^t of them.
+pub fn collide_within<F: Compression, G: Compression>(
+    mc: &Multicollision<F>,
+    state: G::State,
+) -> (Option<(u64, u64)>, u64)
+where
+    F::State: Hash + Eq,
+    G::State: Hash + Eq,
+{
+    fn walk<G: Compression>(
+        pairs: &[(Vec<u8>, Vec<u8>)],
+        depth: usize,
+        state: G::State,
+        choice: u64,
+        seen: &mut HashMap<G::State, u64>,
+        calls: &mut u64,
+    ) -> Option<(u64, u64)>
+    where
+        G::State: Hash + Eq,
+    {
+        if depth == pairs.len() {
+            return seen.insert(state, choice).map(|other| (other, choice));
+        }
+        let (a, b) = &pairs[depth];
+        for (bit, block) in [a, b].iter().enumerate() {
+            let mut next = state;
+            G::compress(&mut next, block);
+            *calls += 1;
+            let found = walk::<G>(
+                pairs,
+                depth + 1,
+                next,
+                choice | (bit as u64) << depth,
+                seen,
+                calls,
+            );
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    let mut seen = HashMap::new();
+    let mut calls = 0;
+    let found = walk::<G>(&mc.pairs, 0, state, 0, &mut seen, &mut calls);
+    (found, calls)
+}
+
+/// `F(message) || G(message)`, the cascade challenge 52 hopes is as strong
+/// as both together.
+pub fn cascade_hash<F: Compression, G: Compression>(message: &[u8]) -> Vec<u8> {
+    let mut f = MerkleDamgard::<F>::new();
+    f.update(message);
+    let mut g = MerkleDamgard::<G>::new();
+    g.update(message);
+    [f.finalize(), g.finalize()].concat()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CascadeCollision {
+    pub a: Vec<u8>,
+    pub b: Vec<u8>,
+    pub f_calls: u64,
+    pub g_calls: u64,
+}
+
+/// The challenge 52 attack: a collision in `F || G` for about the price of
+/// one in `G`. Build a multicollision in the cheaper `F` with 2^(b/2)
+/// messages, `b` being `G`'s state size in bits, and look among them for
+/// a `G` collision. If there isn't one, one more pair doubles the
+/// messages and the search goes again, up to `MAX_PAIRS`.
+///
+/// The two messages are the same length, so the padding both hashes add
+/// changes nothing.
+pub fn cascade_collision<F: Compression, G: Compression, R: Rng>(
+    rng: &mut R,
+) -> anyhow::Result<CascadeCollision>
+where
+    F::State: Hash + Eq,
+    G::State: Hash + Eq,
+{
+    let mut mc = Multicollision::<F>::new();
+    while mc.pairs.len() < G::OUTPUT_SIZE * 8 / 2 {
+        mc.extend(rng)?;
+    }
+    let mut g_calls = 0;
+    loop {
+        let (found, calls) = collide_within::<F, G>(&mc, G::initial_state());
+        g_calls += calls;
+        if let Some((a, b)) = found {
+            return Ok(CascadeCollision {
+                a: mc.message(a),
+                b: mc.message(b),
+                f_calls: mc.calls,
+                g_calls,
+            });
+        }
+        mc.extend(rng)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cheap_hash::*;
+
+    #[test]
+    fn test_multicollision() {
+        let mut rng = StdRng::seed_from_u64(52);
+        let mut mc = Multicollision::<AesCompression<2>>::new();
+        for _ in 0..4 {
+            mc.extend(&mut rng).unwrap();
+        }
+        let hashes: Vec<Vec<u8>> = (0..16).map(|c| cheap_hash::<2>(&mc.message(c))).collect();
+        assert!(hashes.iter().all(|h| *h == hashes[0]));
+        let messages: std::collections::HashSet<Vec<u8>> = (0..16).map(|c| mc.message(c)).collect();
+        assert_eq!(messages.len(), 16);
+        // Four birthday searches on 16 bits, a few hundred calls each.
+        assert!(mc.calls < 4 * 2000);
+    }
+
+    #[test]
+    fn test_pair_limit() {
+        let mut rng = StdRng::seed_from_u64(625);
+        let mut mc = Multicollision::<AesCompression<1>>::new();
+        for _ in 0..MAX_PAIRS {
+            mc.extend(&mut rng).unwrap();
+        }
+        assert!(mc.extend(&mut rng).is_err());
+        assert_eq!(mc.pairs.len(), MAX_PAIRS);
+
+        // The last pair is still reachable through the top bit.
+        let last = mc.message(1 << (MAX_PAIRS - 1));
+        assert_eq!(last[last.len() - 16..], mc.pairs[MAX_PAIRS - 1].1[..]);
+        assert_eq!(cheap_hash::<1>(&last), cheap_hash::<1>(&mc.message(0)));
+    }
+
+    #[test]
+    fn test_cascade_collision() {
+        let mut rng = StdRng::seed_from_u64(52);
+        let found = cascade_collision::<AesCompression<2>, AesCompression<3>, _>(&mut rng).unwrap();
+        assert_ne!(found.a, found.b);
+        assert_eq!(
+            cascade_hash::<AesCompression<2>, AesCompression<3>>(&found.a),
+            cascade_hash::<AesCompression<2>, AesCompression<3>>(&found.b)
+        );
+        assert_eq!(cheap_hash::<3>(&found.a), cheap_hash::<3>(&found.b));
+    }
+}
@@ -0,0 +1,142 @@
+#![allow(dead_code)]
+
+//! What attack code gets to talk to. Each attack takes one of these traits
+//! rather than a concrete server, so the same code runs against the
+//! challenge servers, against closures, and against mocks in tests.
+//! Closures of the matching shape implement every trait.
+
+/// Something that encrypts attacker-chosen input, usually together with
+/// hidden bytes of its own.
+pub trait EncryptionOracle {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8>;
+}
+
+impl<F: FnMut(&[u8]) -> Vec<u8>> EncryptionOracle for F {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        self(input)
+    }
+}
+
+/// Something that decrypts attacker-chosen ciphertext and reports back a
+/// `T`: whether the result grants admin, the parsed fields, or just
+/// success. Whatever its errors say is part of what it leaks.
+pub trait DecryptionOracle<T = Vec<u8>> {
+    fn decrypt(&mut self, ct: &[u8]) -> anyhow::Result<T>;
+}
+
+impl<T, F: FnMut(&[u8]) -> anyhow::Result<T>> DecryptionOracle<T> for F {
+    fn decrypt(&mut self, ct: &[u8]) -> anyhow::Result<T> {
+        self(ct)
+    }
+}
+
+/// Something that decrypts a CBC ciphertext under the given IV and says
+/// only whether the padding was valid.
+pub trait PaddingOracle {
+    fn padding_valid(&mut self, iv: &[u8], ct: &[u8]) -> bool;
+}
+
+impl<F: FnMut(&[u8], &[u8]) -> bool> PaddingOracle for F {
+    fn padding_valid(&mut self, iv: &[u8], ct: &[u8]) -> bool {
+        self(iv, ct)
+    }
+}
+
+/// Something that checks a MAC over a message, saying only yes or no.
+pub trait MacVerifier {
+    fn verify(&mut self, message: &[u8], mac: &[u8]) -> bool;
+}
+
+impl<F: FnMut(&[u8], &[u8]) -> bool> MacVerifier for F {
+    fn verify(&mut self, message: &[u8], mac: &[u8]) -> bool {
+        self(message, mac)
+    }
+}
+
+/// Something that rewrites ciphertext in place: `new_plaintext` goes in at
+/// `offset`, encrypted the way the rest was, and the whole ciphertext
+/// comes back.
+pub trait EditOracle {
+    fn edit(&mut self, ct: &[u8], offset: usize, new_plaintext: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+impl<F: FnMut(&[u8], usize, &[u8]) -> anyhow::Result<Vec<u8>>> EditOracle for F {
+    fn edit(&mut self, ct: &[u8], offset: usize, new_plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self(ct, offset, new_plaintext)
+    }
+}
+
+/// Wraps an oracle and counts the calls made through it.
+#[derive(Debug, Clone)]
+pub struct Counting<O> {
+    pub inner: O,
+    pub queries: u64,
+}
+
+impl<O> Counting<O> {
+    pub fn new(inner: O) -> Self {
+        Self { inner, queries: 0 }
+    }
+}
+
+impl<O: EncryptionOracle> EncryptionOracle for Counting<O> {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        self.queries += 1;
+        self.inner.encrypt(input)
+    }
+}
+
+impl<T, O: DecryptionOracle<T>> DecryptionOracle<T> for Counting<O> {
+    fn decrypt(&mut self, ct: &[u8]) -> anyhow::Result<T> {
+        self.queries += 1;
+        self.inner.decrypt(ct)
+    }
+}
+
+impl<O: PaddingOracle> PaddingOracle for Counting<O> {
+    fn padding_valid(&mut self, iv: &[u8], ct: &[u8]) -> bool {
+        self.queries += 1;
+        self.inner.padding_valid(iv, ct)
+    }
+}
+
+impl<O: EditOracle> EditOracle for Counting<O> {
+    fn edit(&mut self, ct: &[u8], offset: usize, new_plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.queries += 1;
+        self.inner.edit(ct, offset, new_plaintext)
+    }
+}
+
+impl<O: MacVerifier> MacVerifier for Counting<O> {
+    fn verify(&mut self, message: &[u8], mac: &[u8]) -> bool {
+        self.queries += 1;
+        self.inner.verify(message, mac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepted<O: MacVerifier>(mut verifier: O, cases: &[(&[u8], &[u8])]) -> Vec<bool> {
+        cases.iter().map(|(m, t)| verifier.verify(m, t)).collect()
+    }
+
+    #[test]
+    fn test_closures_and_counting() {
+        let same_len = |m: &[u8], t: &[u8]| m.len() == t.len();
+        let ans = accepted(same_len, &[(b"ab", b"cd"), (b"abc", b"d")]);
+        assert_eq!(ans, vec![true, false]);
+
+        let mut counted = Counting::new(same_len);
+        assert!(counted.verify(b"ab", b"cd"));
+        assert!(!counted.verify(b"abc", b"d"));
+        assert_eq!(counted.queries, 2);
+
+        let mut enc = Counting::new(|input: &[u8]| input.to_vec());
+        assert_eq!(enc.encrypt(b"echo"), b"echo");
+        let mut dec = Counting::new(|ct: &[u8]| -> anyhow::Result<usize> { Ok(ct.len()) });
+        assert_eq!(dec.decrypt(b"four").unwrap(), 4);
+        assert_eq!((enc.queries, dec.queries), (1, 1));
+    }
+}
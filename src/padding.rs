@@ -0,0 +1,360 @@
+#![allow(dead_code)]
+use crate::cipher::*;
+use crate::raw_bytes::*;
+
+/// A block padding scheme. `pad` always yields a whole number of blocks and
+/// `unpad` reverses it, rejecting inputs the scheme could not have produced.
+pub trait Padding {
+    fn pad(&self, rb: &RawBytes, block_len: usize) -> RawBytes;
+    fn unpad(&self, rb: &RawBytes, block_len: usize) -> Result<RawBytes, PaddingError>;
+}
+
+/// `n` bytes of value `n`.
+pub struct Pkcs7;
+
+/// `n - 1` zero bytes followed by the pad length.
+pub struct AnsiX923;
+
+/// A single 0x80 byte followed by zeros (ISO/IEC 7816-4, aka bit padding).
+pub struct Iso7816;
+
+/// Zeros up to the block boundary, nothing if already aligned. Not
+/// reversible for data ending in zero bytes.
+pub struct ZeroPadding;
+
+fn check_length(rb: &RawBytes, block_len: usize) -> Result<(), PaddingError> {
+    let len = rb.bytes.len();
+    if len == 0 || !len.is_multiple_of(block_len) {
+        return Err(PaddingError::BadLength(len));
+    }
+    Ok(())
+}
+
+impl Padding for Pkcs7 {
+    fn pad(&self, rb: &RawBytes, block_len: usize) -> RawBytes {
+        RawBytes {
+            bytes: add_pkcs7_padding(rb, block_len).collect(),
+        }
+    }
+
+    fn unpad(&self, rb: &RawBytes, block_len: usize) -> Result<RawBytes, PaddingError> {
+        strip_pkcs7_padding(rb, block_len)
+    }
+}
+
+impl Padding for AnsiX923 {
+    fn pad(&self, rb: &RawBytes, block_len: usize) -> RawBytes {
+        let pad_len = block_len - rb.bytes.len() % block_len;
+
+        let mut bytes = rb.bytes.clone();
+        bytes.resize(rb.bytes.len() + pad_len - 1, 0);
+        bytes.push(pad_len as u8);
+        RawBytes { bytes }
+    }
+
+    fn unpad(&self, rb: &RawBytes, block_len: usize) -> Result<RawBytes, PaddingError> {
+        check_length(rb, block_len)?;
+
+        let len = rb.bytes.len();
+        let pad_len = rb.bytes[len - 1];
+        if pad_len == 0 || pad_len as usize > block_len {
+            return Err(PaddingError::BadPadLength(pad_len));
+        }
+
+        let data_len = len - pad_len as usize;
+        if rb.bytes[data_len..len - 1].iter().any(|b| *b != 0) {
+            return Err(PaddingError::InconsistentPadding);
+        }
+
+        Ok(RawBytes {
+            bytes: rb.bytes[..data_len].to_vec(),
+        })
+    }
+}
+
+impl Padding for Iso7816 {
+    fn pad(&self, rb: &RawBytes, block_len: usize) -> RawBytes {
+        let pad_len = block_len - rb.bytes.len() % block_len;
+
+        let mut bytes = rb.bytes.clone();
+        bytes.push(0x80);
+        bytes.resize(rb.bytes.len() + pad_len, 0);
+        RawBytes { bytes }
+    }
+
+    fn unpad(&self, rb: &RawBytes, block_len: usize) -> Result<RawBytes, PaddingError> {
+        check_length(rb, block_len)?;
+
+        let last_block = &rb.bytes[rb.bytes.len() - block_len..];
+        let zeros = last_block.iter().rev().take_while(|b| **b == 0).count();
+        if zeros == block_len || last_block[block_len - zeros - 1] != 0x80 {
+            return Err(PaddingError::InconsistentPadding);
+        }
+
+        let data_len = rb.bytes.len() - zeros - 1;
+        Ok(RawBytes {
+            bytes: rb.bytes[..data_len].to_vec(),
+        })
+    }
+}
+
+impl Padding for ZeroPadding {
+    fn pad(&self, rb: &RawBytes, block_len: usize) -> RawBytes {
+        let len = rb.bytes.len().next_multiple_of(block_len);
+
+        let mut bytes = rb.bytes.clone();
+        bytes.resize(len, 0);
+        RawBytes { bytes }
+    }
+
+    fn unpad(&self, rb: &RawBytes, block_len: usize) -> Result<RawBytes, PaddingError> {
+        if !rb.bytes.len().is_multiple_of(block_len) {
+            return Err(PaddingError::BadLength(rb.bytes.len()));
+        }
+
+        let zeros = rb.bytes.iter().rev().take_while(|b| **b == 0).count();
+        let data_len = rb.bytes.len() - zeros.min(block_len - 1);
+        Ok(RawBytes {
+            bytes: rb.bytes[..data_len].to_vec(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MalformedPadding {
+    /// The final byte claims a run length the preceding bytes don't have.
+    WrongFinalByte,
+    /// The final byte is right but one byte inside its run differs.
+    InconsistentRun,
+    /// The final byte is zero.
+    ZeroLengthPad,
+    /// The final byte exceeds the block length, even though enough bytes of
+    /// that value precede it.
+    PadLongerThanBlock,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NegativeCase {
+    pub class: MalformedPadding,
+    pub bytes: RawBytes,
+}
+
+/// Every class of malformed PKCS#7 padding, for every pad length that can
+/// express it. Message bytes are zero, which no valid pad byte can equal, so
+/// each case is invalid for exactly the reason its class states.
+pub fn pkcs7_negative_cases(block_len: usize) -> Vec<NegativeCase> {
+    assert!((2..256).contains(&block_len));
+
+    let mut cases = Vec::new();
+    let mut push = |class, bytes: Vec<u8>| {
+        cases.push(NegativeCase {
+            class,
+            bytes: RawBytes { bytes },
+        })
+    };
+
+    for n in 1..=block_len {
+        for last in 2..=block_len {
+            if last == n {
+                continue;
+            }
+            let mut block = vec![0u8; block_len];
+            block[block_len - n..].fill(n as u8);
+            block[block_len - 1] = last as u8;
+            push(MalformedPadding::WrongFinalByte, block);
+        }
+
+        for i in block_len - n..block_len - 1 {
+            let mut block = vec![0u8; block_len];
+            block[block_len - n..].fill(n as u8);
+            block[i] ^= 0x80;
+            push(MalformedPadding::InconsistentRun, block);
+        }
+    }
+
+    push(MalformedPadding::ZeroLengthPad, vec![0u8; block_len]);
+
+    for over in [block_len + 1, 255] {
+        if over < 256 {
+            push(
+                MalformedPadding::PadLongerThanBlock,
+                vec![over as u8; over.next_multiple_of(block_len)],
+            );
+        }
+    }
+
+    cases
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConformanceFailure {
+    /// Padding `data_len` bytes did not give whole blocks.
+    PaddedLength { data_len: usize },
+    /// Unpadding did not give back the original `data_len` bytes.
+    RoundTrip { data_len: usize },
+    /// A malformed input was accepted.
+    AcceptedMalformed(NegativeCase),
+}
+
+/// Checks that `padding` round-trips messages of every length up to three
+/// blocks and rejects every case in `negative`. Messages never end in a zero
+/// byte, so ambiguous schemes like `ZeroPadding` still pass.
+pub fn check_padding_conformance<P: Padding + ?Sized>(
+    padding: &P,
+    block_len: usize,
+    negative: &[NegativeCase],
+) -> Result<(), ConformanceFailure> {
+    for data_len in 0..=3 * block_len {
+        let data = RawBytes {
+            bytes: (0..data_len).map(|i| (i % 255) as u8 + 1).collect(),
+        };
+
+        let padded = padding.pad(&data, block_len);
+        if padded.bytes.len() < data_len || !padded.bytes.len().is_multiple_of(block_len) {
+            return Err(ConformanceFailure::PaddedLength { data_len });
+        }
+        if padding.unpad(&padded, block_len).as_ref() != Ok(&data) {
+            return Err(ConformanceFailure::RoundTrip { data_len });
+        }
+    }
+
+    for case in negative {
+        if padding.unpad(&case.bytes, block_len).is_ok() {
+            return Err(ConformanceFailure::AcceptedMalformed(case.clone()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad_schemes() {
+        let data = RawBytes::from_str("YELLOW SUB");
+
+        assert_eq!(
+            Pkcs7.pad(&data, 16).to_hex(),
+            "59454c4c4f5720535542060606060606"
+        );
+        assert_eq!(
+            AnsiX923.pad(&data, 16).to_hex(),
+            "59454c4c4f5720535542000000000006"
+        );
+        assert_eq!(
+            Iso7816.pad(&data, 16).to_hex(),
+            "59454c4c4f5720535542800000000000"
+        );
+        assert_eq!(
+            ZeroPadding.pad(&data, 16).to_hex(),
+            "59454c4c4f5720535542000000000000"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let schemes: Vec<&dyn Padding> = vec![&Pkcs7, &AnsiX923, &Iso7816, &ZeroPadding];
+
+        for padding in schemes {
+            for len in 0..40 {
+                let data = RawBytes {
+                    bytes: (1..=len).collect(),
+                };
+                let padded = padding.pad(&data, 16);
+                assert!(padded.bytes.len().is_multiple_of(16));
+                assert_eq!(padding.unpad(&padded, 16).unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pkcs7_negative_cases() {
+        let cases = pkcs7_negative_cases(16);
+
+        for class in [
+            MalformedPadding::WrongFinalByte,
+            MalformedPadding::InconsistentRun,
+            MalformedPadding::ZeroLengthPad,
+            MalformedPadding::PadLongerThanBlock,
+        ] {
+            assert!(cases.iter().any(|c| c.class == class), "{:?}", class);
+        }
+        for case in &cases {
+            assert!(case.bytes.bytes.len().is_multiple_of(16));
+            assert!(strip_pkcs7_padding(&case.bytes, 16).is_err(), "{:?}", case);
+        }
+    }
+
+    #[test]
+    fn test_padding_conformance() {
+        let negative = pkcs7_negative_cases(16);
+        assert_eq!(check_padding_conformance(&Pkcs7, 16, &negative), Ok(()));
+        assert_eq!(
+            check_padding_conformance(&Pkcs7, 8, &pkcs7_negative_cases(8)),
+            Ok(())
+        );
+
+        let schemes: Vec<&dyn Padding> = vec![&AnsiX923, &Iso7816, &ZeroPadding];
+        for padding in schemes {
+            assert_eq!(check_padding_conformance(padding, 16, &[]), Ok(()));
+        }
+    }
+
+    // Only checks that the pad bytes agree, forgetting the block bound.
+    struct SloppyPkcs7;
+
+    impl Padding for SloppyPkcs7 {
+        fn pad(&self, rb: &RawBytes, block_len: usize) -> RawBytes {
+            Pkcs7.pad(rb, block_len)
+        }
+
+        fn unpad(&self, rb: &RawBytes, _block_len: usize) -> Result<RawBytes, PaddingError> {
+            let n = *rb.bytes.last().ok_or(PaddingError::BadLength(0))? as usize;
+            if n == 0 || n > rb.bytes.len() {
+                return Err(PaddingError::BadPadLength(n as u8));
+            }
+            if rb.bytes[rb.bytes.len() - n..]
+                .iter()
+                .any(|b| *b as usize != n)
+            {
+                return Err(PaddingError::InconsistentPadding);
+            }
+            Ok(RawBytes {
+                bytes: rb.bytes[..rb.bytes.len() - n].to_vec(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_padding_conformance_catches_bugs() {
+        let ans = check_padding_conformance(&SloppyPkcs7, 16, &pkcs7_negative_cases(16));
+        match ans {
+            Err(ConformanceFailure::AcceptedMalformed(case)) => {
+                assert_eq!(case.class, MalformedPadding::PadLongerThanBlock)
+            }
+            _ => panic!("sloppy validator passed: {:?}", ans),
+        }
+    }
+
+    #[test]
+    fn test_unpad_rejects() {
+        let bad_x923 = RawBytes::from_hex("59454c4c4f5720535542000000010006");
+        assert_eq!(
+            AnsiX923.unpad(&bad_x923, 16),
+            Err(PaddingError::InconsistentPadding)
+        );
+
+        let bad_iso = RawBytes::from_hex("59454c4c4f5720535542000000000000");
+        assert_eq!(
+            Iso7816.unpad(&bad_iso, 16),
+            Err(PaddingError::InconsistentPadding)
+        );
+
+        let short = RawBytes::from_str("YELLOW");
+        assert_eq!(
+            ZeroPadding.unpad(&short, 16),
+            Err(PaddingError::BadLength(6))
+        );
+    }
+}
@@ -0,0 +1,412 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::cipher::*;
+use crate::iv::*;
+use crate::modes::*;
+use crate::oracles::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+use rand::prelude::*;
+
+pub const CHALLENGE_17_STRINGS: [&str; 10] = [
+    "MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc=",
+    "MDAwMDAxV2l0aCB0aGUgYmFzcyBraWNrZWQgaW4gYW5kIHRoZSBWZWdhJ3MgYXJlIHB1bXBpbic=",
+    "MDAwMDAyUXVpY2sgdG8gdGhlIHBvaW50LCB0byB0aGUgcG9pbnQsIG5vIGZha2luZw==",
+    "MDAwMDAzQ29va2luZyBNQydzIGxpa2UgYSBwb3VuZCBvZiBiYWNvbg==",
+    "MDAwMDA0QnVybmluZyAnZW0sIGlmIHlvdSBhaW4ndCBxdWljayBhbmQgbmltYmxl",
+    "MDAwMDA1SSBnbyBjcmF6eSB3aGVuIEkgaGVhciBhIGN5bWJhbA==",
+    "MDAwMDA2QW5kIGEgaGlnaCBoYXQgd2l0aCBhIHNvdXBlZCB1cCB0ZW1wbw==",
+    "MDAwMDA3SSdtIG9uIGEgcm9sbCwgaXQncyB0aW1lIHRvIGdvIHNvbG8=",
+    "MDAwMDA4b2xsaW4nIGluIG15IGZpdmUgcG9pbnQgb2g=",
+    "MDAwMDA5aXRoIG15IHJhZy10b3AgZG93biBzbyBteSBoYWlyIGNhbiBibG93",
+];
+
+/// The challenge 17 server. `encrypt` hands out one of the challenge
+/// strings under AES-128-CBC with a fresh IV; `padding_valid` decrypts
+/// anything and says only whether the PKCS#7 padding checked out.
+pub struct PaddingOracleServer {
+    aes: Aes,
+}
+
+impl PaddingOracleServer {
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        Self {
+            aes: Aes::new(&key).expect("16 byte key"),
+        }
+    }
+
+    /// Returns `(iv, ciphertext)`.
+    pub fn encrypt<R: Rng>(&self, rng: &mut R) -> (Iv, Vec<u8>) {
+        let choice = CHALLENGE_17_STRINGS[rng.random_range(0..CHALLENGE_17_STRINGS.len())];
+        self.encrypt_bytes(&RawBytes::from_base64(choice), rng)
+    }
+
+    pub fn encrypt_bytes<R: Rng>(&self, pt: &RawBytes, rng: &mut R) -> (Iv, Vec<u8>) {
+        let iv = Iv::random(rng, 16);
+        let ct = cbc_encrypt_with_padding(&self.aes, &iv, pt, &Pkcs7).expect("padded");
+        (iv, ct.bytes)
+    }
+
+    pub fn padding_valid(&self, iv: &[u8], ct: &[u8]) -> bool {
+        match cbc_decrypt(&self.aes, &Iv::from_bytes(iv), ct) {
+            Ok(pt) => strip_pkcs7_padding(&RawBytes { bytes: pt }, 16).is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+impl PaddingOracle for &PaddingOracleServer {
+    fn padding_valid(&mut self, iv: &[u8], ct: &[u8]) -> bool {
+        PaddingOracleServer::padding_valid(self, iv, ct)
+    }
+}
+
+/// A padding-oracle attack on CBC, for any block size and any target
+/// that answers `PaddingOracle` queries: the challenge 17 server, a
+/// closure, or something remote. Counts the queries it makes.
+pub struct PaddingOracleAttack<O: PaddingOracle> {
+    oracle: Counting<O>,
+    block_size: usize,
+    iv: Option<Iv>,
+    secret_iv: bool,
+}
+
+impl<O: PaddingOracle> PaddingOracleAttack<O> {
+    pub fn new(oracle: O, block_size: usize) -> anyhow::Result<Self> {
+        if block_size < 2 {
+            anyhow::bail!("block size {} is too small to attack", block_size);
+        }
+        Ok(Self {
+            oracle: Counting::new(oracle),
+            block_size,
+            iv: None,
+            secret_iv: false,
+        })
+    }
+
+    /// Sets the IV the ciphertexts were made with. Without it, `decrypt`
+    /// can't recover the first block.
+    pub fn with_iv(mut self, iv: &Iv) -> anyhow::Result<Self> {
+        if iv.len() != self.block_size {
+            anyhow::bail!(
+                "IV is {} bytes, the block size is {}",
+                iv.len(),
+                self.block_size
+            );
+        }
+        self.iv = Some(iv.clone());
+        Ok(self)
+    }
+
+    /// For targets that decrypt under an IV of their own and ignore the one
+    /// they are given. Forged blocks then go in front of the block under
+    /// attack, as a two-block ciphertext, instead of in the IV.
+    pub fn secret_iv(mut self) -> Self {
+        self.secret_iv = true;
+        self
+    }
+
+    /// Oracle queries made so far.
+    pub fn queries(&self) -> u64 {
+        self.oracle.queries
+    }
+
+    pub fn into_oracle(self) -> O {
+        self.oracle.inner
+    }
+
+    fn check_blocks(&self, ct: &[u8]) -> anyhow::Result<()> {
+        if ct.is_empty() || !ct.len().is_multiple_of(self.block_size) {
+            anyhow::bail!(
+                "{} bytes is not a whole number of {} byte blocks",
+                ct.len(),
+                self.block_size
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether `block` has valid padding when `prev` comes before it.
+    fn query(oracle: &mut Counting<O>, secret_iv: bool, prev: &[u8], block: &[u8]) -> bool {
+        if secret_iv {
+            let ct = [prev, block].concat();
+            oracle.padding_valid(&vec![0u8; prev.len()], &ct)
+        } else {
+            oracle.padding_valid(prev, block)
+        }
+    }
+
+    /// Recovers `D(block)`, the block's decryption before the CBC XOR,
+    /// from oracle queries with forged previous blocks.
+    ///
+    /// Byte by byte from the end: with the bytes after position `i`
+    /// already known, a forged IV makes them decrypt to the pad value
+    /// `n = bs - i`, and the one IV byte at `i` that gives valid padding
+    /// reveals `D(block)[i] = iv[i] ^ n`. For the last byte a hit could
+    /// also come from the plaintext ending in `02 02` and so on, so hits
+    /// there are confirmed by changing the byte before.
+    pub fn intermediate(&mut self, block: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let bs = self.block_size;
+        if block.len() != bs {
+            anyhow::bail!("block is {} bytes, not {}", block.len(), bs);
+        }
+        let mut inter = vec![0u8; bs];
+
+        for i in (0..bs).rev() {
+            let n = (bs - i) as u8;
+            let mut iv = vec![0u8; bs];
+            for j in i + 1..bs {
+                iv[j] = inter[j] ^ n;
+            }
+
+            let (oracle, secret_iv) = (&mut self.oracle, self.secret_iv);
+            let found = (0..=255u8).find(|guess| {
+                iv[i] = *guess;
+                if !Self::query(oracle, secret_iv, &iv, block) {
+                    return false;
+                }
+                if i == bs - 1 {
+                    let mut check = iv.clone();
+                    check[i - 1] ^= 0xff;
+                    return Self::query(oracle, secret_iv, &check, block);
+                }
+                true
+            });
+            match found {
+                Some(g) => inter[i] = g ^ n,
+                None => anyhow::bail!("no IV byte gives valid padding at {}", i),
+            }
+        }
+        Ok(inter)
+    }
+
+    /// Decrypts `ct` and strips its PKCS#7 padding. Each block is attacked
+    /// on its own, so the oracle only ever sees single blocks. Without a
+    /// known IV the first block can't be decrypted and is left out.
+    pub fn decrypt(&mut self, ct: &[u8]) -> anyhow::Result<RawBytes> {
+        self.check_blocks(ct)?;
+        let bs = self.block_size;
+
+        let mut pt = Vec::with_capacity(ct.len());
+        let iv = self.iv.clone();
+        let (mut prev, rest) = match &iv {
+            Some(iv) => (iv.as_bytes(), ct),
+            None => (&ct[..bs], &ct[bs..]),
+        };
+        if rest.is_empty() {
+            anyhow::bail!("nothing to decrypt without the IV");
+        }
+        for block in rest.chunks(bs) {
+            let inter = self.intermediate(block)?;
+            pt.extend(inter.iter().zip(prev.iter()).map(|(d, p)| d ^ p));
+            prev = block;
+        }
+        Ok(strip_pkcs7_padding(&RawBytes { bytes: pt }, bs)?)
+    }
+
+    /// Forges a ciphertext that decrypts to `plaintext` under the oracle's
+    /// key, without knowing it (CBC-R). Working back from an arbitrary
+    /// last block, each block's intermediate `D(C[i])` comes from the
+    /// oracle, and `C[i-1] = D(C[i]) ^ P[i]` makes it decrypt as wanted.
+    /// The block before the first is the IV, so the target must accept a
+    /// chosen IV.
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> anyhow::Result<(Iv, Vec<u8>)> {
+        if self.secret_iv {
+            anyhow::bail!("can't forge the first block without choosing the IV");
+        }
+        let bs = self.block_size;
+        let padded = Pkcs7.pad(
+            &RawBytes {
+                bytes: plaintext.to_vec(),
+            },
+            bs,
+        );
+
+        let mut blocks = vec![vec![0u8; bs]];
+        for p in padded.bytes.chunks(bs).rev() {
+            let inter = self.intermediate(&blocks[0])?;
+            blocks.insert(0, inter.iter().zip(p.iter()).map(|(d, p)| d ^ p).collect());
+        }
+        let iv = Iv::from_bytes(&blocks[0]);
+        Ok((iv, blocks[1..].concat()))
+    }
+
+    /// Recovers the IV `first_block` was encrypted under, given the
+    /// plaintext block it decrypts to. The IV is just the block before the
+    /// first, so it falls out as `D(C[0]) ^ P[0]` like any other. Once
+    /// known, it is used by `decrypt` from then on.
+    pub fn recover_iv(&mut self, first_block: &[u8], plaintext: &[u8]) -> anyhow::Result<Iv> {
+        if plaintext.len() != self.block_size {
+            anyhow::bail!(
+                "need one whole block of known plaintext, got {} bytes",
+                plaintext.len()
+            );
+        }
+        let inter = self.intermediate(first_block)?;
+        let iv: Vec<u8> = inter
+            .iter()
+            .zip(plaintext.iter())
+            .map(|(d, p)| d ^ p)
+            .collect();
+        let iv = Iv::from_bytes(&iv);
+        self.iv = Some(iv.clone());
+        Ok(iv)
+    }
+}
+
+/// Decrypts a CBC ciphertext with nothing but a padding oracle (challenge
+/// 17). Returns the plaintext with its padding stripped.
+pub fn padding_oracle_decrypt<O: PaddingOracle>(
+    oracle: O,
+    iv: &Iv,
+    ct: &[u8],
+) -> anyhow::Result<RawBytes> {
+    PaddingOracleAttack::new(oracle, iv.len())?
+        .with_iv(iv)?
+        .decrypt(ct)
+}
+
+/// Forges a 16-byte-block CBC ciphertext that decrypts to `plaintext`; see
+/// `PaddingOracleAttack::encrypt`.
+pub fn padding_oracle_encrypt<O: PaddingOracle>(
+    oracle: O,
+    plaintext: &[u8],
+) -> anyhow::Result<(Iv, Vec<u8>)> {
+    PaddingOracleAttack::new(oracle, 16)?.encrypt(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_padding_oracle_all_strings() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let server = PaddingOracleServer::new(&mut rng);
+        for s in CHALLENGE_17_STRINGS.iter() {
+            let expected = RawBytes::from_base64(s);
+            let (iv, ct) = server.encrypt_bytes(&expected, &mut rng);
+            assert!(server.padding_valid(iv.as_bytes(), &ct));
+
+            let ans = padding_oracle_decrypt(&server, &iv, &ct);
+            assert_eq!(ans.unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_padding_oracle_block_aligned_and_pad_like() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let server = PaddingOracleServer::new(&mut rng);
+        // A full block of padding, and plaintexts ending in bytes that look
+        // like padding once the last byte is forged.
+        for pt in [&b"YELLOW SUBMARINE"[..], b"ends in two\x02\x02", b"\x01"] {
+            let expected = RawBytes { bytes: pt.to_vec() };
+            let (iv, ct) = server.encrypt_bytes(&expected, &mut rng);
+            let ans = padding_oracle_decrypt(&server, &iv, &ct);
+            assert_eq!(ans.unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_padding_oracle_rejects_partial_block() {
+        let ans = padding_oracle_decrypt(|_: &[u8], _: &[u8]| true, &Iv::zero(16), &[0u8; 17]);
+        assert!(ans.is_err());
+    }
+
+    #[test]
+    fn test_padding_oracle_encrypt() {
+        let mut rng = StdRng::seed_from_u64(596);
+        let server = PaddingOracleServer::new(&mut rng);
+        for pt in [
+            &b""[..],
+            b"YELLOW SUBMARINE",
+            b";admin=true;role=admin;uid=0;x=forged",
+        ] {
+            let (iv, ct) = padding_oracle_encrypt(&server, pt).unwrap();
+            let back = cbc_decrypt_with_padding(&server.aes, &iv, &RawBytes { bytes: ct }, &Pkcs7);
+            assert_eq!(back.unwrap().bytes, pt);
+        }
+    }
+
+    /// A toy 8-byte block cipher, to show nothing assumes AES.
+    struct Xor8([u8; 8]);
+
+    impl BlockCipher for Xor8 {
+        fn block_size(&self) -> usize {
+            8
+        }
+
+        fn encrypt_block(&self, block: &mut [u8]) {
+            for (b, k) in block.iter_mut().zip(self.0.iter()) {
+                *b = b.rotate_left(3) ^ k;
+            }
+        }
+
+        fn decrypt_block(&self, block: &mut [u8]) {
+            for (b, k) in block.iter_mut().zip(self.0.iter()) {
+                *b = (*b ^ k).rotate_right(3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_engine_on_other_block_sizes() {
+        let cipher = Xor8(*b"8bytekey");
+        let valid = |iv: &[u8], ct: &[u8]| match cbc_decrypt(&cipher, &Iv::from_bytes(iv), ct) {
+            Ok(pt) => strip_pkcs7_padding(&RawBytes { bytes: pt }, 8).is_ok(),
+            Err(_) => false,
+        };
+        let iv = Iv::from_bytes(b"\x01\x02\x03\x04\x05\x06\x07\x08");
+        let pt = RawBytes::from_str("not every cipher has 16 byte blocks");
+        let ct = cbc_encrypt_with_padding(&cipher, &iv, &pt, &Pkcs7).unwrap();
+
+        let mut attack = PaddingOracleAttack::new(valid, 8)
+            .unwrap()
+            .with_iv(&iv)
+            .unwrap();
+        assert_eq!(attack.decrypt(&ct.bytes).unwrap(), pt);
+        let queries = attack.queries();
+        assert!(queries > 0 && queries <= 5 * 256 * 9, "{} queries", queries);
+
+        // Without the IV, everything but the first block.
+        let mut attack = PaddingOracleAttack::new(valid, 8).unwrap();
+        assert_eq!(attack.decrypt(&ct.bytes).unwrap().bytes, &pt.bytes[8..]);
+
+        let (iv, forged) = attack.encrypt(b"chosen").unwrap();
+        let back = cbc_decrypt_with_padding(&cipher, &iv, &RawBytes { bytes: forged }, &Pkcs7);
+        assert_eq!(back.unwrap().bytes, b"chosen");
+
+        assert!(PaddingOracleAttack::new(valid, 1).is_err());
+        assert!(PaddingOracleAttack::new(valid, 8)
+            .unwrap()
+            .with_iv(&Iv::zero(16))
+            .is_err());
+    }
+
+    #[test]
+    fn test_recover_secret_iv() {
+        let mut rng = StdRng::seed_from_u64(598);
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let secret = Iv::random(&mut rng, 16);
+        let valid = |_: &[u8], ct: &[u8]| match cbc_decrypt(&aes, &secret, ct) {
+            Ok(pt) => strip_pkcs7_padding(&RawBytes { bytes: pt }, 16).is_ok(),
+            Err(_) => false,
+        };
+        let pt = RawBytes::from_str("comment1=cooking%20MCs;userdata=whatever the IV is");
+        let ct = cbc_encrypt_with_padding(&aes, &secret, &pt, &Pkcs7)
+            .unwrap()
+            .bytes;
+
+        let mut attack = PaddingOracleAttack::new(valid, 16).unwrap().secret_iv();
+        let iv = attack.recover_iv(&ct[..16], &pt.bytes[..16]).unwrap();
+        assert_eq!(iv, secret);
+        assert_eq!(attack.decrypt(&ct).unwrap(), pt);
+        assert!(attack.encrypt(b"x").is_err());
+
+        // Forging through the IV gets nowhere when the target ignores it.
+        let mut attack = PaddingOracleAttack::new(valid, 16).unwrap();
+        assert!(attack.recover_iv(&ct[..16], &pt.bytes[..16]).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+#![allow(dead_code)]
+use std::convert::TryInto;
+
+const MASK26: u64 = (1 << 26) - 1;
+
+/// An element of GF(2^130 - 5), as five 26-bit limbs, least significant
+/// first. Limbs may run a little over 26 bits between operations;
+/// `freeze` gives the canonical form.
+#[derive(Debug, Clone, Copy)]
+pub struct Fe([u64; 5]);
+
+impl PartialEq for Fe {
+    fn eq(&self, other: &Fe) -> bool {
+        self.freeze().0 == other.freeze().0
+    }
+}
+
+impl Fe {
+    pub const ZERO: Fe = Fe([0; 5]);
+    pub const ONE: Fe = Fe([1, 0, 0, 0, 0]);
+    /// 2^128, the amount the final tag addition wraps by.
+    pub const TWO_128: Fe = Fe([0, 0, 0, 0, 1 << 24]);
+
+    /// Up to 17 little-endian bytes. Anything from 2^130 up wraps mod p.
+    pub fn from_le_bytes(bytes: &[u8]) -> Fe {
+        let mut buf = [0u8; 24];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        let lo = u128::from_le_bytes(buf[..16].try_into().unwrap());
+        let hi = u64::from_le_bytes(buf[16..24].try_into().unwrap());
+        let mut limbs = [0u64; 5];
+        for (i, l) in limbs.iter_mut().enumerate() {
+            *l = (lo >> (26 * i)) as u64 & MASK26;
+        }
+        limbs[4] |= (hi << 24) & MASK26;
+        let mut fe = Fe(limbs);
+        fe.0[0] += (hi >> 2) * 5;
+        fe.carry();
+        fe
+    }
+
+    pub fn from_u128(x: u128) -> Fe {
+        Fe::from_le_bytes(&x.to_le_bytes())
+    }
+
+    /// The low 128 bits of the canonical value.
+    pub fn low_u128(&self) -> u128 {
+        let l = self.freeze().0;
+        l.iter()
+            .enumerate()
+            .fold(0u128, |acc, (i, x)| acc | ((*x as u128) << (26 * i)))
+    }
+
+    /// Bits 128 and 129 of the canonical value.
+    pub fn high_bits(&self) -> u64 {
+        self.freeze().0[4] >> 24
+    }
+
+    fn carry(&mut self) {
+        let l = &mut self.0;
+        while l.iter().any(|x| *x > MASK26) {
+            for i in 0..4 {
+                l[i + 1] += l[i] >> 26;
+                l[i] &= MASK26;
+            }
+            l[0] += (l[4] >> 26) * 5;
+            l[4] &= MASK26;
+        }
+    }
+
+    /// The value reduced to below p.
+    pub fn freeze(&self) -> Fe {
+        let mut h = *self;
+        h.carry();
+        // h < 2^130 now; subtract p if h + 5 reaches 2^130.
+        let mut g = h.0;
+        g[0] += 5;
+        for i in 0..4 {
+            g[i + 1] += g[i] >> 26;
+            g[i] &= MASK26;
+        }
+        if g[4] >> 26 != 0 {
+            g[4] &= MASK26;
+            Fe(g)
+        } else {
+            h
+        }
+    }
+
+    pub fn add(&self, other: &Fe) -> Fe {
+        let mut out = [0u64; 5];
+        for (i, o) in out.iter_mut().enumerate() {
+            *o = self.0[i] + other.0[i];
+        }
+        let mut fe = Fe(out);
+        fe.carry();
+        fe
+    }
+
+    pub fn sub(&self, other: &Fe) -> Fe {
+        // Adding 2p limb by limb keeps every limb from going negative.
+        let two_p = [
+            (MASK26 - 4) * 2,
+            MASK26 * 2,
+            MASK26 * 2,
+            MASK26 * 2,
+            MASK26 * 2,
+        ];
+        let b = other.freeze().0;
+        let mut out = [0u64; 5];
+        for (i, o) in out.iter_mut().enumerate() {
+            *o = self.0[i] + two_p[i] - b[i];
+        }
+        let mut fe = Fe(out);
+        fe.carry();
+        fe
+    }
+
+    pub fn mul(&self, other: &Fe) -> Fe {
+        let a = self.0;
+        let b = other.0;
+        let b5: Vec<u64> = b.iter().map(|x| x * 5).collect();
+        let mut d = [
+            a[0] * b[0] + a[1] * b5[4] + a[2] * b5[3] + a[3] * b5[2] + a[4] * b5[1],
+            a[0] * b[1] + a[1] * b[0] + a[2] * b5[4] + a[3] * b5[3] + a[4] * b5[2],
+            a[0] * b[2] + a[1] * b[1] + a[2] * b[0] + a[3] * b5[4] + a[4] * b5[3],
+            a[0] * b[3] + a[1] * b[2] + a[2] * b[1] + a[3] * b[0] + a[4] * b5[4],
+            a[0] * b[4] + a[1] * b[3] + a[2] * b[2] + a[3] * b[1] + a[4] * b[0],
+        ];
+        for i in 0..4 {
+            d[i + 1] += d[i] >> 26;
+            d[i] &= MASK26;
+        }
+        d[0] += (d[4] >> 26) * 5;
+        d[4] &= MASK26;
+        let mut fe = Fe(d);
+        fe.carry();
+        fe
+    }
+
+    /// `self` to the power `exp`, given as little-endian bytes.
+    pub fn pow(&self, exp: &[u8]) -> Fe {
+        let mut ans = Fe::ONE;
+        for byte in exp.iter().rev() {
+            for bit in (0..8).rev() {
+                ans = ans.mul(&ans);
+                if (byte >> bit) & 1 == 1 {
+                    ans = ans.mul(self);
+                }
+            }
+        }
+        ans
+    }
+
+    /// The multiplicative inverse, as `self^(p - 2)`.
+    pub fn invert(&self) -> Fe {
+        // p - 2 = 2^130 - 7.
+        let mut exp = [0xffu8; 17];
+        exp[0] = 0xf9;
+        exp[16] = 0x03;
+        self.pow(&exp)
+    }
+
+    /// A square root, if there is one. p is 3 mod 4, so it is
+    /// `self^((p + 1) / 4) = self^(2^128 - 1)`, and the other root is its
+    /// negation.
+    pub fn sqrt(&self) -> Option<Fe> {
+        let root = self.pow(&u128::MAX.to_le_bytes());
+        if root.mul(&root) == *self {
+            Some(root)
+        } else {
+            None
+        }
+    }
+}
+
+/// Clears the bits RFC 8439 requires to be zero in `r`.
+pub fn clamp_r(r: &[u8]) -> u128 {
+    u128::from_le_bytes(r[..16].try_into().unwrap()) & 0x0ffffffc0ffffffc0ffffffc0fffffff
+}
+
+/// The polynomial part of Poly1305: each 16-byte chunk of `msg`, with a 1
+/// byte appended, is added to the accumulator, which is then multiplied
+/// by `r`. The result is reduced mod p.
+pub fn poly1305_hash(r: u128, msg: &[u8]) -> Fe {
+    let r = Fe::from_u128(r);
+    let mut h = Fe::ZERO;
+    for chunk in msg.chunks(16) {
+        let mut block = chunk.to_vec();
+        block.push(1);
+        h = h.add(&Fe::from_le_bytes(&block)).mul(&r);
+    }
+    h.freeze()
+}
+
+/// Poly1305 under a one-time `key`: `r` (clamped) from the first half and
+/// `s` from the second. The tag is `(hash mod p + s) mod 2^128`.
+pub fn poly1305(key: &[u8; 32], msg: &[u8]) -> [u8; 16] {
+    let r = clamp_r(&key[..16]);
+    let s = u128::from_le_bytes(key[16..].try_into().unwrap());
+    poly1305_hash(r, msg)
+        .low_u128()
+        .wrapping_add(s)
+        .to_le_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_bytes::*;
+
+    fn key(hex: &str) -> [u8; 32] {
+        RawBytes::from_hex(hex).bytes.try_into().unwrap()
+    }
+
+    #[test]
+    fn test_rfc_8439_vector() {
+        // 2.5.2.
+        let k = key("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b");
+        let tag = poly1305(&k, b"Cryptographic Forum Research Group");
+        assert_eq!(
+            RawBytes {
+                bytes: tag.to_vec()
+            }
+            .to_hex(),
+            "a8061dc1305136c6c22b8baf0c0127a9"
+        );
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        // From RFC 8439 A.3: the accumulator reaching p, and the final
+        // addition of s carrying past 2^128.
+        let mut k = [0u8; 32];
+        k[0] = 1;
+        assert_eq!(poly1305(&k, &[0xff; 16]), [0xff; 16]);
+
+        let mut k = [0xffu8; 32];
+        k[..16].fill(0);
+        k[0] = 2;
+        let mut msg = [0u8; 16];
+        msg[0] = 2;
+        let mut expected = [0u8; 16];
+        expected[0] = 3;
+        assert_eq!(poly1305(&k, &msg), expected);
+    }
+
+    #[test]
+    fn test_field_arithmetic() {
+        let x = Fe::from_u128(0x1234_5678_9abc_def0_1122_3344_5566_7788);
+        let y = Fe::from_le_bytes(&[0xff; 17]);
+        assert_eq!(x.mul(&x.invert()), Fe::ONE);
+        assert_eq!(y.mul(&y.invert()), Fe::ONE);
+        assert_eq!(x.add(&y).sub(&y), x);
+        assert_eq!(Fe::ZERO.sub(&x).add(&x), Fe::ZERO);
+
+        let square = x.mul(&x);
+        let root = square.sqrt().unwrap();
+        assert!(root == x || root == Fe::ZERO.sub(&x));
+
+        // p itself is zero, and 2^130 is 5.
+        let mut p = [0xffu8; 17];
+        p[0] = 0xfb;
+        p[16] = 0x03;
+        assert_eq!(Fe::from_le_bytes(&p), Fe::ZERO);
+        let mut two_130 = [0u8; 17];
+        two_130[16] = 4;
+        assert_eq!(Fe::from_le_bytes(&two_130), Fe::from_u128(5));
+        assert_eq!(Fe::TWO_128.high_bits(), 1);
+    }
+}
@@ -0,0 +1,202 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::iv::*;
+use crate::modes::*;
+use crate::oracles::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+use rand::prelude::*;
+
+/// A CBC sender that chains IVs across messages, as SSL 3.0 and TLS 1.0
+/// did: each message's IV is the last ciphertext block of the one before,
+/// so anyone watching the wire knows the next IV before it is used.
+pub struct ChainedIvServer {
+    aes: Aes,
+    last_block: Vec<u8>,
+    secret: Vec<u8>,
+}
+
+impl ChainedIvServer {
+    /// A server that will, on request, send `secret` among whatever else
+    /// it is asked to encrypt.
+    pub fn new<R: Rng>(rng: &mut R, secret: &[u8]) -> Self {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        Self {
+            aes: Aes::new(&key).expect("16 byte key"),
+            last_block: Iv::random(rng, 16).as_bytes().to_vec(),
+            secret: secret.to_vec(),
+        }
+    }
+
+    /// The IV the next message will go out under.
+    pub fn next_iv(&self) -> Iv {
+        Iv::from_bytes(&self.last_block)
+    }
+
+    /// Encrypts `pt` under the next IV, with PKCS#7 padding.
+    pub fn encrypt(&mut self, pt: &[u8]) -> Vec<u8> {
+        let pt = RawBytes { bytes: pt.to_vec() };
+        let ct = cbc_encrypt_with_padding(&self.aes, &self.next_iv(), &pt, &Pkcs7)
+            .expect("padded")
+            .bytes;
+        self.last_block = ct[ct.len() - 16..].to_vec();
+        ct
+    }
+
+    /// Sends the secret, returning `(iv, ciphertext)` as seen on the wire.
+    pub fn send_secret(&mut self) -> (Iv, Vec<u8>) {
+        let iv = self.next_iv();
+        let secret = self.secret.clone();
+        (iv, self.encrypt(&secret))
+    }
+}
+
+impl EncryptionOracle for ChainedIvServer {
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        ChainedIvServer::encrypt(self, input)
+    }
+}
+
+/// Finds which of `candidates` is the plaintext of `target`, an observed
+/// ciphertext block whose previous block (or IV) was `target_prev`.
+///
+/// With the next IV known to be `next_iv`, encrypting
+/// `guess ^ target_prev ^ next_iv` puts `E(guess ^ target_prev)` first in
+/// the ciphertext, which is `target` exactly when the guess is right. Each
+/// guess costs one query, so this only pays off when the plaintext is one
+/// of few values: a PIN, a yes or no, a byte of a cookie. After each query
+/// the next IV is the last block of the ciphertext just returned.
+///
+/// Gives up with `None` if `target`, `target_prev` and `next_iv` aren't
+/// all one block long, or the oracle returns less than a block.
+pub fn recover_chained_iv_block<O, I>(
+    mut oracle: O,
+    next_iv: &Iv,
+    target_prev: &[u8],
+    target: &[u8],
+    candidates: I,
+) -> Option<Vec<u8>>
+where
+    O: EncryptionOracle,
+    I: IntoIterator<Item = Vec<u8>>,
+{
+    let bs = target.len();
+    if bs == 0 || target_prev.len() != bs || next_iv.len() != bs {
+        return None;
+    }
+    let mut next_iv = next_iv.as_bytes().to_vec();
+    for guess in candidates {
+        if guess.len() != bs {
+            continue;
+        }
+        let forged: Vec<u8> = (0..bs)
+            .map(|i| guess[i] ^ target_prev[i] ^ next_iv[i])
+            .collect();
+        let ct = oracle.encrypt(&forged);
+        if ct.len() < bs {
+            return None;
+        }
+        if ct[..bs] == *target {
+            return Some(guess);
+        }
+        next_iv = ct[ct.len() - bs..].to_vec();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pin_block(pin: u32) -> Vec<u8> {
+        Pkcs7
+            .pad(&RawBytes::from_str(&format!("pin={:04}", pin)), 16)
+            .bytes
+    }
+
+    #[test]
+    fn test_recover_pin_through_chained_ivs() {
+        let mut rng = StdRng::seed_from_u64(599);
+        let mut server = ChainedIvServer::new(&mut rng, b"pin=4821");
+        server.encrypt(b"some earlier traffic");
+
+        let (iv, ct) = server.send_secret();
+        let next_iv = Iv::from_bytes(&ct[ct.len() - 16..]);
+        let mut queries = 0;
+        let ans = recover_chained_iv_block(
+            |pt: &[u8]| {
+                queries += 1;
+                server.encrypt(pt)
+            },
+            &next_iv,
+            iv.as_bytes(),
+            &ct[..16],
+            (0..10000).map(pin_block),
+        );
+        assert_eq!(ans, Some(pin_block(4821)));
+        assert_eq!(queries, 4822);
+    }
+
+    #[test]
+    fn test_unpredictable_ivs_defeat_the_guesses() {
+        let mut rng = StdRng::seed_from_u64(599);
+        let aes = Aes::new(b"YELLOW SUBMARINE").unwrap();
+        let secret = RawBytes {
+            bytes: pin_block(4821),
+        };
+        let iv = Iv::random(&mut rng, 16);
+        let ct = cbc_encrypt(&aes, &iv, &secret.bytes).unwrap();
+
+        // The attacker's prediction is stale: every message gets a fresh IV.
+        let ans = recover_chained_iv_block(
+            |pt: &[u8]| {
+                let pt = RawBytes { bytes: pt.to_vec() };
+                cbc_encrypt_with_padding(&aes, &Iv::random(&mut rng, 16), &pt, &Pkcs7)
+                    .unwrap()
+                    .bytes
+            },
+            &Iv::from_bytes(&ct[..16]),
+            iv.as_bytes(),
+            &ct[..16],
+            (4800..4900).map(pin_block),
+        );
+        assert_eq!(ans, None);
+    }
+
+    #[test]
+    fn test_mismatched_lengths_give_up() {
+        let mut rng = StdRng::seed_from_u64(599);
+        let mut server = ChainedIvServer::new(&mut rng, b"pin=4821");
+        let (iv, ct) = server.send_secret();
+        let next_iv = Iv::from_bytes(&ct[ct.len() - 16..]);
+        let guesses = || (4820..4823).map(pin_block);
+
+        let mut oracle = |pt: &[u8]| server.encrypt(pt);
+        let short_prev = &iv.as_bytes()[..8];
+        assert_eq!(
+            recover_chained_iv_block(&mut oracle, &next_iv, short_prev, &ct[..16], guesses()),
+            None
+        );
+        assert_eq!(
+            recover_chained_iv_block(&mut oracle, &next_iv, iv.as_bytes(), &ct[..8], guesses()),
+            None
+        );
+        let short_iv = Iv::from_bytes(&ct[..8]);
+        assert_eq!(
+            recover_chained_iv_block(&mut oracle, &short_iv, iv.as_bytes(), &ct[..16], guesses()),
+            None
+        );
+        // An oracle that returns less than a block.
+        assert_eq!(
+            recover_chained_iv_block(
+                |_: &[u8]| vec![0u8; 4],
+                &next_iv,
+                iv.as_bytes(),
+                &ct[..16],
+                guesses()
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,129 @@
+#![allow(dead_code)]
+use crate::block_cipher::*;
+use crate::kv::*;
+use crate::modes::*;
+use crate::oracles::*;
+use crate::padding::*;
+use crate::raw_bytes::*;
+
+/// The encoded profile for `email`, escaped so the address can't add
+/// fields of its own.
+pub fn profile_for(email: &str) -> String {
+    QUERY.serialize(&[("email", email), ("uid", "10"), ("role", "user")])
+}
+
+/// The challenge 13 server: hands out encrypted profiles and reads them
+/// back, with AES-128-ECB under a key the attacker never sees.
+pub struct ProfileServer {
+    aes: Aes,
+}
+
+impl ProfileServer {
+    pub fn new(key: &[u8; 16]) -> Self {
+        Self {
+            aes: Aes::new(key).expect("16 byte key"),
+        }
+    }
+
+    pub fn encrypt_profile(&self, email: &str) -> Vec<u8> {
+        let profile = RawBytes::from_str(&profile_for(email));
+        ecb_encrypt_with_padding(&self.aes, &profile, &Pkcs7)
+            .expect("padded")
+            .bytes
+    }
+
+    pub fn decrypt_profile(&self, ct: &[u8]) -> anyhow::Result<Vec<(String, String)>> {
+        let ct = RawBytes { bytes: ct.to_vec() };
+        let pt = ecb_decrypt_with_padding(&self.aes, &ct, &Pkcs7)?;
+        QUERY.parse(&pt.to_str())
+    }
+}
+
+/// An email that isn't UTF-8 is read the way a lenient web form would.
+impl EncryptionOracle for &ProfileServer {
+    fn encrypt(&mut self, email: &[u8]) -> Vec<u8> {
+        self.encrypt_profile(&String::from_utf8_lossy(email))
+    }
+}
+
+impl DecryptionOracle<Vec<(String, String)>> for &ProfileServer {
+    fn decrypt(&mut self, ct: &[u8]) -> anyhow::Result<Vec<(String, String)>> {
+        self.decrypt_profile(ct)
+    }
+}
+
+/// The value of the `role` field, if there is one.
+pub fn role(profile: &[(String, String)]) -> Option<&str> {
+    profile
+        .iter()
+        .find(|(k, _)| k == "role")
+        .map(|(_, v)| v.as_str())
+}
+
+/// Forges a profile with `role=admin` from encrypted profiles alone (ECB
+/// cut-and-paste). Two requests:
+///
+/// - an email that pushes `admin` plus a full block of PKCS#7 padding into
+///   a block of its own, giving a valid final block that decrypts to
+///   `admin`;
+/// - an email of the length that ends a block right after `role=`.
+///
+/// The first blocks of the second ciphertext followed by that final block
+/// decrypt to `email=...&uid=10&role=admin`.
+pub fn forge_admin_profile<O: EncryptionOracle>(mut oracle: O) -> Vec<u8> {
+    const BS: usize = 16;
+    let head = "email=".len();
+    let tail = "&uid=10&role=".len();
+
+    let pad = BS - "admin".len();
+    let mut email = "A".repeat(BS - head);
+    email.push_str("admin");
+    email.extend(std::iter::repeat_n(pad as u8 as char, pad));
+    let admin_block = oracle.encrypt(email.as_bytes())[BS..2 * BS].to_vec();
+
+    // Leave the prefix up to `role=` block-aligned.
+    let fill = (BS - (head + tail) % BS) % BS;
+    let mut email = "@bar.com".to_string();
+    while email.len() % BS != fill {
+        email.insert(0, 'f');
+    }
+    let ct = oracle.encrypt(email.as_bytes());
+    let aligned = head + email.len() + tail;
+
+    let mut forged = ct[..aligned].to_vec();
+    forged.extend_from_slice(&admin_block);
+    forged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_for() {
+        assert_eq!(
+            profile_for("foo@bar.com"),
+            "email=foo@bar.com&uid=10&role=user"
+        );
+        assert_eq!(
+            profile_for("foo@bar.com&role=admin"),
+            "email=foo@bar.com%26role%3Dadmin&uid=10&role=user"
+        );
+        let profile = QUERY.parse(&profile_for("foo@bar.com&role=admin")).unwrap();
+        assert_eq!(role(&profile), Some("user"));
+    }
+
+    #[test]
+    fn test_forge_admin_profile() {
+        let server = ProfileServer::new(b"YELLOW SUBMARINE");
+        let honest = server
+            .decrypt_profile(&server.encrypt_profile("foo@bar.com"))
+            .unwrap();
+        assert_eq!(role(&honest), Some("user"));
+
+        let forged = forge_admin_profile(&server);
+        let profile = server.decrypt_profile(&forged).unwrap();
+        assert_eq!(role(&profile), Some("admin"));
+        assert_eq!(profile[1], ("uid".to_string(), "10".to_string()));
+    }
+}
@@ -1,4 +1,5 @@
 #![allow(dead_code)]
+use crate::input::*;
 use itertools::Itertools;
 use std::ops::BitXor;
 
@@ -22,21 +23,30 @@ impl RawBytes {
         let x = str.chars().step_by(2);
         let y = str.chars().skip(1).step_by(2);
 
-        let to_num = |a| -> u8 {
-            if a >= '0' && a <= '9' {
-                a as u8 - '0' as u8
-            } else if a >= 'a' && a <= 'z' {
-                10 + a as u8 - 'a' as u8
+        let to_num = |a: char| -> u8 {
+            if a.is_ascii_digit() {
+                a as u8 - b'0'
+            } else if a.is_ascii_lowercase() {
+                10 + a as u8 - b'a'
             } else {
-                10 + a as u8 - 'A' as u8
+                10 + a as u8 - b'A'
             }
         };
-        let bytes = itertools::zip(x, y)
+        let bytes = std::iter::zip(x, y)
             .map(|(a, b)| 16 * to_num(a) + to_num(b))
             .collect();
         Self { bytes }
     }
 
+    /// Decodes `s` as whatever `detect_encoding` thinks it is.
+    pub fn from_auto(s: &str) -> Self {
+        match detect_encoding(s) {
+            Encoding::Hex => Self::from_hex(&normalize_lines(s).concat()),
+            Encoding::Base64 => Self::from_base64(&normalize_lines(s).concat()),
+            Encoding::Text => Self::from_str(s),
+        }
+    }
+
     pub fn to_str(&self) -> String {
         let s = self.bytes.iter().map(|c| *c as char).join("");
         s
@@ -60,12 +70,12 @@ impl RawBytes {
         let bytes = str
             .chars()
             .map(|c| -> u8 {
-                if c >= 'A' && c <= 'Z' {
-                    c as u8 - 'A' as u8
-                } else if c >= 'a' && c <= 'z' {
-                    c as u8 - 'a' as u8 + 26
-                } else if c >= '0' && c <= '9' {
-                    c as u8 - '0' as u8 + 52
+                if c.is_ascii_uppercase() {
+                    c as u8 - b'A'
+                } else if c.is_ascii_lowercase() {
+                    c as u8 - b'a' + 26
+                } else if c.is_ascii_digit() {
+                    c as u8 - b'0' + 52
                 } else if c == '+' {
                     62
                 } else if c == '/' {
@@ -102,12 +112,12 @@ impl RawBytes {
             padding_len = 3 - padding_len;
         }
 
-        let padding = std::iter::repeat('=').take(padding_len);
+        let padding = std::iter::repeat_n('=', padding_len);
 
         let bits = self
             .bytes
             .iter()
-            .flat_map(|n| byte_to_bits(n))
+            .flat_map(byte_to_bits)
             .chunks(6)
             .into_iter()
             .map(|c| {
@@ -119,19 +129,17 @@ impl RawBytes {
                     multiplier /= 2;
                 }
 
-                let base64_char = if ans < 26 {
-                    (ans + 'A' as u8) as char
+                if ans < 26 {
+                    (ans + b'A') as char
                 } else if ans < 52 {
-                    (ans - 26 + 'a' as u8) as char
+                    (ans - 26 + b'a') as char
                 } else if ans < 62 {
-                    (ans - 52 + '0' as u8) as char
+                    (ans - 52 + b'0') as char
                 } else if ans < 63 {
                     '+'
                 } else {
                     '/'
-                };
-
-                base64_char
+                }
             })
             .chain(padding)
             .collect();
@@ -180,7 +188,7 @@ impl BitXor for RawBytes {
 
 impl PartialEq for RawBytes {
     fn eq(&self, other: &RawBytes) -> bool {
-        return self.bytes == other.bytes;
+        self.bytes == other.bytes
     }
 }
 
@@ -290,6 +298,16 @@ mod tests {
         assert_eq!(rb.to_hex(), s);
     }
 
+    #[test]
+    fn test_from_auto() {
+        let text = "I'm killing your brain like a poisonous mushroom";
+        let rb = RawBytes::from_str(text);
+
+        assert_eq!(RawBytes::from_auto(&rb.to_hex()), rb);
+        assert_eq!(RawBytes::from_auto(&rb.to_base64()), rb);
+        assert_eq!(RawBytes::from_auto(text), rb);
+    }
+
     #[test]
     fn test_xor() {
         let b1 = RawBytes::from_hex("1c0111001f010100061a024b53535009181c");
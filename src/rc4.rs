@@ -0,0 +1,92 @@
+#![allow(dead_code)]
+use crate::keystream::*;
+
+/// RC4, keyed with the usual key schedule and no dropped bytes.
+#[derive(Debug, Clone)]
+pub struct Rc4 {
+    initial: [u8; 256],
+    s: [u8; 256],
+    i: u8,
+    j: u8,
+    pos: u64,
+}
+
+impl Rc4 {
+    pub fn new(key: &[u8]) -> anyhow::Result<Self> {
+        if key.is_empty() || key.len() > 256 {
+            anyhow::bail!("RC4 keys are 1 to 256 bytes, got {}", key.len());
+        }
+
+        let mut s = [0u8; 256];
+        for (i, b) in s.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let mut j = 0u8;
+        for i in 0..256 {
+            j = j.wrapping_add(s[i]).wrapping_add(key[i % key.len()]);
+            s.swap(i, j as usize);
+        }
+
+        Ok(Self {
+            initial: s,
+            s,
+            i: 0,
+            j: 0,
+            pos: 0,
+        })
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        self.i = self.i.wrapping_add(1);
+        self.j = self.j.wrapping_add(self.s[self.i as usize]);
+        self.s.swap(self.i as usize, self.j as usize);
+        self.pos += 1;
+        let t = self.s[self.i as usize].wrapping_add(self.s[self.j as usize]);
+        self.s[t as usize]
+    }
+}
+
+impl KeystreamGenerator for Rc4 {
+    fn fill(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            *b = self.next_byte();
+        }
+    }
+
+    fn seek(&mut self, pos: u64) {
+        if pos < self.pos {
+            self.s = self.initial;
+            self.i = 0;
+            self.j = 0;
+            self.pos = 0;
+        }
+        while self.pos < pos {
+            self.next_byte();
+        }
+    }
+
+    fn position(&self) -> u64 {
+        self.pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_bytes::*;
+
+    #[test]
+    fn test_rc4_vectors() {
+        let cases = [
+            ("Key", "Plaintext", "bbf316e8d940af0ad3"),
+            ("Wiki", "pedia", "1021bf0420"),
+            ("Secret", "Attack at dawn", "45a01f645fc35b383552544b9bf5"),
+        ];
+        for (key, pt, ct) in cases.iter() {
+            let mut rc4 = Rc4::new(key.as_bytes()).unwrap();
+            let ans = stream_apply(&mut rc4, pt.as_bytes());
+            assert_eq!(RawBytes { bytes: ans }.to_hex(), *ct);
+        }
+        assert!(Rc4::new(b"").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+#![allow(dead_code)]
+use crate::histogram::*;
+use crate::keystream::*;
+use crate::oracles::*;
+use crate::raw_bytes::*;
+use crate::rc4::*;
+use rand::prelude::*;
+use rayon::prelude::*;
+
+/// The challenge 56 cookie, base64.
+pub const COOKIE: &str = "QkUgU1VSRSBUTyBEUklOSyBZT1VSIE9WQUxUSU5F";
+
+/// RC4's 16th keystream byte (Z16, counting from one) comes out as 240 a
+/// few percent more often than the 1/256 it should, and Z32 as 224.
+pub const Z16_BIAS: u8 = 0xf0;
+pub const Z32_BIAS: u8 = 0xe0;
+
+/// Requests encrypted in one go on a worker, under keys from one seed.
+const CHUNK: u64 = 1 << 12;
+
+/// The challenge 56 oracle: encrypts `prefix || cookie` under a fresh
+/// 128-bit RC4 key every time. As an `EncryptionOracle` the keys come from
+/// its own generator; `encrypt_with` takes one from the caller, so callers
+/// on different threads can each bring their own.
+#[derive(Debug)]
+pub struct Rc4CookieOracle {
+    cookie: Vec<u8>,
+    rng: StdRng,
+}
+
+impl Rc4CookieOracle {
+    pub fn new(cookie: &[u8], seed: u64) -> Self {
+        Self {
+            cookie: cookie.to_vec(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// The oracle with the challenge's cookie.
+    pub fn challenge(seed: u64) -> Self {
+        Self::new(&RawBytes::from_base64(COOKIE).bytes, seed)
+    }
+
+    pub fn encrypt_with<R: Rng>(&self, prefix: &[u8], rng: &mut R) -> Vec<u8> {
+        let mut key = [0u8; 16];
+        rng.fill_bytes(&mut key);
+        self.encrypt_under(&key, prefix)
+    }
+
+    fn encrypt_under(&self, key: &[u8; 16], prefix: &[u8]) -> Vec<u8> {
+        let mut rc4 = Rc4::new(key).expect("16 byte key");
+        stream_apply(&mut rc4, &[prefix, &self.cookie].concat())
+    }
+}
+
+impl EncryptionOracle for Rc4CookieOracle {
+    fn encrypt(&mut self, prefix: &[u8]) -> Vec<u8> {
+        let mut key = [0u8; 16];
+        self.rng.fill_bytes(&mut key);
+        self.encrypt_under(&key, prefix)
+    }
+}
+
+/// How often each ciphertext byte turned up at indexes 15 and 31, over
+/// `samples` requests (rounded up to whole chunks) with `prefix_len`
+/// bytes in front of the cookie. The requests are spread over rayon's
+/// threads.
+pub fn ciphertext_counts<O>(
+    oracle: &O,
+    prefix_len: usize,
+    samples: u64,
+    seed: u64,
+) -> [Histogram256; 2]
+where
+    O: Fn(&[u8], &mut StdRng) -> Vec<u8> + Sync,
+{
+    let prefix = vec![b'A'; prefix_len];
+    (0..samples.div_ceil(CHUNK))
+        .into_par_iter()
+        .map(|chunk| {
+            let mut rng = StdRng::seed_from_u64(seed ^ (prefix_len as u64) << 48 ^ chunk);
+            let mut counts = [Histogram256::new(), Histogram256::new()];
+            for _ in 0..CHUNK {
+                let ct = oracle(&prefix, &mut rng);
+                for (count, i) in counts.iter_mut().zip([15, 31].iter()) {
+                    if let Some(b) = ct.get(*i) {
+                        count.add(*b);
+                    }
+                }
+            }
+            counts
+        })
+        .reduce(
+            || [Histogram256::new(), Histogram256::new()],
+            |mut a, b| {
+                for (x, y) in a.iter_mut().zip(b.iter()) {
+                    x.merge(y);
+                }
+                a
+            },
+        )
+}
+
+/// The plaintext byte that, XORed with the keystream's favourite `bias`,
+/// gives the commonest ciphertext byte.
+pub fn most_likely_plaintext(counts: &Histogram256, bias: u8) -> u8 {
+    let (commonest, _) = counts
+        .counts()
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, n)| **n)
+        .expect("256 counts");
+    commonest as u8 ^ bias
+}
+
+/// The challenge 56 attack: recovers the cookie through `oracle` from
+/// RC4's single-byte biases. Each prefix length from 0 to 15 slides one
+/// cookie byte under Z16 and another under Z32, and `samples` requests
+/// per prefix vote on them; about 2^24 makes every byte come out right
+/// for the real cipher. The cookie may be at most 32 bytes.
+pub fn recover_cookie<O>(oracle: O, samples: u64, seed: u64) -> anyhow::Result<Vec<u8>>
+where
+    O: Fn(&[u8], &mut StdRng) -> Vec<u8> + Sync,
+{
+    let len = oracle(b"", &mut StdRng::seed_from_u64(seed)).len();
+    if len > 32 {
+        anyhow::bail!("a {} byte cookie reaches past Z32", len);
+    }
+
+    let mut cookie = vec![0u8; len];
+    for prefix_len in 0..16 {
+        let [z16, z32] = ciphertext_counts(&oracle, prefix_len, samples, seed);
+        if let Some(b) = cookie.get_mut(15 - prefix_len) {
+            *b = most_likely_plaintext(&z16, Z16_BIAS);
+        }
+        if let Some(b) = cookie.get_mut(31 - prefix_len) {
+            *b = most_likely_plaintext(&z32, Z32_BIAS);
+        }
+    }
+    Ok(cookie)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oracle() {
+        let mut oracle = Rc4CookieOracle::challenge(56);
+        let mut rng = StdRng::seed_from_u64(56);
+        assert_eq!(oracle.encrypt_with(b"AAA", &mut rng).len(), 33);
+        assert_ne!(
+            oracle.encrypt_with(b"", &mut rng),
+            oracle.encrypt_with(b"", &mut rng)
+        );
+        assert_eq!(oracle.encrypt(b"AAA").len(), 33);
+        assert_ne!(oracle.encrypt(b""), oracle.encrypt(b""));
+        assert_eq!(
+            RawBytes::from_base64(COOKIE).to_str(),
+            "BE SURE TO DRINK YOUR OVALTINE"
+        );
+    }
+
+    #[test]
+    fn test_recover_cookie_from_biased_keystream() {
+        // The real biases need millions of samples per byte, so here a
+        // keystream leans on the same bytes one time in eight.
+        let cookie = b"BE SURE TO DRINK YOUR OVALTINE";
+        let oracle = |prefix: &[u8], rng: &mut StdRng| {
+            let mut ks = vec![0u8; 64];
+            rng.fill_bytes(&mut ks);
+            if rng.random_range(0..8) == 0 {
+                ks[15] = Z16_BIAS;
+            }
+            if rng.random_range(0..8) == 0 {
+                ks[31] = Z32_BIAS;
+            }
+            [prefix, &cookie[..]]
+                .concat()
+                .iter()
+                .zip(&ks)
+                .map(|(p, k)| p ^ k)
+                .collect::<Vec<u8>>()
+        };
+        assert_eq!(recover_cookie(oracle, 4096, 56).unwrap(), cookie);
+    }
+
+    #[test]
+    #[ignore = "2^28 RC4 encryptions; run with --release"]
+    fn test_recover_cookie_from_real_rc4() {
+        let oracle = Rc4CookieOracle::challenge(56);
+        let cookie = recover_cookie(
+            |p: &[u8], rng: &mut StdRng| oracle.encrypt_with(p, rng),
+            1 << 24,
+            56,
+        )
+        .unwrap();
+        assert_eq!(cookie, RawBytes::from_base64(COOKIE).bytes);
+    }
+}
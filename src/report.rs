@@ -0,0 +1,108 @@
+#![allow(dead_code)]
+use crate::challenges::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Instant;
+
+pub const DEFAULT_REPORT_PATH: &str = "target/cryptopals-report.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChallengeReport {
+    pub set: u8,
+    pub number: u8,
+    pub name: String,
+    pub passed: bool,
+    pub error: Option<String>,
+    pub wall_time_ms: f64,
+    pub oracle_queries: u64,
+    pub bytes_processed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub challenges: Vec<ChallengeReport>,
+}
+
+impl Report {
+    pub fn all_passed(&self) -> bool {
+        self.challenges.iter().all(|c| c.passed)
+    }
+
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+pub fn run_challenge(challenge: &Challenge) -> ChallengeReport {
+    let mut meter = Meter::new();
+    let start = Instant::now();
+    let ans = (challenge.run)(&mut meter);
+    let wall_time_ms = start.elapsed().as_secs_f64() * 1e3;
+
+    ChallengeReport {
+        set: challenge.set,
+        number: challenge.number,
+        name: challenge.name.to_string(),
+        passed: ans.is_ok(),
+        error: ans.err().map(|e| e.to_string()),
+        wall_time_ms,
+        oracle_queries: meter.queries,
+        bytes_processed: meter.bytes,
+    }
+}
+
+pub fn run_report(challenges: &[Challenge]) -> Report {
+    Report {
+        challenges: challenges.iter().map(run_challenge).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failing(meter: &mut Meter) -> anyhow::Result<String> {
+        let mut oracle = meter.oracle(|input: &[u8]| input.to_vec());
+        oracle(b"YELLOW SUBMARINE");
+        anyhow::bail!("nope")
+    }
+
+    #[test]
+    fn test_run_report() {
+        let challenges = vec![Challenge {
+            set: 0,
+            number: 0,
+            name: "failing",
+            run: failing,
+        }];
+
+        let report = run_report(&challenges);
+        assert!(!report.all_passed());
+
+        let c = &report.challenges[0];
+        assert_eq!(c.error.as_deref(), Some("nope"));
+        assert_eq!((c.oracle_queries, c.bytes_processed), (1, 32));
+
+        // Unique per run, so concurrent runs don't trample each other.
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!(
+            "cryptopals-report-test-{}-{}.json",
+            std::process::id(),
+            nanos
+        ));
+        report.write(&path).unwrap();
+        assert_eq!(Report::read(&path).unwrap(), report);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+#![allow(dead_code)]
+use crate::merkle_damgard::*;
+use crate::modes::*;
+
+pub const BLOCK_SIZE: usize = 64;
+pub const DIGEST_SIZE: usize = 20;
+
+const INITIAL_STATE: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+/// The padding appended to a `message_len`-byte message: a 1 bit, zeros
+/// up to 56 bytes mod 64, then the length in bits as a 64-bit big-endian
+/// number.
+pub fn md_padding(message_len: u64) -> Vec<u8> {
+    crate::merkle_damgard::md_padding(message_len, BLOCK_SIZE, Endian::Big)
+}
+
+/// The compression function: 80 steps over the expanded 64-byte block,
+/// added back into `state`.
+pub fn compress(state: &mut [u32; 5], block: &[u8]) {
+    let mut w = [0u32; 80];
+    for (i, chunk) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, wi) in w.iter().enumerate() {
+        let (f, k) = match i / 20 {
+            0 => ((b & c) | (!b & d), 0x5a827999),
+            1 => (b ^ c ^ d, 0x6ed9eba1),
+            2 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+            _ => (b ^ c ^ d, 0xca62c1d6),
+        };
+        let t = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(*wi);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = t;
+    }
+    for (s, x) in state.iter_mut().zip([a, b, c, d, e]) {
+        *s = s.wrapping_add(x);
+    }
+}
+
+/// The SHA-1 compression function, for `MerkleDamgard`.
+#[derive(Debug, Clone)]
+pub struct Sha1Compression;
+
+impl Compression for Sha1Compression {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const OUTPUT_SIZE: usize = DIGEST_SIZE;
+    const ENDIANNESS: Endian = Endian::Big;
+    type State = [u32; 5];
+
+    fn initial_state() -> [u32; 5] {
+        INITIAL_STATE
+    }
+
+    fn compress(state: &mut [u32; 5], block: &[u8]) {
+        compress(state, block);
+    }
+
+    fn state_to_bytes(state: &[u32; 5]) -> Vec<u8> {
+        words_to_bytes(state, Endian::Big)
+    }
+
+    fn state_from_bytes(bytes: &[u8]) -> [u32; 5] {
+        let mut state = [0u32; 5];
+        state.copy_from_slice(&words_from_bytes(&bytes[..DIGEST_SIZE], Endian::Big));
+        state
+    }
+}
+
+/// Incremental SHA-1 (FIPS 180-4).
+pub type Sha1 = MerkleDamgard<Sha1Compression>;
+
+pub fn sha1(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut h = Sha1::new();
+    h.update(data);
+    let mut out = [0u8; DIGEST_SIZE];
+    out.copy_from_slice(&h.finalize());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_nist_vectors() {
+        assert_eq!(hex(&sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(&sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hex(&sha1(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+
+        let mut h = Sha1::new();
+        for _ in 0..1000 {
+            h.update(&[b'a'; 1000]);
+        }
+        assert_eq!(
+            hex(&h.finalize()),
+            "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+        );
+    }
+
+    #[test]
+    fn test_incremental_matches_one_shot() {
+        let data: Vec<u8> = (0..300u32).map(|i| (i * 7) as u8).collect();
+        for split in [0, 1, 55, 56, 63, 64, 65, 128, 299, 300] {
+            let mut h = Sha1::new();
+            h.update(&data[..split]);
+            h.update(&data[split..]);
+            assert_eq!(h.finalize(), sha1(&data), "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        for len in 0..200u64 {
+            let pad = md_padding(len);
+            assert_eq!((len as usize + pad.len()) % BLOCK_SIZE, 0);
+            assert!(pad.len() >= 9 && pad.len() <= 72);
+        }
+        assert_eq!(md_padding(55).len(), 9);
+        assert_eq!(md_padding(56).len(), 72);
+    }
+}
@@ -0,0 +1,93 @@
+#![allow(dead_code)]
+use crate::oracles::*;
+use crate::sha1::*;
+use rand::prelude::*;
+
+/// The challenge 28 MAC: SHA-1 over the key followed by the message. It
+/// stops anyone without the key from making a tag for a message of their
+/// choosing, except by extending one they already have (challenge 29).
+pub fn sha1_mac(key: &[u8], message: &[u8]) -> [u8; DIGEST_SIZE] {
+    let mut h = Sha1::new();
+    h.update(key);
+    h.update(message);
+    let mut out = [0u8; DIGEST_SIZE];
+    out.copy_from_slice(&h.finalize());
+    out
+}
+
+pub fn sha1_mac_verify(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    let expected = sha1_mac(key, message);
+    mac.len() == expected.len()
+        && expected
+            .iter()
+            .zip(mac)
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// A server holding a random secret-prefix key of 8 to 32 bytes: it hands
+/// out tags for messages it likes and checks tags on everything else.
+pub struct Sha1MacServer {
+    key: Vec<u8>,
+}
+
+impl Sha1MacServer {
+    pub fn new<R: Rng>(rng: &mut R) -> Self {
+        let mut key = vec![0u8; rng.random_range(8..=32)];
+        rng.fill_bytes(&mut key);
+        Self { key }
+    }
+
+    pub fn sign(&self, message: &[u8]) -> [u8; DIGEST_SIZE] {
+        sha1_mac(&self.key, message)
+    }
+
+    /// For checking guesses in tests.
+    pub fn key_len(&self) -> usize {
+        self.key.len()
+    }
+}
+
+impl MacVerifier for &Sha1MacServer {
+    fn verify(&mut self, message: &[u8], mac: &[u8]) -> bool {
+        sha1_mac_verify(&self.key, message, mac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mac_is_sha1_of_key_and_message() {
+        assert_eq!(
+            sha1_mac(b"YELLOW ", b"SUBMARINE"),
+            sha1(b"YELLOW SUBMARINE")
+        );
+    }
+
+    #[test]
+    fn test_tampering_fails_verification() {
+        let mut rng = StdRng::seed_from_u64(612);
+        let server = Sha1MacServer::new(&mut rng);
+        let message =
+            b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+        let mac = server.sign(message);
+        let mut verifier = &server;
+        assert!(verifier.verify(message, &mac));
+
+        let mut tampered = message.to_vec();
+        tampered[10] ^= 1;
+        assert!(!verifier.verify(&tampered, &mac));
+
+        let mut bad_mac = mac;
+        bad_mac[19] ^= 0x80;
+        assert!(!verifier.verify(message, &bad_mac));
+        assert!(!verifier.verify(message, &mac[..19]));
+
+        // Without the key, a tag can't be made from scratch.
+        assert!(!verifier.verify(message, &sha1(message)));
+        let other = Sha1MacServer::new(&mut rng);
+        assert!(!verifier.verify(message, &other.sign(message)));
+    }
+}
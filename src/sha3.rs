@@ -0,0 +1,283 @@
+#![allow(dead_code)]
+use crate::digest::*;
+
+/// Keccak-f[1600]'s round constants, XORed into lane (0, 0) by ι.
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+/// ρ's rotation of lane (x, y), at `x + 5 * y`.
+const ROTATIONS: [u32; 25] = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+/// The Keccak-f[1600] permutation (FIPS 202, 3.3): 24 rounds of θ, ρ, π,
+/// χ and ι over 25 64-bit lanes, lane (x, y) at `x + 5 * y`.
+pub fn keccak_f1600(a: &mut [u64; 25]) {
+    for rc in ROUND_CONSTANTS.iter() {
+        // θ: each bit picks up the parity of two nearby columns.
+        let mut c = [0u64; 5];
+        for (x, c) in c.iter_mut().enumerate() {
+            *c = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+
+        // ρ and π: rotate each lane and move (x, y) to (y, 2x + 3y).
+        let mut b = [0u64; 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = a[x + 5 * y].rotate_left(ROTATIONS[x + 5 * y]);
+            }
+        }
+
+        // χ, the only nonlinear step, along each row.
+        for y in 0..5 {
+            for x in 0..5 {
+                a[x + 5 * y] = b[x + 5 * y] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+            }
+        }
+
+        a[0] ^= rc;
+    }
+}
+
+/// XORs a block into the start of the state, little-endian, and permutes.
+fn absorb(state: &mut [u64; 25], block: &[u8]) {
+    for (lane, bytes) in state.iter_mut().zip(block.chunks(8)) {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(bytes);
+        *lane ^= u64::from_le_bytes(word);
+    }
+    keccak_f1600(state);
+}
+
+/// The sponge construction over Keccak-f[1600]: input is XORed into the
+/// first `rate` bytes of the state a block at a time, with the permutation
+/// in between, and output squeezed out of the same bytes. The other
+/// `200 - rate` bytes, the capacity, are never given out, which is why
+/// there is no state in a digest to carry on from, and no length
+/// extension.
+#[derive(Debug, Clone)]
+pub struct Keccak {
+    state: [u64; 25],
+    rate: usize,
+    /// The domain bits and the first padding bit, as one byte.
+    suffix: u8,
+    buffer: Vec<u8>,
+}
+
+impl Keccak {
+    pub fn new(rate: usize, suffix: u8) -> Self {
+        assert!(
+            rate > 0 && rate < 200 && rate.is_multiple_of(8),
+            "bad rate {}",
+            rate
+        );
+        Self {
+            state: [0; 25],
+            rate,
+            suffix,
+            buffer: Vec::with_capacity(rate),
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let take = (self.rate - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buffer.len() == self.rate {
+                absorb(&mut self.state, &self.buffer);
+                self.buffer.clear();
+            }
+        }
+    }
+
+    /// Pads with the suffix and pad10*1, then squeezes out `len` bytes.
+    pub fn squeeze(mut self, len: usize) -> Vec<u8> {
+        let mut block = std::mem::take(&mut self.buffer);
+        block.push(self.suffix);
+        block.resize(self.rate, 0);
+        block[self.rate - 1] |= 0x80;
+        absorb(&mut self.state, &block);
+
+        let mut out = Vec::with_capacity(len);
+        loop {
+            for lane in &self.state[..self.rate / 8] {
+                out.extend_from_slice(&lane.to_le_bytes());
+            }
+            if out.len() >= len {
+                out.truncate(len);
+                return out;
+            }
+            keccak_f1600(&mut self.state);
+        }
+    }
+}
+
+pub const SHA3_256_RATE: usize = 136;
+pub const SHA3_256_DIGEST_SIZE: usize = 32;
+pub const SHAKE128_RATE: usize = 168;
+
+/// SHA3-256 (FIPS 202): Keccak with a 512-bit capacity and the `01`
+/// domain bits.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone)]
+pub struct Sha3_256(Keccak);
+
+impl Digest for Sha3_256 {
+    fn new() -> Self {
+        Self(Keccak::new(SHA3_256_RATE, 0x06))
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        self.0.squeeze(SHA3_256_DIGEST_SIZE)
+    }
+
+    fn output_size(&self) -> usize {
+        SHA3_256_DIGEST_SIZE
+    }
+
+    /// The rate, which is what HMAC pads its key to.
+    fn block_size(&self) -> usize {
+        SHA3_256_RATE
+    }
+}
+
+pub fn sha3_256(data: &[u8]) -> [u8; SHA3_256_DIGEST_SIZE] {
+    let mut out = [0u8; SHA3_256_DIGEST_SIZE];
+    out.copy_from_slice(&Sha3_256::digest(data));
+    out
+}
+
+/// SHAKE128 (FIPS 202): an extendable-output function with a 256-bit
+/// capacity and the `1111` domain bits; ask for as many bytes as needed.
+#[derive(Debug, Clone)]
+pub struct Shake128(Keccak);
+
+impl Default for Shake128 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shake128 {
+    pub fn new() -> Self {
+        Self(Keccak::new(SHAKE128_RATE, 0x1f))
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    pub fn finalize(self, len: usize) -> Vec<u8> {
+        self.0.squeeze(len)
+    }
+}
+
+pub fn shake128(data: &[u8], len: usize) -> Vec<u8> {
+    let mut h = Shake128::new();
+    h.update(data);
+    h.finalize(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hmac::*;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_sha3_256_vectors() {
+        let repeated = vec![0xa3u8; 200];
+        for (input, expected) in [
+            (
+                &b""[..],
+                "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a",
+            ),
+            (
+                b"abc",
+                "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+            ),
+            (
+                &repeated,
+                "79f38adec5c20307a98ef76e8324afbfd46cfd81b22e3973c65fa1bd9de31787",
+            ),
+        ] {
+            assert_eq!(hex(&sha3_256(input)), expected);
+        }
+    }
+
+    #[test]
+    fn test_shake128_vectors() {
+        assert_eq!(
+            hex(&shake128(b"", 32)),
+            "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26"
+        );
+        assert_eq!(
+            hex(&shake128(b"abc", 32)),
+            "5881092dd818bf5cf8a3ddb793fbcba74097d5c526a6d35f97b83351940f2cc8"
+        );
+        // Squeezing past one block of output.
+        let long = shake128(&[0xa3; 200], 200);
+        assert_eq!(
+            hex(&long[168..]),
+            "09ba9e94f7266122ed7ac24e5e266c42a82fa1bbefb7b8db0066e16a85e0493f"
+        );
+        assert_eq!(long[..32], shake128(&[0xa3; 200], 32)[..]);
+    }
+
+    #[test]
+    fn test_incremental_matches_one_shot() {
+        let data: Vec<u8> = (0..400u32).map(|i| (i * 7) as u8).collect();
+        for split in [0, 1, 135, 136, 137, 272, 399] {
+            let mut h = Sha3_256::new();
+            h.update(&data[..split]);
+            h.update(&data[split..]);
+            assert_eq!(h.finalize(), sha3_256(&data), "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_hmac_sha3_256() {
+        assert_eq!(
+            hex(&hmac::<Sha3_256>(
+                b"key",
+                b"The quick brown fox jumps over the lazy dog"
+            )),
+            "8c6e0683409427f8931711b10ca92a506eb1fafa48fadd66d76126f47ac2c333"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+#![allow(dead_code)]
+use crate::spn::*;
+use rand::prelude::*;
+
+/// `ddt[dx][dy]`: how many of the 16 inputs `x` have
+/// `sbox[x] ^ sbox[x ^ dx] == dy`.
+pub fn difference_distribution_table(sbox: &[u8; 16]) -> [[u32; 16]; 16] {
+    let mut ddt = [[0u32; 16]; 16];
+    for (dx, row) in ddt.iter_mut().enumerate() {
+        for x in 0..16 {
+            let dy = sbox[x] ^ sbox[x ^ dx];
+            row[dy as usize] += 1;
+        }
+    }
+    ddt
+}
+
+fn parity(x: u16) -> u32 {
+    x.count_ones() & 1
+}
+
+/// `lat[a][b]`: how many of the 16 inputs `x` have the parity of `x & a`
+/// equal to that of `sbox[x] & b`, less 8. Divided by 16 that is the bias
+/// of the linear approximation.
+pub fn linear_approximation_table(sbox: &[u8; 16]) -> [[i32; 16]; 16] {
+    let mut lat = [[0i32; 16]; 16];
+    for (a, row) in lat.iter_mut().enumerate() {
+        for (b, entry) in row.iter_mut().enumerate() {
+            let agree = (0..16)
+                .filter(|&x| parity((x & a) as u16) == parity((sbox[x] as usize & b) as u16))
+                .count();
+            *entry = agree as i32 - 8;
+        }
+    }
+    lat
+}
+
+/// The nibbles of the last-round key that an attack on `u4` touches: those
+/// where `u4` is nonzero.
+fn active_mask(u4: u16) -> u16 {
+    (0..4)
+        .filter(|i| (u4 >> (4 * i)) & 0xf != 0)
+        .fold(0, |acc, i| acc | (0xf << (4 * i)))
+}
+
+/// Every value of the last-round key bits under `mask`.
+fn candidate_subkeys(mask: u16) -> Vec<u16> {
+    let positions: Vec<u16> = (0..16).filter(|b| (mask >> b) & 1 == 1).collect();
+    (0..1u32 << positions.len())
+        .map(|i| {
+            positions
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (j, b)| acc | ((((i >> j) & 1) as u16) << b))
+        })
+        .collect()
+}
+
+/// Undoes the final key mix and the last S-box layer under a guessed
+/// last-round key, giving the input to the last S-boxes.
+fn partial_decrypt(inv_sbox: &[u8; 16], ct: u16, subkey: u16) -> u16 {
+    substitute(inv_sbox, ct ^ subkey)
+}
+
+/// Differential cryptanalysis of the last round. `encrypt` is a chosen
+/// plaintext oracle. A three-round characteristic takes the plaintext
+/// difference `dp` to the difference `du4` at the input to the last
+/// S-boxes with some probability; for each of `pairs` random pairs with
+/// difference `dp`, every guess at the last-round key bits under the
+/// S-boxes `du4` touches is tried, and the guess that sees `du4` most
+/// often wins. Pairs whose ciphertexts differ under the other S-boxes
+/// can't have followed the characteristic and are thrown out first.
+///
+/// Returns the recovered key bits, with zeros under the other S-boxes.
+pub fn differential_attack<F: FnMut(u16) -> u16, R: Rng>(
+    mut encrypt: F,
+    sbox: &[u8; 16],
+    dp: u16,
+    du4: u16,
+    pairs: usize,
+    rng: &mut R,
+) -> u16 {
+    let inv = invert_sbox(sbox);
+    let mask = active_mask(du4);
+    let right_pairs: Vec<(u16, u16)> = (0..pairs)
+        .map(|_| {
+            let p: u16 = rng.random();
+            (encrypt(p), encrypt(p ^ dp))
+        })
+        .filter(|(c1, c2)| (c1 ^ c2) & !mask == 0)
+        .collect();
+
+    candidate_subkeys(mask)
+        .into_iter()
+        .max_by_key(|&k| {
+            right_pairs
+                .iter()
+                .filter(|(c1, c2)| {
+                    partial_decrypt(&inv, *c1, k) ^ partial_decrypt(&inv, *c2, k) == du4
+                })
+                .count()
+        })
+        .expect("at least one candidate")
+}
+
+/// Linear cryptanalysis of the last round, from known plaintexts. A
+/// three-round approximation says the parity of the plaintext bits under
+/// `in_mask` matches that of the last S-box inputs under `out_mask` with
+/// probability noticeably away from 1/2. Under the right guess for the
+/// last-round key bits it does; under wrong ones the partial decryption is
+/// closer to random and the bias shrinks. Returns the guess with the
+/// largest bias, zeros elsewhere.
+pub fn linear_attack(known: &[(u16, u16)], sbox: &[u8; 16], in_mask: u16, out_mask: u16) -> u16 {
+    let inv = invert_sbox(sbox);
+    let half = known.len() as i64 / 2;
+    candidate_subkeys(active_mask(out_mask))
+        .into_iter()
+        .max_by_key(|&k| {
+            let holds = known
+                .iter()
+                .filter(|(p, c)| {
+                    parity(p & in_mask) == parity(partial_decrypt(&inv, *c, k) & out_mask)
+                })
+                .count() as i64;
+            (holds - half).abs()
+        })
+        .expect("at least one candidate")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Heys' characteristic: S12 takes B to 2 (8/16), then S23 takes 4 to
+    // 6 (6/16), then S32 and S33 take 2 to 5 (6/16 each), so the inputs to
+    // S42 and S44 differ by 6 with probability 27/1024.
+    const DP: u16 = 0x0b00;
+    const DU4: u16 = 0x0606;
+
+    // Heys' approximation: from the plaintext bits 5, 7 and 8 to the inputs
+    // of S42 and S44 at bits 6, 8, 14 and 16, with bias -1/32.
+    const IN_MASK: u16 = 0x0b00;
+    const OUT_MASK: u16 = 0x0505;
+
+    #[test]
+    fn test_tables() {
+        let ddt = difference_distribution_table(&HEYS_SBOX);
+        assert_eq!(ddt[0][0], 16);
+        assert_eq!(ddt[0xb][0x2], 8);
+        assert_eq!(ddt[0x4][0x6], 6);
+        assert_eq!(ddt[0x2][0x5], 6);
+        // Every row accounts for all 16 inputs, in pairs.
+        for row in &ddt {
+            assert_eq!(row.iter().sum::<u32>(), 16);
+            assert!(row.iter().all(|n| n % 2 == 0));
+        }
+
+        let lat = linear_approximation_table(&HEYS_SBOX);
+        assert_eq!(lat[0][0], 8);
+        assert_eq!(lat[0xb][0x4], 4);
+        assert_eq!(lat[0x4][0x5], -4);
+        assert!(lat[0][1..].iter().all(|x| *x == 0));
+    }
+
+    #[test]
+    fn test_differential_attack_recovers_last_round_key_bits() {
+        let mut rng = StdRng::seed_from_u64(610);
+        let spn = Spn::from_round_keys(rng.random());
+        let found = differential_attack(
+            |p: u16| spn.encrypt_u16(p),
+            &HEYS_SBOX,
+            DP,
+            DU4,
+            5000,
+            &mut rng,
+        );
+        assert_eq!(found, spn.round_keys()[ROUNDS] & 0x0f0f);
+    }
+
+    #[test]
+    fn test_linear_attack_recovers_last_round_key_bits() {
+        let mut rng = StdRng::seed_from_u64(610);
+        let spn = Spn::from_round_keys(rng.random());
+        let known: Vec<(u16, u16)> = (0..10000)
+            .map(|_| {
+                let p: u16 = rng.random();
+                (p, spn.encrypt_u16(p))
+            })
+            .collect();
+        let found = linear_attack(&known, &HEYS_SBOX, IN_MASK, OUT_MASK);
+        assert_eq!(found, spn.round_keys()[ROUNDS] & 0x0f0f);
+    }
+}
@@ -0,0 +1,20 @@
+//! Helpers shared by the unit tests, for writing test vectors as hex.
+use crate::raw_bytes::*;
+
+pub fn hex(bytes: &[u8]) -> String {
+    RawBytes {
+        bytes: bytes.to_vec(),
+    }
+    .to_hex()
+}
+
+pub fn unhex(s: &str) -> Vec<u8> {
+    RawBytes::from_hex(s).bytes
+}
+
+/// A 16-byte block written in hex.
+pub fn block(s: &str) -> [u8; 16] {
+    let mut b = [0u8; 16];
+    b.copy_from_slice(&unhex(s));
+    b
+}
@@ -0,0 +1,236 @@
+#![allow(dead_code)]
+use std::time::{Duration, Instant};
+
+/// A monotonic tick source for timing side channels.
+pub trait Timer {
+    fn name(&self) -> &'static str;
+
+    /// The raw counter; only differences between readings are meaningful.
+    fn now(&self) -> u64;
+
+    fn ns_per_tick(&self) -> f64;
+
+    fn elapsed_ns(&self, start: u64) -> f64 {
+        self.now().wrapping_sub(start) as f64 * self.ns_per_tick()
+    }
+
+    /// Runs `f` and returns its result with the time it took in nanoseconds.
+    fn measure<T, F: FnOnce() -> T>(&self, f: F) -> (T, f64)
+    where
+        Self: Sized,
+    {
+        let start = self.now();
+        let ans = f();
+        (ans, self.elapsed_ns(start))
+    }
+}
+
+/// Estimates the tick period of `now` against `Instant` over `window`.
+pub fn calibrate<F: Fn() -> u64>(now: F, window: Duration) -> f64 {
+    let start = Instant::now();
+    let ticks = now();
+    while start.elapsed() < window {
+        std::hint::spin_loop();
+    }
+    let ticks = now().wrapping_sub(ticks).max(1);
+    start.elapsed().as_nanos() as f64 / ticks as f64
+}
+
+/// Smallest non-zero difference between consecutive readings, in
+/// nanoseconds: a rough lower bound on the jitter a measurement will have.
+pub fn resolution_ns(timer: &dyn Timer) -> f64 {
+    let mut best = u64::MAX;
+    for _ in 0..1000 {
+        let a = timer.now();
+        let mut b = timer.now();
+        while b == a {
+            b = timer.now();
+        }
+        best = best.min(b.wrapping_sub(a));
+    }
+    best as f64 * timer.ns_per_tick()
+}
+
+pub struct InstantTimer {
+    origin: Instant,
+}
+
+impl InstantTimer {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for InstantTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer for InstantTimer {
+    fn name(&self) -> &'static str {
+        "instant"
+    }
+
+    fn now(&self) -> u64 {
+        self.origin.elapsed().as_nanos() as u64
+    }
+
+    fn ns_per_tick(&self) -> f64 {
+        1.0
+    }
+}
+
+/// The x86 time-stamp counter. Only trustworthy when the CPU advertises an
+/// invariant TSC, which `best_timer` checks.
+#[cfg(target_arch = "x86_64")]
+pub struct TscTimer {
+    ns_per_tick: f64,
+}
+
+#[cfg(target_arch = "x86_64")]
+impl TscTimer {
+    pub fn new() -> Self {
+        let ns_per_tick = calibrate(Self::read, Duration::from_millis(20));
+        Self { ns_per_tick }
+    }
+
+    fn read() -> u64 {
+        unsafe { std::arch::x86_64::_rdtsc() }
+    }
+
+    pub fn is_invariant() -> bool {
+        use std::arch::x86_64::__cpuid;
+
+        let max_extended = __cpuid(0x8000_0000).eax;
+        max_extended >= 0x8000_0007 && __cpuid(0x8000_0007).edx & (1 << 8) != 0
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Default for TscTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Timer for TscTimer {
+    fn name(&self) -> &'static str {
+        "rdtsc"
+    }
+
+    fn now(&self) -> u64 {
+        Self::read()
+    }
+
+    fn ns_per_tick(&self) -> f64 {
+        self.ns_per_tick
+    }
+}
+
+/// The aarch64 virtual counter, whose frequency the CPU reports directly.
+#[cfg(target_arch = "aarch64")]
+pub struct CntvctTimer {
+    ns_per_tick: f64,
+}
+
+#[cfg(target_arch = "aarch64")]
+impl CntvctTimer {
+    pub fn new() -> Self {
+        let freq: u64;
+        unsafe { std::arch::asm!("mrs {}, cntfrq_el0", out(reg) freq) };
+
+        let ns_per_tick = if freq > 0 {
+            1e9 / freq as f64
+        } else {
+            calibrate(Self::read, Duration::from_millis(20))
+        };
+        Self { ns_per_tick }
+    }
+
+    fn read() -> u64 {
+        let ticks: u64;
+        unsafe { std::arch::asm!("isb", "mrs {}, cntvct_el0", out(reg) ticks) };
+        ticks
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+impl Default for CntvctTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+impl Timer for CntvctTimer {
+    fn name(&self) -> &'static str {
+        "cntvct"
+    }
+
+    fn now(&self) -> u64 {
+        Self::read()
+    }
+
+    fn ns_per_tick(&self) -> f64 {
+        self.ns_per_tick
+    }
+}
+
+/// The finest reliable timer for this machine, falling back to `Instant`.
+pub fn best_timer() -> Box<dyn Timer> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if TscTimer::is_invariant() {
+            return Box::new(TscTimer::new());
+        }
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        return Box::new(CntvctTimer::new());
+    }
+
+    #[allow(unreachable_code)]
+    Box::new(InstantTimer::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_sleep(timer: &dyn Timer) {
+        let start = timer.now();
+        std::thread::sleep(Duration::from_millis(5));
+        let ns = timer.elapsed_ns(start);
+
+        assert!(ns > 4e6 && ns < 5e8, "{} measured {}ns", timer.name(), ns);
+    }
+
+    #[test]
+    fn test_instant_timer() {
+        let timer = InstantTimer::new();
+        check_sleep(&timer);
+
+        let (ans, ns) = timer.measure(|| 6 * 7);
+        assert_eq!(ans, 42);
+        assert!(ns >= 0.0);
+    }
+
+    #[test]
+    fn test_best_timer() {
+        let timer = best_timer();
+        check_sleep(timer.as_ref());
+        assert!(resolution_ns(timer.as_ref()) < 1e6);
+    }
+
+    #[test]
+    fn test_calibrate() {
+        let timer = InstantTimer::new();
+        let ns_per_tick = calibrate(|| timer.now(), Duration::from_millis(5));
+        assert!((ns_per_tick - 1.0).abs() < 0.1);
+    }
+}
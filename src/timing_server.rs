@@ -0,0 +1,299 @@
+#![allow(dead_code)]
+use crate::hmac::*;
+use crate::kv::*;
+use crate::raw_bytes::*;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The challenge 31 comparison: a byte at a time, giving up at the first
+/// difference, and sleeping `delay` after every byte that matches. How
+/// long it takes says how many leading bytes of `given` are right.
+pub fn insecure_compare(expected: &[u8], given: &[u8], delay: Duration) -> bool {
+    for i in 0..expected.len().max(given.len()) {
+        if expected.get(i) != given.get(i) {
+            return false;
+        }
+        thread::sleep(delay);
+    }
+    true
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(RawBytes::from_hex(s).bytes)
+}
+
+/// A minimal HTTP server on localhost answering
+/// `GET /test?file=...&signature=...`: 200 if the hex signature is the
+/// HMAC-SHA1 of the file name under the server's key, 500 if not, and 400
+/// for anything it doesn't understand. Signatures go through
+/// `insecure_compare`. Requests are handled one at a time on a single
+/// thread, so timings aren't muddied by other requests.
+///
+/// The server stops when dropped.
+pub struct TimingServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TimingServer {
+    /// Listens on an ephemeral port on 127.0.0.1.
+    pub fn start(key: &[u8], delay: Duration) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let key = key.to_vec();
+
+        let handle = {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        // A client hanging up early is its own problem.
+                        let _ = handle_connection(stream, &key, delay);
+                    }
+                }
+            })
+        };
+
+        Ok(Self {
+            addr,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for TimingServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// How long a client gets to send its request head. Requests are handled
+/// one at a time, so without a limit a client that connects and says
+/// nothing would hold up every request after it, and the server's `Drop`.
+const HEAD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Reads up to the end of the request head; the body, if any, is ignored.
+/// Fails if that takes longer than `HEAD_TIMEOUT` all told.
+fn read_head(stream: &mut TcpStream) -> io::Result<String> {
+    let deadline = Instant::now() + HEAD_TIMEOUT;
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request head too slow",
+            ));
+        }
+        stream.set_read_timeout(Some(left))?;
+        let n = stream.read(&mut buf)?;
+        if n == 0 || head.len() > 16 * 1024 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// The status for a request line such as `GET /test?file=foo&signature=...
+/// HTTP/1.1`.
+fn respond(request_line: &str, key: &[u8], delay: Duration) -> u16 {
+    let mut parts = request_line.split(' ');
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(m), Some(t)) => (m, t),
+        _ => return 400,
+    };
+    let query = match target.strip_prefix("/test?") {
+        Some(q) if method == "GET" => q,
+        _ => return 404,
+    };
+    let pairs = match QUERY.parse(query) {
+        Ok(pairs) => pairs,
+        Err(_) => return 400,
+    };
+    let get = |name: &str| {
+        pairs
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    };
+    let (file, signature) = match (get("file"), get("signature").and_then(decode_hex)) {
+        (Some(f), Some(s)) => (f, s),
+        _ => return 400,
+    };
+    if insecure_compare(&hmac_sha1(key, file.as_bytes()), &signature, delay) {
+        200
+    } else {
+        500
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, key: &[u8], delay: Duration) -> io::Result<()> {
+    let head = read_head(&mut stream)?;
+    let status = respond(head.lines().next().unwrap_or_default(), key, delay);
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        status, reason
+    )?;
+    stream.flush()?;
+    stream.shutdown(Shutdown::Both)
+}
+
+/// Sends `GET path` to `addr` and returns the response status.
+pub fn http_get_status(addr: SocketAddr, path: &str) -> io::Result<u16> {
+    let mut stream = TcpStream::connect(addr)?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, addr
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    response
+        .split(' ')
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no status line"))
+}
+
+/// The request path asking whether `signature` is right for `file`.
+pub fn test_path(file: &str, signature: &[u8]) -> String {
+    let hex = RawBytes {
+        bytes: signature.to_vec(),
+    }
+    .to_hex();
+    format!(
+        "/test?{}",
+        QUERY.serialize(&[("file", file), ("signature", &hex)])
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_insecure_compare() {
+        let zero = Duration::from_millis(0);
+        assert!(insecure_compare(b"abc", b"abc", zero));
+        assert!(!insecure_compare(b"abc", b"abd", zero));
+        assert!(!insecure_compare(b"abc", b"ab", zero));
+        assert!(!insecure_compare(b"abc", b"abcd", zero));
+
+        // Two right bytes then a wrong one sleeps twice.
+        let delay = Duration::from_millis(10);
+        let start = Instant::now();
+        assert!(!insecure_compare(b"abc", b"abx", delay));
+        assert!(start.elapsed() >= 2 * delay);
+    }
+
+    #[test]
+    fn test_server_statuses() {
+        let key = b"server key";
+        let server = TimingServer::start(key, Duration::from_millis(0)).unwrap();
+        let mac = hmac_sha1(key, b"foo");
+        let status = |path: &str| http_get_status(server.addr(), path).unwrap();
+
+        assert_eq!(status(&test_path("foo", &mac)), 200);
+        assert_eq!(status(&test_path("bar", &mac)), 500);
+        assert_eq!(status(&test_path("foo", &mac[..19])), 500);
+        assert_eq!(status("/test?file=foo&signature=zz"), 400);
+        assert_eq!(status("/test?file=foo"), 400);
+        assert_eq!(status("/other"), 404);
+    }
+
+    #[test]
+    fn test_server_leaks_matching_prefix() {
+        let key = b"server key";
+        let delay = Duration::from_millis(5);
+        let server = TimingServer::start(key, delay).unwrap();
+        let mac = hmac_sha1(key, b"foo");
+
+        let time = |sig: &[u8]| {
+            let start = Instant::now();
+            http_get_status(server.addr(), &test_path("foo", sig)).unwrap();
+            start.elapsed()
+        };
+        // Signatures with no, one and two leading bytes right, timed in
+        // turn so that any drift in the machine's load hits all three.
+        let wrong = mac.map(|b| !b);
+        let sigs: Vec<[u8; 20]> = (0..3)
+            .map(|right| {
+                let mut sig = wrong;
+                sig[..right].copy_from_slice(&mac[..right]);
+                sig
+            })
+            .collect();
+        let mut samples = vec![Vec::new(); sigs.len()];
+        for _ in 0..9 {
+            for (sig, times) in sigs.iter().zip(&mut samples) {
+                times.push(time(sig));
+            }
+        }
+        let medians: Vec<Duration> = samples
+            .iter_mut()
+            .map(|times| {
+                times.sort();
+                times[times.len() / 2]
+            })
+            .collect();
+
+        // Each right byte adds about one delay over the one before.
+        for pair in medians.windows(2) {
+            assert!(pair[1] > pair[0] + delay / 2, "{:?}", medians);
+        }
+    }
+
+    #[test]
+    fn test_silent_client_times_out() {
+        let key = b"server key";
+        let server = TimingServer::start(key, Duration::from_millis(0)).unwrap();
+        let mac = hmac_sha1(key, b"foo");
+
+        // Connects and never sends anything.
+        let _silent = TcpStream::connect(server.addr()).unwrap();
+        let start = Instant::now();
+        assert_eq!(
+            http_get_status(server.addr(), &test_path("foo", &mac)).unwrap(),
+            200
+        );
+        assert!(start.elapsed() < 3 * HEAD_TIMEOUT);
+
+        // Nor does it keep the server from stopping.
+        let _silent = TcpStream::connect(server.addr()).unwrap();
+        let start = Instant::now();
+        drop(server);
+        assert!(start.elapsed() < 3 * HEAD_TIMEOUT);
+    }
+}
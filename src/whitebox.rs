@@ -0,0 +1,119 @@
+#![allow(dead_code)]
+use crate::aes::*;
+
+const ROUNDS: usize = 10;
+
+/// A deliberately naive "white-box" AES-128: the round keys never appear on
+/// their own, only folded into per-byte lookup tables
+/// `T[r][i](x) = S(x ^ k'[r][i])`, where `k'[r]` is round key `r` with
+/// ShiftRows applied. The last round's tables also absorb round key 10.
+pub struct WhiteBoxAes {
+    tables: Vec<[[u8; 256]; 16]>,
+}
+
+impl WhiteBoxAes {
+    pub fn new(key: &[u8; 16]) -> Self {
+        let round_keys = round_keys_128(key);
+
+        let tables = (0..ROUNDS)
+            .map(|r| {
+                let mut k = round_keys[r];
+                shift_rows(&mut k);
+
+                let mut t = [[0u8; 256]; 16];
+                for (i, table) in t.iter_mut().enumerate() {
+                    for (x, out) in table.iter_mut().enumerate() {
+                        *out = sbox(x as u8 ^ k[i]);
+                        if r == ROUNDS - 1 {
+                            *out ^= round_keys[ROUNDS][i];
+                        }
+                    }
+                }
+                t
+            })
+            .collect();
+
+        Self { tables }
+    }
+
+    /// What an attacker holding the "protected" implementation gets to see.
+    pub fn tables(&self) -> &[[[u8; 256]; 16]] {
+        &self.tables
+    }
+
+    pub fn encrypt_block(&self, block: &[u8; 16]) -> [u8; 16] {
+        let mut state = *block;
+
+        for (r, t) in self.tables.iter().enumerate() {
+            shift_rows(&mut state);
+            for (i, b) in state.iter_mut().enumerate() {
+                *b = t[i][*b as usize];
+            }
+            if r < ROUNDS - 1 {
+                mix_columns(&mut state);
+            }
+        }
+        state
+    }
+}
+
+/// Reads round key `round` (0..=8) straight out of its tables: `T(x) = S(0)`
+/// exactly when `x` equals the embedded key byte. The key schedule reversal
+/// then walks back to the cipher key.
+pub fn extract_key_from_round(tables: &[[[u8; 256]; 16]], round: usize) -> [u8; 16] {
+    let mut k = [0u8; 16];
+    for (i, b) in k.iter_mut().enumerate() {
+        *b = tables[round][i].iter().position(|y| *y == sbox(0)).unwrap() as u8;
+    }
+    inv_shift_rows(&mut k);
+
+    recover_key_from_round_key(&k, round)
+}
+
+/// The last round tables mix two key bytes, `T(x) = S(x ^ k9) ^ k10`. Only
+/// the right guess for `k9` makes `T(x) ^ S(x ^ k9)` constant over all `x`,
+/// and that constant is the byte of round key 10.
+pub fn extract_key_from_last_round(tables: &[[[u8; 256]; 16]]) -> [u8; 16] {
+    let last = &tables[ROUNDS - 1];
+
+    let mut k10 = [0u8; 16];
+    for (i, b) in k10.iter_mut().enumerate() {
+        let t = &last[i];
+        let guess = (0..=255u8)
+            .find(|g| (0..256).all(|x| t[x] ^ sbox(x as u8 ^ g) == t[0] ^ sbox(*g)))
+            .unwrap();
+        *b = t[0] ^ sbox(guess);
+    }
+
+    recover_key_from_round_key(&k10, ROUNDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_bytes::*;
+    use crate::test_helpers::*;
+
+    #[test]
+    fn test_whitebox_encrypt() {
+        // FIPS-197 appendix B
+        let wb = WhiteBoxAes::new(&block("2b7e151628aed2a6abf7158809cf4f3c"));
+
+        assert_eq!(
+            wb.encrypt_block(&block("3243f6a8885a308d313198a2e0370734")),
+            block("3925841d02dc09fbdc118597196a0b32")
+        );
+    }
+
+    #[test]
+    fn test_whitebox_key_extraction() {
+        let key = RawBytes::from_str("YELLOW SUBMARINE");
+        let key = block(&key.to_hex());
+        let wb = WhiteBoxAes::new(&key);
+
+        for round in 0..ROUNDS - 1 {
+            assert_eq!(extract_key_from_round(wb.tables(), round), key);
+        }
+        assert_eq!(extract_key_from_last_round(wb.tables()), key);
+    }
+}
@@ -0,0 +1,176 @@
+#![allow(dead_code)]
+use std::io::{self, Read, Write};
+
+/// A repeating XOR key together with how far into it the stream is.
+#[derive(Debug, Clone)]
+struct Keystream {
+    key: Vec<u8>,
+    pos: usize,
+}
+
+impl Keystream {
+    fn new(key: &[u8]) -> Self {
+        assert!(!key.is_empty(), "empty XOR key");
+        Self {
+            key: key.to_vec(),
+            pos: 0,
+        }
+    }
+
+    fn apply(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            *b ^= self.key[self.pos];
+            self.pos = (self.pos + 1) % self.key.len();
+        }
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.pos = (self.pos + n) % self.key.len();
+    }
+}
+
+/// Reads from `inner`, XORing with a repeating key on the way through.
+pub struct XorReader<R: Read> {
+    inner: R,
+    keystream: Keystream,
+}
+
+impl<R: Read> XorReader<R> {
+    pub fn new(inner: R, key: &[u8]) -> Self {
+        Self {
+            inner,
+            keystream: Keystream::new(key),
+        }
+    }
+
+    pub fn single_byte(inner: R, key: u8) -> Self {
+        Self::new(inner, &[key])
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for XorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.keystream.apply(&mut buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Writes to `inner`, XORing with a repeating key on the way through.
+pub struct XorWriter<W: Write> {
+    inner: W,
+    keystream: Keystream,
+    scratch: Vec<u8>,
+}
+
+impl<W: Write> XorWriter<W> {
+    pub fn new(inner: W, key: &[u8]) -> Self {
+        Self {
+            inner,
+            keystream: Keystream::new(key),
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn single_byte(inner: W, key: u8) -> Self {
+        Self::new(inner, &[key])
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for XorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.scratch.clear();
+        self.scratch.extend_from_slice(buf);
+
+        // Only advance the key over what `inner` actually took.
+        let mut keystream = self.keystream.clone();
+        keystream.apply(&mut self.scratch);
+        let n = self.inner.write(&self.scratch)?;
+        self.keystream.advance(n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cipher::*;
+    use crate::raw_bytes::*;
+
+    #[test]
+    fn test_xor_reader() {
+        let data = RawBytes::from_str(
+            "Burning 'em, if you ain't quick and nimble
+I go crazy when I hear a cymbal",
+        );
+
+        // Small reads must carry the key position across calls.
+        let mut reader = XorReader::new(&data.bytes[..], b"ICE");
+        let mut out = Vec::new();
+        let mut buf = [0u8; 4];
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(
+            out,
+            repeating_key_xor(&data, &RawBytes::from_str("ICE")).bytes
+        );
+
+        let mut out = Vec::new();
+        XorReader::single_byte(&data.bytes[..], 0x58)
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, single_key_xor(&data, 0x58).bytes);
+    }
+
+    /// Accepts at most three bytes per call.
+    struct Trickle(Vec<u8>);
+
+    impl Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(3);
+            self.0.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_xor_writer() {
+        let data = RawBytes::from_str("YELLOW SUBMARINE, yellow submarine");
+        let key = RawBytes::from_str("key!");
+
+        let mut writer = XorWriter::new(Trickle(Vec::new()), &key.bytes);
+        writer.write_all(&data.bytes[..10]).unwrap();
+        writer.write_all(&data.bytes[10..]).unwrap();
+        assert_eq!(writer.into_inner().0, repeating_key_xor(&data, &key).bytes);
+
+        // Writing then reading back with the same key is the identity.
+        let mut writer = XorWriter::new(Vec::new(), &key.bytes);
+        io::copy(
+            &mut XorReader::new(&data.bytes[..], &key.bytes),
+            &mut writer,
+        )
+        .unwrap();
+        assert_eq!(writer.into_inner(), data.bytes);
+    }
+}